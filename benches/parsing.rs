@@ -0,0 +1,165 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use futures::executor::block_on;
+use parser::eksmo::{self, EksmoParser};
+use parser::igraslov::{self, IgraSlov};
+use parser::labirint::{self, LabirintParser};
+use parser::parse_traits::{BookParser, PageHtml};
+
+const LABIRINT_HTML: &str = include_str!("../page_examples/labirint.html");
+const IGRASLOV_HTML: &str = include_str!("../page_examples/igraslov.html");
+const EKSMO_HTML: &str = include_str!("../page_examples/eksmo.html");
+
+/// Стоимость построения DOM, без выборки полей.
+fn parse_document(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_document");
+    for (name, html) in [
+        ("labirint", LABIRINT_HTML),
+        ("igraslov", IGRASLOV_HTML),
+        ("eksmo", EKSMO_HTML),
+    ] {
+        group.bench_function(name, |b| {
+            b.iter(|| scraper::Html::parse_document(black_box(html)))
+        });
+    }
+    group.finish();
+}
+
+/// Стоимость выборки всех полей из уже разобранного DOM через `parse_fields`.
+fn extract_fields<P: BookParser<Url = String>>(
+    c: &mut Criterion,
+    name: &str,
+    parser: &P,
+    ctx: &P::Context,
+    url: &str,
+) {
+    let url = url.to_string();
+    c.bench_function(&format!("extract_fields/{name}"), |b| {
+        b.iter(|| block_on(async { black_box(parser.parse_fields(ctx, &url).await) }))
+    });
+}
+
+/// Однопроходная выборка: один обход DOM, каждый элемент проверяется всеми селекторами.
+fn select_all<'a, const N: usize>(
+    ctx: &'a scraper::Html,
+    selectors: &[scraper::Selector; N],
+) -> [Vec<scraper::ElementRef<'a>>; N] {
+    let mut found: [Vec<scraper::ElementRef<'a>>; N] = std::array::from_fn(|_| Vec::new());
+    for elem in ctx.tree.nodes().filter_map(scraper::ElementRef::wrap) {
+        for (sel, nodes) in selectors.iter().zip(found.iter_mut()) {
+            if sel.matches(&elem) {
+                nodes.push(elem);
+            }
+        }
+    }
+    found
+}
+
+/// Выборка по полю (`Html::select` на каждый селектор) против одного обхода DOM.
+fn per_field_vs_single_pass(c: &mut Criterion) {
+    let sites: [(&str, &str, [&str; 5]); 3] = [
+        (
+            "labirint",
+            LABIRINT_HTML,
+            [
+                labirint::AUTHOR_SEL_STR,
+                labirint::ISBN_SEL_STR,
+                labirint::TITLE_SEL_STR,
+                labirint::DESCR_SEL_STR,
+                labirint::PRICE_SEL_STR,
+            ],
+        ),
+        (
+            "igraslov",
+            IGRASLOV_HTML,
+            [
+                igraslov::AUTHOR_SEL_STR,
+                igraslov::ISBN_SEL_STR,
+                igraslov::TITLE_SEL_STR,
+                igraslov::DESCR_SEL_STR,
+                igraslov::PRICE_SEL_STR,
+            ],
+        ),
+        (
+            "eksmo",
+            EKSMO_HTML,
+            [
+                eksmo::AUTHOR_SEL_STR,
+                eksmo::ISBN_SEL_STR,
+                eksmo::TITLE_SEL_STR,
+                eksmo::DESCR_SEL_STR,
+                eksmo::PRICE_SEL_STR,
+            ],
+        ),
+    ];
+    for (name, html, sel_strs) in sites {
+        let ctx = scraper::Html::parse_document(html);
+        let selectors = sel_strs.map(|s| scraper::Selector::parse(s).expect("selector"));
+        let mut group = c.benchmark_group(format!("select/{name}"));
+        group.bench_function("per_field", |b| {
+            b.iter(|| {
+                selectors
+                    .iter()
+                    .map(|sel| ctx.select(sel).collect::<Vec<_>>())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_function("single_pass", |b| {
+            b.iter(|| select_all(&ctx, black_box(&selectors)))
+        });
+        group.finish();
+    }
+}
+
+fn select_fields(c: &mut Criterion) {
+    extract_fields(
+        c,
+        "labirint",
+        &LabirintParser,
+        &PageHtml::from_body(LABIRINT_HTML),
+        "https://www.labirint.ru/books/123456/",
+    );
+    extract_fields(
+        c,
+        "igraslov",
+        &IgraSlov,
+        &PageHtml::from_body(IGRASLOV_HTML),
+        "https://igraslov.store/product/example",
+    );
+    extract_fields(
+        c,
+        "eksmo",
+        &EksmoParser,
+        &PageHtml::from_body(EKSMO_HTML),
+        "https://eksmo.ru/book/example",
+    );
+}
+
+/// Проверяет, что кэширование скомпилированных `Selector` в `OnceLock` окупается.
+fn selector_reuse(c: &mut Criterion) {
+    const SEL: &str = "div.spoiler__text.t.t_last-p-no-offset.book-page__card-description-text p";
+    let ctx = scraper::Html::parse_document(EKSMO_HTML);
+    let compiled = scraper::Selector::parse(SEL).expect("selector");
+
+    let mut group = c.benchmark_group("selector");
+    group.bench_function("reuse_compiled", |b| {
+        b.iter(|| ctx.select(black_box(&compiled)).count())
+    });
+    group.bench_function("recompile_each_time", |b| {
+        b.iter(|| {
+            let sel = scraper::Selector::parse(black_box(SEL)).expect("selector");
+            ctx.select(&sel).count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse_document,
+    select_fields,
+    per_field_vs_single_pass,
+    selector_reuse
+);
+criterion_main!(benches);
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, anyhow};
+use tracing::info;
+
+use crate::csv_save::{BOOK_CSV_FLAG_HEADERS, BOOK_CSV_HEADERS, CsvSave};
+use crate::parse_traits::{Book, PriceFormat};
+use crate::sink::BookSink;
+
+/// Поля, без которых строка считается неполной. Колонки, которых нет в старом файле
+/// (добавлены после него), тоже считаются пустыми.
+pub static REQUIRED_FIELDS: &[&str] = &["isbn", "title", "authors", "description", "price"];
+
+fn is_missing(field: &str, value: Option<&str>) -> bool {
+    match value.map(str::trim) {
+        None | Some("") => true,
+        Some("0") => field == "price",
+        Some(_) => false,
+    }
+}
+
+/// Уже выгруженный `books.csv` для `--only-missing-fields`: строки перечитываются,
+/// заново скачиваются только неполные, результат пишется поверх исходного файла.
+pub struct BackfillSink {
+    path: PathBuf,
+    headers: csv::StringRecord,
+    rows: Vec<csv::StringRecord>,
+    with_flags: bool,
+    price_format: PriceFormat,
+    /// Свежие записи по `source` исходной строки.
+    fresh: HashMap<String, csv::StringRecord>,
+}
+
+impl BackfillSink {
+    pub fn open(path: impl Into<PathBuf>, with_flags: bool) -> anyhow::Result<Self> {
+        let path = path.into();
+        let mut rdr = csv::Reader::from_path(&path)
+            .with_context(|| format!("can't open existing csv {}", path.display()))?;
+        let headers = rdr.headers()?.clone();
+        if !headers.iter().any(|h| h == "source") {
+            return Err(anyhow!("{} has no source column", path.display()));
+        }
+        let rows = rdr
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .with_context(|| format!("bad csv {}", path.display()))?;
+        Ok(Self {
+            path,
+            headers,
+            rows,
+            with_flags,
+            price_format: PriceFormat::default(),
+            fresh: HashMap::new(),
+        })
+    }
+
+    pub fn with_price_format(mut self, price_format: PriceFormat) -> Self {
+        self.price_format = price_format;
+        self
+    }
+
+    fn get<'a>(&self, row: &'a csv::StringRecord, column: &str) -> Option<&'a str> {
+        let idx = self.headers.iter().position(|h| h == column)?;
+        row.get(idx)
+    }
+
+    /// URL строк, где не заполнено хотя бы одно из `REQUIRED_FIELDS`, в порядке файла.
+    pub fn missing_urls(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .filter(|row| {
+                REQUIRED_FIELDS
+                    .iter()
+                    .any(|field| is_missing(field, self.get(row, field)))
+            })
+            .filter_map(|row| self.get(row, "source").map(str::to_string))
+            .collect()
+    }
+
+    fn output_headers(&self) -> Vec<&'static str> {
+        let mut headers = BOOK_CSV_HEADERS.to_vec();
+        if self.with_flags {
+            headers.extend_from_slice(BOOK_CSV_FLAG_HEADERS);
+        }
+        headers
+    }
+
+    /// Старая строка в текущем наборе колонок: по имени, недостающие пустые.
+    fn remap(&self, row: &csv::StringRecord) -> Vec<String> {
+        self.output_headers()
+            .iter()
+            .map(|column| self.get(row, column).unwrap_or_default().to_string())
+            .collect()
+    }
+
+    fn write_merged(&self, path: &Path) -> anyhow::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(self.output_headers())?;
+        let mut replaced = 0;
+        for row in &self.rows {
+            let fresh = self
+                .get(row, "source")
+                .and_then(|source| self.fresh.get(source));
+            match fresh {
+                Some(record) => {
+                    replaced += 1;
+                    wtr.write_record(record)?;
+                }
+                None => wtr.write_record(self.remap(row))?,
+            }
+        }
+        wtr.flush()?;
+        info!(
+            rows = self.rows.len(),
+            replaced, "merged re-parsed books into existing csv"
+        );
+        Ok(())
+    }
+}
+
+impl BookSink for BackfillSink {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        let mut wtr = csv::WriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        book.write_csv_record(&mut wtr, self.with_flags, self.price_format)?;
+        let data = wtr.into_inner().map_err(|e| anyhow!("{e}"))?;
+        let record = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_slice())
+            .records()
+            .next()
+            .ok_or_else(|| anyhow!("empty csv record"))??;
+        // Строка файла ищется по URL, с которым книгу запрашивали.
+        let url = book.requested_url.as_ref().unwrap_or(&book.source);
+        self.fresh.insert(url.clone(), record);
+        Ok(())
+    }
+
+    /// Пишет во временный файл рядом и переименовывает: упавшая запись не портит исходник.
+    fn finish(&mut self) -> anyhow::Result<()> {
+        let tmp = self.path.with_extension("csv.tmp");
+        self.write_merged(&tmp)?;
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("can't replace {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+
+    // Старый формат: без колонок about_author/rating/review_count/source_id.
+    const OLD_CSV: &str = "\
+site,source,isbn,title,authors,description,price
+labirint,https://www.labirint.ru/books/1/,9785926830153,Джейн Эйр,Бронте Шарлотта,Роман,10001
+labirint,https://www.labirint.ru/books/2/,9785926830153,Джейн Эйр,Бронте Шарлотта,Роман,
+labirint,https://www.labirint.ru/books/3/,9785926830153,Джейн Эйр,Бронте Шарлотта,,10003
+labirint,https://www.labirint.ru/books/4/,9785926830153,Джейн Эйр,Бронте Шарлотта,Роман,0
+";
+
+    fn temp_csv(name: &str, text: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_{name}_{}.csv", std::process::id()));
+        std::fs::write(&path, text).expect("write csv");
+        path
+    }
+
+    #[test]
+    fn rows_without_price_or_description_are_refetched() {
+        let path = temp_csv("backfill_urls", OLD_CSV);
+        let sink = BackfillSink::open(&path, false).expect("open");
+        assert_eq!(
+            sink.missing_urls(),
+            [
+                "https://www.labirint.ru/books/2/",
+                "https://www.labirint.ru/books/3/",
+                "https://www.labirint.ru/books/4/",
+            ]
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn fresh_books_are_merged_in_place() {
+        let path = temp_csv("backfill_merge", OLD_CSV);
+        let mut sink = BackfillSink::open(&path, false).expect("open");
+        sink.write_book(&sample_book(2)).expect("write");
+        sink.finish().expect("finish");
+
+        let mut rdr = csv::Reader::from_path(&path).expect("read merged");
+        assert_eq!(
+            rdr.headers().expect("headers").len(),
+            BOOK_CSV_HEADERS.len()
+        );
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.expect("row")).collect();
+        assert_eq!(rows.len(), 4);
+        let price = BOOK_CSV_HEADERS.iter().position(|h| *h == "price").unwrap();
+        let source_id = BOOK_CSV_HEADERS
+            .iter()
+            .position(|h| *h == "source_id")
+            .unwrap();
+        assert_eq!(&rows[0][price], "10001");
+        assert_eq!(&rows[0][source_id], "");
+        assert_eq!(&rows[1][price], "10002");
+        assert_eq!(&rows[1][source_id], "2");
+        // Не перескачанная строка остаётся как была.
+        assert_eq!(&rows[3][price], "0");
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::registry::ParserRegistry;
+
+/// Число URL по сайтам для `--head-only-count`; URL без парсера считаются как `unknown`.
+pub fn count_by_site(registry: &ParserRegistry, urls: &[String]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for url in urls {
+        let site = registry
+            .for_url(url)
+            .map_or_else(|| "unknown".to_string(), |p| p.site().to_string());
+        *counts.entry(site).or_default() += 1;
+    }
+    counts
+}
+
+/// По сайту на строку и итог последней строкой.
+pub fn format_counts(counts: &BTreeMap<String, usize>) -> String {
+    let mut out = String::new();
+    for (site, count) in counts {
+        let _ = writeln!(out, "{site:<12} {count}");
+    }
+    let _ = writeln!(out, "{:<12} {}", "total", counts.values().sum::<usize>());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sitemap::fetch_urlsets;
+    use crate::url_filter::UrlFilter;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn urlset(urls: &[&str]) -> String {
+        let locs: String = urls
+            .iter()
+            .map(|url| format!("<url><loc>{url}</loc></url>"))
+            .collect();
+        format!(r#"<?xml version="1.0" encoding="UTF-8"?><urlset>{locs}</urlset>"#)
+    }
+
+    #[tokio::test]
+    async fn counts_urls_per_site_over_sitemaps() {
+        let server = MockServer::start().await;
+        let sitemaps = [
+            (
+                "/labirint.xml",
+                urlset(&[
+                    "https://www.labirint.ru/books/1/",
+                    "https://www.labirint.ru/books/2/",
+                    "https://www.labirint.ru/genres/3/",
+                ]),
+            ),
+            (
+                "/eksmo.xml",
+                urlset(&[
+                    "https://eksmo.ru/book/a/",
+                    "https://eksmo.ru/book/b-detskaya/",
+                ]),
+            ),
+        ];
+        for (sitemap, body) in &sitemaps {
+            Mock::given(method("GET"))
+                .and(path(*sitemap))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body.clone()))
+                .mount(&server)
+                .await;
+        }
+        let registry = ParserRegistry::default();
+        let mut urls = fetch_urlsets(&[
+            format!("{}/labirint.xml", server.uri()),
+            format!("{}/eksmo.xml", server.uri()),
+        ])
+        .await;
+        urls.retain(|url| registry.for_url(url).is_none_or(|p| p.is_book_url(url)));
+        UrlFilter::new(&[], &["detskaya".to_string()])
+            .expect("filter")
+            .retain(&mut urls);
+
+        let counts = count_by_site(&registry, &urls);
+        assert_eq!(
+            counts,
+            BTreeMap::from([("eksmo".to_string(), 1), ("labirint".to_string(), 2)])
+        );
+        assert_eq!(
+            format_counts(&counts),
+            "eksmo        1\nlabirint     2\ntotal        3\n"
+        );
+    }
+}
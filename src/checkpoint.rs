@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use tracing::info;
+
+use crate::csv_save::{BOOK_CSV_HEADERS, book_csv_headers, migrate_csv};
+use crate::parse_traits::Book;
+use crate::sink::{BookSink, pending_path};
+
+/// Строки CSV, которые точно дошли до диска. `csv::Writer` заканчивает каждую запись
+/// переводом строки, поэтому всё после последнего `\n` — оборванная при падении строка.
+pub fn persisted_rows(path: &Path, with_flags: bool) -> anyhow::Result<Vec<csv::StringRecord>> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e).with_context(|| format!("can't read {}", path.display())),
+    };
+    let complete = data.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    if complete == 0 {
+        return Ok(vec![]);
+    }
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(&data[..complete]);
+    let headers = book_csv_headers(with_flags);
+    if rdr.headers()?.iter().ne(headers.iter().copied()) {
+        return Err(anyhow!(
+            "{} has other columns, can't resume into it",
+            path.display()
+        ));
+    }
+    Ok(rdr
+        .records()
+        .map_while(Result::ok)
+        .filter(|row| row.len() == headers.len())
+        .collect())
+}
+
+/// URL, под которым книга была во входном списке: `requested_url`, если сайт
+/// отредиректил её, иначе `source`.
+fn input_url(row: &csv::StringRecord) -> Option<&str> {
+    let column = |name| {
+        BOOK_CSV_HEADERS
+            .iter()
+            .position(|h| *h == name)
+            .expect("book csv column")
+    };
+    row.get(column("requested_url"))
+        .filter(|url| !url.is_empty())
+        .or_else(|| row.get(column("source")))
+}
+
+/// Согласует чекпоинт с выгрузкой: остаются только книги, которые есть и там и там.
+/// URL из чекпоинта без строки в файле будут скачаны заново, строки без записи
+/// в чекпоинте выбрасываются, чтобы не задвоиться.
+pub fn reconcile(
+    checkpoint: &HashSet<String>,
+    rows: Vec<csv::StringRecord>,
+) -> (HashSet<String>, Vec<csv::StringRecord>) {
+    let rows: Vec<csv::StringRecord> = rows
+        .into_iter()
+        .filter(|row| input_url(row).is_some_and(|url| checkpoint.contains(url)))
+        .collect();
+    let done = rows
+        .iter()
+        .filter_map(input_url)
+        .map(str::to_string)
+        .collect();
+    (done, rows)
+}
+
+/// `--resume-from-checkpoint`: URL книг, уже записанных в `books.csv`, по одному на строку.
+/// Дописывается после каждой записанной книги.
+pub struct Checkpoint {
+    out: File,
+    done: HashSet<String>,
+}
+
+impl Checkpoint {
+    /// Читает чекпоинт и то, что успело попасть в `csv` (или в его незавершённый
+    /// `pending_path`, если прошлый прогон упал), согласует их и переписывает чекпоинт.
+    /// Возвращает строки, которые нужно перенести в новую выгрузку.
+    pub fn resume(
+        path: &Path,
+        csv: &Path,
+        with_flags: bool,
+    ) -> anyhow::Result<(Self, Vec<csv::StringRecord>)> {
+        let pending = pending_path(csv);
+        let output = if pending.exists() { &pending } else { csv };
+        migrate_csv(output, with_flags)?;
+        let rows = persisted_rows(output, with_flags)?;
+        let recorded: HashSet<String> = match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(e) if e.kind() == ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e).with_context(|| format!("can't read {}", path.display())),
+        };
+        let (done, rows) = reconcile(&recorded, rows);
+        info!(
+            output = %output.display(),
+            recorded = recorded.len(),
+            resumed = done.len(),
+            "reconciled checkpoint with written books"
+        );
+
+        let tmp = path.with_extension("tmp");
+        let mut text = String::new();
+        for url in &done {
+            text.push_str(url);
+            text.push('\n');
+        }
+        std::fs::write(&tmp, text)?;
+        std::fs::rename(&tmp, path).with_context(|| format!("can't replace {}", path.display()))?;
+        let out = OpenOptions::new()
+            .append(true)
+            .open(path)
+            .with_context(|| format!("can't open {}", path.display()))?;
+        Ok((Self { out, done }, rows))
+    }
+
+    pub fn is_done(&self, url: &str) -> bool {
+        self.done.contains(url)
+    }
+
+    /// URL, которые уже записаны и в этом прогоне не скачиваются.
+    pub fn done(&self) -> &HashSet<String> {
+        &self.done
+    }
+
+    pub fn len(&self) -> usize {
+        self.done.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    /// Отмечает книгу записанной.
+    pub fn record(&mut self, url: &str) -> anyhow::Result<()> {
+        writeln!(self.out, "{url}")?;
+        self.done.insert(url.to_string());
+        Ok(())
+    }
+}
+
+/// Отмечает книгу в чекпоинте, только когда `inner` её записал. Ставится прямо
+/// над выгрузкой, под фильтрами: книга, отброшенная `--idempotent`, `--skip-existing`
+/// или фильтрами, в чекпоинт не попадает и при продолжении будет разобрана заново.
+pub struct CheckpointSink<S> {
+    inner: S,
+    checkpoint: Checkpoint,
+}
+
+impl<S: BookSink> CheckpointSink<S> {
+    pub fn new(inner: S, checkpoint: Checkpoint) -> Self {
+        Self { inner, checkpoint }
+    }
+}
+
+impl<S: BookSink> BookSink for CheckpointSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        self.inner.write_book(book)?;
+        self.checkpoint
+            .record(book.requested_url.as_deref().unwrap_or(&book.source))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+    use crate::sink::{CsvSink, IdempotentSink};
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("bd_parser_{name}_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        dir
+    }
+
+    #[test]
+    fn crash_then_clean_resume() {
+        let dir = temp_dir("checkpoint");
+        let csv = dir.join("books.csv");
+        let checkpoint = dir.join("checkpoint.txt");
+
+        // Прогон упал: книги 1 и 2 записаны целиком, строка книги 3 оборвана,
+        // файл не переименован, а в чекпоинте успели отметиться 1..=4.
+        let mut buf = vec![];
+        let mut sink = CsvSink::new(&mut buf, false).expect("sink");
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&sample_book(2)).expect("write");
+        sink.finish().expect("finish");
+        drop(sink);
+        buf.extend_from_slice("labirint,https://www.labirint.ru/books/3/,97859".as_bytes());
+        std::fs::write(pending_path(&csv), &buf).expect("write pending");
+        let urls: Vec<String> = (1..=4).map(|n| sample_book(n).source).collect();
+        std::fs::write(&checkpoint, urls.join("\n") + "\n").expect("write checkpoint");
+
+        let (mut resumed, rows) = Checkpoint::resume(&checkpoint, &csv, false).expect("resume");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(resumed.len(), 2);
+        assert!(resumed.is_done(&urls[0]) && resumed.is_done(&urls[1]));
+        assert!(!resumed.is_done(&urls[2]) && !resumed.is_done(&urls[3]));
+        assert_eq!(
+            std::fs::read_to_string(&checkpoint)
+                .expect("read")
+                .lines()
+                .count(),
+            2
+        );
+
+        // Продолжение дописывает книгу 3 и завершается переименованием.
+        let mut sink = CsvSink::create_atomic(&csv, false, false).expect("sink");
+        for row in &rows {
+            sink.write_record(row).expect("row");
+        }
+        let book = sample_book(3);
+        sink.write_book(&book).expect("write");
+        resumed.record(&book.source).expect("record");
+        sink.finish().expect("finish");
+        assert!(!pending_path(&csv).exists());
+
+        let (resumed, rows) = Checkpoint::resume(&checkpoint, &csv, false).expect("resume");
+        assert_eq!(rows.len(), 3);
+        assert_eq!(resumed.len(), 3);
+        assert!(resumed.is_done(&urls[2]));
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn only_books_accepted_by_output_are_recorded() {
+        let dir = temp_dir("checkpoint_sink");
+        let csv = dir.join("books.csv");
+        let path = dir.join("checkpoint.txt");
+        let (checkpoint, _) = Checkpoint::resume(&path, &csv, false).expect("resume");
+        let output = CsvSink::create_atomic(&csv, false, false).expect("sink");
+        // У всех образцов один ISBN: `--idempotent` пропускает только первую книгу.
+        let mut sink = IdempotentSink::new(CheckpointSink::new(output, checkpoint));
+        let first = sample_book(1);
+        sink.write_book(&first).expect("write");
+        sink.write_book(&sample_book(2)).expect("write");
+
+        // Отметка появляется сразу, до `finish`.
+        let recorded = std::fs::read_to_string(&path).expect("read");
+        assert_eq!(
+            recorded.lines().collect::<Vec<_>>(),
+            [first.source.as_str()]
+        );
+        sink.finish().expect("finish");
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
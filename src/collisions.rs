@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::parse_traits::{Book, Sites};
+
+/// Отчёт о повторах ISBN внутри одного магазина.
+pub static COLLISIONS_FILE: &str = "collisions.csv";
+
+/// Один ISBN под несколькими карточками магазина (например, разные переплёты).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub site: Sites,
+    pub isbn: String,
+    /// URL карточек в порядке появления.
+    pub sources: Vec<String>,
+}
+
+/// Повторы ISBN в пределах сайта; одинаковый ISBN у разных магазинов — не повтор.
+/// Книги не удаляются, это только отчёт для чистки каталога.
+pub fn find_collisions<'a>(books: impl IntoIterator<Item = &'a Book<String>>) -> Vec<Collision> {
+    let mut by_isbn: BTreeMap<(String, &str), (Sites, Vec<String>)> = BTreeMap::new();
+    for book in books {
+        let (_, sources) = by_isbn
+            .entry((book.site.to_string(), book.isbn.as_str()))
+            .or_insert_with(|| (book.site, vec![]));
+        if !sources.contains(&book.source) {
+            sources.push(book.source.clone());
+        }
+    }
+    by_isbn
+        .into_iter()
+        .filter(|(_, (_, sources))| sources.len() > 1)
+        .map(|((_, isbn), (site, sources))| Collision {
+            site,
+            isbn: isbn.to_string(),
+            sources,
+        })
+        .collect()
+}
+
+/// `site,isbn,sources`: URL карточек через `"; "`, как авторы в `books.csv`.
+pub fn write_collisions(path: &Path, collisions: &[Collision]) -> anyhow::Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["site", "isbn", "sources"])?;
+    for collision in collisions {
+        wtr.write_record([
+            collision.site.to_string(),
+            collision.isbn.clone(),
+            collision.sources.join("; "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+
+    #[test]
+    fn same_isbn_labirint_books_collide() {
+        let hardcover = sample_book(1);
+        let paperback = sample_book(2);
+        let mut other_store = sample_book(3);
+        other_store.site = Sites::Eksmo;
+        other_store.source = "https://eksmo.ru/book/dzheyn-eyr/".to_string();
+        let collisions = find_collisions([&hardcover, &paperback, &other_store, &hardcover]);
+        assert_eq!(
+            collisions,
+            [Collision {
+                site: Sites::Labirint,
+                isbn: "978-5-9268-3015-3".to_string(),
+                sources: vec![hardcover.source.clone(), paperback.source.clone()],
+            }]
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_collisions_{}.csv", std::process::id()));
+        write_collisions(&path, &collisions).expect("write");
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "site,isbn,sources\n\
+             labirint,978-5-9268-3015-3,https://www.labirint.ru/books/1/; https://www.labirint.ru/books/2/\n"
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+        assert!(find_collisions([&hardcover, &other_store]).is_empty());
+    }
+}
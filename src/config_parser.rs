@@ -0,0 +1,337 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, anyhow};
+use serde::Deserialize;
+use tracing::{debug, instrument, warn};
+
+use crate::description::description_fallback;
+use crate::json_ld::{authors_fallback, price_fallback, title_fallback};
+use crate::parse_traits::{
+    Author, BookParser, Description, Isbn, Price, Sites, Title, isbn_fallback, split_authors,
+};
+
+/// Файл `--sites-config`: список простых HTML-сайтов, которым не нужен свой модуль.
+#[derive(Debug, Deserialize)]
+pub struct SitesConfig {
+    #[serde(rename = "site", default)]
+    pub sites: Vec<SiteConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SiteConfig {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub sitemap_urls: Vec<String>,
+    pub selectors: SelectorsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SelectorsConfig {
+    pub authors: String,
+    pub isbn: String,
+    pub title: String,
+    pub description: String,
+    pub price: String,
+}
+
+impl SitesConfig {
+    pub fn from_toml(s: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("can't read sites config {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("bad sites config {}", path.display()))
+    }
+}
+
+struct Selectors {
+    authors: Arc<scraper::Selector>,
+    isbn: Arc<scraper::Selector>,
+    title: Arc<scraper::Selector>,
+    description: Arc<scraper::Selector>,
+    price: Arc<scraper::Selector>,
+}
+
+/// `BookParser`, целиком управляемый `SiteConfig`: селекторы берутся из общего кэша
+/// `selectors::selector` при создании.
+pub struct ConfigParser {
+    site: Sites,
+    sitemap_urls: Vec<String>,
+    selectors: Selectors,
+    selector_strs: SelectorsConfig,
+    client: reqwest::Client,
+}
+
+fn compile(field: &str, selector: &str) -> anyhow::Result<Arc<scraper::Selector>> {
+    crate::selectors::selector(selector).with_context(|| format!("bad {field} selector"))
+}
+
+impl ConfigParser {
+    pub fn new(config: SiteConfig) -> anyhow::Result<Self> {
+        let selectors = Selectors {
+            authors: compile("authors", &config.selectors.authors)?,
+            isbn: compile("isbn", &config.selectors.isbn)?,
+            title: compile("title", &config.selectors.title)?,
+            description: compile("description", &config.selectors.description)?,
+            price: compile("price", &config.selectors.price)?,
+        };
+        let client = crate::http::global().build_client()?;
+        // Конфиг читается один раз за запуск, а `Sites` — `Copy` со `&'static str`.
+        let site = Sites::Custom {
+            name: Box::leak(config.name.into_boxed_str()),
+            host: Box::leak(config.host.into_boxed_str()),
+        };
+        Ok(Self {
+            site,
+            sitemap_urls: config.sitemap_urls,
+            selectors,
+            selector_strs: config.selectors,
+            client,
+        })
+    }
+
+    pub fn sitemap_urls(&self) -> &[String] {
+        &self.sitemap_urls
+    }
+}
+
+/// Цена в копейках из текста вида `1 469 ₽`, `895,00 ₽` или `1469.5`.
+fn parse_price_text(text: &str) -> anyhow::Result<Price> {
+    let cleaned: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == ',' || *c == '.')
+        .collect();
+    let (rub, kop) = match cleaned.rsplit_once([',', '.']) {
+        Some((rub, kop)) if (1..=2).contains(&kop.len()) => (rub.replace([',', '.'], ""), kop),
+        _ => (cleaned.replace([',', '.'], ""), ""),
+    };
+    if rub.is_empty() {
+        return Err(anyhow!("no digits in price {text:?}"));
+    }
+    format!("{rub}{kop:0<2}").parse()
+}
+
+impl BookParser for ConfigParser {
+    fn site(&self) -> Sites {
+        self.site
+    }
+
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        let s = &self.selector_strs;
+        vec![
+            ("authors", s.authors.clone()),
+            ("isbn", s.isbn.clone()),
+            ("title", s.title.clone()),
+            ("description", s.description.clone()),
+            ("price", s.price.clone()),
+        ]
+    }
+
+    type Url = String;
+
+    type Context = scraper::Html;
+
+    #[instrument(skip(self), fields(url=%url))]
+    async fn fetch_body(&self, url: &Self::Url) -> anyhow::Result<String> {
+        let body = crate::http::fetch_page(&self.client, self.site(), url).await?;
+        crate::page_cache::observe(url, &body);
+        Ok(body)
+    }
+
+    async fn parse_authors(
+        &self,
+        ctx: &Self::Context,
+        _log_url: &Self::Url,
+    ) -> anyhow::Result<Vec<Author>> {
+        Ok(authors_fallback(
+            ctx,
+            ctx.select(&self.selectors.authors)
+                .flat_map(|node| split_authors(&node.text().collect::<String>()))
+                .collect(),
+        ))
+    }
+
+    async fn parse_isbn(&self, ctx: &Self::Context, log_url: &Self::Url) -> anyhow::Result<Isbn> {
+        let structured = match ctx.select(&self.selectors.isbn).next_back() {
+            Some(elem) => {
+                let raw: String = elem.text().collect::<String>().replace('\u{a0}', "");
+                Isbn::try_from(raw).map_err(|e| {
+                    warn!("can't parse isbn:{e}");
+                    anyhow!("can't parse isbn")
+                })
+            }
+            None => {
+                warn!(target: "time","ISBN not found on page {log_url}");
+                Err(anyhow!("can't find isbn on this page"))
+            }
+        };
+        structured.or_else(|e| isbn_fallback(ctx, e))
+    }
+
+    async fn parse_title(
+        &self,
+        ctx: &Self::Context,
+        _log_url: &Self::Url,
+    ) -> anyhow::Result<Title> {
+        Ok(title_fallback(
+            ctx,
+            Title::new(
+                ctx.select(&self.selectors.title)
+                    .map(|node| node.text().collect::<String>())
+                    .collect::<String>(),
+            ),
+        ))
+    }
+
+    async fn parse_description(&self, ctx: &Self::Context) -> anyhow::Result<Description> {
+        let descr = ctx
+            .select(&self.selectors.description)
+            .map(|node| node.text().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(description_fallback(ctx, Description::new(descr)))
+    }
+
+    async fn parse_price(&self, ctx: &Self::Context) -> anyhow::Result<Price> {
+        let selected = match ctx.select(&self.selectors.price).next() {
+            Some(node) => {
+                let text: String = node.text().collect();
+                debug!(text);
+                parse_price_text(&text).inspect_err(|e| warn!("can't parse price : {e}"))
+            }
+            None => Err(anyhow!("can't find price on this page")),
+        };
+        price_fallback(ctx, selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ParserRegistry;
+
+    // Селекторы eksmo, но сайт описан только конфигом.
+    const CONFIG: &str = r#"
+        [[site]]
+        name = "eksmo_config"
+        host = "eksmo.example"
+        sitemap_urls = ["https://eksmo.example/sitemap.xml"]
+
+        [site.selectors]
+        authors = ".book-page__card-author-link"
+        isbn = "span.copy__val"
+        title = ".book-page__card-title"
+        description = "div.spoiler__text.t.t_last-p-no-offset.book-page__card-description-text p"
+        price = "div.price-insert__price"
+    "#;
+
+    fn parser() -> ConfigParser {
+        let config = SitesConfig::from_toml(CONFIG).expect("config");
+        ConfigParser::new(config.sites.into_iter().next().expect("one site")).expect("parser")
+    }
+
+    #[tokio::test]
+    async fn toml_site_extracts_fields() {
+        let parser = parser();
+        let ctx = scraper::Html::parse_document(include_str!("../page_examples/eksmo.html"));
+        let url = "https://eksmo.example/book/1".to_string();
+        let fields = parser.parse_fields(&ctx, &url).await.expect("fields");
+        assert_eq!(fields.authors[0].as_str(), "Андрей Самарин");
+        assert_eq!(fields.isbn.as_str(), "978-5-04-156838-2");
+        assert_eq!(
+            fields.title.as_str(),
+            "Структура таланта. От иллюзий к реальности: как стать настоящим художником"
+        );
+        assert!(
+            fields
+                .description
+                .as_str()
+                .starts_with("Книга, которая поможет")
+        );
+        assert_eq!(u128::from(fields.price), 146900);
+        assert_eq!(parser.sitemap_urls(), ["https://eksmo.example/sitemap.xml"]);
+    }
+
+    #[test]
+    fn registry_dispatches_config_site_by_host() {
+        let mut registry = ParserRegistry::default();
+        registry.register(parser());
+        let found = registry
+            .for_url("https://www.eksmo.example/book/1")
+            .expect("config parser");
+        assert_eq!(found.site().to_string(), "eksmo_config");
+        assert_eq!(
+            registry
+                .for_url("https://eksmo.ru/book/1")
+                .map(|p| p.site()),
+            Some(Sites::Eksmo)
+        );
+    }
+
+    #[test]
+    fn bad_selector_is_rejected() {
+        let mut site = SitesConfig::from_toml(CONFIG)
+            .expect("config")
+            .sites
+            .remove(0);
+        site.selectors.title = "h1[".to_string();
+        let err = ConfigParser::new(site).err().expect("bad selector");
+        assert!(err.to_string().contains("title"));
+    }
+
+    #[test]
+    fn price_text_formats() {
+        assert_eq!(u128::from(parse_price_text("1 469 ₽").unwrap()), 146900);
+        assert_eq!(u128::from(parse_price_text("895,00 ₽").unwrap()), 89500);
+        assert_eq!(u128::from(parse_price_text("1469.5").unwrap()), 146950);
+        assert_eq!(u128::from(parse_price_text("1,469 ₽").unwrap()), 146900);
+        assert!(parse_price_text("нет в наличии").is_err());
+    }
+
+    #[tokio::test]
+    async fn redirect_chain_records_final_url() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        for (from, to) in [
+            ("/book/old", "/book/renamed"),
+            ("/book/renamed", "/book/new"),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(from))
+                .respond_with(ResponseTemplate::new(301).insert_header("location", to))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/book/new"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../page_examples/eksmo.html")),
+            )
+            .mount(&server)
+            .await;
+        let mut site = SitesConfig::from_toml(CONFIG)
+            .expect("config")
+            .sites
+            .remove(0);
+        site.host = "127.0.0.1".to_string();
+        let parser = ConfigParser::new(site).expect("parser");
+
+        let requested = format!("{}/book/old", server.uri());
+        let book = parser.parse_book(requested.clone()).await.expect("book");
+        assert_eq!(book.source, format!("{}/book/new", server.uri()));
+        assert_eq!(book.requested_url, Some(requested));
+        assert_eq!(book.isbn.as_str(), "978-5-04-156838-2");
+
+        let direct = format!("{}/book/new", server.uri());
+        let book = parser.parse_book(direct.clone()).await.expect("book");
+        assert_eq!(book.source, direct);
+        assert_eq!(book.requested_url, None);
+    }
+}
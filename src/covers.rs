@@ -0,0 +1,169 @@
+use std::path::Path;
+
+use anyhow::Context;
+use futures::{StreamExt, stream};
+use tracing::{debug, info, warn};
+
+use crate::parse_traits::Book;
+use crate::sink::isbn_key;
+
+/// Расширения, которые узнаём в пути URL обложки.
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "gif"];
+
+/// Расширение файла обложки: по `Content-Type`, иначе по пути URL
+/// (у labirint это `.../cover.jpg/484-0`), иначе `jpg`.
+fn cover_extension(url: &str, content_type: Option<&str>) -> &'static str {
+    let by_type =
+        content_type.and_then(|ct| match ct.split(';').next().unwrap_or_default().trim() {
+            "image/jpeg" => Some("jpg"),
+            "image/png" => Some("png"),
+            "image/webp" => Some("webp"),
+            "image/gif" => Some("gif"),
+            _ => None,
+        });
+    let by_path = || {
+        let path = reqwest::Url::parse(url).ok()?.path().to_ascii_lowercase();
+        path.rsplit('/').find_map(|segment| {
+            let (_, ext) = segment.rsplit_once('.')?;
+            IMAGE_EXTENSIONS.iter().copied().find(|known| *known == ext)
+        })
+    };
+    by_type.or_else(by_path).unwrap_or("jpg")
+}
+
+async fn download_cover(
+    client: &reqwest::Client,
+    dir: &Path,
+    url: &str,
+    isbn: &str,
+) -> anyhow::Result<String> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("can't fetch cover {url}"))?
+        .error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(str::to_string);
+    let bytes = response.bytes().await?;
+    let path = dir.join(format!(
+        "{isbn}.{}",
+        cover_extension(url, content_type.as_deref())
+    ));
+    tokio::fs::write(&path, &bytes)
+        .await
+        .with_context(|| format!("can't write {}", path.display()))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// `--download-covers`: сохраняет обложки в `dir` под именем ISBN и записывает путь
+/// в `cover_path`. Книги без `cover_image_url` или без ISBN пропускаются, неудачная
+/// загрузка оставляет `cover_path` пустым. Возвращает число сохранённых обложек.
+pub async fn download_covers<'a>(
+    client: &reqwest::Client,
+    dir: &Path,
+    books: impl IntoIterator<Item = &'a mut Book<String>>,
+    concurrency: usize,
+) -> anyhow::Result<usize> {
+    std::fs::create_dir_all(dir).with_context(|| format!("can't create {}", dir.display()))?;
+    let saved = stream::iter(books)
+        .filter_map(|book| async move {
+            let url = book.cover_image_url.clone()?;
+            let isbn = isbn_key(book.isbn.as_str());
+            if isbn.is_empty() {
+                debug!(url = %book.source, "no isbn to name the cover after");
+                return None;
+            }
+            Some((book, url, isbn))
+        })
+        .map(|(book, url, isbn)| async move {
+            match download_cover(client, dir, &url, &isbn).await {
+                Ok(path) => {
+                    book.cover_path = Some(path);
+                    true
+                }
+                Err(e) => {
+                    warn!("can't download cover for {}: {e:#}", book.source);
+                    false
+                }
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter(|saved| std::future::ready(*saved))
+        .count()
+        .await;
+    info!(saved, dir = %dir.display(), "covers downloaded");
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn extension_from_content_type_then_path() {
+        assert_eq!(
+            cover_extension("https://x.ru/a.jpg", Some("image/png")),
+            "png"
+        );
+        assert_eq!(
+            cover_extension("https://imo10.labirint.ru/books/1/cover.jpg/484-0", None),
+            "jpg"
+        );
+        assert_eq!(
+            cover_extension("https://i0.wp.com/a/B.WEBP?fit=1", Some("text/plain")),
+            "webp"
+        );
+        assert_eq!(cover_extension("https://x.ru/cover", None), "jpg");
+    }
+
+    #[tokio::test]
+    async fn covers_are_saved_by_isbn() {
+        let server = MockServer::start().await;
+        let image = b"\x89PNG\r\n\x1a\nfake".to_vec();
+        Mock::given(method("GET"))
+            .and(path("/covers/1.png"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "image/png")
+                    .set_body_bytes(image.clone()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/covers/missing.jpg"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let mut with_cover = sample_book(1);
+        with_cover.cover_image_url = Some(format!("{}/covers/1.png", server.uri()));
+        let without_cover = sample_book(2);
+        let mut broken = sample_book(3);
+        broken.cover_image_url = Some(format!("{}/covers/missing.jpg", server.uri()));
+        let mut books = [with_cover, without_cover, broken];
+
+        let dir = std::env::temp_dir().join(format!("bd_parser_covers_{}", std::process::id()));
+        let saved = download_covers(&reqwest::Client::new(), &dir, books.iter_mut(), 4)
+            .await
+            .expect("download");
+        assert_eq!(saved, 1);
+
+        let expected = dir.join("9785926830153.png");
+        assert_eq!(
+            books[0].cover_path.as_deref(),
+            Some(expected.to_string_lossy().as_ref())
+        );
+        assert_eq!(std::fs::read(&expected).expect("cover file"), image);
+        assert_eq!(books[1].cover_path, None);
+        assert_eq!(books[2].cover_path, None);
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -1,8 +1,18 @@
 use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
+use anyhow::{Context, anyhow};
 use reqwest::IntoUrl;
+use tracing::info;
 
-use crate::parse_traits::Book;
+use crate::parse_traits::{Book, PriceFormat};
+use crate::sink::UTF8_BOM;
+
+/// Версия формата выгрузки (`_schema` в JSON, `<stem>.schema.json` рядом с CSV).
+/// Увеличивается при любом изменении набора или смысла колонок и полей `Book`.
+pub const BOOK_SCHEMA_VERSION: u32 = 11;
 
 pub static BOOK_CSV_HEADERS: &[&str] = &[
     "site",
@@ -12,17 +22,153 @@ pub static BOOK_CSV_HEADERS: &[&str] = &[
     "authors",
     "description",
     "price",
+    "about_author",
+    "rating",
+    "review_count",
+    "source_id",
+    "price_max",
+    "price_is_from",
+    "cover_image_url",
+    "cover_path",
+    "availability",
+    "isbn13_group",
+    "requested_url",
+    "original_title",
+    "publisher",
+    "series",
+    "series_index",
+    "language",
+];
+
+/// Дополнительные колонки `--with-flags`: отличают пустое поле от отсутствующего.
+pub static BOOK_CSV_FLAG_HEADERS: &[&str] = &[
+    "has_authors",
+    "has_isbn",
+    "has_title",
+    "has_description",
+    "has_price",
 ];
 
+/// Заголовок CSV: основные колонки и, с `with_flags`, `BOOK_CSV_FLAG_HEADERS`.
+pub fn book_csv_headers(with_flags: bool) -> Vec<&'static str> {
+    let mut headers = BOOK_CSV_HEADERS.to_vec();
+    if with_flags {
+        headers.extend_from_slice(BOOK_CSV_FLAG_HEADERS);
+    }
+    headers
+}
+
+/// Содержимое sidecar-файла схемы для CSV: версия и фактический список колонок.
+pub fn csv_schema(with_flags: bool) -> serde_json::Value {
+    let columns = book_csv_headers(with_flags);
+    serde_json::json!({ "_schema": BOOK_SCHEMA_VERSION, "columns": columns })
+}
+
+pub fn write_csv_schema(path: &std::path::Path, with_flags: bool) -> anyhow::Result<()> {
+    let text = serde_json::to_string_pretty(&csv_schema(with_flags))?;
+    std::fs::write(path, text + "\n")?;
+    Ok(())
+}
+
+/// Строка старой выгрузки в текущем наборе колонок: значения переносятся по имени
+/// колонки, новые колонки остаются пустыми.
+pub fn migrate_record(
+    old_headers: &csv::StringRecord,
+    row: &csv::StringRecord,
+    headers: &[&str],
+) -> csv::StringRecord {
+    headers
+        .iter()
+        .map(|name| {
+            old_headers
+                .iter()
+                .position(|old| old == *name)
+                .and_then(|idx| row.get(idx))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Переписывает `books.csv` прошлых версий в текущую схему перед дозаписью: колонки
+/// добавлялись в конец, и строки старого файла съехали бы относительно новых.
+/// `false`, если файла нет или схема уже текущая. Колонка, которой нет в текущей схеме
+/// (например, `has_*` без `--with-flags`), — ошибка: молча терять данные нельзя.
+/// Оборванная последняя строка отбрасывается, BOM сохраняется.
+pub fn migrate_csv(path: &Path, with_flags: bool) -> anyhow::Result<bool> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e).with_context(|| format!("can't read {}", path.display())),
+    };
+    let complete = data.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    if complete == 0 {
+        return Ok(false);
+    }
+    let bom = data.starts_with(UTF8_BOM.as_bytes());
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(&data[..complete]);
+    let old_headers = rdr.headers()?.clone();
+    let headers = book_csv_headers(with_flags);
+    if old_headers.iter().eq(headers.iter().copied()) {
+        return Ok(false);
+    }
+    if let Some(unknown) = old_headers.iter().find(|old| !headers.contains(old)) {
+        return Err(anyhow!(
+            "{} has unknown column {unknown}, can't migrate it to schema {BOOK_SCHEMA_VERSION}",
+            path.display()
+        ));
+    }
+
+    let tmp = path.with_extension("csv.migrating");
+    let mut rows = 0;
+    {
+        let mut out = BufWriter::new(
+            File::create(&tmp).with_context(|| format!("can't create {}", tmp.display()))?,
+        );
+        if bom {
+            out.write_all(UTF8_BOM.as_bytes())?;
+        }
+        let mut wtr = csv::Writer::from_writer(out);
+        wtr.write_record(&headers)?;
+        for row in rdr.records() {
+            let row = row.with_context(|| format!("bad csv {}", path.display()))?;
+            wtr.write_record(&migrate_record(&old_headers, &row, &headers))?;
+            rows += 1;
+        }
+        wtr.flush()?;
+    }
+    std::fs::rename(&tmp, path).with_context(|| format!("can't replace {}", path.display()))?;
+    info!(
+        path = %path.display(),
+        from_columns = old_headers.len(),
+        to_columns = headers.len(),
+        rows,
+        "migrated csv to current schema"
+    );
+    Ok(true)
+}
+
 pub trait CsvSave {
-    fn write_csv_record<W: std::io::Write>(&self, wtr: &mut csv::Writer<W>) -> csv::Result<()>;
+    /// С `with_flags` после основных колонок пишутся `BOOK_CSV_FLAG_HEADERS`.
+    fn write_csv_record<W: std::io::Write>(
+        &self,
+        wtr: &mut csv::Writer<W>,
+        with_flags: bool,
+        price_format: PriceFormat,
+    ) -> csv::Result<()>;
 }
 
 impl<T> CsvSave for Book<T>
 where
     T: IntoUrl + Into<String> + Display + Clone,
 {
-    fn write_csv_record<W: std::io::Write>(&self, wtr: &mut csv::Writer<W>) -> csv::Result<()> {
+    fn write_csv_record<W: std::io::Write>(
+        &self,
+        wtr: &mut csv::Writer<W>,
+        with_flags: bool,
+        price_format: PriceFormat,
+    ) -> csv::Result<()> {
         let authors_joined = self
             .authors
             .iter()
@@ -30,14 +176,193 @@ where
             .collect::<Vec<_>>()
             .join("; ");
 
-        wtr.write_record(&[
+        let mut record = vec![
             format!("{}", self.site),
             self.source.to_string(),
-            self.isbn.to_string(),
-            self.title.to_string(),
+            self.isbn.as_str().to_string(),
+            self.title.as_str().to_string(),
             authors_joined,
             self.description.as_str().to_string(),
-            self.price.to_string(),
-        ])
+            self.price.format_for(price_format, self.site),
+            self.about_author.clone().unwrap_or_default(),
+            self.rating.map(|r| r.to_string()).unwrap_or_default(),
+            self.review_count.map(|n| n.to_string()).unwrap_or_default(),
+            self.source_id.clone().unwrap_or_default(),
+            self.price_max
+                .map(|max| max.format_for(price_format, self.site))
+                .unwrap_or_default(),
+            self.price_is_from.to_string(),
+            self.cover_image_url.clone().unwrap_or_default(),
+            self.cover_path.clone().unwrap_or_default(),
+            self.availability.map(|a| a.to_string()).unwrap_or_default(),
+            self.isbn13_group().unwrap_or_default(),
+            self.requested_url.clone().unwrap_or_default(),
+            self.original_title
+                .as_ref()
+                .map(|t| t.as_str().to_string())
+                .unwrap_or_default(),
+            self.publisher.clone().unwrap_or_default(),
+            self.series.clone().unwrap_or_default(),
+            self.series_index.map(|n| n.to_string()).unwrap_or_default(),
+            self.language.clone().unwrap_or_default(),
+        ];
+        if with_flags {
+            let flags = [
+                self.authors.iter().any(|a| !a.as_str().is_empty()),
+                !self.isbn.as_str().trim().is_empty(),
+                !self.title.as_str().is_empty(),
+                !self.description.as_str().trim().is_empty(),
+                u128::from(self.price) > 0,
+            ];
+            record.extend(flags.iter().map(bool::to_string));
+        }
+        wtr.write_record(&record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::Description;
+    use crate::sink::tests::sample_book;
+
+    fn record(book: &Book<String>, with_flags: bool) -> csv::StringRecord {
+        record_with(book, with_flags, PriceFormat::Kopecks)
+    }
+
+    fn record_with(
+        book: &Book<String>,
+        with_flags: bool,
+        price_format: PriceFormat,
+    ) -> csv::StringRecord {
+        let mut wtr = csv::Writer::from_writer(vec![]);
+        book.write_csv_record(&mut wtr, with_flags, price_format)
+            .expect("write");
+        let data = wtr.into_inner().expect("flush");
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_slice())
+            .records()
+            .next()
+            .expect("one record")
+            .expect("valid record")
+    }
+
+    #[test]
+    fn empty_description_flagged() {
+        let mut book = sample_book(1);
+        book.description = Description::new("  ".to_string());
+        let row = record(&book, true);
+        assert_eq!(
+            row.len(),
+            BOOK_CSV_HEADERS.len() + BOOK_CSV_FLAG_HEADERS.len()
+        );
+        let flag = |name| {
+            let idx = BOOK_CSV_FLAG_HEADERS
+                .iter()
+                .position(|h| *h == name)
+                .expect(name);
+            row[BOOK_CSV_HEADERS.len() + idx].to_string()
+        };
+        assert_eq!(flag("has_description"), "false");
+        assert_eq!(flag("has_isbn"), "true");
+        assert_eq!(flag("has_price"), "true");
+        assert_eq!(flag("has_authors"), "true");
+    }
+
+    #[test]
+    fn no_flag_columns_by_default() {
+        assert_eq!(record(&sample_book(1), false).len(), BOOK_CSV_HEADERS.len());
+    }
+
+    #[test]
+    fn source_id_column() {
+        let row = record(&sample_book(801841), false);
+        let idx = BOOK_CSV_HEADERS
+            .iter()
+            .position(|h| *h == "source_id")
+            .expect("source_id header");
+        assert_eq!(&row[idx], "801841");
+    }
+
+    #[test]
+    fn human_prices_column() {
+        let idx = BOOK_CSV_HEADERS
+            .iter()
+            .position(|h| *h == "price")
+            .expect("price header");
+        let book = sample_book(1);
+        assert_eq!(&record(&book, false)[idx], "10001");
+        assert_eq!(
+            &record_with(&book, false, PriceFormat::Rubles)[idx],
+            "100,01 ₽"
+        );
+    }
+
+    #[test]
+    fn csv_schema_matches_constant() {
+        let schema = csv_schema(true);
+        assert_eq!(schema["_schema"], BOOK_SCHEMA_VERSION);
+        let columns = schema["columns"].as_array().expect("columns");
+        assert_eq!(
+            columns.len(),
+            BOOK_CSV_HEADERS.len() + BOOK_CSV_FLAG_HEADERS.len()
+        );
+        assert_eq!(columns[0], "site");
+        assert_eq!(
+            csv_schema(false)["columns"]
+                .as_array()
+                .expect("columns")
+                .len(),
+            BOOK_CSV_HEADERS.len()
+        );
+    }
+
+    #[test]
+    fn old_csv_migrated_to_current_schema() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_migrate_{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            format!(
+                "{UTF8_BOM}site,source,isbn,title,authors\n\
+                 labirint,https://www.labirint.ru/books/1/,978-5-9268-3015-3,Джейн Эйр,Бронте Шарлотта\n\
+                 eksmo,https://eksmo.ru/book/idiot/,978-5-04-156838-2,\"Идиот, роман\",Достоевский Ф. М.\n\
+                 labirint,https://www.labirint.ru/books/3/,97"
+            ),
+        )
+        .expect("write v1");
+        assert!(migrate_csv(&path, false).expect("migrate"));
+
+        let text = std::fs::read_to_string(&path).expect("read");
+        assert!(text.starts_with(UTF8_BOM));
+        let mut rdr = csv::Reader::from_path(&path).expect("reader");
+        assert!(
+            rdr.headers()
+                .expect("headers")
+                .iter()
+                .eq(BOOK_CSV_HEADERS.iter().copied())
+        );
+        let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.expect("row")).collect();
+        // Оборванная третья строка не переносится.
+        assert_eq!(rows.len(), 2);
+        let column = |name| {
+            BOOK_CSV_HEADERS
+                .iter()
+                .position(|h| *h == name)
+                .expect(name)
+        };
+        assert_eq!(rows[1].len(), BOOK_CSV_HEADERS.len());
+        assert_eq!(&rows[1][column("title")], "Идиот, роман");
+        assert_eq!(&rows[1][column("authors")], "Достоевский Ф. М.");
+        assert_eq!(&rows[1][column("price")], "");
+        assert_eq!(&rows[1][column("original_title")], "");
+
+        // Файл уже в текущей схеме не трогается; лишние колонки — ошибка.
+        assert!(!migrate_csv(&path, false).expect("current"));
+        assert!(migrate_csv(&path, true).expect("add flags"));
+        assert!(migrate_csv(&path, false).is_err());
+        std::fs::remove_file(&path).expect("cleanup");
+        assert!(!migrate_csv(&path, false).expect("missing file"));
     }
 }
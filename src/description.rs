@@ -0,0 +1,123 @@
+use scraper::ElementRef;
+use tracing::warn;
+
+use crate::parse_traits::Description;
+use crate::selectors;
+
+/// Описание короче стольких символов считается ненайденным: селектор, скорее всего,
+/// попал в соседний блок или в пустую обёртку после редизайна.
+pub const MIN_DESCRIPTION_CHARS: usize = 40;
+
+fn in_chrome(el: ElementRef) -> bool {
+    let chrome = selectors::chrome();
+    chrome.matches(&el)
+        || el
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .any(|a| chrome.matches(&a))
+}
+
+/// Текст блока с одиночными пробелами между словами.
+fn block_text(el: ElementRef) -> String {
+    el.text()
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Самый длинный текст среди `DESCRIPTION_CANDIDATE_SEL_STR` вне навигации и подвала.
+pub fn longest_candidate(ctx: &scraper::Html) -> Option<String> {
+    ctx.select(selectors::description_candidate())
+        .filter(|el| !in_chrome(*el))
+        .map(block_text)
+        .max_by_key(|text| text.chars().count())
+        .filter(|text| !text.is_empty())
+}
+
+/// Запасной путь для `parse_description`: если селектор сайта дал меньше
+/// `MIN_DESCRIPTION_CHARS`, берётся самый длинный блок-кандидат, когда он длиннее.
+pub fn description_fallback(ctx: &scraper::Html, description: Description) -> Description {
+    let found = description.char_len();
+    if found >= MIN_DESCRIPTION_CHARS {
+        return description;
+    }
+    match longest_candidate(ctx).filter(|text| text.chars().count() > found) {
+        Some(text) => {
+            warn!(
+                found,
+                recovered = text.chars().count(),
+                "description selector found too little text, took the longest candidate block"
+            );
+            Description::new(text)
+        }
+        None => description,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REDESIGNED: &str = r#"<html><body>
+<header><div class="description">Книжный магазин с доставкой по всей России и самовывозом</div></header>
+<nav><article><p>Главная / Книги / Художественная литература / Классика / Зарубежная классика</p></article></nav>
+<main>
+  <div class="old-descr"></div>
+  <div itemprop="description">
+    <p>Роман Шарлотты Бронте о сироте, которая становится гувернанткой</p>
+    <p>в поместье Торнфилд-холл и находит там любовь.</p>
+  </div>
+  <article><p>Коротко о книге.</p></article>
+</main>
+<footer><p class="description">© 2024 Все права защищены. Политика конфиденциальности, оферта, контакты и реквизиты.</p></footer>
+</body></html>"#;
+
+    #[test]
+    fn longest_block_outside_chrome_is_recovered() {
+        let html = scraper::Html::parse_document(REDESIGNED);
+        let recovered = description_fallback(&html, Description::new(String::new()));
+        assert_eq!(
+            recovered.as_str(),
+            "Роман Шарлотты Бронте о сироте, которая становится гувернанткой \
+             в поместье Торнфилд-холл и находит там любовь."
+        );
+        // Заглушка короче порога тоже заменяется.
+        let recovered = description_fallback(&html, Description::new("—".to_string()));
+        assert!(recovered.as_str().starts_with("Роман Шарлотты Бронте"));
+    }
+
+    #[test]
+    fn good_primary_is_kept() {
+        let html = scraper::Html::parse_document(REDESIGNED);
+        let primary = "Аннотация, найденная селектором сайта, длиннее порога в сорок символов.";
+        assert_eq!(
+            description_fallback(&html, Description::new(primary.to_string())).as_str(),
+            primary
+        );
+        // Без кандидатов вне оформления остаётся то, что нашёл селектор.
+        let chrome_only = scraper::Html::parse_document(
+            r#"<html><body><footer><div class="description">Подвал сайта с длинным текстом про оферту и доставку</div></footer></body></html>"#,
+        );
+        assert_eq!(
+            description_fallback(&chrome_only, Description::new("Кратко".to_string())).as_str(),
+            "Кратко"
+        );
+    }
+
+    #[tokio::test]
+    async fn site_parser_recovers_description() {
+        use crate::eksmo::EksmoParser;
+        use crate::parse_traits::{BookParser, PageHtml};
+
+        let html = crate::specs::SpecsPage::<EksmoParser>::from_body(REDESIGNED);
+        let descr = EksmoParser
+            .parse_description(&html)
+            .await
+            .expect("description");
+        assert!(
+            descr.as_str().ends_with("находит там любовь."),
+            "{}",
+            descr.as_str()
+        );
+    }
+}
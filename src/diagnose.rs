@@ -0,0 +1,421 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use tracing::warn;
+
+use crate::registry::ParserRegistry;
+
+/// Сколько символов текста первого узла показывать в отчёте.
+const PREVIEW_CHARS: usize = 80;
+/// `--warn-on-empty-selectors`: селектор, не нашедший ничего на такой доле выборки,
+/// помечается — скорее всего, вёрстка сайта поменялась.
+pub const EMPTY_RATIO_WARN: f64 = 0.5;
+
+/// Результат одного селектора на странице.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FieldDiagnosis {
+    pub field: &'static str,
+    pub selector: String,
+    pub matches: usize,
+    /// Текст первого совпавшего узла, схлопнутый в одну строку.
+    pub preview: Option<String>,
+    /// Селектор не компилируется.
+    pub error: Option<String>,
+}
+
+impl FieldDiagnosis {
+    pub fn matched(&self) -> bool {
+        self.matches > 0
+    }
+}
+
+fn preview(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(PREVIEW_CHARS) {
+        Some((idx, _)) => format!("{}…", &collapsed[..idx]),
+        None => collapsed,
+    }
+}
+
+/// Прогоняет селекторы по странице, не собирая `Book`.
+pub fn diagnose_html(
+    html: &scraper::Html,
+    selectors: &[(&'static str, String)],
+) -> Vec<FieldDiagnosis> {
+    selectors
+        .iter()
+        .map(|(field, selector)| {
+            let mut diagnosis = FieldDiagnosis {
+                field,
+                selector: selector.clone(),
+                matches: 0,
+                preview: None,
+                error: None,
+            };
+            match crate::selectors::selector(selector) {
+                Ok(sel) => {
+                    let mut nodes = html.select(&sel);
+                    if let Some(first) = nodes.next() {
+                        diagnosis.preview = Some(preview(&first.text().collect::<String>()));
+                        diagnosis.matches = 1 + nodes.count();
+                    }
+                }
+                Err(e) => diagnosis.error = Some(e.to_string()),
+            }
+            diagnosis
+        })
+        .collect()
+}
+
+pub fn format_report(url: &str, report: &[FieldDiagnosis]) -> String {
+    let mut out = format!("diagnose {url}\n");
+    for d in report {
+        let status = if d.matched() { "OK  " } else { "MISS" };
+        let _ = writeln!(
+            out,
+            "{status} {:<12} {:>3} node(s)  {}",
+            d.field, d.matches, d.selector
+        );
+        if let Some(error) = &d.error {
+            let _ = writeln!(out, "       invalid selector: {error}");
+        }
+        if let Some(preview) = &d.preview {
+            let _ = writeln!(out, "       {preview:?}");
+        }
+    }
+    out
+}
+
+/// Итог `BookParser::extract_fields`: что парсер в итоге достал со страницы.
+pub fn format_fields(fields: &BTreeMap<&'static str, String>) -> String {
+    let mut out = String::from("extracted fields\n");
+    for (field, value) in fields {
+        let _ = writeln!(out, "     {field:<12} {:?}", preview(value));
+    }
+    out
+}
+
+/// Скачивает одну страницу и проверяет на ней селекторы парсера, отвечающего за URL.
+pub async fn diagnose_url(registry: &ParserRegistry, url: &str) -> anyhow::Result<String> {
+    let parser = registry
+        .for_url(url)
+        .ok_or_else(|| anyhow!("no parser registered for url: {url}"))?;
+    let selectors = parser.field_selectors();
+    if selectors.is_empty() {
+        return Err(anyhow!("parser for {} has no selectors", parser.site()));
+    }
+    let client = crate::http::global().build_client()?;
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let report = {
+        let html = scraper::Html::parse_document(&body);
+        format_report(url, &diagnose_html(&html, &selectors))
+    };
+    let fields = parser.extract_fields_dyn(url.to_string(), body).await?;
+    Ok(report + &format_fields(&fields))
+}
+
+/// Селектор поля на выборке страниц сайта: на скольких он не нашёл ни одного узла.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectorAudit {
+    pub site: String,
+    pub field: &'static str,
+    pub selector: String,
+    pub pages: usize,
+    pub empty: usize,
+}
+
+impl SelectorAudit {
+    pub fn empty_ratio(&self) -> f64 {
+        if self.pages == 0 {
+            return 0.0;
+        }
+        self.empty as f64 / self.pages as f64
+    }
+
+    /// Доля пустых страниц не меньше `EMPTY_RATIO_WARN`.
+    pub fn suspicious(&self) -> bool {
+        self.pages > 0 && self.empty_ratio() >= EMPTY_RATIO_WARN
+    }
+}
+
+/// `diagnose_html` по каждой странице выборки одного сайта, сведённый по полям.
+/// Некомпилирующийся селектор считается пустым на всех страницах.
+pub fn audit_pages(
+    site: &str,
+    selectors: &[(&'static str, String)],
+    pages: &[scraper::Html],
+) -> Vec<SelectorAudit> {
+    let mut audit: Vec<SelectorAudit> = selectors
+        .iter()
+        .map(|(field, selector)| SelectorAudit {
+            site: site.to_string(),
+            field,
+            selector: selector.clone(),
+            pages: pages.len(),
+            empty: 0,
+        })
+        .collect();
+    for html in pages {
+        for (entry, diagnosis) in audit.iter_mut().zip(diagnose_html(html, selectors)) {
+            if !diagnosis.matched() {
+                entry.empty += 1;
+            }
+        }
+    }
+    audit
+}
+
+pub fn format_audit(audit: &[SelectorAudit]) -> String {
+    let mut out = String::from("selector audit\n");
+    for a in audit {
+        let status = if a.suspicious() { "WARN" } else { "OK  " };
+        let _ = writeln!(
+            out,
+            "{status} {:<10} {:<12} empty {:>3}/{:<3} ({:>3.0}%)  {}",
+            a.site,
+            a.field,
+            a.empty,
+            a.pages,
+            a.empty_ratio() * 100.0,
+            a.selector
+        );
+    }
+    out
+}
+
+/// `--warn-on-empty-selectors`: загружает выборку страниц (как для обычного прогона)
+/// и сводит по сайтам, какие селекторы ничего не нашли. Страницы, которые не загрузились,
+/// в выборку не входят.
+pub async fn audit_urls(registry: &ParserRegistry, urls: &[String]) -> Vec<SelectorAudit> {
+    // Страницы по сайтам в порядке первого появления сайта в выборке.
+    let mut sample: Vec<(&dyn crate::parse_traits::DynBookParser, Vec<String>)> = vec![];
+    for url in urls {
+        let Some(parser) = registry.for_url(url) else {
+            continue;
+        };
+        let body = match parser.fetch_body_dyn(url.clone()).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(url, "audit: can't fetch page: {e:#}");
+                continue;
+            }
+        };
+        match sample.iter_mut().find(|(p, _)| p.site() == parser.site()) {
+            Some((_, bodies)) => bodies.push(body),
+            None => sample.push((parser, vec![body])),
+        }
+    }
+    sample
+        .into_iter()
+        .flat_map(|(parser, bodies)| {
+            let pages: Vec<scraper::Html> = bodies
+                .iter()
+                .map(|body| scraper::Html::parse_document(body))
+                .collect();
+            audit_pages(
+                &parser.site().to_string(),
+                &parser.field_selectors(),
+                &pages,
+            )
+        })
+        .collect()
+}
+
+/// `--parse-only`: полный разбор одной страницы без sitemap и выгрузки.
+/// Возвращает `Book` pretty-printed JSON.
+pub async fn parse_only(registry: &ParserRegistry, url: &str) -> anyhow::Result<String> {
+    let parser = registry
+        .for_url(url)
+        .ok_or_else(|| anyhow!("no parser registered for url: {url}"))?;
+    let book = parser.parse_book_dyn(url.to_string()).await?;
+    Ok(serde_json::to_string_pretty(&book)?)
+}
+
+/// `--capture-fixture`: скачивает страницу и сохраняет тело байт в байт в `path` —
+/// для новых `page_examples/*.html`, когда сайт меняет вёрстку. Ничего не вырезается
+/// и не перекодируется, чтобы тесты видели ровно то, что отдал сайт. Возвращает размер.
+pub async fn capture_fixture(
+    client: &reqwest::Client,
+    url: &str,
+    path: &Path,
+) -> anyhow::Result<usize> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    std::fs::write(path, &body).with_context(|| format!("can't write {}", path.display()))?;
+    Ok(body.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_parser::{ConfigParser, SitesConfig};
+    use crate::eksmo::EksmoParser;
+    use crate::parse_traits::BookParser;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn report_flags_selector_that_matches_nothing() {
+        let html = scraper::Html::parse_document(include_str!("../page_examples/eksmo.html"));
+        let mut selectors = EksmoParser.field_selectors();
+        selectors[2].1 = "h1.renamed-after-redesign".to_string();
+        selectors.push(("broken", "div[".to_string()));
+
+        let report = diagnose_html(&html, &selectors);
+        let by_field = |field| report.iter().find(|d| d.field == field).expect(field);
+
+        assert_eq!(by_field("authors").matches, 1);
+        assert_eq!(
+            by_field("authors").preview.as_deref(),
+            Some("Андрей Самарин")
+        );
+        assert!(by_field("description").matches > 1);
+        assert!(by_field("price").matched());
+
+        let title = by_field("title");
+        assert!(!title.matched());
+        assert_eq!(title.preview, None);
+        assert!(by_field("broken").error.is_some());
+
+        let text = format_report("https://eksmo.ru/book/1", &report);
+        assert!(text.contains("MISS title"));
+        assert!(text.contains("OK   authors"));
+        assert!(text.contains("invalid selector"));
+    }
+
+    #[test]
+    fn fields_report_lists_values() {
+        let fields = BTreeMap::from([
+            ("isbn", "978-5-04-1".to_string()),
+            ("title", "Т".repeat(100)),
+        ]);
+        let text = format_fields(&fields);
+        assert!(text.contains("isbn         \"978-5-04-1\""));
+        assert!(text.contains("…"));
+    }
+
+    #[test]
+    fn preview_is_collapsed_and_truncated() {
+        assert_eq!(preview("  a\n\n  b  "), "a b");
+        let long = "я".repeat(PREVIEW_CHARS + 5);
+        assert_eq!(preview(&long).chars().count(), PREVIEW_CHARS + 1);
+    }
+
+    #[test]
+    fn audit_computes_empty_ratio_per_field() {
+        let current = include_str!("../page_examples/eksmo.html");
+        // Редизайн: заголовок переехал в другой класс.
+        let redesigned = current.replace("book-page__card-title", "product-header__title");
+        let pages: Vec<scraper::Html> = [current, &redesigned, &redesigned, &redesigned]
+            .iter()
+            .map(|body| scraper::Html::parse_document(body))
+            .collect();
+        let mut selectors = EksmoParser.field_selectors();
+        selectors.push(("broken", "div[".to_string()));
+
+        let audit = audit_pages("eksmo", &selectors, &pages);
+        let by_field = |field| audit.iter().find(|a| a.field == field).expect(field);
+        assert_eq!(by_field("authors").empty, 0);
+        assert!(!by_field("authors").suspicious());
+        let title = by_field("title");
+        assert_eq!((title.empty, title.pages), (3, 4));
+        assert_eq!(title.empty_ratio(), 0.75);
+        assert!(title.suspicious());
+        assert_eq!(by_field("broken").empty_ratio(), 1.0);
+
+        let text = format_audit(&audit);
+        assert!(text.contains("WARN eksmo      title        empty   3/4   ( 75%)"));
+        assert!(text.contains("OK   eksmo      authors      empty   0/4   (  0%)"));
+        assert!(
+            audit_pages("eksmo", &selectors, &[])
+                .iter()
+                .all(|a| !a.suspicious())
+        );
+    }
+
+    #[tokio::test]
+    async fn parse_only_prints_book_from_one_page() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/book/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string(include_str!("../page_examples/eksmo.html")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let config = SitesConfig::from_toml(
+            r#"
+            [[site]]
+            name = "mock_eksmo"
+            host = "127.0.0.1"
+            [site.selectors]
+            authors = ".book-page__card-author-link"
+            isbn = "span.copy__val"
+            title = ".book-page__card-title"
+            description = "div.spoiler__text.t.t_last-p-no-offset.book-page__card-description-text p"
+            price = "div.price-insert__price"
+            "#,
+        )
+        .expect("config");
+        let mut registry = ParserRegistry::default();
+        registry.register(
+            ConfigParser::new(config.sites.into_iter().next().expect("site")).expect("parser"),
+        );
+
+        let url = format!("{}/book/1", server.uri());
+        let json = parse_only(&registry, &url).await.expect("book");
+        let book: serde_json::Value = serde_json::from_str(&json).expect("json");
+        assert_eq!(book["isbn"], "978-5-04-156838-2");
+        assert_eq!(book["source"], url);
+        assert_eq!(book["authors"][0], "Андрей Самарин");
+        assert!(json.contains("\n  \"title\""), "pretty-printed: {json}");
+
+        assert!(
+            parse_only(&registry, "https://example.com/1")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn capture_fixture_saves_body_verbatim() {
+        // Невалидный UTF-8, `\r\n` и пробелы по краям должны дойти до файла как есть.
+        let body = b"  <html>\r\n<script>track()</script>\xff\xfe</html>\n\n".to_vec();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/book/1"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body.clone()))
+            .mount(&server)
+            .await;
+        let target =
+            std::env::temp_dir().join(format!("bd_parser_fixture_{}.html", std::process::id()));
+        let client = reqwest::Client::new();
+
+        let url = format!("{}/book/1", server.uri());
+        let written = capture_fixture(&client, &url, &target)
+            .await
+            .expect("capture");
+        assert_eq!(written, body.len());
+        assert_eq!(std::fs::read(&target).expect("read"), body);
+        std::fs::remove_file(&target).expect("cleanup");
+
+        let missing = format!("{}/book/2", server.uri());
+        assert!(capture_fixture(&client, &missing, &target).await.is_err());
+        assert!(!target.exists());
+    }
+}
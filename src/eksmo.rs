@@ -1,58 +1,143 @@
-use std::{sync::OnceLock, time::Duration};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
 use anyhow::anyhow;
 use tracing::{debug, instrument, warn};
 
-use crate::parse_traits::{Author, BookParser, Description, Isbn, Price, Sites, Title};
+use crate::description::description_fallback;
+use crate::json_ld::{authors_fallback, page_availability, price_fallback, title_fallback};
+use crate::parse_traits::{
+    Availability, BookParser, Description, Isbn, Price, Sites, Title, absolute_image_url,
+    book_page_signals, host_of, isbn_fallback, normalize_ws, parse_count_text, parse_rating_text,
+    path_segments, split_authors,
+};
+use crate::selectors::{SelectorChain, spec_table};
+use crate::specs::{SpecsPage, SpecsTable};
+use crate::{http, page_cache};
 
-static AUTHOR_SEL_STR: &str = ".book-page__card-author-link";
-static ISBN_SEL_STR: &str = "span.copy__val";
-static TITLE_SEL_STR: &str = ".book-page__card-title";
-static DESCR_SEL_STR: &str =
+pub static AUTHOR_SEL_STR: &str = ".book-page__card-author-link";
+pub static ISBN_SEL_STR: &str = "span.copy__val";
+pub static TITLE_SEL_STR: &str = ".book-page__card-title";
+pub static DESCR_SEL_STR: &str =
     "div.spoiler__text.t.t_last-p-no-offset.book-page__card-description-text p";
-static PRICE_SEL_STR: &str = "div.price-insert__price";
+pub static PRICE_SEL_STR: &str = "div.price-insert__price";
+pub static RATING_SEL_STR: &str = ".livelib-rating__avg-value";
+pub static REVIEW_COUNT_SEL_STR: &str =
+    ".book-page__card-control-reviews .book-page__card-control-a";
+pub static SECTION_SEL_STR: &str = "section.book-page__section";
+pub static SECTION_TITLE_SEL_STR: &str = ".book-page__section-title";
+pub static SECTION_TEXT_SEL_STR: &str = ".spoiler__text p, .spoiler__text";
+/// Строка блока характеристик и её подпись («ISBN:», «Оригинальное название:»).
+pub static SPEC_ROW_SEL_STR: &str = ".book-page__card-prop";
+pub static SPEC_LABEL_SEL_STR: &str = ".book-page__card-prop-name";
+/// Заголовок секции с биографией; у аннотации заголовок «О книге».
+pub static ABOUT_AUTHOR_TITLE: &str = "Об авторе";
+
+/// Цепочки полей (см. `SelectorChain`): `*_SEL_STR` — текущая вёрстка, дальше — тот же
+/// блок без класса элемента внутри: переименование ссылки или смена тега не ломает поле.
+pub static AUTHOR_SELECTORS: SelectorChain =
+    SelectorChain::new("authors", &[AUTHOR_SEL_STR, ".book-page__card-author a"]);
+pub static TITLE_SELECTORS: SelectorChain =
+    SelectorChain::new("title", &[TITLE_SEL_STR, ".book-page__card h1"]);
+pub static PRICE_SELECTORS: SelectorChain =
+    SelectorChain::new("price", &[PRICE_SEL_STR, ".price-insert__price"]);
 
 static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-static AUTHOR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SPEC_ROW_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SPEC_LABEL_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static ISBN_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static TITLE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static DESCR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static PRICE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static RATING_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static REVIEW_COUNT_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SECTION_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SECTION_TITLE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SECTION_TEXT_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+
+/// Цена из разметки страницы; без JSON-LD запасного пути (см. `parse_price`).
+fn price_from_selector(ctx: &scraper::Html) -> anyhow::Result<Price> {
+    let mut price: String = PRICE_SELECTORS
+        .select(ctx)
+        .iter()
+        .take(1)
+        .map(|node| {
+            node.text()
+                .collect::<String>()
+                .replace(Sites::Eksmo.currency().symbol(), "")
+        })
+        .collect();
+    price = price.trim().to_string();
+    price.push_str("00");
+    debug!(price);
+    let price: Price = match price.parse() {
+        Ok(price) => price,
+        Err(e) => {
+            warn!("can't parse price {e}");
+            return Err(e);
+        }
+    };
+    Ok(price)
+}
+
+/// Таблица характеристик страницы целиком: подпись строки → значение (см. `Specs`).
+pub fn parse_specs(ctx: &scraper::Html) -> HashMap<String, String> {
+    let row = SPEC_ROW_SEL
+        .get_or_init(|| scraper::Selector::parse(SPEC_ROW_SEL_STR).expect("spec row selector"));
+    let label = SPEC_LABEL_SEL
+        .get_or_init(|| scraper::Selector::parse(SPEC_LABEL_SEL_STR).expect("spec label selector"));
+    spec_table(ctx, row, label)
+}
+
 pub struct EksmoParser;
+
+impl SpecsTable for EksmoParser {
+    fn parse_specs(html: &scraper::Html) -> HashMap<String, String> {
+        parse_specs(html)
+    }
+}
+
 impl BookParser for EksmoParser {
-    const SITE: crate::parse_traits::Sites = Sites::Eksmo;
+    fn site(&self) -> Sites {
+        Sites::Eksmo
+    }
+
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("authors", AUTHOR_SEL_STR),
+            ("isbn", ISBN_SEL_STR),
+            ("title", TITLE_SEL_STR),
+            ("description", DESCR_SEL_STR),
+            ("price", PRICE_SEL_STR),
+            ("rating", RATING_SEL_STR),
+            ("review_count", REVIEW_COUNT_SEL_STR),
+            ("about_author", SECTION_SEL_STR),
+        ]
+        .into_iter()
+        .map(|(field, sel)| (field, sel.to_string()))
+        .collect()
+    }
 
+    /// `/book/<slug>/`; каталог, серии и авторы лежат под другими префиксами.
+    fn is_book_url(url: &str) -> bool {
+        Sites::Eksmo.matches_host(&host_of(url).unwrap_or_default())
+            && path_segments(url)
+                .is_some_and(|path| matches!(path.as_slice(), [book, _] if book == "book"))
+    }
     type Url = String;
 
-    type Context = scraper::Html;
+    type Context = SpecsPage<EksmoParser>;
+
+    fn looks_like_book_page(ctx: &Self::Context) -> bool {
+        let isbn =
+            ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
+        book_page_signals(ctx, PRICE_SELECTORS.any(), isbn)
+    }
 
     #[instrument(skip(self, url))]
-    async fn fetch(&self, url: &Self::Url) -> anyhow::Result<Self::Context> {
-        let client = CLIENT.get_or_init(|| {
-            reqwest::Client::builder()
-                .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-                .connect_timeout(Duration::from_secs(5))
-                .timeout(Duration::from_secs(15))
-                .pool_max_idle_per_host(4)
-                .tcp_keepalive(Some(Duration::from_secs(30)))
-                .redirect(reqwest::redirect::Policy::limited(5))
-                .build()
-                .expect("http client")
-        });
-        match client.get(url).send().await {
-            Ok(response) if !response.status().is_success() => {
-                warn!(
-                    "bad status code probably rate limit code: {}",
-                    response.status()
-                );
-                return Err(anyhow!("response status is not success"));
-            }
-            Ok(response) => {
-                let resp = response.text().await?;
-                Ok(scraper::Html::parse_document(&resp))
-            }
-            Err(e) => return Err(e.into()),
-        }
+    async fn fetch_body(&self, url: &Self::Url) -> anyhow::Result<String> {
+        let client = CLIENT.get_or_init(|| http::global().build_client().expect("http client"));
+        let body = http::fetch_page(client, BookParser::site(self), url).await?;
+        page_cache::observe(url, &body);
+        Ok(body)
     }
     #[instrument(skip(self, ctx, _log_url))]
     async fn parse_authors(
@@ -60,13 +145,14 @@ impl BookParser for EksmoParser {
         ctx: &Self::Context,
         _log_url: &Self::Url,
     ) -> anyhow::Result<Vec<crate::parse_traits::Author>> {
-        let author_selector = AUTHOR_SEL
-            .get_or_init(|| scraper::Selector::parse(AUTHOR_SEL_STR).expect("author selector"));
-
-        Ok(ctx
-            .select(author_selector)
-            .map(|node| Author::new(node.text().collect::<String>()))
-            .collect())
+        Ok(authors_fallback(
+            ctx,
+            AUTHOR_SELECTORS
+                .select(ctx)
+                .iter()
+                .flat_map(|node| split_authors(&node.text().collect::<String>()))
+                .collect(),
+        ))
     }
 
     #[instrument(skip(self, ctx, _log_url))]
@@ -78,22 +164,33 @@ impl BookParser for EksmoParser {
         let isbn_selector =
             ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
 
-        match ctx.select(isbn_selector).next_back() {
-            Some(elem) => {
-                let raw: String = elem.text().collect::<String>().replace("\u{a0}", "");
-                match Isbn::try_from(raw) {
-                    Ok(isbn) => Ok(isbn),
-                    Err(e) => {
-                        warn!("can't parse isbn:{e}");
-                        Err(anyhow!("can't parse isbn"))
+        // `copy__val` — любое копируемое значение карточки: ISBN, артикул, код товара.
+        // Берётся первое, чья контрольная сумма сходится, а не последнее по порядку.
+        let mut candidates = ctx.select(isbn_selector).peekable();
+        let structured = if candidates.peek().is_none() {
+            warn!(target: "time","ISBN not found on page{_log_url}");
+            Err(anyhow!("can't find isbn on this page"))
+        } else {
+            candidates
+                .find_map(|elem| {
+                    let raw: String = elem.text().collect::<String>().replace("\u{a0}", "");
+                    match Isbn::try_from(raw.clone()) {
+                        Ok(isbn) if isbn.checksum_ok() => Some(isbn),
+                        Ok(_) => {
+                            warn!(raw, "copy__val is not an isbn: checksum mismatch");
+                            None
+                        }
+                        Err(e) => {
+                            warn!("can't parse isbn:{e}");
+                            None
+                        }
                     }
-                }
-            }
-            None => {
-                warn!(target: "time","ISBN not found on page{_log_url}");
-                Err(anyhow!("can't find isbn on this page"))
-            }
-        }
+                })
+                .ok_or_else(|| anyhow!("can't parse isbn"))
+        };
+        structured
+            .or_else(|e| ctx.specs().isbn().ok_or(e))
+            .or_else(|e| isbn_fallback(ctx, e))
     }
 
     #[instrument(skip(self, ctx, _log_url))]
@@ -102,14 +199,12 @@ impl BookParser for EksmoParser {
         ctx: &Self::Context,
         _log_url: &Self::Url,
     ) -> anyhow::Result<crate::parse_traits::Title> {
-        let book_title_selector = TITLE_SEL
-            .get_or_init(|| scraper::Selector::parse(TITLE_SEL_STR).expect("title selector"));
-        let title = {
-            ctx.select(book_title_selector)
-                .map(|node| node.text().collect::<String>())
-                .collect::<String>()
-        };
-        Ok(Title::new(title))
+        let title = TITLE_SELECTORS
+            .select(ctx)
+            .iter()
+            .map(|node| node.text().collect::<String>())
+            .collect::<String>();
+        Ok(title_fallback(ctx, Title::new(title)))
     }
 
     #[instrument(skip(self, ctx))]
@@ -124,28 +219,84 @@ impl BookParser for EksmoParser {
             .map(|p| p.text().collect::<String>())
             .collect::<Vec<_>>()
             .join("\n");
-        Ok(Description::new(descr))
+        Ok(description_fallback(ctx, Description::new(descr)))
     }
     #[instrument(skip(self, ctx))]
     async fn parse_price(&self, ctx: &Self::Context) -> anyhow::Result<crate::parse_traits::Price> {
-        let price_descr_sel =
-            PRICE_SEL.get_or_init(|| scraper::Selector::parse(PRICE_SEL_STR).expect("should be"));
-        let mut price: String = ctx
-            .select(price_descr_sel)
-            .take(1)
-            .map(|node| node.text().collect::<String>().replace("₽", ""))
-            .collect();
-        price = price.trim().to_string();
-        price.push_str("00");
-        debug!(price);
-        let price: Price = match price.parse() {
-            Ok(price) => price,
-            Err(e) => {
-                warn!("can't parse price {e}");
-                return Err(e);
-            }
+        price_fallback(ctx, price_from_selector(ctx))
+    }
+    /// Текст секции «Об авторе». Ищется по заголовку секции, а не по классам текста:
+    /// у аннотации и биографии одинаковая разметка спойлера.
+    async fn parse_about_author(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        let section_sel = SECTION_SEL
+            .get_or_init(|| scraper::Selector::parse(SECTION_SEL_STR).expect("section selector"));
+        let title_sel = SECTION_TITLE_SEL.get_or_init(|| {
+            scraper::Selector::parse(SECTION_TITLE_SEL_STR).expect("section title selector")
+        });
+        let text_sel = SECTION_TEXT_SEL.get_or_init(|| {
+            scraper::Selector::parse(SECTION_TEXT_SEL_STR).expect("section text selector")
+        });
+        let Some(section) = ctx.select(section_sel).find(|section| {
+            section
+                .select(title_sel)
+                .next()
+                .is_some_and(|t| t.text().collect::<String>().trim() == ABOUT_AUTHOR_TITLE)
+        }) else {
+            debug!("no about author section");
+            return Ok(None);
         };
-        Ok(price)
+        // `.spoiler__text` без `<p>` совпадает сам; с `<p>` берём только абзацы.
+        let texts: Vec<String> = section
+            .select(text_sel)
+            .filter(|node| node.value().name() == "p" || node.select(text_sel).next().is_none())
+            .map(|node| node.text().collect::<String>().trim().to_string())
+            .filter(|text| !text.is_empty())
+            .collect();
+        Ok((!texts.is_empty()).then(|| texts.join("\n")))
+    }
+    async fn parse_rating(&self, ctx: &Self::Context) -> anyhow::Result<Option<f32>> {
+        let rating_sel = RATING_SEL
+            .get_or_init(|| scraper::Selector::parse(RATING_SEL_STR).expect("rating selector"));
+        Ok(ctx
+            .select(rating_sel)
+            .next()
+            .and_then(|node| parse_rating_text(&node.text().collect::<String>())))
+    }
+    async fn parse_review_count(&self, ctx: &Self::Context) -> anyhow::Result<Option<u32>> {
+        let count_sel = REVIEW_COUNT_SEL.get_or_init(|| {
+            scraper::Selector::parse(REVIEW_COUNT_SEL_STR).expect("review count selector")
+        });
+        Ok(ctx
+            .select(count_sel)
+            .next()
+            .and_then(|node| parse_count_text(&node.text().collect::<String>())))
+    }
+    /// Обложка из `og:image`.
+    async fn parse_cover_url(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(crate::selectors::meta_content(ctx, "og:image")
+            .as_deref()
+            .and_then(absolute_image_url))
+    }
+    async fn parse_availability(
+        &self,
+        ctx: &Self::Context,
+    ) -> anyhow::Result<Option<Availability>> {
+        Ok(page_availability(ctx))
+    }
+    async fn parse_original_title(&self, ctx: &Self::Context) -> anyhow::Result<Option<Title>> {
+        Ok(ctx.specs().original_title())
+    }
+    async fn parse_publisher(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().publisher().map(normalize_ws))
+    }
+    async fn parse_series(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().series())
+    }
+    async fn parse_language(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().language())
+    }
+    async fn parse_series_index(&self, ctx: &Self::Context) -> anyhow::Result<Option<u16>> {
+        Ok(ctx.specs().series_index())
     }
     // #[instrument(skip(self),fields(url=&url))]
     // async fn parse_book(
@@ -174,6 +325,10 @@ impl BookParser for EksmoParser {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse_traits::PageHtml;
+    use crate::specs::Specs;
+
+    type Page = SpecsPage<EksmoParser>;
 
     const EXPECTED_AUTHOR: &str = "Андрей Самарин";
     const EXPECTED_ISBN: &str = "978-5-04-156838-2";
@@ -200,9 +355,139 @@ mod tests {
 
 В книге "Структура таланта" художник Андрей Самарин исследует внутренний мир творцов, особенности их мышления и подхода к искусству. Автор раскрывает, как сочетание уникального восприятия, дисциплины, смелости и внутренней честности формирует путь к успеху. Вместе с ним вы разберете, что такое талант, с точки зрения когнитивного навыка. Вы разоблачите мифы и иллюзии, связанные с творческими профессиями. В практической части на примере рисования автор расскажет, какой подход в обучении по-настоящему эффективен и какие существуют неочевидные, но ключевые нюансы, о которых не говорят в традиционных программах. Вы поговорите об искусстве, мастерстве и творчестве, их месте на рынке в условиях инклюзивного тренда, а также о влиянии ИИ на развитие современного художника и других факторах, определяющих его новую роль."###;
     const EXPECTED_PRICE: u128 = 146900;
-    fn get_context() -> scraper::Html {
+    fn get_context() -> Page {
         let context = include_str!("../page_examples/eksmo.html");
-        scraper::Html::parse_document(context)
+        Page::from_body(context)
+    }
+
+    /// Сохранённая страница без биографии, дополненная секцией «Об авторе»
+    /// с той же разметкой спойлера, что и у аннотации.
+    fn get_context_with_about_author() -> Page {
+        let page = include_str!("../page_examples/eksmo.html");
+        let about = r#"<section class="book-page__section container container_xs-sm"> <h2 class="book-page__section-title"> Об авторе </h2> <div class="spoiler"> <div class="spoiler__text t t_last-p-no-offset"> <p>Андрей Самарин — художник и педагог.</p> <p>Основатель студии рисования.</p> </div> </div> </section>"#;
+        let anchor = r#"<section class="book-page__section container container_xs-sm">"#;
+        assert!(page.contains(anchor));
+        Page::from_body(&page.replacen(anchor, &format!("{about}{anchor}"), 1))
+    }
+
+    #[test]
+    fn specs_table_and_derived_fields() {
+        let table = parse_specs(&get_context());
+        assert_eq!(
+            table.get("Издательство").map(String::as_str),
+            Some("БОМБОРА")
+        );
+        assert_eq!(table.get("Кол-во страниц").map(String::as_str), Some("336"));
+        let specs = Specs::from(table);
+        assert_eq!(
+            specs.isbn().map(|isbn| isbn.as_str().to_string()),
+            Some(EXPECTED_ISBN.to_string())
+        );
+        assert_eq!(specs.publisher(), Some("БОМБОРА"));
+    }
+
+    #[tokio::test]
+    async fn fields_fall_back_to_next_selector() {
+        // Классы ссылки автора, заголовка и цены сменились, блоки карточки остались.
+        let ctx = Page::from_body(
+            r#"<div class="book-page__card"><h1 class="card-title-new">Джейн Эйр</h1>
+            <div class="book-page__card-author"><a href="/author/bronte/">Шарлотта Бронте</a></div>
+            <span class="price-insert__price">299 ₽</span></div>"#,
+        );
+        let url = "https://eksmo.ru/book/example".to_string();
+        let title = EksmoParser.parse_title(&ctx, &url).await.expect("title");
+        assert_eq!(title.as_str(), "Джейн Эйр");
+        let authors = EksmoParser
+            .parse_authors(&ctx, &url)
+            .await
+            .expect("authors");
+        assert_eq!(authors[0].as_str(), "Шарлотта Бронте");
+        let price = EksmoParser.parse_price(&ctx).await.expect("price");
+        assert_eq!(u128::from(price), 29_900);
+    }
+
+    #[test]
+    fn book_page_vs_series_page() {
+        assert!(EksmoParser::looks_like_book_page(&get_context()));
+        let series = Page::from_body(
+            r#"<main class="series-page"><h1 class="series-page__title">Серия «Pocket book»</h1>
+            <div class="book-card"><a class="book-card__name" href="/book/dzheyn-eyr-ITD000000000111111/">Джейн Эйр</a>
+            <div class="book-card__price">299 ₽</div></div>
+            <div class="book-card"><a class="book-card__name" href="/book/grozovoy-pereval-ITD000000000222222/">Грозовой перевал</a>
+            <div class="book-card__price">319 ₽</div></div></main>"#,
+        );
+        assert!(!EksmoParser::looks_like_book_page(&series));
+    }
+
+    #[tokio::test]
+    async fn publisher_from_specs() {
+        let publisher = EksmoParser
+            .parse_publisher(&get_context())
+            .await
+            .expect("no error");
+        assert_eq!(publisher.as_deref(), Some("БОМБОРА"));
+    }
+
+    #[tokio::test]
+    async fn series_from_specs() {
+        let series = EksmoParser
+            .parse_series(&get_context())
+            .await
+            .expect("no error");
+        assert_eq!(series.as_deref(), Some("Подарочные издания. Рисование"));
+    }
+
+    #[tokio::test]
+    async fn original_title_absent_on_domestic_book() {
+        let original = EksmoParser
+            .parse_original_title(&get_context())
+            .await
+            .expect("no error");
+        assert_eq!(original, None);
+    }
+
+    #[tokio::test]
+    async fn original_title_of_translated_book() {
+        let ctx = Page::from_body(
+            r#"<div class="book-page__card-props">
+            <div class="book-page__card-prop"><span class="book-page__card-prop-name">ISBN: </span><span class="copy__val">978-5-04-116618-2</span></div>
+            <div class="book-page__card-prop"><span class="book-page__card-prop-name">Оригинальное название: </span>Jane Eyre</div>
+            </div>"#,
+        );
+        let original = EksmoParser
+            .parse_original_title(&ctx)
+            .await
+            .expect("no error")
+            .expect("original title");
+        assert_eq!(original.as_str(), "Jane Eyre");
+    }
+
+    #[tokio::test]
+    async fn about_author_absent_on_saved_page() {
+        let about = EksmoParser
+            .parse_about_author(&get_context())
+            .await
+            .expect("no error");
+        assert_eq!(about, None);
+    }
+
+    #[tokio::test]
+    async fn about_author_and_description_do_not_bleed() {
+        let parser = EksmoParser;
+        let ctx = get_context_with_about_author();
+        let about = parser
+            .parse_about_author(&ctx)
+            .await
+            .expect("no error")
+            .expect("about author");
+        assert_eq!(
+            about,
+            "Андрей Самарин — художник и педагог.\nОснователь студии рисования."
+        );
+        assert!(!about.contains("феномен «таланта»"));
+        let descr = parser.parse_description(&ctx).await.expect("description");
+        assert!(descr.as_str().starts_with("Книга, которая поможет"));
+        assert!(!descr.as_str().contains("художник и педагог"));
     }
 
     #[tokio::test]
@@ -218,6 +503,47 @@ mod tests {
         assert_eq!(authors[0].as_str(), EXPECTED_AUTHOR);
     }
 
+    #[tokio::test]
+    async fn parse_isbn_falls_back_to_page_text() {
+        let html = Page::from_body(
+            r#"<html><body><div class="book-page__card-props"><p>Код: 978-5-04-156838-3</p>
+            <p>ISBN 978-5-04-156838-2</p></div></body></html>"#,
+        );
+        let url = "https://eksmo.ru/book/example".to_string();
+        let isbn = EksmoParser
+            .parse_isbn(&html, &url)
+            .await
+            .expect("isbn from text");
+        assert_eq!(isbn.as_str(), EXPECTED_ISBN);
+    }
+
+    #[tokio::test]
+    async fn parse_isbn_skips_copy_val_that_is_not_isbn() {
+        let html = Page::from_body(
+            r#"<div class="book-page__card-props">
+            <div class="book-page__card-prop"><span class="book-page__card-prop-name">ISBN: </span><span class="copy__val">978-5-04-156838-2</span></div>
+            <div class="book-page__card-prop"><span class="book-page__card-prop-name">Артикул: </span><span class="copy__val">978-5-04-156838-3</span></div>
+            <div class="book-page__card-prop"><span class="book-page__card-prop-name">Код товара: </span><span class="copy__val">100312345678</span></div>
+            </div>"#,
+        );
+        let url = "https://eksmo.ru/book/example".to_string();
+        let isbn = EksmoParser.parse_isbn(&html, &url).await.expect("isbn");
+        assert_eq!(isbn.as_str(), EXPECTED_ISBN);
+    }
+
+    #[tokio::test]
+    async fn parse_isbn_fails_without_valid_isbn() {
+        let html = Page::from_body(
+            "<html><body><p>Код: 978-5-04-156838-3</p><p>Тел. 8 800 555 35 35</p></body></html>",
+        );
+        let url = "https://eksmo.ru/book/example".to_string();
+        let err = EksmoParser
+            .parse_isbn(&html, &url)
+            .await
+            .expect_err("no valid isbn");
+        assert_eq!(err.to_string(), "can't find isbn on this page");
+    }
+
     #[tokio::test]
     async fn parse_isbn_ok() {
         let parser = EksmoParser;
@@ -262,12 +588,63 @@ mod tests {
         let price = parser.parse_price(&ctx).await.expect("must be");
         assert_eq!(u128::from(price), EXPECTED_PRICE)
     }
+    #[test]
+    fn book_urls_are_recognized() {
+        assert!(EksmoParser::is_book_url(
+            "https://eksmo.ru/book/dzheyn-eyr-ITD1131209/"
+        ));
+        for url in [
+            "https://eksmo.ru/catalog/khudozhestvennaya-literatura/",
+            "https://eksmo.ru/series/pocket-book/",
+            "https://eksmo.ru/book/",
+            "https://eksmo.ru/",
+            "https://igraslov.store/book/dzheyn-eyr/",
+        ] {
+            assert!(!EksmoParser::is_book_url(url), "{url}");
+        }
+    }
+    #[tokio::test]
+    async fn cover_url_from_og_image() {
+        let parser = EksmoParser;
+        assert_eq!(
+            parser
+                .parse_cover_url(&get_context())
+                .await
+                .expect("cover")
+                .as_deref(),
+            Some("https://eksmo.ru/upload/books_sn_images/ITD000000001223515-fb.jpg")
+        );
+    }
+    #[tokio::test]
+    async fn unrated_book_has_no_rating() {
+        let parser = EksmoParser;
+        let ctx = get_context();
+        assert_eq!(parser.parse_rating(&ctx).await.expect("rating"), None);
+        assert_eq!(
+            parser.parse_review_count(&ctx).await.expect("reviews"),
+            Some(0)
+        );
+    }
     #[tokio::test]
     async fn parse_isbn_not_found() {
         let parser = EksmoParser;
-        let empty_ctx = scraper::Html::parse_document("<html><body></body></html>");
+        let empty_ctx = Page::from_body("<html><body></body></html>");
         let url = "https://eksmo.ru/book/example".to_string();
         let res = parser.parse_isbn(&empty_ctx, &url).await;
         assert!(res.is_err());
     }
+    #[tokio::test]
+    async fn parse_fields_matches_per_field() {
+        let parser = EksmoParser;
+        let ctx = get_context();
+        let url = "https://eksmo.ru/book/example".to_string();
+        let fields = parser.parse_fields(&ctx, &url).await.expect("fields");
+        let authors = parser.parse_authors(&ctx, &url).await.expect("authors");
+        let descr = parser.parse_description(&ctx).await.expect("descr");
+        assert_eq!(fields.authors, authors);
+        assert_eq!(fields.isbn.as_str(), EXPECTED_ISBN);
+        assert_eq!(fields.title.as_str(), EXPECTED_TITLE);
+        assert_eq!(fields.description.as_str(), descr.as_str());
+        assert_eq!(u128::from(fields.price), EXPECTED_PRICE);
+    }
 }
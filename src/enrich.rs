@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{debug, info, instrument};
+
+use crate::parse_traits::{Author, Book, Title};
+use crate::post_parse::PostParse;
+
+pub static OPENLIBRARY_BASE_URL: &str = "https://openlibrary.org";
+/// Open Library просит не больше ~3 запросов в секунду с указанным User-Agent.
+const OPENLIBRARY_MIN_INTERVAL: Duration = Duration::from_millis(350);
+
+static ENRICHER: OnceLock<Enricher> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+struct OlAuthor {
+    name: String,
+}
+
+/// Запись из `/api/books?jscmd=data`; нужны только поля, которые мы умеем дополнять.
+#[derive(Debug, Deserialize)]
+struct OlBook {
+    title: Option<String>,
+    #[serde(default)]
+    authors: Vec<OlAuthor>,
+}
+
+/// Дополняет книги данными Open Library по ISBN, не чаще одного запроса в `min_interval`.
+pub struct Enricher {
+    client: reqwest::Client,
+    base_url: String,
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl Enricher {
+    pub fn new(base_url: impl Into<String>, min_interval: Duration) -> anyhow::Result<Self> {
+        let client = crate::http::global()
+            .client_builder()?
+            .user_agent(concat!("bd_parser/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+        Ok(Self {
+            client,
+            base_url: base_url.into(),
+            min_interval,
+            last_request: Mutex::new(None),
+        })
+    }
+
+    async fn wait_turn(&self) {
+        let mut last = self.last_request.lock().await;
+        if let Some(prev) = *last {
+            tokio::time::sleep_until(prev + self.min_interval).await;
+        }
+        *last = Some(Instant::now());
+    }
+
+    /// Заполняет только пустые поля книги; непустые не трогает.
+    #[instrument(skip(self, book), fields(url=%book.source))]
+    pub async fn enrich(&self, book: &mut Book<String>) -> anyhow::Result<()> {
+        if !book.authors.is_empty() && !book.title.as_str().is_empty() {
+            return Ok(());
+        }
+        let isbn: String = book
+            .isbn
+            .as_str()
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == 'X')
+            .collect();
+        let bibkey = format!("ISBN:{isbn}");
+
+        self.wait_turn().await;
+        let mut found: HashMap<String, OlBook> = self
+            .client
+            .get(format!("{}/api/books", self.base_url))
+            .query(&[
+                ("bibkeys", bibkey.as_str()),
+                ("format", "json"),
+                ("jscmd", "data"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let ol_book = found
+            .remove(&bibkey)
+            .ok_or_else(|| anyhow!("isbn {isbn} not found in Open Library"))?;
+        debug!(?ol_book);
+
+        if book.authors.is_empty() && !ol_book.authors.is_empty() {
+            book.authors = ol_book
+                .authors
+                .into_iter()
+                .map(|a| Author::new(a.name))
+                .collect();
+            info!("authors filled from Open Library");
+        }
+        if book.title.as_str().is_empty()
+            && let Some(title) = ol_book.title
+        {
+            book.title = Title::new(title);
+            info!("title filled from Open Library");
+        }
+        Ok(())
+    }
+}
+
+/// Дополняет книгу из Open Library через общий для процесса `Enricher`.
+pub async fn enrich_from_openlibrary(book: &mut Book<String>) -> anyhow::Result<()> {
+    let enricher = match ENRICHER.get() {
+        Some(enricher) => enricher,
+        None => {
+            let enricher = Enricher::new(OPENLIBRARY_BASE_URL, OPENLIBRARY_MIN_INTERVAL)?;
+            ENRICHER.get_or_init(|| enricher)
+        }
+    };
+    enricher.enrich(book).await
+}
+
+/// `--enrich` как хук `PostParse`: `enrich_from_openlibrary` для каждой книги.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenLibraryHook;
+
+impl PostParse for OpenLibraryHook {
+    fn name(&self) -> &'static str {
+        "openlibrary"
+    }
+
+    async fn process(&self, book: &mut Book<String>) -> anyhow::Result<()> {
+        enrich_from_openlibrary(book).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const OL_RESPONSE: &str = r#"{
+        "ISBN:9785926830153": {
+            "title": "Jane Eyre",
+            "authors": [{"url": "https://openlibrary.org/authors/OL1A", "name": "Charlotte Brontë"}]
+        }
+    }"#;
+
+    async fn mock_openlibrary() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/books"))
+            .and(query_param("bibkeys", "ISBN:9785926830153"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(OL_RESPONSE, "application/json"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn fills_missing_author() {
+        let server = mock_openlibrary().await;
+        let enricher = Enricher::new(server.uri(), Duration::ZERO).expect("enricher");
+        let mut book = sample_book(1);
+        book.authors.clear();
+
+        enricher.enrich(&mut book).await.expect("enriched");
+        assert_eq!(book.authors.len(), 1);
+        assert_eq!(book.authors[0].as_str(), "Charlotte Brontë");
+        // Непустой заголовок не перезаписывается.
+        assert_eq!(book.title.as_str(), "Джейн Эйр");
+    }
+
+    #[tokio::test]
+    async fn complete_book_is_not_requested() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let enricher = Enricher::new(server.uri(), Duration::ZERO).expect("enricher");
+        let mut book = sample_book(1);
+        enricher.enrich(&mut book).await.expect("no-op");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn requests_are_rate_limited() {
+        let enricher = Enricher::new("http://unused", Duration::from_secs(1)).expect("enricher");
+        let start = Instant::now();
+        enricher.wait_turn().await;
+        enricher.wait_turn().await;
+        enricher.wait_turn().await;
+        assert!(start.elapsed() >= Duration::from_secs(2));
+    }
+}
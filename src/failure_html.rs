@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, anyhow};
+use tracing::{info, warn};
+
+use crate::page_cache::content_hash;
+use crate::parse_traits::PageHtml;
+
+static GLOBAL: OnceLock<PathBuf> = OnceLock::new();
+
+/// Включает `--save-failures-html`: страницы, на которых не разобрались поля,
+/// сохраняются в `dir`. Вызывается один раз до начала парсинга.
+pub fn init_global(dir: impl Into<PathBuf>) -> anyhow::Result<()> {
+    let dir = dir.into();
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("can't create failures html dir {}", dir.display()))?;
+    GLOBAL
+        .set(dir)
+        .map_err(|_| anyhow!("failures html dir is already initialized"))
+}
+
+/// Пишет `<sha256(url)>.html`; повторная неудача той же страницы перезаписывает файл.
+pub fn save_snapshot(dir: &Path, url: &str, html: &str) -> anyhow::Result<PathBuf> {
+    let path = dir.join(format!("{}.html", content_hash(url)));
+    std::fs::write(&path, html).with_context(|| format!("can't write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Сохраняет страницу неудачного разбора, если снимки включены. Сериализация DOM
+/// делается только тогда: на успешных страницах снимки не нужны.
+pub fn observe(url: &str, page: &impl PageHtml) {
+    let Some(dir) = GLOBAL.get() else {
+        return;
+    };
+    match save_snapshot(dir, url, &page.page_html()) {
+        Ok(path) => info!(url, path = %path.display(), "saved html of failed page"),
+        Err(e) => warn!(url, "can't save html of failed page: {e:#}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_parser::{ConfigParser, SitesConfig};
+    use crate::parse_traits::BookParser;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn failed_parse_writes_html() {
+        let dir = std::env::temp_dir().join(format!("bd_parser_failures_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        init_global(&dir).expect("init");
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/book/1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_string("<html><body><h1>Редизайн</h1></body></html>"),
+            )
+            .mount(&server)
+            .await;
+        let config = SitesConfig::from_toml(
+            r#"
+            [[site]]
+            name = "mock"
+            host = "127.0.0.1"
+            [site.selectors]
+            authors = ".author"
+            isbn = ".isbn"
+            title = "h2"
+            description = ".descr"
+            price = ".price"
+            "#,
+        )
+        .expect("config");
+        let parser =
+            ConfigParser::new(config.sites.into_iter().next().expect("site")).expect("parser");
+        let url = format!("{}/book/1", server.uri());
+
+        assert!(parser.parse_book(url.clone()).await.is_err());
+        let saved = std::fs::read_to_string(dir.join(format!("{}.html", content_hash(&url))))
+            .expect("snapshot written");
+        assert!(saved.contains("<h1>Редизайн</h1>"));
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
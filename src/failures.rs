@@ -0,0 +1,124 @@
+use std::fmt::Display;
+use std::io::Write;
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+/// Неудачный парс книги: URL и причина (цепочка ошибок `anyhow`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Failure {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Отправляющая сторона агрегатора ошибок. Дёшево клонируется в каждую задачу;
+/// запись идёт через канал, поэтому задачи не конкурируют за общий мьютекс.
+#[derive(Debug, Clone)]
+pub struct FailureSink {
+    tx: mpsc::UnboundedSender<Failure>,
+}
+
+/// Принимающая сторона агрегатора: забирает все записанные ошибки в конце прогона.
+#[derive(Debug)]
+pub struct FailureCollector {
+    rx: mpsc::UnboundedReceiver<Failure>,
+}
+
+pub fn failure_channel() -> (FailureSink, FailureCollector) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    (FailureSink { tx }, FailureCollector { rx })
+}
+
+impl FailureSink {
+    pub fn record(&self, url: impl Into<String>, reason: impl Display) {
+        // Получатель живёт до конца прогона; если его уже нет, ошибку некому отдать.
+        let _ = self.tx.send(Failure {
+            url: url.into(),
+            reason: format!("{reason:#}"),
+        });
+    }
+}
+
+impl FailureCollector {
+    /// Закрывает канал и возвращает все ошибки, отправленные до этого момента.
+    pub fn finish(mut self) -> Vec<Failure> {
+        self.rx.close();
+        let mut failures = vec![];
+        while let Ok(failure) = self.rx.try_recv() {
+            failures.push(failure);
+        }
+        failures
+    }
+}
+
+/// Пишет ошибки в формате JSON Lines (одна запись на строку).
+pub fn write_jsonl<W: Write>(failures: &[Failure], mut wtr: W) -> anyhow::Result<()> {
+    for failure in failures {
+        serde_json::to_writer(&mut wtr, failure)?;
+        wtr.write_all(b"\n")?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_failures_all_recorded() {
+        const N: usize = 200;
+        let (sink, collector) = failure_channel();
+        let handles: Vec<_> = (0..N)
+            .map(|i| {
+                let sink = sink.clone();
+                tokio::spawn(async move {
+                    let res: anyhow::Result<()> = Err(anyhow!("boom {i}"));
+                    if let Err(e) = res {
+                        sink.record(format!("https://example.com/{i}"), e);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.expect("task");
+        }
+        drop(sink);
+
+        let mut failures = collector.finish();
+        assert_eq!(failures.len(), N);
+        failures.sort_by_key(|f| {
+            f.url
+                .trim_start_matches("https://example.com/")
+                .parse::<usize>()
+                .unwrap()
+        });
+        for (i, failure) in failures.iter().enumerate() {
+            assert_eq!(failure.url, format!("https://example.com/{i}"));
+            assert_eq!(failure.reason, format!("boom {i}"));
+        }
+    }
+
+    #[test]
+    fn write_jsonl_one_line_per_failure() {
+        let failures = vec![
+            Failure {
+                url: "https://eksmo.ru/book/a".to_string(),
+                reason: "parse_isbn failed: can't find isbn".to_string(),
+            },
+            Failure {
+                url: "https://eksmo.ru/book/b".to_string(),
+                reason: "HTTP error: 404".to_string(),
+            },
+        ];
+        let mut buf = vec![];
+        write_jsonl(&failures, &mut buf).expect("write");
+        let text = String::from_utf8(buf).expect("utf8");
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(lines[0]).expect("json");
+        assert_eq!(first["url"], "https://eksmo.ru/book/a");
+        assert_eq!(first["reason"], "parse_isbn failed: can't find isbn");
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+/// Коды выхода по итогам прогона, чтобы cron и CI замечали сломанный парсинг.
+pub const EXIT_OK: u8 = 0;
+/// Доля ошибок больше `--fail-threshold`. Не `1`: с ним `main` выходит при ошибке запуска.
+pub const EXIT_TOO_MANY_FAILURES: u8 = 3;
+/// У какого-то сайта не разобралась ни одна книга — скорее всего, сломался селектор.
+pub const EXIT_SITE_FAILED: u8 = 2;
+
+/// Сколько книг сайта ушло в парсинг и сколько из них с ошибкой.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SiteOutcome {
+    pub attempted: u64,
+    pub failed: u64,
+}
+
+impl SiteOutcome {
+    /// Ошибок больше `percent` процентов от всех URL: даже если остальные разберутся,
+    /// доля ошибок уже выше порога.
+    pub fn exceeds(&self, percent: u8) -> bool {
+        self.failed * 100 > u64::from(percent) * self.attempted
+    }
+}
+
+/// Код выхода: сайт, у которого упали все книги, важнее общей доли ошибок.
+/// `fail_threshold` — процент; без него доля ошибок не проверяется.
+pub fn exit_code(sites: &BTreeMap<String, SiteOutcome>, fail_threshold: Option<u8>) -> u8 {
+    if sites
+        .values()
+        .any(|site| site.attempted > 0 && site.failed == site.attempted)
+    {
+        return EXIT_SITE_FAILED;
+    }
+    let total = SiteOutcome {
+        attempted: sites.values().map(|site| site.attempted).sum(),
+        failed: sites.values().map(|site| site.failed).sum(),
+    };
+    match fail_threshold {
+        Some(percent) if total.exceeds(percent) => EXIT_TOO_MANY_FAILURES,
+        _ => EXIT_OK,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sites(outcomes: &[(&str, u64, u64)]) -> BTreeMap<String, SiteOutcome> {
+        outcomes
+            .iter()
+            .map(|&(site, attempted, failed)| (site.to_string(), SiteOutcome { attempted, failed }))
+            .collect()
+    }
+
+    #[test]
+    fn stats_map_to_exit_codes() {
+        let healthy = sites(&[("labirint", 100, 5), ("eksmo", 50, 10)]);
+        assert_eq!(exit_code(&healthy, None), EXIT_OK);
+        // 15 из 150 — ровно 10%: граница не превышена.
+        assert_eq!(exit_code(&healthy, Some(10)), EXIT_OK);
+        assert_eq!(exit_code(&healthy, Some(9)), EXIT_TOO_MANY_FAILURES);
+
+        let broken = sites(&[("labirint", 100, 0), ("eksmo", 3, 3)]);
+        assert_eq!(exit_code(&broken, None), EXIT_SITE_FAILED);
+        assert_eq!(exit_code(&broken, Some(50)), EXIT_SITE_FAILED);
+
+        // Сайт без URL не считается сломанным.
+        let empty_site = sites(&[("labirint", 10, 1), ("igra_slov", 0, 0)]);
+        assert_eq!(exit_code(&empty_site, Some(20)), EXIT_OK);
+        assert_eq!(exit_code(&BTreeMap::new(), Some(0)), EXIT_OK);
+    }
+
+    #[test]
+    fn site_exceeds_threshold_only_past_share_of_all_urls() {
+        let site = |failed| SiteOutcome {
+            attempted: 40,
+            failed,
+        };
+        // 10% от 40 URL — 4 ошибки: пятая уже не оставляет шанса уложиться в порог.
+        assert!(!site(4).exceeds(10));
+        assert!(site(5).exceeds(10));
+        assert!(!site(40).exceeds(100));
+    }
+}
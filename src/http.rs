@@ -0,0 +1,1555 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+
+use crate::page_cache::PageCache;
+use crate::parse_traits::{Sites, host_of};
+
+pub static BROWSER_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+/// Нижняя граница пула: столько держали всегда, и при малой конкурентности меньше не нужно.
+pub const MIN_POOL_MAX_IDLE_PER_HOST: usize = 4;
+
+/// Повторов после первой неудачной попытки, если `--max-retries` не задан.
+pub const DEFAULT_MAX_RETRIES: u8 = 2;
+/// Верхняя граница `--max-retries`: дальше повторы только дольше держат мёртвый URL.
+pub const MAX_RETRIES_LIMIT: u8 = 10;
+/// Предел тела страницы, если `--max-body-bytes` не задан: страницы товаров весят сотни
+/// килобайт, а несколько мегабайт — уже не карточка книги.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 8 * 1024 * 1024;
+/// Потолок экспоненциальной паузы между попытками, секунд.
+const MAX_BACKOFF_SECS: u64 = 8;
+/// SOCKS-порт локального демона Tor; `socks5h`, чтобы имена (и `.onion`) резолвил Tor.
+pub static DEFAULT_TOR_SOCKS: &str = "socks5h://127.0.0.1:9050";
+/// Управляющий порт Tor (`ControlPort` в torrc) для `--new-identity-every`.
+pub static DEFAULT_TOR_CONTROL: &str = "127.0.0.1:9051";
+/// Пароль управляющего порта (`HashedControlPassword`): из окружения, а не из аргументов,
+/// чтобы не попасть в `ps` и `manifest.json`.
+pub static TOR_CONTROL_PASSWORD_ENV: &str = "TOR_CONTROL_PASSWORD";
+
+static GLOBAL: OnceLock<HttpConfig> = OnceLock::new();
+/// Курсор ротации User-Agent, общий для всех клиентов.
+static NEXT_USER_AGENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Набор таймаутов под качество сети (`--timeout-profile`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum TimeoutProfile {
+    /// Быстрая стабильная сеть: зависшие запросы обрываются раньше.
+    Fast,
+    #[default]
+    Default,
+    /// Мобильная сеть, VPN: медленные ответы ещё считаются живыми.
+    Patient,
+}
+
+impl FromStr for TimeoutProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "fast" => Ok(Self::Fast),
+            "default" => Ok(Self::Default),
+            "patient" => Ok(Self::Patient),
+            other => Err(anyhow!(
+                "unknown timeout profile {other}, expected fast|default|patient"
+            )),
+        }
+    }
+}
+
+/// Версия HTTP клиентов (`--http-version`). Некоторые серверы иначе ограничивают частоту
+/// по протоколу или ломаются на мультиплексированных запросах HTTP/2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum HttpVersion {
+    /// Как решит reqwest: по TLS клиент предлагает через ALPN сначала `h2`, потом
+    /// `http/1.1` (`native-tls-alpn`), так что HTTP/2 предпочитается, а сервер без него
+    /// получает HTTP/1.1. Без TLS — HTTP/1.1.
+    #[default]
+    Auto,
+    /// Только HTTP/1.1 (`http1_only`).
+    Http1,
+    /// Сразу HTTP/2 без согласования (`http2_prior_knowledge`): сервер, знающий только
+    /// HTTP/1.1, не ответит. Для серверов, которые точно говорят HTTP/2, в том числе без TLS.
+    Http2PriorKnowledge,
+}
+
+impl FromStr for HttpVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "http1" => Ok(Self::Http1),
+            "http2-prior-knowledge" => Ok(Self::Http2PriorKnowledge),
+            other => Err(anyhow!(
+                "unknown http version {other}, expected auto|http1|http2-prior-knowledge"
+            )),
+        }
+    }
+}
+
+/// Явные значения из CLI поверх профиля.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TimeoutOverrides {
+    pub connect_timeout: Option<Duration>,
+    pub timeout: Option<Duration>,
+    pub tcp_keepalive: Option<Duration>,
+}
+
+/// Настройки HTTP-клиентов парсеров.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    pub timeout: Duration,
+    pub tcp_keepalive: Duration,
+    /// Сколько простаивающих соединений держать на хост. Меньше числа одновременных
+    /// запросов к хосту — и лишние соединения закрываются и открываются заново.
+    pub pool_max_idle_per_host: usize,
+    /// Повторы `get_with_retries` на 429, 5xx и сетевых ошибках.
+    pub max_retries: u8,
+    /// Пул User-Agent для ротации по запросам; пусто — всегда `BROWSER_USER_AGENT`.
+    pub user_agents: Vec<String>,
+    /// Сайты (по имени, как в выгрузке), где перед первой страницей товара
+    /// запрашивается главная, чтобы сайт выставил сессионные cookie.
+    pub warm_up_sites: Vec<String>,
+    /// Тело страницы длиннее — `FetchError::TooLarge`, дальше не читается.
+    pub max_body_bytes: usize,
+    /// `--tor`: все клиенты ходят через SOCKS-прокси демона Tor.
+    pub tor: Option<TorConfig>,
+    /// `--http-version`: по умолчанию версию выбирает reqwest.
+    pub http_version: HttpVersion,
+    /// `--per-site-timeout`: таймаут запроса страницы товара по имени сайта (как в выгрузке)
+    /// вместо общего `timeout`.
+    pub site_timeouts: BTreeMap<String, Duration>,
+}
+
+/// Загрузка через Tor (`--tor`, сборка `--features tor`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TorConfig {
+    /// `socks5h://host:port` демона Tor.
+    pub socks: String,
+    /// `host:port` управляющего порта.
+    pub control: String,
+    /// Сменить цепочки (`SIGNAL NEWNYM`) после каждых N загрузок страниц.
+    pub new_identity_every: Option<u32>,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        Self {
+            socks: DEFAULT_TOR_SOCKS.to_string(),
+            control: DEFAULT_TOR_CONTROL.to_string(),
+            new_identity_every: None,
+        }
+    }
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            timeout: Duration::from_secs(15),
+            tcp_keepalive: Duration::from_secs(30),
+            pool_max_idle_per_host: MIN_POOL_MAX_IDLE_PER_HOST,
+            max_retries: DEFAULT_MAX_RETRIES,
+            user_agents: vec![],
+            warm_up_sites: vec![],
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            tor: None,
+            http_version: HttpVersion::default(),
+            site_timeouts: BTreeMap::new(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Пул под `concurrency` одновременных запросов к одному хосту.
+    pub fn for_concurrency(concurrency: usize) -> Self {
+        Self {
+            pool_max_idle_per_host: concurrency.max(MIN_POOL_MAX_IDLE_PER_HOST),
+            ..Self::default()
+        }
+    }
+
+    /// Таймауты профиля, затем явные переопределения; пул не меняется.
+    pub fn with_timeouts(self, profile: TimeoutProfile, overrides: TimeoutOverrides) -> Self {
+        let (connect_timeout, timeout, tcp_keepalive) = match profile {
+            TimeoutProfile::Fast => (2, 8, 15),
+            TimeoutProfile::Default => (5, 15, 30),
+            TimeoutProfile::Patient => (15, 60, 60),
+        };
+        Self {
+            connect_timeout: overrides
+                .connect_timeout
+                .unwrap_or(Duration::from_secs(connect_timeout)),
+            timeout: overrides.timeout.unwrap_or(Duration::from_secs(timeout)),
+            tcp_keepalive: overrides
+                .tcp_keepalive
+                .unwrap_or(Duration::from_secs(tcp_keepalive)),
+            ..self
+        }
+    }
+
+    /// Builder с таймаутами, пулом, браузерным User-Agent и хранилищем cookie;
+    /// вызывающий может донастроить. Ошибка — неверный адрес SOCKS-прокси Tor.
+    pub fn client_builder(&self) -> anyhow::Result<reqwest::ClientBuilder> {
+        let builder = reqwest::Client::builder()
+            .user_agent(BROWSER_USER_AGENT)
+            .cookie_store(true)
+            .connect_timeout(self.connect_timeout)
+            .timeout(self.timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .tcp_keepalive(Some(self.tcp_keepalive))
+            .redirect(reqwest::redirect::Policy::limited(5));
+        let builder = match self.http_version {
+            HttpVersion::Auto => builder,
+            HttpVersion::Http1 => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        };
+        Ok(match &self.tor {
+            Some(tor) => builder.proxy(
+                reqwest::Proxy::all(&tor.socks)
+                    .with_context(|| format!("invalid tor socks proxy {}", tor.socks))?,
+            ),
+            None => builder,
+        })
+    }
+
+    /// Таймаут страницы товара `site`: переопределение `--per-site-timeout`, иначе `None`,
+    /// и действует таймаут клиента.
+    pub fn site_timeout(&self, site: Sites) -> Option<Duration> {
+        self.site_timeouts.get(&site.to_string()).copied()
+    }
+
+    pub fn build_client(&self) -> anyhow::Result<reqwest::Client> {
+        Ok(self.client_builder()?.build()?)
+    }
+
+    /// Следующий User-Agent пула по кругу; `None`, если пул пуст и действует UA клиента.
+    pub fn next_user_agent(&self) -> Option<&str> {
+        if self.user_agents.is_empty() {
+            return None;
+        }
+        let n = NEXT_USER_AGENT.fetch_add(1, Ordering::Relaxed);
+        Some(&self.user_agents[n % self.user_agents.len()])
+    }
+}
+
+/// Управляющий порт Tor; трейт, чтобы ротацию можно было проверить без демона.
+pub trait TorControl: Send + Sync {
+    /// `SIGNAL NEWNYM`: новые соединения пойдут по новым цепочкам.
+    fn new_identity(&self) -> BoxFuture<'_, anyhow::Result<()>>;
+}
+
+/// Сколько ждать весь обмен с управляющим портом: зависший порт не должен держать
+/// загрузку страницы, на которой запрошена смена цепочек.
+const TOR_CONTROL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Управляющий порт по TCP: `AUTHENTICATE`, `SIGNAL NEWNYM`, `QUIT`, на каждый
+/// запрос ждёт ответ `250`, весь обмен — не дольше `TOR_CONTROL_TIMEOUT`.
+#[derive(Debug, Clone)]
+pub struct ControlPort {
+    addr: String,
+    password: Option<String>,
+}
+
+impl ControlPort {
+    pub fn new(addr: impl Into<String>, password: Option<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            password,
+        }
+    }
+
+    async fn signal_newnym(&self) -> anyhow::Result<()> {
+        tokio::time::timeout(TOR_CONTROL_TIMEOUT, self.exchange())
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "tor control port {} did not answer in {}s",
+                    self.addr,
+                    TOR_CONTROL_TIMEOUT.as_secs()
+                )
+            })?
+    }
+
+    async fn exchange(&self) -> anyhow::Result<()> {
+        let stream = tokio::net::TcpStream::connect(&self.addr)
+            .await
+            .with_context(|| format!("can't connect to tor control port {}", self.addr))?;
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let auth = match &self.password {
+            Some(password) => format!("AUTHENTICATE \"{}\"\r\n", password.replace('"', "\\\"")),
+            None => "AUTHENTICATE\r\n".to_string(),
+        };
+        for command in [auth.as_str(), "SIGNAL NEWNYM\r\n", "QUIT\r\n"] {
+            write.write_all(command.as_bytes()).await?;
+            let reply = lines
+                .next_line()
+                .await?
+                .ok_or_else(|| anyhow!("tor control port closed the connection"))?;
+            if !reply.starts_with("250") {
+                let command = command.split_whitespace().next().unwrap_or_default();
+                return Err(anyhow!("tor control port rejected {command}: {reply}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TorControl for ControlPort {
+    fn new_identity(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+        Box::pin(self.signal_newnym())
+    }
+}
+
+/// `--new-identity-every`: считает загрузки страниц и каждую `every`-ю просит Tor
+/// сменить цепочки. Tor сам ограничивает NEWNYM (не чаще раза в ~10 с), лишние сигналы
+/// он игнорирует. Неудача только логируется: страница грузится по старой цепочке.
+pub struct IdentityRotator {
+    control: Box<dyn TorControl>,
+    every: u64,
+    requests: AtomicU64,
+}
+
+impl IdentityRotator {
+    pub fn new(control: impl TorControl + 'static, every: u32) -> Self {
+        Self {
+            control: Box::new(control),
+            every: u64::from(every.max(1)),
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Отмечает загрузку; `true`, если на ней запрошена смена цепочек.
+    pub async fn on_request(&self) -> bool {
+        let n = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        if !n.is_multiple_of(self.every) {
+            return false;
+        }
+        match self.control.new_identity().await {
+            Ok(()) => info!(requests = n, "requested new tor identity"),
+            Err(e) => warn!(requests = n, "can't rotate tor identity: {e:#}"),
+        }
+        true
+    }
+}
+
+static TOR_ROTATOR: OnceLock<IdentityRotator> = OnceLock::new();
+
+/// Включает `--new-identity-every` для `fetch_page`; вызывается один раз до парсинга.
+pub fn init_tor_rotation(rotator: IdentityRotator) -> anyhow::Result<()> {
+    TOR_ROTATOR
+        .set(rotator)
+        .map_err(|_| anyhow!("tor rotation is already initialized"))
+}
+
+/// Главные страницы, уже запрошенные `warm_up_once`, по origin.
+static WARMED_UP: OnceLock<DashMap<String, Arc<tokio::sync::OnceCell<()>>>> = OnceLock::new();
+
+/// Один раз на origin запрашивает главную страницу сайта, чтобы cookie сессии легли
+/// в хранилище клиента до первой страницы товара. Параллельные загрузки ждут
+/// завершения; неудачный прогрев только логируется.
+pub async fn warm_up_once(client: &reqwest::Client, url: &str) -> anyhow::Result<()> {
+    let origin = reqwest::Url::parse(url)?.origin().ascii_serialization();
+    let cell = WARMED_UP
+        .get_or_init(DashMap::new)
+        .entry(origin.clone())
+        .or_default()
+        .clone();
+    cell.get_or_init(|| async {
+        let homepage = format!("{origin}/");
+        match client
+            .get(&homepage)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+        {
+            Ok(_) => info!(homepage, "warmed up session"),
+            Err(e) => warn!(homepage, "warm-up request failed: {e}"),
+        }
+    })
+    .await;
+    Ok(())
+}
+
+/// Страница товара сайта: `warm_up_once`, если сайт в `warm_up_sites`, затем `get_with_retries`.
+/// С `--proxy-list` страница идёт через пул прокси, а `client` парсера и прогрев не используются.
+/// Таймаут запроса — `HttpConfig::site_timeout` сайта, если он задан.
+pub async fn fetch_page(
+    client: &reqwest::Client,
+    site: Sites,
+    url: &str,
+) -> anyhow::Result<String> {
+    if let Some(offline) = crate::offline::global() {
+        return offline.page(url);
+    }
+    let config = global();
+    let timeout = config.site_timeout(site);
+    if let Some(pool) = crate::proxy_pool::global() {
+        return pool.fetch(url, config.max_retries, timeout).await;
+    }
+    if config
+        .warm_up_sites
+        .iter()
+        .any(|name| *name == site.to_string())
+    {
+        warm_up_once(client, url).await?;
+    }
+    if let Some(rotator) = TOR_ROTATOR.get() {
+        rotator.on_request().await;
+    }
+    match crate::page_cache::global() {
+        Some(cache) => get_cached(client, cache, url, config.max_retries, timeout).await,
+        None => get_with_timeout(client, url, config.max_retries, timeout).await,
+    }
+}
+
+/// Валидаторы ответа для условных запросов: `ETag` и `Last-Modified` как их прислал сервер.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Результат загрузки с валидаторами прошлой версии.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Page {
+    Body(String, Validators),
+    /// `304 Not Modified`: страница та же, что в кэше.
+    NotModified,
+}
+
+/// Страница через кэш `--cache-dir`: если у закэшированной версии есть `ETag` или
+/// `Last-Modified`, уходят `If-None-Match`/`If-Modified-Since`, и на `304` возвращается
+/// тело из кэша — страница не скачивается заново, а `PageCache::store` увидит её неизменной.
+/// Свежий ответ сохраняется сразу: сначала тело, затем его валидаторы. Если тело записать
+/// не удалось, прежние валидаторы стираются, чтобы `304` не отдал устаревшее тело.
+pub async fn get_cached(
+    client: &reqwest::Client,
+    cache: &PageCache,
+    url: &str,
+    max_retries: u8,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    let cached = cache.conditional(url);
+    let validators = cached.as_ref().map(|(_, validators)| validators);
+    match get_with_retries_counted(client, url, max_retries, timeout, validators, retry_stats())
+        .await?
+    {
+        Page::Body(body, validators) => {
+            let validators = match cache.store(url, &body) {
+                Ok(_) => validators,
+                Err(e) => {
+                    warn!(url, "can't store page in cache: {e:#}");
+                    Validators::default()
+                }
+            };
+            if let Err(e) = cache.store_validators(url, &validators) {
+                warn!(url, "can't store page validators in cache: {e:#}");
+            }
+            Ok(body)
+        }
+        Page::NotModified => {
+            let (body, _) = cached.expect("304 only answers a conditional request");
+            info!(url, "page not modified, using cached body");
+            Ok(body)
+        }
+    }
+}
+
+/// Читает пул User-Agent для `--user-agent-from-file`: по строке на UA, пустые строки
+/// и строки с `#` пропускаются. Файл без единого UA — ошибка.
+pub fn load_user_agents(path: &Path) -> anyhow::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("can't read user agents file {}", path.display()))?;
+    let agents: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if agents.is_empty() {
+        return Err(anyhow!("user agents file {} is empty", path.display()));
+    }
+    Ok(agents)
+}
+
+/// Класс сетевой ошибки `reqwest`: от него зависит, есть ли смысл повторять запрос.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// DNS, отказ в соединении, TLS — сервер или сеть моргнули.
+    Connect,
+    Timeout,
+    /// Соединение оборвалось во время отправки запроса.
+    Request,
+    /// Тело ответа не читается или не декодируется: повтор вернёт то же самое.
+    Body,
+    /// Редиректы, ошибки builder и прочее, что повтором не лечится.
+    Other,
+}
+
+impl ErrorClass {
+    pub fn of(err: &reqwest::Error) -> Self {
+        // Таймаут соединения — одновременно `is_connect` и `is_timeout`; таймаут чтения
+        // тела — `is_body` и `is_timeout`. Проверки идут от более конкретного класса.
+        if err.is_connect() {
+            Self::Connect
+        } else if err.is_timeout() {
+            Self::Timeout
+        } else if err.is_body() || err.is_decode() {
+            Self::Body
+        } else if err.is_request() {
+            Self::Request
+        } else {
+            Self::Other
+        }
+    }
+
+    pub fn is_transient(self) -> bool {
+        matches!(self, Self::Connect | Self::Timeout | Self::Request)
+    }
+}
+
+/// Ошибка загрузки страницы по категориям — одна на все `fetch`: `get_with_retries` отдаёт её
+/// в цепочке `anyhow`, и вызывающий достаёт категорию через `downcast_ref`, не разбирая текст.
+/// `ErrorClass` решает, повторять ли запрос; `FetchError` — чем он в итоге закончился.
+#[derive(Debug)]
+pub enum FetchError {
+    Timeout(reqwest::Error),
+    Connect(reqwest::Error),
+    /// Сайт ответил кодом ошибки (после всех повторов).
+    Status(reqwest::StatusCode),
+    /// Тело ответа не читается или не декодируется.
+    Body(reqwest::Error),
+    /// Остальные ошибки `reqwest`: оборванный запрос, редиректы и т.п.
+    Network(reqwest::Error),
+    /// Тело длиннее `max_body_bytes`: чтение прервано, не дойдя до разбора.
+    TooLarge {
+        limit: usize,
+    },
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Self::Timeout(err)
+        } else if err.is_connect() {
+            Self::Connect(err)
+        } else if let Some(status) = err.status().filter(|_| err.is_status()) {
+            Self::Status(status)
+        } else if err.is_body() || err.is_decode() {
+            Self::Body(err)
+        } else {
+            Self::Network(err)
+        }
+    }
+}
+
+impl std::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout(_) => write!(f, "request timed out"),
+            Self::Connect(_) => write!(f, "can't connect"),
+            Self::Status(status) => write!(f, "HTTP error: {status}"),
+            Self::Body(_) => write!(f, "can't read response body"),
+            Self::Network(_) => write!(f, "network error"),
+            Self::TooLarge { limit } => write!(f, "response body exceeds {limit} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Timeout(e) | Self::Connect(e) | Self::Body(e) | Self::Network(e) => Some(e),
+            Self::Status(_) | Self::TooLarge { .. } => None,
+        }
+    }
+}
+
+/// Тело ответа не длиннее `limit` байт. `Content-Length` больше предела отсекается сразу,
+/// иначе тело читается по кускам и обрывается на превышении, так что гигантский ответ
+/// не попадает в память целиком. Кодировка — из `charset` в `Content-Type`, как у `Response::text`.
+pub async fn read_body(mut resp: reqwest::Response, limit: usize) -> Result<String, FetchError> {
+    if resp.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(FetchError::TooLarge { limit });
+    }
+    let encoding = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|ct| {
+            ct.split(';')
+                .find_map(|p| p.trim().strip_prefix("charset="))
+        })
+        .and_then(|label| encoding_rs::Encoding::for_label(label.trim_matches('"').as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await? {
+        if body.len() + chunk.len() > limit {
+            return Err(FetchError::TooLarge { limit });
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(encoding.decode(&body).0.into_owned())
+}
+
+/// Запрошенный URL → итоговый после редиректов, пока страницу не забрал `parse_page`.
+static REDIRECTS: LazyLock<DashMap<String, String>> = LazyLock::new(DashMap::new);
+
+/// Итоговый URL страницы, если `get_with_retries` пришёл к ней через редиректы.
+/// Запись удаляется: за каждой загрузкой следует ровно один разбор.
+pub fn take_redirect(url: &str) -> Option<String> {
+    REDIRECTS.remove(url).map(|(_, final_url)| final_url)
+}
+
+/// Счётчики повторов `get_with_retries` за прогон: сколько повторов и по какой причине
+/// (код ответа или класс сетевой ошибки) было на каждом сайте, и чем закончились
+/// запросы, которым понадобился хотя бы один повтор.
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    retries: DashMap<(String, String), u64>,
+    recovered: AtomicU64,
+    gave_up: AtomicU64,
+}
+
+static RETRY_STATS: LazyLock<RetryStats> = LazyLock::new(RetryStats::default);
+
+/// Счётчики повторов всего процесса.
+pub fn retry_stats() -> &'static RetryStats {
+    &RETRY_STATS
+}
+
+impl RetryStats {
+    fn record_retry(&self, site: &str, reason: &str) {
+        *self
+            .retries
+            .entry((site.to_string(), reason.to_string()))
+            .or_default() += 1;
+    }
+
+    fn record_outcome(&self, succeeded: bool) {
+        let counter = if succeeded {
+            &self.recovered
+        } else {
+            &self.gave_up
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Повторы по `(сайт, причина)`; причина — код ответа (`429`) или класс ошибки (`timeout`).
+    pub fn retries(&self) -> BTreeMap<(String, String), u64> {
+        self.retries
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+
+    /// Запросы, которые после повторов всё-таки удались.
+    pub fn recovered(&self) -> u64 {
+        self.recovered.load(Ordering::Relaxed)
+    }
+
+    /// Запросы, которые повторялись и всё равно закончились ошибкой.
+    pub fn gave_up(&self) -> u64 {
+        self.gave_up.load(Ordering::Relaxed)
+    }
+
+    /// Итог для конца прогона: `retries 12 (labirint 429: 10, eksmo 503: 2), recovered 5, gave up 1`.
+    pub fn summary(&self) -> String {
+        let retries = self.retries();
+        let mut out = format!("retries {}", retries.values().sum::<u64>());
+        if !retries.is_empty() {
+            let by_reason: Vec<String> = retries
+                .iter()
+                .map(|((site, reason), count)| format!("{site} {reason}: {count}"))
+                .collect();
+            let _ = write!(out, " ({})", by_reason.join(", "));
+        }
+        let _ = write!(
+            out,
+            ", recovered {}, gave up {}",
+            self.recovered(),
+            self.gave_up()
+        );
+        out
+    }
+}
+
+/// Метка сайта для `RetryStats`: встроенный сайт, иначе хост.
+fn retry_site(url: &str) -> String {
+    Sites::from_url(url)
+        .map(|site| site.to_string())
+        .or_else(|| host_of(url))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Пауза перед повтором, секунд: `Retry-After` в секундах, если сервер его прислал, иначе
+/// `base`. Не дольше `MAX_BACKOFF_SECS`: огромный `Retry-After` не должен держать воркер.
+fn retry_wait(headers: &reqwest::header::HeaderMap, base: u64) -> u64 {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(base)
+        .min(MAX_BACKOFF_SECS)
+}
+
+/// `GET` страницы с повторами: на 429 и 5xx ждёт `Retry-After` (не дольше `MAX_BACKOFF_SECS`)
+/// или экспоненциальную паузу, на временных сетевых ошибках (см. `ErrorClass::is_transient`) —
+/// паузу.
+/// Остальные коды и ошибки чтения тела не повторяются. Повторы считаются в `retry_stats`.
+pub async fn get_with_retries(
+    client: &reqwest::Client,
+    url: &str,
+    max_retries: u8,
+) -> anyhow::Result<String> {
+    get_with_timeout(client, url, max_retries, None).await
+}
+
+/// `get_with_retries`, но каждая попытка ограничена `timeout` вместо таймаута клиента.
+pub async fn get_with_timeout(
+    client: &reqwest::Client,
+    url: &str,
+    max_retries: u8,
+    timeout: Option<Duration>,
+) -> anyhow::Result<String> {
+    match get_with_retries_counted(client, url, max_retries, timeout, None, retry_stats()).await? {
+        Page::Body(body, _) => Ok(body),
+        Page::NotModified => Err(FetchError::Status(reqwest::StatusCode::NOT_MODIFIED).into()),
+    }
+}
+
+async fn get_with_retries_counted(
+    client: &reqwest::Client,
+    url: &str,
+    max_retries: u8,
+    timeout: Option<Duration>,
+    validators: Option<&Validators>,
+    stats: &RetryStats,
+) -> anyhow::Result<Page> {
+    let site = retry_site(url);
+    let mut retried = false;
+    let result = fetch_with_retries(client, url, max_retries, timeout, validators, |reason| {
+        stats.record_retry(&site, reason);
+        retried = true;
+    })
+    .await;
+    if retried {
+        stats.record_outcome(result.is_ok());
+    }
+    result
+}
+
+/// Цикл попыток `get_with_retries`; `on_retry` вызывается перед каждым повтором с его причиной.
+/// С `validators` запрос условный, и `304` — это `Page::NotModified`, а не ошибка.
+async fn fetch_with_retries(
+    client: &reqwest::Client,
+    url: &str,
+    max_retries: u8,
+    timeout: Option<Duration>,
+    validators: Option<&Validators>,
+    mut on_retry: impl FnMut(&str),
+) -> anyhow::Result<Page> {
+    let mut last_err: Option<reqwest::Error> = None;
+    let mut last_status: Option<reqwest::StatusCode> = None;
+    for attempt in 0..=max_retries {
+        let base = (1_u64 << attempt.min(6)).min(MAX_BACKOFF_SECS);
+        let mut request = client.get(url);
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        if let Some(user_agent) = global().next_user_agent() {
+            request = request.header(reqwest::header::USER_AGENT, user_agent);
+        }
+        if let Some(validators) = validators {
+            if let Some(etag) = &validators.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let err = match request.send().await {
+            Ok(resp) => {
+                let status = resp.status();
+                let not_modified =
+                    status == reqwest::StatusCode::NOT_MODIFIED && validators.is_some();
+                // До ответа из кэша: иначе `source` закэшированной страницы откатится
+                // к запрошенному URL.
+                if (status.is_success() || not_modified) && resp.url().as_str() != url {
+                    info!(url, final_url = %resp.url(), "followed redirects");
+                    REDIRECTS.insert(url.to_string(), resp.url().to_string());
+                }
+                if not_modified {
+                    return Ok(Page::NotModified);
+                }
+                if status.is_success() {
+                    let page_validators = Validators::from_headers(resp.headers());
+                    match read_body(resp, global().max_body_bytes).await {
+                        Ok(body) => return Ok(Page::Body(body, page_validators)),
+                        Err(
+                            FetchError::Timeout(e)
+                            | FetchError::Connect(e)
+                            | FetchError::Body(e)
+                            | FetchError::Network(e),
+                        ) => e,
+                        Err(e) => {
+                            warn!(target: "time", url, "{e}, giving up");
+                            return Err(e.into());
+                        }
+                    }
+                } else {
+                    last_status = Some(status);
+                    if (status.as_u16() == 429 || status.is_server_error()) && attempt < max_retries
+                    {
+                        let wait = retry_wait(resp.headers(), base);
+                        warn!(target: "time", attempt, %status, wait, "Retrying after backoff");
+                        on_retry(status.as_str());
+                        tokio::time::sleep(Duration::from_secs(wait)).await;
+                        continue;
+                    }
+                    return Err(FetchError::Status(status).into());
+                }
+            }
+            Err(e) => e,
+        };
+        let class = ErrorClass::of(&err);
+        if !class.is_transient() {
+            warn!(target: "time", attempt, ?class, "Network error is not transient, giving up");
+            return Err(FetchError::from(err).into());
+        }
+        last_status = None;
+        last_err = Some(err);
+        if attempt < max_retries {
+            warn!(target: "time", attempt, ?class, wait = base, "Network error, retrying after backoff");
+            on_retry(&format!("{class:?}").to_lowercase());
+            tokio::time::sleep(Duration::from_secs(base)).await;
+        }
+    }
+    match (last_status, last_err) {
+        (Some(status), _) => Err(FetchError::Status(status).into()),
+        (None, Some(e)) => Err(FetchError::from(e).into()),
+        (None, None) => unreachable!("at least one attempt is made"),
+    }
+}
+
+/// Задаёт настройки для клиентов, которые ещё не созданы. Работает только до первого
+/// `global()`: клиенты парсеров создаются лениво и живут весь процесс.
+pub fn init_global(config: HttpConfig) -> anyhow::Result<()> {
+    GLOBAL
+        .set(config)
+        .map_err(|_| anyhow!("http config is already initialized"))
+}
+
+/// Общие настройки; `HttpConfig::default()`, если `init_global` не вызывали.
+pub fn global() -> &'static HttpConfig {
+    GLOBAL.get_or_init(HttpConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    /// HTTP/1.1 сервер с keep-alive, считающий принятые TCP-соединения.
+    async fn counting_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let (read, mut write) = stream.into_split();
+                    let mut lines = BufReader::new(read).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line.is_empty() {
+                            let resp = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                            if write.write_all(resp.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        (format!("http://{addr}/"), accepted)
+    }
+
+    async fn sequential_requests(config: &HttpConfig) -> usize {
+        let (url, accepted) = counting_server().await;
+        let client = config.build_client().expect("client");
+        for _ in 0..5 {
+            let body = client
+                .get(&url)
+                .send()
+                .await
+                .expect("send")
+                .text()
+                .await
+                .expect("body");
+            assert_eq!(body, "ok");
+        }
+        accepted.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn sequential_requests_reuse_connection() {
+        assert_eq!(sequential_requests(&HttpConfig::default()).await, 1);
+        // Без пула каждое соединение закрывается после ответа.
+        let no_pool = HttpConfig {
+            pool_max_idle_per_host: 0,
+            ..HttpConfig::default()
+        };
+        assert_eq!(sequential_requests(&no_pool).await, 5);
+    }
+
+    /// Сервер обещает 100 байт тела, отдаёт 2 и закрывает соединение.
+    async fn truncated_body_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let (read, mut write) = stream.into_split();
+                let mut lines = BufReader::new(read).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if line.is_empty() {
+                        let resp = "HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\nok";
+                        let _ = write.write_all(resp.as_bytes()).await;
+                        break;
+                    }
+                }
+            }
+        });
+        (format!("http://{addr}/"), accepted)
+    }
+
+    #[tokio::test]
+    async fn connect_error_is_transient() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        drop(listener);
+        let err = reqwest::get(format!("http://{addr}/"))
+            .await
+            .expect_err("nothing listens");
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Connect);
+        assert!(ErrorClass::of(&err).is_transient());
+        assert!(matches!(FetchError::from(err), FetchError::Connect(_)));
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_too_large() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(4096)))
+            .mount(&server)
+            .await;
+        let resp = reqwest::get(server.uri()).await.expect("response");
+        assert!(matches!(
+            read_body(resp, 1024).await,
+            Err(FetchError::TooLarge { limit: 1024 })
+        ));
+        let resp = reqwest::get(server.uri()).await.expect("response");
+        assert_eq!(read_body(resp, 4096).await.expect("body").len(), 4096);
+    }
+
+    #[tokio::test]
+    async fn chunked_body_is_capped_while_streaming() {
+        // Без Content-Length предел срабатывает на середине потока.
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.is_empty() {
+                    break;
+                }
+            }
+            let head = "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n";
+            let _ = write.write_all(head.as_bytes()).await;
+            let chunk = format!("400\r\n{}\r\n", "x".repeat(0x400));
+            for _ in 0..16 {
+                if write.write_all(chunk.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            let _ = write.write_all(b"0\r\n\r\n").await;
+        });
+        let resp = reqwest::get(format!("http://{addr}/"))
+            .await
+            .expect("response");
+        assert_eq!(resp.content_length(), None);
+        let err = read_body(resp, 4000).await.expect_err("cap");
+        assert_eq!(err.to_string(), "response body exceeds 4000 bytes");
+    }
+
+    #[tokio::test]
+    async fn body_charset_from_content_type() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let (cp1251, _, _) = encoding_rs::WINDOWS_1251.encode("Книга");
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(cp1251.into_owned(), "text/html; charset=windows-1251"),
+            )
+            .mount(&server)
+            .await;
+        let resp = reqwest::get(server.uri()).await.expect("response");
+        assert_eq!(read_body(resp, 1024).await.expect("body"), "Книга");
+    }
+
+    #[tokio::test]
+    async fn status_error_maps_to_status() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let err = reqwest::get(server.uri())
+            .await
+            .expect("response")
+            .error_for_status()
+            .expect_err("404");
+        assert!(matches!(
+            FetchError::from(err),
+            FetchError::Status(reqwest::StatusCode::NOT_FOUND)
+        ));
+        // Тот же код из `get_with_retries` достаётся из цепочки `anyhow` без разбора текста.
+        let err = get_with_retries(&reqwest::Client::new(), &server.uri(), 0)
+            .await
+            .expect_err("404");
+        assert!(matches!(
+            err.downcast_ref::<FetchError>(),
+            Some(FetchError::Status(reqwest::StatusCode::NOT_FOUND))
+        ));
+    }
+
+    #[tokio::test]
+    async fn timeout_through_retries_is_fetch_timeout() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .expect("client");
+        let err = get_with_retries(&client, &server.uri(), 0)
+            .await
+            .expect_err("slow server");
+        assert!(matches!(
+            err.downcast_ref::<FetchError>(),
+            Some(FetchError::Timeout(_))
+        ));
+        assert!(format!("{err:#}").starts_with("request timed out: "));
+    }
+
+    #[tokio::test]
+    async fn per_site_timeout_overrides_client_timeout() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(300)))
+            .mount(&server)
+            .await;
+        let config = HttpConfig {
+            timeout: Duration::from_millis(100),
+            site_timeouts: BTreeMap::from([("eksmo".to_string(), Duration::from_secs(5))]),
+            ..HttpConfig::default()
+        };
+        assert_eq!(
+            config.site_timeout(Sites::Eksmo),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(config.site_timeout(Sites::Labirint), None);
+        let client = config.build_client().expect("client");
+        let body =
+            get_with_timeout(&client, &server.uri(), 0, config.site_timeout(Sites::Eksmo)).await;
+        assert!(body.is_ok());
+        let err = get_with_timeout(
+            &client,
+            &server.uri(),
+            0,
+            config.site_timeout(Sites::Labirint),
+        )
+        .await
+        .expect_err("default timeout");
+        assert!(matches!(
+            err.downcast_ref::<FetchError>(),
+            Some(FetchError::Timeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn timeout_is_retried() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(100))
+            .build()
+            .expect("client");
+        let err = client
+            .get(server.uri())
+            .send()
+            .await
+            .expect_err("slow server");
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Timeout);
+        assert!(matches!(FetchError::from(err), FetchError::Timeout(_)));
+        // Первая попытка уже была выше: с одним повтором сервер увидит ещё две.
+        assert!(get_with_retries(&client, &server.uri(), 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cookie_from_warm_up_is_sent_with_page_request() {
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("set-cookie", "session=abc; Path=/"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/book/1"))
+            .and(header("cookie", "session=abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("full page"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/book/1"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+
+        let client = HttpConfig::default().build_client().expect("client");
+        let url = format!("{}/book/1", server.uri());
+        assert!(get_with_retries(&client, &url, 0).await.is_err());
+        warm_up_once(&client, &url).await.expect("warm up");
+        // Повторный прогрев того же хоста не ходит на главную.
+        warm_up_once(&client, &url).await.expect("warm up");
+        assert_eq!(
+            get_with_retries(&client, &url, 0).await.expect("page"),
+            "full page"
+        );
+    }
+
+    #[tokio::test]
+    async fn body_error_is_not_retried() {
+        let (url, accepted) = truncated_body_server().await;
+        let client = reqwest::Client::new();
+        let err = client
+            .get(&url)
+            .send()
+            .await
+            .expect("headers")
+            .text()
+            .await
+            .expect_err("truncated body");
+        assert_eq!(ErrorClass::of(&err), ErrorClass::Body);
+        assert!(!ErrorClass::of(&err).is_transient());
+        assert!(matches!(FetchError::from(err), FetchError::Body(_)));
+
+        accepted.store(0, Ordering::SeqCst);
+        assert!(get_with_retries(&client, &url, 3).await.is_err());
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn retry_after_is_clamped_to_max_backoff() {
+        use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+        let with = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+            headers
+        };
+        assert_eq!(retry_wait(&HeaderMap::new(), 2), 2);
+        assert_eq!(retry_wait(&with("3"), 1), 3);
+        assert_eq!(retry_wait(&with("86400"), 1), MAX_BACKOFF_SECS);
+        assert_eq!(retry_wait(&with("Wed, 21 Oct 2026 07:28:00 GMT"), 4), 4);
+    }
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(3)
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("книга"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+        let body = get_with_retries(&client, &server.uri(), 3)
+            .await
+            .expect("fourth request succeeds");
+        assert_eq!(body, "книга");
+    }
+
+    #[tokio::test]
+    async fn retry_stats_count_recovered_and_given_up() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/flaky"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("книга"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/down"))
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "0"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ok"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+        let stats = RetryStats::default();
+        let url = |p: &str| format!("{}{p}", server.uri());
+        for _ in 0..2 {
+            get_with_retries_counted(&client, &url("/ok"), 3, None, None, &stats)
+                .await
+                .expect("ok");
+        }
+        assert_eq!((stats.recovered(), stats.gave_up()), (0, 0));
+
+        get_with_retries_counted(&client, &url("/flaky"), 3, None, None, &stats)
+            .await
+            .expect("429 then 200");
+        assert_eq!(stats.recovered(), 1);
+        get_with_retries_counted(&client, &url("/down"), 2, None, None, &stats)
+            .await
+            .expect_err("503 every time");
+        assert_eq!(stats.gave_up(), 1);
+
+        let host = host_of(&server.uri()).expect("host");
+        assert_eq!(
+            stats.retries(),
+            BTreeMap::from([
+                ((host.clone(), "429".to_string()), 1),
+                ((host.clone(), "503".to_string()), 2),
+            ])
+        );
+        assert_eq!(
+            stats.summary(),
+            format!("retries 3 ({host} 429: 1, {host} 503: 2), recovered 1, gave up 1")
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_are_bounded() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "0"))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = reqwest::Client::new();
+        let err = get_with_retries(&client, &server.uri(), 1)
+            .await
+            .expect_err("retries exhausted");
+        assert_eq!(err.to_string(), "HTTP error: 503 Service Unavailable");
+    }
+
+    #[test]
+    fn timeout_profiles() {
+        let secs = |profile| {
+            let config =
+                HttpConfig::for_concurrency(8).with_timeouts(profile, TimeoutOverrides::default());
+            assert_eq!(config.pool_max_idle_per_host, 8);
+            (
+                config.connect_timeout.as_secs(),
+                config.timeout.as_secs(),
+                config.tcp_keepalive.as_secs(),
+            )
+        };
+        assert_eq!(secs(TimeoutProfile::Fast), (2, 8, 15));
+        assert_eq!(secs(TimeoutProfile::Default), (5, 15, 30));
+        assert_eq!(secs(TimeoutProfile::Patient), (15, 60, 60));
+        assert_eq!(
+            HttpConfig::default()
+                .with_timeouts(TimeoutProfile::Default, TimeoutOverrides::default()),
+            HttpConfig::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn http_version_is_applied_to_clients() {
+        let (url, _) = counting_server().await;
+        for (version, speaks_http1) in [
+            (HttpVersion::Auto, true),
+            (HttpVersion::Http1, true),
+            (HttpVersion::Http2PriorKnowledge, false),
+        ] {
+            let config = HttpConfig {
+                http_version: version,
+                ..HttpConfig::default()
+            };
+            assert_eq!(config.http_version, version);
+            let client = config.build_client().expect("client");
+            // Тестовый сервер понимает только HTTP/1.1.
+            let ok = client.get(&url).send().await.is_ok();
+            assert_eq!(ok, speaks_http1, "{version:?}");
+        }
+        assert_eq!(HttpConfig::default().http_version, HttpVersion::Auto);
+        assert_eq!(
+            "http1".parse::<HttpVersion>().expect("version"),
+            HttpVersion::Http1
+        );
+        assert_eq!(
+            "http2-prior-knowledge"
+                .parse::<HttpVersion>()
+                .expect("version"),
+            HttpVersion::Http2PriorKnowledge
+        );
+        assert!("http2".parse::<HttpVersion>().is_err());
+        assert!("http3".parse::<HttpVersion>().is_err());
+    }
+
+    #[test]
+    fn explicit_timeouts_override_profile() {
+        let overrides = TimeoutOverrides {
+            timeout: Some(Duration::from_secs(120)),
+            ..TimeoutOverrides::default()
+        };
+        let config = HttpConfig::default().with_timeouts(TimeoutProfile::Fast, overrides);
+        assert_eq!(config.connect_timeout, Duration::from_secs(2));
+        assert_eq!(config.timeout, Duration::from_secs(120));
+        assert_eq!(config.tcp_keepalive, Duration::from_secs(15));
+    }
+
+    #[test]
+    fn pool_scales_with_concurrency() {
+        assert_eq!(HttpConfig::for_concurrency(1).pool_max_idle_per_host, 4);
+        assert_eq!(HttpConfig::for_concurrency(32).pool_max_idle_per_host, 32);
+    }
+
+    #[test]
+    fn user_agents_file_feeds_rotation() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_user_agents_{}.txt", std::process::id()));
+        std::fs::write(&path, "# pool\nAgent/1\n\n  Agent/2  \nAgent/3\n").expect("write");
+        let agents = load_user_agents(&path).expect("agents");
+        assert_eq!(agents, ["Agent/1", "Agent/2", "Agent/3"]);
+
+        let config = HttpConfig {
+            user_agents: agents,
+            ..HttpConfig::default()
+        };
+        let mut seen: Vec<&str> = (0..6).filter_map(|_| config.next_user_agent()).collect();
+        assert_eq!(seen[..3], seen[3..]);
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen, ["Agent/1", "Agent/2", "Agent/3"]);
+        assert_eq!(HttpConfig::default().next_user_agent(), None);
+
+        std::fs::write(&path, "# only comments\n\n").expect("write");
+        assert!(load_user_agents(&path).is_err());
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    /// Управляющий порт без Tor: считает запросы смены цепочек.
+    #[derive(Clone, Default)]
+    struct CountingControl(Arc<AtomicUsize>);
+
+    impl TorControl for CountingControl {
+        fn new_identity(&self) -> BoxFuture<'_, anyhow::Result<()>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn tor_identity_rotates_every_n_requests() {
+        let control = CountingControl::default();
+        let rotator = IdentityRotator::new(control.clone(), 3);
+        let mut rotated_at = vec![];
+        for n in 1..=7 {
+            if rotator.on_request().await {
+                rotated_at.push(n);
+            }
+        }
+        assert_eq!(rotated_at, [3, 6]);
+        assert_eq!(control.0.load(Ordering::SeqCst), 2);
+    }
+
+    /// Управляющий порт Tor: отвечает `250 OK`, пока не встретит `reject`, и пишет команды.
+    async fn fake_control_port(
+        reject: &'static str,
+    ) -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let commands = Arc::new(std::sync::Mutex::new(vec![]));
+        let log = Arc::clone(&commands);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let reply = if line.starts_with(reject) {
+                    "515 Authentication failed\r\n"
+                } else {
+                    "250 OK\r\n"
+                };
+                log.lock().expect("lock").push(line);
+                if write.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (addr.to_string(), commands)
+    }
+
+    #[tokio::test]
+    async fn control_port_sends_newnym() {
+        let (addr, commands) = fake_control_port("nothing").await;
+        ControlPort::new(&addr, Some("s3\"cret".to_string()))
+            .new_identity()
+            .await
+            .expect("newnym");
+        assert_eq!(
+            *commands.lock().expect("lock"),
+            ["AUTHENTICATE \"s3\\\"cret\"", "SIGNAL NEWNYM", "QUIT"]
+        );
+
+        let (addr, commands) = fake_control_port("AUTHENTICATE").await;
+        let err = ControlPort::new(&addr, None)
+            .new_identity()
+            .await
+            .expect_err("rejected");
+        assert!(err.to_string().contains("rejected AUTHENTICATE"), "{err}");
+        assert_eq!(*commands.lock().expect("lock"), ["AUTHENTICATE"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn silent_control_port_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let addr = listener.local_addr().expect("addr").to_string();
+        // Соединение принимается, но ответа нет.
+        let _server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.expect("accept");
+            std::future::pending::<()>().await;
+        });
+        let err = ControlPort::new(&addr, None)
+            .new_identity()
+            .await
+            .expect_err("timeout");
+        assert!(err.to_string().contains("did not answer"), "{err}");
+    }
+
+    #[test]
+    fn invalid_tor_socks_is_an_error() {
+        let config = HttpConfig {
+            tor: Some(TorConfig {
+                socks: "socks5h://127.0.0.1:port".to_string(),
+                ..TorConfig::default()
+            }),
+            ..HttpConfig::default()
+        };
+        assert!(config.build_client().is_err());
+    }
+
+    #[tokio::test]
+    async fn tor_routes_clients_via_socks() {
+        let (url, accepted) = counting_server().await;
+        let dead = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let socks = format!("socks5h://{}", dead.local_addr().expect("addr"));
+        drop(dead);
+        let config = HttpConfig {
+            tor: Some(TorConfig {
+                socks,
+                ..TorConfig::default()
+            }),
+            ..HttpConfig::default()
+        };
+        let client = config.build_client().expect("client");
+        // Демона Tor нет: запрос падает на прокси и до сервера напрямую не доходит.
+        assert!(client.get(&url).send().await.is_err());
+        assert_eq!(accepted.load(Ordering::SeqCst), 0);
+    }
+}
@@ -1,54 +1,145 @@
-use anyhow::{Context, anyhow};
-use std::{sync::OnceLock, time::Duration};
+use anyhow::anyhow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
 use tracing::{debug, instrument, warn};
 
-use crate::parse_traits::{self, Author, BookParser, Description, Isbn, Price, Sites, Title};
-static AUTHOR_SEL_STR: &str = "tr.woocommerce-product-attributes-item:nth-child(1) > td:nth-child(2) > p:nth-child(1) > a:nth-child(1)";
-static ISBN_SEL_STR: &str = "tr.woocommerce-product-attributes-item--attribute_pa_isbn-issn-1 td p";
-static TITLE_SEL_STR: &str = ".single-post-title";
-static DESCR_SEL_STR: &str = ".woocommerce-product-details__short-description > p:nth-child(1)";
-static PRICE_SEL_STR: &str = "p.price > span:nth-child(1) > bdi:nth-child(1)";
+use crate::description::description_fallback;
+use crate::json_ld::{authors_fallback, page_availability, price_fallback, title_fallback};
+use crate::parse_traits::{
+    self, Author, Availability, BookParser, Description, Isbn, PriceQuote, Sites, Title,
+    absolute_image_url, book_page_signals, host_of, isbn_fallback, normalize_ws, parse_count_text,
+    parse_rating_text, path_segments, price_quote_from_text, split_authors,
+};
+use crate::selectors::{SelectorChain, spec_table};
+use crate::specs::{SpecsPage, SpecsTable};
+use crate::{http, page_cache};
+pub static AUTHOR_SEL_STR: &str = "tr.woocommerce-product-attributes-item:nth-child(1) > td:nth-child(2) > p:nth-child(1) > a:nth-child(1)";
+pub static ISBN_SEL_STR: &str =
+    "tr.woocommerce-product-attributes-item--attribute_pa_isbn-issn-1 td p";
+pub static TITLE_SEL_STR: &str = ".single-post-title";
+pub static DESCR_SEL_STR: &str = ".woocommerce-product-details__short-description > p:nth-child(1)";
+/// Весь блок цены: в нём же «от» и вторая сумма диапазона.
+pub static PRICE_SEL_STR: &str = "p.price";
+pub static RATING_SEL_STR: &str = ".woocommerce-product-rating strong.rating";
+pub static REVIEW_COUNT_SEL_STR: &str = ".woocommerce-product-rating .count";
+/// Ссылка на полноразмерную обложку в галерее товара; `og:image` у игры слов — логотип сайта.
+pub static COVER_SEL_STR: &str = ".woocommerce-product-gallery__image a";
+/// Строка таблицы атрибутов товара и её подпись.
+pub static SPEC_ROW_SEL_STR: &str = "tr.woocommerce-product-attributes-item";
+pub static SPEC_LABEL_SEL_STR: &str = ".woocommerce-product-attributes-item__label";
+
+/// Цепочки полей (см. `SelectorChain`): `*_SEL_STR` — вёрстка темы сайта, дальше — классы
+/// самого WooCommerce, которые остаются при смене темы: атрибут «Автор» по слагу, а не по
+/// номеру строки, `product_title` и цена в блоке `summary`.
+pub static AUTHOR_SELECTORS: SelectorChain = SelectorChain::new(
+    "authors",
+    &[
+        AUTHOR_SEL_STR,
+        "tr.woocommerce-product-attributes-item--attribute_pa_avtor td a",
+    ],
+);
+pub static TITLE_SELECTORS: SelectorChain =
+    SelectorChain::new("title", &[TITLE_SEL_STR, ".product_title"]);
+pub static PRICE_SELECTORS: SelectorChain =
+    SelectorChain::new("price", &[PRICE_SEL_STR, ".summary .price"]);
 
 static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-static AUTHOR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SPEC_ROW_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static SPEC_LABEL_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static ISBN_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static TITLE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static DESCR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static PRICE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static RATING_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static REVIEW_COUNT_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static COVER_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+
+/// Сумма вида `895,00 ₽` или `1 200,00 ₽` в копейках.
+fn amount_to_kopecks(text: &str) -> anyhow::Result<parse_traits::Price> {
+    let locale = Sites::IgraSlov.locale();
+    let mut price_string = text.replace(Sites::IgraSlov.currency().symbol(), "");
+    price_string.retain(|x| x != locale.decimal_separator() && !x.is_whitespace());
+    debug!(price_string);
+    price_string.parse()
+}
+
+/// Цена из разметки страницы; без JSON-LD запасного пути (см. `parse_price_quote`).
+/// Зачёркнутая старая цена (`<del>`) при скидке пропускается.
+fn price_from_selector(ctx: &scraper::Html) -> anyhow::Result<PriceQuote> {
+    let Some(price_el) = PRICE_SELECTORS.select(ctx).pop() else {
+        return Err(anyhow!("can't parse price"));
+    };
+    let text: String = price_el
+        .children()
+        .filter_map(|child| match child.value() {
+            scraper::Node::Text(text) => Some(text.to_string()),
+            scraper::Node::Element(el) if el.name() != "del" => {
+                scraper::ElementRef::wrap(child).map(|el| el.text().collect())
+            }
+            _ => None,
+        })
+        .collect();
+    price_quote_from_text(&text, amount_to_kopecks)
+        .inspect_err(|e| warn!("can't parse price {text:?}: {e}"))
+}
+
+/// Таблица характеристик страницы целиком: подпись строки → значение (см. `Specs`).
+pub fn parse_specs(ctx: &scraper::Html) -> HashMap<String, String> {
+    let row = SPEC_ROW_SEL
+        .get_or_init(|| scraper::Selector::parse(SPEC_ROW_SEL_STR).expect("spec row selector"));
+    let label = SPEC_LABEL_SEL
+        .get_or_init(|| scraper::Selector::parse(SPEC_LABEL_SEL_STR).expect("spec label selector"));
+    spec_table(ctx, row, label)
+}
+
 pub struct IgraSlov;
+
+impl SpecsTable for IgraSlov {
+    fn parse_specs(html: &scraper::Html) -> HashMap<String, String> {
+        parse_specs(html)
+    }
+}
+
 impl BookParser for IgraSlov {
-    const SITE: parse_traits::Sites = Sites::IgraSlov;
+    fn site(&self) -> Sites {
+        Sites::IgraSlov
+    }
+
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("authors", AUTHOR_SEL_STR),
+            ("isbn", ISBN_SEL_STR),
+            ("title", TITLE_SEL_STR),
+            ("description", DESCR_SEL_STR),
+            ("price", PRICE_SEL_STR),
+            ("rating", RATING_SEL_STR),
+            ("review_count", REVIEW_COUNT_SEL_STR),
+            ("cover", COVER_SEL_STR),
+        ]
+        .into_iter()
+        .map(|(field, sel)| (field, sel.to_string()))
+        .collect()
+    }
 
+    /// `/product/<slug>/`; категории WooCommerce лежат под `/product-category/`.
+    fn is_book_url(url: &str) -> bool {
+        Sites::IgraSlov.matches_host(&host_of(url).unwrap_or_default())
+            && path_segments(url)
+                .is_some_and(|path| matches!(path.as_slice(), [product, _] if product == "product"))
+    }
     type Url = String;
 
-    type Context = scraper::Html;
+    type Context = SpecsPage<IgraSlov>;
+
+    fn looks_like_book_page(ctx: &Self::Context) -> bool {
+        let isbn =
+            ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
+        book_page_signals(ctx, PRICE_SELECTORS.any(), isbn)
+    }
     #[instrument(skip(self),fields(url=%url))]
-    async fn fetch(&self, url: &Self::Url) -> anyhow::Result<Self::Context> {
-        let client = CLIENT.get_or_init(|| {
-            reqwest::Client::builder()
-                .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-                .connect_timeout(Duration::from_secs(5))
-                .timeout(Duration::from_secs(15))
-                .pool_max_idle_per_host(4)
-                .tcp_keepalive(Some(Duration::from_secs(30)))
-                .redirect(reqwest::redirect::Policy::limited(5))
-                .build()
-                .expect("http client")
-        });
-        match client.get(url).send().await {
-            Ok(response) if !response.status().is_success() => {
-                warn!(
-                    "bad status code probably rate limit code: {}",
-                    response.status()
-                );
-                return Err(anyhow!("response status is not success"));
-            }
-            Ok(response) => {
-                let resp = response.text().await?;
-                Ok(scraper::Html::parse_document(&resp))
-            }
-            Err(e) => return Err(e.into()),
-        }
+    async fn fetch_body(&self, url: &Self::Url) -> anyhow::Result<String> {
+        let client = CLIENT.get_or_init(|| http::global().build_client().expect("http client"));
+        let body = http::fetch_page(client, BookParser::site(self), url).await?;
+        page_cache::observe(url, &body);
+        Ok(body)
     }
 
     #[instrument(skip(self,ctx),fields(url=%log_url))]
@@ -57,20 +148,21 @@ impl BookParser for IgraSlov {
         ctx: &Self::Context,
         log_url: &Self::Url,
     ) -> anyhow::Result<Vec<Author>> {
-        let author_selector = AUTHOR_SEL
-            .get_or_init(|| scraper::Selector::parse(AUTHOR_SEL_STR).expect("author selector"));
-
-        Ok(ctx
-            .select(author_selector)
-            .map(|node| Author::new(node.text().collect::<String>()))
-            .collect())
+        Ok(authors_fallback(
+            ctx,
+            AUTHOR_SELECTORS
+                .select(ctx)
+                .iter()
+                .flat_map(|node| split_authors(&node.text().collect::<String>()))
+                .collect(),
+        ))
     }
     #[instrument(skip(self, ctx, _log_url))]
     async fn parse_isbn(&self, ctx: &Self::Context, _log_url: &Self::Url) -> anyhow::Result<Isbn> {
         let isbn_selector =
             ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
 
-        match ctx.select(isbn_selector).next_back() {
+        let structured = match ctx.select(isbn_selector).next_back() {
             Some(elem) => {
                 let raw: String = elem.text().collect::<String>().replace("\u{a0}", "");
                 match Isbn::try_from(raw) {
@@ -85,24 +177,21 @@ impl BookParser for IgraSlov {
                 warn!(target: "time","ISBN not found on page {_log_url}");
                 Err(anyhow!("can't find isbn on this page"))
             }
-        }
+        };
+        structured
+            .or_else(|e| ctx.specs().isbn().ok_or(e))
+            .or_else(|e| isbn_fallback(ctx, e))
     }
 
     #[instrument(skip(self,ctx),fields(url=%log_url))]
     async fn parse_title(&self, ctx: &Self::Context, log_url: &Self::Url) -> anyhow::Result<Title> {
-        let book_title_selector = TITLE_SEL
-            .get_or_init(|| scraper::Selector::parse(TITLE_SEL_STR).expect("title selector"));
-        let title = {
-            let mut title = ctx
-                .select(book_title_selector)
-                .map(|node| node.text().collect::<String>())
-                .collect::<String>();
-            if let Some(striped) = title.strip_prefix("_") {
-                title = striped.to_string();
-            }
-            title
-        };
-        Ok(Title::new(title))
+        // Ведущий `_` срезает `Title::new`.
+        let title = TITLE_SELECTORS
+            .select(ctx)
+            .iter()
+            .map(|node| node.text().collect::<String>())
+            .collect::<String>();
+        Ok(title_fallback(ctx, Title::new(title)))
     }
 
     #[instrument(skip(self, ctx))]
@@ -116,27 +205,65 @@ impl BookParser for IgraSlov {
             .select(book_descr_sel)
             .map(|node| node.text().collect::<String>())
             .collect();
-        Ok(Description::new(descr))
+        Ok(description_fallback(ctx, Description::new(descr)))
     }
 
     async fn parse_price(&self, ctx: &Self::Context) -> anyhow::Result<parse_traits::Price> {
-        let price_sel = PRICE_SEL
-            .get_or_init(|| scraper::Selector::parse(PRICE_SEL_STR).expect("price selector"));
-        let mut price_string: String = match ctx.select(price_sel).next_back() {
-            Some(elref) => elref.text().collect(),
-            None => return Err(anyhow!("can't parse price")),
-        };
-        let forbidden_symb = [',', '\u{a0}', '₽'];
-        price_string.retain(|x| !forbidden_symb.contains(&x));
-        debug!(price_string);
-        let price = match price_string.parse() {
-            Ok(price) => price,
-            Err(e) => {
-                warn!("can't parse price : {e}");
-                return Err(e);
+        Ok(self.parse_price_quote(ctx).await?.min)
+    }
+    async fn parse_price_quote(&self, ctx: &Self::Context) -> anyhow::Result<PriceQuote> {
+        match price_from_selector(ctx) {
+            Ok(quote) if u128::from(quote.min) > 0 => Ok(quote),
+            selected => {
+                price_fallback(ctx, selected.map(|quote| quote.min)).map(PriceQuote::single)
             }
-        };
-        Ok(price)
+        }
+    }
+    async fn parse_rating(&self, ctx: &Self::Context) -> anyhow::Result<Option<f32>> {
+        let rating_sel = RATING_SEL
+            .get_or_init(|| scraper::Selector::parse(RATING_SEL_STR).expect("rating selector"));
+        Ok(ctx
+            .select(rating_sel)
+            .next()
+            .and_then(|node| parse_rating_text(&node.text().collect::<String>())))
+    }
+    async fn parse_review_count(&self, ctx: &Self::Context) -> anyhow::Result<Option<u32>> {
+        let count_sel = REVIEW_COUNT_SEL.get_or_init(|| {
+            scraper::Selector::parse(REVIEW_COUNT_SEL_STR).expect("review count selector")
+        });
+        Ok(ctx
+            .select(count_sel)
+            .next()
+            .and_then(|node| parse_count_text(&node.text().collect::<String>())))
+    }
+    async fn parse_cover_url(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        let cover_sel = COVER_SEL
+            .get_or_init(|| scraper::Selector::parse(COVER_SEL_STR).expect("cover selector"));
+        Ok(ctx
+            .select(cover_sel)
+            .filter_map(|node| node.value().attr("href"))
+            .find_map(absolute_image_url))
+    }
+    async fn parse_availability(
+        &self,
+        ctx: &Self::Context,
+    ) -> anyhow::Result<Option<Availability>> {
+        Ok(page_availability(ctx))
+    }
+    async fn parse_original_title(&self, ctx: &Self::Context) -> anyhow::Result<Option<Title>> {
+        Ok(ctx.specs().original_title())
+    }
+    async fn parse_publisher(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().publisher().map(normalize_ws))
+    }
+    async fn parse_series(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().series())
+    }
+    async fn parse_language(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(ctx.specs().language())
+    }
+    async fn parse_series_index(&self, ctx: &Self::Context) -> anyhow::Result<Option<u16>> {
+        Ok(ctx.specs().series_index())
     }
     // #[instrument(skip(self),fields(url=&url))]
     // async fn parse_book(&self, url: Self::Url) -> anyhow::Result<parse_traits::Book<Self::Url>> {
@@ -176,12 +303,16 @@ impl BookParser for IgraSlov {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parse_traits::PageHtml;
+    use crate::specs::Specs;
+
+    type Page = SpecsPage<IgraSlov>;
     use std::fs;
     const EXPECTED_PRICE: u128 = 89500;
 
-    fn load_html() -> scraper::Html {
+    fn load_html() -> Page {
         let html = fs::read_to_string("page_examples/igraslov.html").expect("read igraslov.html");
-        scraper::Html::parse_document(&html)
+        Page::from_body(&html)
     }
 
     #[tokio::test]
@@ -194,6 +325,102 @@ mod tests {
         assert!(!authors.is_empty());
     }
 
+    #[test]
+    fn specs_table_and_derived_fields() {
+        let table = parse_specs(&load_html());
+        assert_eq!(
+            table.get("Переводчик").map(String::as_str),
+            Some("Сухляева В.")
+        );
+        assert_eq!(table.get("Язык").map(String::as_str), Some("Русский"));
+        let specs = Specs::from(table);
+        assert_eq!(
+            specs.isbn().map(|isbn| isbn.as_str().to_string()),
+            Some("978-5-04-122366-3".to_string())
+        );
+        assert_eq!(specs.publisher(), Some("Inspiria"));
+    }
+
+    #[tokio::test]
+    async fn isbn_from_reordered_specs_row() {
+        // Строка ISBN переехала и потеряла класс атрибута: селектор её не видит, таблица — да.
+        let ctx = Page::from_body(
+            r#"<table><tbody>
+            <tr class="woocommerce-product-attributes-item"><th class="woocommerce-product-attributes-item__label">ISBN/ISSN</th><td><p>978-5-04-122366-3</p></td></tr>
+            <tr class="woocommerce-product-attributes-item woocommerce-product-attributes-item--attribute_pa_izdatelstvo"><th class="woocommerce-product-attributes-item__label">Издательство</th><td><p>Inspiria</p></td></tr>
+            </tbody></table>"#,
+        );
+        let url = "https://igraslov.store/product/example".to_string();
+        let isbn = IgraSlov.parse_isbn(&ctx, &url).await.expect("isbn");
+        assert_eq!(isbn.as_str(), "978-5-04-122366-3");
+    }
+
+    #[tokio::test]
+    async fn fields_fall_back_to_woocommerce_classes() {
+        // Другая тема: классов темы нет, атрибут «Автор» не первой строкой.
+        let ctx = Page::from_body(
+            r#"<div class="summary"><h1 class="product_title">Джейн Эйр</h1>
+            <span class="price">895,00 ₽</span></div>
+            <table><tr class="woocommerce-product-attributes-item woocommerce-product-attributes-item--attribute_pa_yazyk-1"><td>Русский</td></tr>
+            <tr class="woocommerce-product-attributes-item woocommerce-product-attributes-item--attribute_pa_avtor"><td><p><a href="/avtor/bronte/">Шарлотта Бронте</a></p></td></tr></table>"#,
+        );
+        let url = "https://igraslov.store/product/example".to_string();
+        let title = IgraSlov.parse_title(&ctx, &url).await.expect("title");
+        assert_eq!(title.as_str(), "Джейн Эйр");
+        let authors = IgraSlov.parse_authors(&ctx, &url).await.expect("authors");
+        assert_eq!(authors[0].as_str(), "Шарлотта Бронте");
+        let price = IgraSlov.parse_price(&ctx).await.expect("price");
+        assert_eq!(u128::from(price), EXPECTED_PRICE);
+    }
+
+    #[test]
+    fn book_page_vs_series_page() {
+        assert!(IgraSlov::looks_like_book_page(&load_html()));
+        // Подборка WooCommerce: цены только в карточках `ul.products`, блока `p.price` товара нет.
+        let series = Page::from_body(
+            r#"<main><h1 class="page-title">Серия «Руны»</h1><ul class="products columns-4">
+            <li class="product"><a href="/product/runy-1/"><h2 class="woocommerce-loop-product__title">Руны. Книга 1</h2>
+            <span class="price"><bdi>895,00&nbsp;₽</bdi></span></a></li>
+            <li class="product"><a href="/product/runy-2/"><h2 class="woocommerce-loop-product__title">Руны. Книга 2</h2>
+            <span class="price"><bdi>925,00&nbsp;₽</bdi></span></a></li></ul></main>"#,
+        );
+        assert!(!IgraSlov::looks_like_book_page(&series));
+    }
+
+    #[tokio::test]
+    async fn publisher_from_specs() {
+        let publisher = IgraSlov
+            .parse_publisher(&load_html())
+            .await
+            .expect("no error");
+        assert_eq!(publisher.as_deref(), Some("Inspiria"));
+    }
+
+    #[tokio::test]
+    async fn original_title_absent_on_domestic_book() {
+        let original = IgraSlov
+            .parse_original_title(&load_html())
+            .await
+            .expect("no error");
+        assert_eq!(original, None);
+    }
+
+    #[tokio::test]
+    async fn original_title_of_translated_book() {
+        let ctx = Page::from_body(
+            r#"<table class="woocommerce-product-attributes shop_attributes"><tbody>
+            <tr class="woocommerce-product-attributes-item woocommerce-product-attributes-item--attribute_pa_avtor"><th class="woocommerce-product-attributes-item__label">Автор</th><td class="woocommerce-product-attributes-item__value"><p>Шарлотта Бронте</p></td></tr>
+            <tr class="woocommerce-product-attributes-item woocommerce-product-attributes-item--attribute_pa_original"><th class="woocommerce-product-attributes-item__label">Название оригинала</th><td class="woocommerce-product-attributes-item__value"><p>Jane Eyre</p></td></tr>
+            </tbody></table>"#,
+        );
+        let original = IgraSlov
+            .parse_original_title(&ctx)
+            .await
+            .expect("no error")
+            .expect("original title");
+        assert_eq!(original.as_str(), "Jane Eyre");
+    }
+
     #[tokio::test]
     async fn parse_isbn_from_example() {
         let parser = IgraSlov;
@@ -220,7 +447,7 @@ mod tests {
     #[tokio::test]
     async fn parse_isbn_not_found() {
         let parser = IgraSlov;
-        let html = scraper::Html::parse_document("<html><body></body></html>");
+        let html = Page::from_body("<html><body></body></html>");
         let url = "https://igraslov.store/product/example".to_string();
         let res = parser.parse_isbn(&html, &url).await;
         assert!(res.is_err());
@@ -229,7 +456,6 @@ mod tests {
     async fn parse_descr() {
         let parser = IgraSlov;
         let ctx = load_html();
-        let url = "https://igraslov.store/product/example".to_string();
         let descr = parser.parse_description(&ctx).await.expect("should");
         assert!(descr.as_str().len() > 10);
     }
@@ -240,4 +466,90 @@ mod tests {
         let price = parser.parse_price(&ctx).await.expect("should be");
         assert_eq!(u128::from(price), EXPECTED_PRICE);
     }
+    #[test]
+    fn price_from_and_range() {
+        let quote = |body: &str| {
+            price_from_selector(&Page::from_body(&format!(
+                "<html><body>{body}</body></html>"
+            )))
+        };
+        let plain = quote(r#"<p class="price"><span class="amount"><bdi>895,00&nbsp;<span>₽</span></bdi></span></p>"#)
+            .expect("plain");
+        assert_eq!(u128::from(plain.min), 89_500);
+        assert!(plain.max.is_none() && !plain.is_from);
+
+        let from = quote(
+            r#"<p class="price">от <span class="amount"><bdi>895,00&nbsp;₽</bdi></span></p>"#,
+        )
+        .expect("from");
+        assert_eq!(u128::from(from.min), 89_500);
+        assert!(from.is_from);
+        assert!(from.max.is_none());
+
+        let range = quote(
+            r#"<p class="price"><span class="amount"><bdi>895,00&nbsp;₽</bdi></span> &ndash; <span class="amount"><bdi>1&nbsp;200,00&nbsp;₽</bdi></span></p>"#,
+        )
+        .expect("range");
+        assert_eq!(u128::from(range.min), 89_500);
+        assert_eq!(range.max.map(u128::from), Some(120_000));
+        assert!(!range.is_from);
+
+        // Скидка: старая цена в <del> не считается диапазоном.
+        let sale = quote(
+            r#"<p class="price"><del><span class="amount"><bdi>1&nbsp;200,00&nbsp;₽</bdi></span></del> <ins><span class="amount"><bdi>895,00&nbsp;₽</bdi></span></ins></p>"#,
+        )
+        .expect("sale");
+        assert_eq!(u128::from(sale.min), 89_500);
+        assert!(sale.max.is_none());
+    }
+    #[test]
+    fn book_urls_are_recognized() {
+        assert!(IgraSlov::is_book_url(
+            "https://igraslov.store/product/dzhejn-ejr-tvyordyj/"
+        ));
+        for url in [
+            "https://igraslov.store/product-category/knigi/",
+            "https://igraslov.store/shop/",
+            "https://igraslov.store/product/",
+            "https://eksmo.ru/product/dzhejn-ejr/",
+        ] {
+            assert!(!IgraSlov::is_book_url(url), "{url}");
+        }
+    }
+    #[tokio::test]
+    async fn cover_url_from_gallery_not_logo() {
+        let cover = IgraSlov
+            .parse_cover_url(&load_html())
+            .await
+            .expect("cover")
+            .expect("gallery link");
+        assert!(cover.starts_with("https://i0.wp.com/igraslov.store/wp-content/uploads/2024/04/"));
+        assert!(cover.contains(".jpeg"));
+    }
+    #[tokio::test]
+    async fn unrated_book_has_no_rating() {
+        let parser = IgraSlov;
+        let ctx = load_html();
+        assert_eq!(parser.parse_rating(&ctx).await.expect("rating"), None);
+        assert_eq!(
+            parser.parse_review_count(&ctx).await.expect("reviews"),
+            None
+        );
+    }
+    #[tokio::test]
+    async fn parse_fields_matches_per_field() {
+        let parser = IgraSlov;
+        let ctx = load_html();
+        let url = "https://igraslov.store/product/example".to_string();
+        let fields = parser.parse_fields(&ctx, &url).await.expect("fields");
+        let authors = parser.parse_authors(&ctx, &url).await.expect("authors");
+        let isbn = parser.parse_isbn(&ctx, &url).await.expect("isbn");
+        let title = parser.parse_title(&ctx, &url).await.expect("title");
+        let descr = parser.parse_description(&ctx).await.expect("descr");
+        assert_eq!(fields.authors, authors);
+        assert_eq!(fields.isbn, isbn);
+        assert_eq!(fields.title, title);
+        assert_eq!(fields.description.as_str(), descr.as_str());
+        assert_eq!(u128::from(fields.price), EXPECTED_PRICE);
+    }
 }
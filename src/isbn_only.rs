@@ -0,0 +1,94 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+
+use crate::parse_traits::Isbn;
+use crate::pipeline::{self, ParseFn, Shutdown, default_parse_workers};
+use crate::registry::ParserRegistry;
+
+/// Файл `--isbn-only`.
+pub static ISBNS_FILE: &str = "isbns.txt";
+
+/// Разбор одной загруженной страницы ради ISBN через парсер сайта.
+pub fn isbn_from_page(registry: &ParserRegistry, url: String, body: &str) -> anyhow::Result<Isbn> {
+    registry
+        .for_url(&url)
+        .ok_or_else(|| anyhow!("no parser registered for url: {url}"))?
+        .parse_isbn_blocking(url, body)
+}
+
+/// Тот же конвейер загрузка → разбор, что и для книг, но со страницы берётся только ISBN.
+pub async fn collect_isbns(
+    registry: Arc<ParserRegistry>,
+    urls: Vec<String>,
+    fetch_concurrency: usize,
+    shutdown: Shutdown,
+    mut on_result: impl FnMut(&str, &anyhow::Result<Isbn>),
+) -> Vec<anyhow::Result<Isbn>> {
+    let parse_registry = Arc::clone(&registry);
+    let parse: ParseFn<Isbn> =
+        Arc::new(move |url, body| isbn_from_page(&parse_registry, url, &body));
+    pipeline::run(
+        urls,
+        |url| {
+            let registry = &registry;
+            async move {
+                match registry.for_url(&url) {
+                    Some(parser) => parser.fetch_body_dyn(url).await,
+                    None => Err(anyhow!("no parser registered for url: {url}")),
+                }
+            }
+        },
+        parse,
+        fetch_concurrency,
+        default_parse_workers(),
+        shutdown,
+        |url, result| on_result(url, result),
+    )
+    .await
+}
+
+/// По ISBN на строку, в порядке получения.
+pub fn write_isbns<'a>(
+    path: &Path,
+    isbns: impl IntoIterator<Item = &'a Isbn>,
+) -> anyhow::Result<usize> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let mut written = 0;
+    for isbn in isbns {
+        writeln!(out, "{}", isbn.as_str())?;
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isbn_only_skips_other_fields() {
+        let registry = ParserRegistry::default();
+        // Ни заголовка, ни цены: полный `Book` отсюда не собрать, а ISBN есть.
+        let body = r#"<html><body><div class="isbn">ISBN: 978-5-9268-3015-3</div></body></html>"#;
+        let isbn = isbn_from_page(
+            &registry,
+            "https://www.labirint.ru/books/801841/".to_string(),
+            body,
+        )
+        .expect("isbn");
+        assert_eq!(isbn.as_str(), "978-5-9268-3015-3");
+        assert!(isbn_from_page(&registry, "https://example.com/1".to_string(), body).is_err());
+
+        let path = std::env::temp_dir().join(format!("bd_parser_isbns_{}.txt", std::process::id()));
+        assert_eq!(write_isbns(&path, [&isbn, &isbn]).expect("write"), 2);
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "978-5-9268-3015-3\n978-5-9268-3015-3\n"
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,330 @@
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::parse_traits::{Author, Availability, Isbn, Price, Title};
+use crate::selectors;
+
+/// Поля книги из разметки schema.org (`<script type="application/ld+json">`).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct JsonLdBook {
+    pub name: Option<String>,
+    pub authors: Vec<String>,
+    pub isbn: Option<String>,
+    /// Цена в копейках, как `Price`.
+    pub price: Option<u128>,
+    pub availability: Option<Availability>,
+}
+
+fn has_book_type(node: &Value) -> bool {
+    let is_book = |t: &Value| matches!(t.as_str(), Some("Book" | "Product"));
+    match &node["@type"] {
+        Value::Array(types) => types.iter().any(is_book),
+        t => is_book(t),
+    }
+}
+
+/// Первый узел `Book`/`Product`: на странице бывает несколько скриптов,
+/// узлы лежат в массивах и в `@graph`.
+fn find_book_node(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(items) => items.iter().find_map(find_book_node),
+        Value::Object(map) => {
+            if has_book_type(value) {
+                return Some(value);
+            }
+            map.get("@graph").and_then(find_book_node)
+        }
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// `author` бывает строкой, объектом `Person` и массивом из них.
+fn authors(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(authors).collect(),
+        Value::Object(_) => text(&value["name"]).into_iter().collect(),
+        other => text(other).into_iter().collect(),
+    }
+}
+
+/// `"895.00"`/`895` в копейки.
+pub fn price_to_kopecks(text: &str) -> Option<u128> {
+    let text = text.trim().replace(',', ".");
+    let (rubles, kopecks) = text.split_once('.').unwrap_or((&text, ""));
+    if rubles.is_empty() || kopecks.len() > 2 {
+        return None;
+    }
+    let rubles: u128 = rubles.parse().ok()?;
+    let kopecks: u128 = match kopecks.len() {
+        0 => 0,
+        1 => kopecks.parse::<u128>().ok()? * 10,
+        _ => kopecks.parse().ok()?,
+    };
+    Some(rubles * 100 + kopecks)
+}
+
+/// `offers` — объект или массив; цена либо в `price`, либо в `priceSpecification`.
+fn offer_price(offers: &Value) -> Option<u128> {
+    match offers {
+        Value::Array(items) => items.iter().find_map(offer_price),
+        Value::Object(_) => text(&offers["price"])
+            .and_then(|p| price_to_kopecks(&p))
+            .or_else(|| offer_price(&offers["priceSpecification"])),
+        _ => None,
+    }
+}
+
+/// `offers.availability` первого предложения, где оно указано.
+fn offer_availability(offers: &Value) -> Option<Availability> {
+    match offers {
+        Value::Array(items) => items.iter().find_map(offer_availability),
+        Value::Object(_) => {
+            text(&offers["availability"]).and_then(|a| Availability::from_schema_org(&a))
+        }
+        _ => None,
+    }
+}
+
+/// Разбирает JSON-LD страницы и возвращает первый узел книги или товара.
+/// Сломанный JSON в одном скрипте не мешает искать в остальных.
+pub fn json_ld_book(ctx: &scraper::Html) -> Option<JsonLdBook> {
+    ctx.select(selectors::json_ld_script()).find_map(|script| {
+        let raw = script.text().collect::<String>();
+        let value: Value = serde_json::from_str(&raw)
+            .inspect_err(|e| debug!("skipping invalid json-ld: {e}"))
+            .ok()?;
+        let node = find_book_node(&value)?;
+        Some(JsonLdBook {
+            name: text(&node["name"]),
+            authors: authors(&node["author"]),
+            isbn: text(&node["isbn"]).or_else(|| text(&node["gtin13"])),
+            price: offer_price(&node["offers"]),
+            availability: offer_availability(&node["offers"]),
+        })
+    })
+}
+
+/// Наличие из JSON-LD, иначе из microdata `itemprop="availability"`.
+pub fn page_availability(ctx: &scraper::Html) -> Option<Availability> {
+    json_ld_book(ctx)
+        .and_then(|book| book.availability)
+        .or_else(|| {
+            ctx.select(selectors::availability()).find_map(|node| {
+                let value = node.value();
+                value
+                    .attr("content")
+                    .or_else(|| value.attr("href"))
+                    .and_then(Availability::from_schema_org)
+            })
+        })
+}
+
+/// Запасной путь для `parse_authors`: авторы из JSON-LD, если селектор ничего не нашёл.
+/// Пустые имена (в том числе отклонённые `Author::new`) отбрасываются.
+pub fn authors_fallback(ctx: &scraper::Html, authors: Vec<Author>) -> Vec<Author> {
+    let authors: Vec<Author> = authors
+        .into_iter()
+        .filter(|a| !a.as_str().trim().is_empty())
+        .collect();
+    if !authors.is_empty() {
+        return authors;
+    }
+    match json_ld_book(ctx).filter(|book| !book.authors.is_empty()) {
+        Some(book) => {
+            warn!("authors selector matched nothing, took authors from json-ld");
+            book.authors.into_iter().map(Author::new).collect()
+        }
+        None => authors,
+    }
+}
+
+/// Запасной путь для `parse_title`: `name` из JSON-LD вместо пустого заголовка.
+pub fn title_fallback(ctx: &scraper::Html, title: Title) -> Title {
+    if !title.as_str().trim().is_empty() {
+        return title;
+    }
+    match json_ld_book(ctx).and_then(|book| book.name) {
+        Some(name) => {
+            warn!("title selector matched nothing, took title from json-ld");
+            Title::new(name)
+        }
+        None => title,
+    }
+}
+
+/// ISBN из JSON-LD; иначе исходная ошибка.
+pub fn isbn_from_json_ld(ctx: &scraper::Html, err: anyhow::Error) -> anyhow::Result<Isbn> {
+    match json_ld_book(ctx)
+        .and_then(|book| book.isbn)
+        .and_then(|isbn| Isbn::try_from(isbn).ok())
+    {
+        Some(isbn) => {
+            warn!(%isbn, "isbn selector failed ({err}), took isbn from json-ld");
+            Ok(isbn)
+        }
+        None => Err(err),
+    }
+}
+
+/// Запасной путь для `parse_price`: `offers.price` из JSON-LD, если селектор дал ошибку
+/// или нулевую цену; иначе исходный результат.
+pub fn price_fallback(
+    ctx: &scraper::Html,
+    selected: anyhow::Result<Price>,
+) -> anyhow::Result<Price> {
+    if selected.as_ref().is_ok_and(|price| u128::from(*price) > 0) {
+        return selected;
+    }
+    match json_ld_book(ctx).and_then(|book| book.price) {
+        Some(price) => {
+            warn!(price, "price selector failed, took price from json-ld");
+            Ok(Price::from(price))
+        }
+        None => selected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    const BOOK_PAGE: &str = r#"<html><head>
+<script type="application/ld+json">{"@type": "Organization", "name": "Магазин"}</script>
+<script type="application/ld+json">{ broken </script>
+<script type="application/ld+json">
+{
+  "@context": "https://schema.org",
+  "@graph": [
+    {"@type": "BreadcrumbList", "name": "Главная"},
+    {
+      "@type": ["Book", "Product"],
+      "name": "Джейн Эйр",
+      "author": [{"@type": "Person", "name": "Шарлотта Бронте"}, "Эмили Бронте"],
+      "isbn": "978-5-9268-3015-3",
+      "offers": {"@type": "Offer", "price": "1084.5", "priceCurrency": "RUB"}
+    }
+  ]
+}
+</script></head><body><h1></h1></body></html>"#;
+
+    #[test]
+    fn book_node_is_found_in_graph() {
+        let html = scraper::Html::parse_document(BOOK_PAGE);
+        assert_eq!(
+            json_ld_book(&html),
+            Some(JsonLdBook {
+                name: Some("Джейн Эйр".to_string()),
+                authors: vec!["Шарлотта Бронте".to_string(), "Эмили Бронте".to_string()],
+                isbn: Some("978-5-9268-3015-3".to_string()),
+                price: Some(108_450),
+                availability: None,
+            })
+        );
+    }
+
+    #[test]
+    fn fallbacks_fill_missing_fields() {
+        let html = scraper::Html::parse_document(BOOK_PAGE);
+        let authors = authors_fallback(&html, vec![]);
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].as_str(), "Шарлотта Бронте");
+        assert_eq!(
+            title_fallback(&html, Title::new(String::new())).as_str(),
+            "Джейн Эйр"
+        );
+        assert_eq!(
+            isbn_from_json_ld(&html, anyhow!("no isbn"))
+                .expect("isbn")
+                .as_str(),
+            "978-5-9268-3015-3"
+        );
+        assert_eq!(
+            u128::from(price_fallback(&html, Err(anyhow!("no price"))).expect("price")),
+            108_450
+        );
+        assert_eq!(
+            u128::from(price_fallback(&html, Ok(Price::from(0))).expect("price")),
+            108_450
+        );
+        // Найденное селектором не перетирается.
+        assert_eq!(
+            u128::from(price_fallback(&html, Ok(Price::from(100))).expect("price")),
+            100
+        );
+        assert_eq!(
+            title_fallback(&html, Title::new("Своё".to_string())).as_str(),
+            "Своё"
+        );
+    }
+
+    #[tokio::test]
+    async fn site_parser_falls_back_to_json_ld() {
+        use crate::eksmo::EksmoParser;
+        use crate::parse_traits::{BookParser, PageHtml};
+
+        // Вёрстка сменилась: ни один CSS-селектор eksmo не находит полей.
+        let html = crate::specs::SpecsPage::<EksmoParser>::from_body(BOOK_PAGE);
+        let url = "https://eksmo.ru/book/dzheyn-eyr/".to_string();
+        let fields = EksmoParser.parse_fields(&html, &url).await.expect("fields");
+        assert_eq!(fields.title.as_str(), "Джейн Эйр");
+        assert_eq!(fields.authors[1].as_str(), "Эмили Бронте");
+        assert_eq!(fields.isbn.as_str(), "978-5-9268-3015-3");
+        assert_eq!(u128::from(fields.price), 108_450);
+    }
+
+    #[test]
+    fn product_price_specification() {
+        let html = scraper::Html::parse_document(include_str!("../page_examples/igraslov.html"));
+        let book = json_ld_book(&html).expect("product node");
+        assert_eq!(book.price, Some(89_500));
+        assert_eq!(book.availability, Some(Availability::InStock));
+        assert!(
+            book.name
+                .is_some_and(|n| n.contains("Последняя история Мины Ли"))
+        );
+    }
+
+    #[test]
+    fn availability_from_json_ld_or_microdata() {
+        let labirint =
+            scraper::Html::parse_document(include_str!("../page_examples/labirint.html"));
+        assert_eq!(page_availability(&labirint), Some(Availability::InStock));
+        let sold_out = scraper::Html::parse_document(
+            r#"<div itemscope><link itemprop="availability" href="https://schema.org/SoldOut"></div>"#,
+        );
+        assert_eq!(page_availability(&sold_out), Some(Availability::OutOfStock));
+        let eksmo = scraper::Html::parse_document(include_str!("../page_examples/eksmo.html"));
+        assert_eq!(page_availability(&eksmo), None);
+    }
+
+    #[test]
+    fn page_without_book_node() {
+        let html = scraper::Html::parse_document(include_str!("../page_examples/eksmo.html"));
+        assert_eq!(json_ld_book(&html), None);
+        assert!(price_fallback(&html, Err(anyhow!("no price"))).is_err());
+        assert_eq!(
+            u128::from(price_fallback(&html, Ok(Price::from(0))).expect("price")),
+            0
+        );
+    }
+
+    #[test]
+    fn kopecks_conversion() {
+        assert_eq!(price_to_kopecks("895.00"), Some(89_500));
+        assert_eq!(price_to_kopecks("895"), Some(89_500));
+        assert_eq!(price_to_kopecks("895,5"), Some(89_550));
+        assert_eq!(price_to_kopecks("8.955"), None);
+        assert_eq!(price_to_kopecks(".5"), None);
+        assert_eq!(price_to_kopecks("цена"), None);
+    }
+}
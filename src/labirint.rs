@@ -1,89 +1,128 @@
-use crate::parse_traits::{self, Author, BookParser, Description, Isbn, Price, Sites, Title};
+use crate::description::description_fallback;
+use crate::json_ld::{authors_fallback, page_availability, price_fallback, title_fallback};
+use crate::parse_traits::{
+    self, Author, Availability, BookParser, Description, Isbn, Price, Sites, Title,
+    absolute_image_url, book_page_signals, host_of, isbn_fallback, normalize_ws, parse_count_text,
+    parse_rating_text, path_segments, split_authors,
+};
+use crate::selectors::SelectorChain;
+use crate::{http, page_cache};
 use anyhow::anyhow;
 use std::sync::OnceLock;
-use std::time::Duration;
-use tracing::{debug, info, instrument, warn};
+use tracing::{debug, instrument, warn};
 
-static AUTHOR_SEL_STR: &str = "._left_u86in_12 > div:nth-child(1) > div:nth-child(2)";
-static ISBN_SEL_STR: &str = "._right_u86in_12 > div:nth-child(2) > div:nth-child(2)";
-static TITLE_SEL_STR: &str = "._h1_5o36c_18";
-static DESCR_SEL_STR: &str = "._wrapper_1rsml_1 > div:nth-child(1) > div:nth-child(1)";
-static PRICE_SEL_STR: &str = ".text-bold-28-md-32";
+pub static AUTHOR_SEL_STR: &str = "._left_u86in_12 > div:nth-child(1) > div:nth-child(2)";
+pub static ISBN_SEL_STR: &str = "._right_u86in_12 > div:nth-child(2) > div:nth-child(2)";
+pub static TITLE_SEL_STR: &str = "._h1_5o36c_18";
+pub static DESCR_SEL_STR: &str = "._wrapper_1rsml_1 > div:nth-child(1) > div:nth-child(1)";
+pub static PRICE_SEL_STR: &str = ".text-bold-28-md-32";
+pub static RATING_SEL_STR: &str = "[itemprop=\"ratingValue\"]";
+pub static REVIEW_COUNT_SEL_STR: &str = "[itemprop=\"reviewCount\"]";
+
+/// Цепочки полей: `*_SEL_STR` — текущая вёрстка, дальше — прежняя (до перехода на
+/// CSS-модули с хэшами в классах), её labirint ещё отдаёт на части страниц.
+pub static AUTHOR_SELECTORS: SelectorChain =
+    SelectorChain::new("authors", &[AUTHOR_SEL_STR, "#product-specs .authors a"]);
+pub static TITLE_SELECTORS: SelectorChain =
+    SelectorChain::new("title", &[TITLE_SEL_STR, "#product-title h1"]);
+/// Ссылка на страницу издательства в характеристиках; за ней в той же строке идёт год.
+pub static PUBLISHER_SELECTORS: SelectorChain = SelectorChain::new(
+    "publisher",
+    &["a[href*=\"/pubhouse/\"]", "#product-specs .publisher a"],
+);
+/// Ссылка на страницу серии в характеристиках; в рекомендациях ниже ссылки на серии другого класса.
+pub static SERIES_SELECTORS: SelectorChain = SelectorChain::new(
+    "series",
+    &[
+        "a.text-blue-800[href*=\"/series/\"]",
+        "#product-specs .series a",
+    ],
+);
+pub static PRICE_SELECTORS: SelectorChain = SelectorChain::new(
+    "price",
+    &[
+        PRICE_SEL_STR,
+        ".buying-pricenew-val-number",
+        ".buying-price-val-number",
+    ],
+);
 
 static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-static AUTHOR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static ISBN_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static TITLE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
 static DESCR_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-static PRICE_SEL: OnceLock<scraper::Selector> = OnceLock::new();
-const MAX_RETRIES: u8 = 1;
+static RATING_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+static REVIEW_COUNT_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+
+/// Цена из разметки страницы; без JSON-LD запасного пути (см. `parse_price`).
+fn price_from_selector(ctx: &scraper::Html) -> anyhow::Result<Price> {
+    let mut price: String = PRICE_SELECTORS
+        .select(ctx)
+        .iter()
+        .map(|node| node.text().collect::<String>())
+        .collect();
+    price = price.replace("\u{a0}", "");
+    price.retain(|c| !c.is_whitespace());
+    price.push_str("00");
+    debug!(price);
+    let price: Price = match price.parse() {
+        Ok(price) => price,
+        Err(e) => {
+            warn!("can't parse price : {e:?}");
+            return Err(e);
+        }
+    };
+    Ok(price)
+}
+
 pub struct LabirintParser;
 impl BookParser for LabirintParser {
-    const SITE: parse_traits::Sites = Sites::Labirint;
+    fn site(&self) -> Sites {
+        Sites::Labirint
+    }
+
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("authors", AUTHOR_SEL_STR),
+            ("isbn", ISBN_SEL_STR),
+            ("title", TITLE_SEL_STR),
+            ("description", DESCR_SEL_STR),
+            ("price", PRICE_SEL_STR),
+            ("rating", RATING_SEL_STR),
+            ("review_count", REVIEW_COUNT_SEL_STR),
+        ]
+        .into_iter()
+        .map(|(field, sel)| (field, sel.to_string()))
+        .collect()
+    }
+    /// `/books/<id>/`.
+    fn is_book_url(url: &str) -> bool {
+        Sites::Labirint.matches_host(&host_of(url).unwrap_or_default())
+            && path_segments(url).is_some_and(|path| match path.as_slice() {
+                [books, id] => books == "books" && id.chars().all(|c| c.is_ascii_digit()),
+                _ => false,
+            })
+    }
     type Url = String;
 
     type Context = scraper::Html;
 
+    fn looks_like_book_page(ctx: &Self::Context) -> bool {
+        let isbn =
+            ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
+        book_page_signals(ctx, PRICE_SELECTORS.any(), isbn)
+    }
+
     #[instrument(skip(self), fields(url=%url))]
-    async fn fetch(&self, url: &Self::Url) -> anyhow::Result<Self::Context> {
-        if !url.contains("books") {
+    async fn fetch_body(&self, url: &Self::Url) -> anyhow::Result<String> {
+        if !Self::is_book_url(url) {
             warn!(target: "time","Rejected non-book URL");
             return Err(anyhow!("bad url"));
         }
-        let client = CLIENT.get_or_init(|| {
-            reqwest::Client::builder()
-                .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36")
-                .connect_timeout(Duration::from_secs(5))
-                .timeout(Duration::from_secs(15))
-                .pool_max_idle_per_host(4)
-                .tcp_keepalive(Some(Duration::from_secs(30)))
-                .redirect(reqwest::redirect::Policy::limited(5))
-                .build()
-                .expect("http client")
-        });
+        let client = CLIENT.get_or_init(|| http::global().build_client().expect("http client"));
 
-        let mut last_err: Option<reqwest::Error> = None;
-        let mut last_status: Option<reqwest::StatusCode> = None;
-        for attempt in 0..=MAX_RETRIES {
-            match client.get(url).send().await {
-                Ok(resp) => {
-                    let status = resp.status();
-                    if status.is_success() {
-                        let body = resp.text().await?;
-                        return Ok(scraper::Html::parse_document(&body));
-                    }
-                    last_status = Some(status);
-                    if (status.as_u16() == 429 || status.is_server_error()) && attempt < MAX_RETRIES
-                    {
-                        let base = 1_u64 << (attempt as u32);
-                        let retry_after = resp
-                            .headers()
-                            .get(reqwest::header::RETRY_AFTER)
-                            .and_then(|h| h.to_str().ok())
-                            .and_then(|s| s.parse::<u64>().ok());
-                        let wait = retry_after.unwrap_or(base.min(8));
-                        warn!(target: "time", attempt, %status, wait, "Retrying after backoff");
-                        tokio::time::sleep(Duration::from_secs(wait)).await;
-                        continue;
-                    }
-                    return Err(anyhow!("HTTP error: {}", status));
-                }
-                Err(e) => {
-                    last_err = Some(e);
-                    if attempt < MAX_RETRIES {
-                        let wait = (1_u64 << (attempt as u32)).min(8);
-                        warn!(target: "time", attempt, wait, "Network error, retrying after backoff");
-                        tokio::time::sleep(Duration::from_secs(wait)).await;
-                        continue;
-                    }
-                }
-            }
-        }
-        if let Some(status) = last_status {
-            Err(anyhow!("HTTP error: {}", status))
-        } else {
-            Err(anyhow!(last_err.unwrap()))
-        }
+        let body = http::fetch_page(client, BookParser::site(self), url).await?;
+        page_cache::observe(url, &body);
+        Ok(body)
     }
 
     #[instrument(skip(self, ctx), fields(url=%url))]
@@ -92,13 +131,14 @@ impl BookParser for LabirintParser {
         ctx: &Self::Context,
         url: &Self::Url,
     ) -> anyhow::Result<Vec<Author>> {
-        let author_selector = AUTHOR_SEL
-            .get_or_init(|| scraper::Selector::parse(AUTHOR_SEL_STR).expect("author selector"));
-
-        Ok(ctx
-            .select(author_selector)
-            .map(|node| Author::new(node.text().collect::<String>()))
-            .collect())
+        Ok(authors_fallback(
+            ctx,
+            AUTHOR_SELECTORS
+                .select(ctx)
+                .iter()
+                .flat_map(|node| split_authors(&node.text().collect::<String>()))
+                .collect(),
+        ))
     }
 
     #[instrument(skip(self, ctx), fields(url=%url))]
@@ -106,7 +146,7 @@ impl BookParser for LabirintParser {
         let isbn_selector =
             ISBN_SEL.get_or_init(|| scraper::Selector::parse(ISBN_SEL_STR).expect("isbn selector"));
 
-        match ctx.select(isbn_selector).next_back() {
+        let structured = match ctx.select(isbn_selector).next_back() {
             Some(elem) => {
                 let raw: String = elem.text().collect::<String>().replace("\u{a0}", "");
                 match Isbn::try_from(raw) {
@@ -121,7 +161,8 @@ impl BookParser for LabirintParser {
                 warn!(target: "time","ISBN not found on page {url}");
                 Err(anyhow!("can't find isbn on this page"))
             }
-        }
+        };
+        structured.or_else(|e| isbn_fallback(ctx, e))
     }
     #[instrument(skip(self, ctx), fields(url=%log_url))]
     async fn parse_title(
@@ -129,12 +170,15 @@ impl BookParser for LabirintParser {
         ctx: &Self::Context,
         log_url: &Self::Url,
     ) -> anyhow::Result<parse_traits::Title> {
-        let book_title_selector = TITLE_SEL
-            .get_or_init(|| scraper::Selector::parse(TITLE_SEL_STR).expect("title selector"));
-        Ok(Title::new(
-            ctx.select(book_title_selector)
-                .map(|node| node.text().collect::<String>())
-                .collect::<String>(),
+        Ok(title_fallback(
+            ctx,
+            Title::new(
+                TITLE_SELECTORS
+                    .select(ctx)
+                    .iter()
+                    .map(|node| node.text().collect::<String>())
+                    .collect::<String>(),
+            ),
         ))
     }
     #[instrument(skip(self, ctx))]
@@ -148,26 +192,56 @@ impl BookParser for LabirintParser {
             .select(book_descr_sel)
             .map(|node| node.text().collect::<String>())
             .collect();
-        Ok(Description::new(descr))
+        Ok(description_fallback(ctx, Description::new(descr)))
     }
     async fn parse_price(&self, ctx: &Self::Context) -> anyhow::Result<parse_traits::Price> {
-        let book_price_sel =
-            PRICE_SEL.get_or_init(|| scraper::Selector::parse(PRICE_SEL_STR).unwrap());
-        let mut price: String = ctx
-            .select(book_price_sel)
-            .map(|node| node.text().collect::<String>())
-            .collect();
-        price = price.replace("\u{a0}", "");
-        price.push_str("00");
-        debug!(price);
-        let price: Price = match price.parse() {
-            Ok(price) => price,
-            Err(e) => {
-                warn!("can't parse price : {e:?}");
-                return Err(e);
-            }
-        };
-        Ok(price)
+        price_fallback(ctx, price_from_selector(ctx))
+    }
+    /// Оценка из разметки schema.org `AggregateRating` (`<meta itemprop content>`).
+    async fn parse_rating(&self, ctx: &Self::Context) -> anyhow::Result<Option<f32>> {
+        let rating_sel = RATING_SEL
+            .get_or_init(|| scraper::Selector::parse(RATING_SEL_STR).expect("rating selector"));
+        Ok(ctx
+            .select(rating_sel)
+            .next()
+            .and_then(|node| node.value().attr("content"))
+            .and_then(parse_rating_text))
+    }
+    async fn parse_review_count(&self, ctx: &Self::Context) -> anyhow::Result<Option<u32>> {
+        let count_sel = REVIEW_COUNT_SEL.get_or_init(|| {
+            scraper::Selector::parse(REVIEW_COUNT_SEL_STR).expect("review count selector")
+        });
+        Ok(ctx
+            .select(count_sel)
+            .next()
+            .and_then(|node| node.value().attr("content"))
+            .and_then(parse_count_text))
+    }
+    /// Обложка из `og:image`; у labirint она протокол-относительная.
+    async fn parse_cover_url(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(crate::selectors::meta_content(ctx, "og:image")
+            .as_deref()
+            .and_then(absolute_image_url))
+    }
+    async fn parse_availability(
+        &self,
+        ctx: &Self::Context,
+    ) -> anyhow::Result<Option<Availability>> {
+        Ok(page_availability(ctx))
+    }
+    async fn parse_publisher(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(PUBLISHER_SELECTORS
+            .select(ctx)
+            .first()
+            .map(|node| normalize_ws(&node.text().collect::<String>()))
+            .filter(|publisher| !publisher.is_empty()))
+    }
+    async fn parse_series(&self, ctx: &Self::Context) -> anyhow::Result<Option<String>> {
+        Ok(SERIES_SELECTORS
+            .select(ctx)
+            .first()
+            .map(|node| normalize_ws(&node.text().collect::<String>()))
+            .filter(|series| !series.is_empty()))
     }
     // #[instrument(skip(self), fields(url=%url))]
     // async fn parse_book(&self, url: Self::Url) -> anyhow::Result<parse_traits::Book<Self::Url>> {
@@ -206,6 +280,67 @@ mod tests {
         scraper::Html::parse_document(TEST_HTML)
     }
 
+    #[test]
+    fn book_page_is_recognized() {
+        assert!(LabirintParser::looks_like_book_page(&create_test_context()));
+    }
+
+    #[tokio::test]
+    async fn series_page_is_not_a_book() {
+        let series = include_str!("../page_examples/labirint_series.html");
+        assert!(!LabirintParser::looks_like_book_page(
+            &scraper::Html::parse_document(series)
+        ));
+        let err = LabirintParser
+            .parse_page(TEST_URL.to_string(), series)
+            .await
+            .expect_err("series page");
+        let not_a_book = err
+            .downcast_ref::<parse_traits::NotABook>()
+            .expect("NotABook");
+        assert_eq!(not_a_book.url, TEST_URL);
+    }
+
+    #[tokio::test]
+    async fn series_from_specs_link_and_index_from_label() {
+        let ctx = create_test_context();
+        let series = LabirintParser.parse_series(&ctx).await.expect("no error");
+        assert_eq!(series.as_deref(), Some("Образ Речи"));
+        let url = TEST_URL.to_string();
+        let fields = LabirintParser
+            .parse_fields(&ctx, &url)
+            .await
+            .expect("fields");
+        assert_eq!(fields.series_index, None);
+
+        // Номер берётся из подписи серии, отдельной строки у Лабиринта нет.
+        let numbered = TEST_HTML.replace("Образ Речи", "Гарри Поттер. Книга 3");
+        let ctx = scraper::Html::parse_document(&numbered);
+        let fields = LabirintParser
+            .parse_fields(&ctx, &url)
+            .await
+            .expect("fields");
+        assert_eq!(fields.series.as_deref(), Some("Гарри Поттер. Книга 3"));
+        assert_eq!(fields.series_index, Some(3));
+    }
+
+    #[tokio::test]
+    async fn publisher_from_specs_link() {
+        let publisher = LabirintParser
+            .parse_publisher(&create_test_context())
+            .await
+            .expect("no error");
+        assert_eq!(publisher.as_deref(), Some("Речь"));
+        let empty = scraper::Html::parse_document("<html><body></body></html>");
+        assert_eq!(
+            LabirintParser
+                .parse_publisher(&empty)
+                .await
+                .expect("no error"),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn test_parse_authors() {
         let parser = LabirintParser;
@@ -272,6 +407,22 @@ mod tests {
         let result = parser.fetch(&invalid_url).await;
         assert!(result.is_err());
     }
+    #[test]
+    fn book_urls_are_recognized() {
+        assert!(LabirintParser::is_book_url(TEST_URL));
+        assert!(LabirintParser::is_book_url(
+            "https://labirint.ru/books/801841"
+        ));
+        for url in [
+            "https://www.labirint.ru/genres/2308/",
+            "https://www.labirint.ru/books/",
+            "https://www.labirint.ru/books/801841/reviews/",
+            "https://www.labirint.ru/authors/1/books/",
+            "https://example.com/books/801841/",
+        ] {
+            assert!(!LabirintParser::is_book_url(url), "{url}");
+        }
+    }
     #[tokio::test]
     async fn test_parce_price() {
         let parser = LabirintParser;
@@ -279,6 +430,28 @@ mod tests {
         let price = parser.parse_price(&ctx).await.expect("should");
         assert_eq!(u128::from(price), EXPECTED_PRICE);
     }
+    #[tokio::test]
+    async fn cover_url_is_absolute() {
+        let parser = LabirintParser;
+        assert_eq!(
+            parser
+                .parse_cover_url(&create_test_context())
+                .await
+                .expect("cover")
+                .as_deref(),
+            Some("https://imo10.labirint.ru/books/696481/cover.jpg/484-0")
+        );
+    }
+    #[tokio::test]
+    async fn test_parse_rating() {
+        let parser = LabirintParser;
+        let ctx = create_test_context();
+        assert_eq!(parser.parse_rating(&ctx).await.expect("rating"), Some(4.5));
+        assert_eq!(
+            parser.parse_review_count(&ctx).await.expect("reviews"),
+            Some(32)
+        );
+    }
 
     #[tokio::test]
     #[ignore = "502 gateaway probably banned"]
@@ -294,4 +467,54 @@ mod tests {
         assert!(!book.isbn.as_str().is_empty());
         assert!(!book.title.as_str().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_parse_fields_matches_per_field() {
+        let parser = LabirintParser;
+        let ctx = create_test_context();
+        let url = TEST_URL.to_string();
+        let fields = parser.parse_fields(&ctx, &url).await.expect("fields");
+        let descr = parser.parse_description(&ctx).await.expect("descr");
+        assert_eq!(fields.authors.len(), 1);
+        assert_eq!(fields.authors[0].as_str(), EXPECTED_AUTHOR);
+        assert_eq!(fields.isbn.as_str(), EXPECTED_ISBN);
+        assert_eq!(fields.title.as_str(), EXPECTED_TITLE);
+        assert_eq!(fields.description.as_str(), descr.as_str());
+        assert_eq!(u128::from(fields.price), EXPECTED_PRICE);
+    }
+
+    #[tokio::test]
+    async fn previous_markup_parsed_by_fallback_selectors() {
+        let parser = LabirintParser;
+        let ctx = scraper::Html::parse_document(
+            r#"<div id="product-title"><h1>Джейн Эйр</h1></div>
+            <div id="product-specs"><div class="authors">Автор: <a href="/authors/1/">Бронте Шарлотта</a></div></div>
+            <span class="buying-pricenew-val-number">1 084</span>"#,
+        );
+        let url = TEST_URL.to_string();
+        assert_eq!(
+            parser
+                .parse_title(&ctx, &url)
+                .await
+                .expect("title")
+                .as_str(),
+            "Джейн Эйр"
+        );
+        let authors = parser.parse_authors(&ctx, &url).await.expect("authors");
+        assert_eq!(authors[0].as_str(), EXPECTED_AUTHOR);
+        assert_eq!(
+            u128::from(parser.parse_price(&ctx).await.expect("price")),
+            EXPECTED_PRICE
+        );
+        // Текущая вёрстка по-прежнему берётся первым селектором цепочки.
+        let current = create_test_context();
+        assert_eq!(
+            parser
+                .parse_title(&current, &url)
+                .await
+                .expect("title")
+                .as_str(),
+            EXPECTED_TITLE
+        );
+    }
 }
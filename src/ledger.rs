@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::parse_traits::Book;
+use crate::sink::{BookSink, Versioned, isbn_key};
+
+/// Реестр `--dedupe-across-runs`: все ISBN, когда-либо записанные в выгрузку.
+pub static LEDGER_FILE: &str = "isbn_ledger.csv";
+/// Книги, чьих ISBN ещё не было в реестре: что появилось с прошлого прогона.
+pub static NEW_BOOKS_FILE: &str = "new_books.jsonl";
+
+/// Строка реестра; время — секунды unix.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub isbn: String,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+/// Реестр ISBN между прогонами: CSV `isbn,first_seen,last_seen`, ISBN без дефисов и пробелов.
+/// Файла ещё нет — реестр пуст, и все книги первого прогона новые.
+#[derive(Debug)]
+pub struct IsbnLedger {
+    path: PathBuf,
+    entries: BTreeMap<String, LedgerEntry>,
+}
+
+impl IsbnLedger {
+    pub fn load(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let mut entries = BTreeMap::new();
+        if path.exists() {
+            let mut rdr = csv::Reader::from_path(&path)
+                .with_context(|| format!("can't open {}", path.display()))?;
+            for entry in rdr.deserialize() {
+                let entry: LedgerEntry =
+                    entry.with_context(|| format!("bad ledger row in {}", path.display()))?;
+                entries.insert(entry.isbn.clone(), entry);
+            }
+        }
+        Ok(Self { path, entries })
+    }
+
+    /// Отмечает ISBN как встреченный в `now`; `true`, если его в реестре ещё не было.
+    pub fn observe(&mut self, isbn: &str, now: u64) -> bool {
+        let key = isbn_key(isbn);
+        match self.entries.get_mut(&key) {
+            Some(entry) => {
+                entry.last_seen = entry.last_seen.max(now);
+                false
+            }
+            None => {
+                self.entries.insert(
+                    key.clone(),
+                    LedgerEntry {
+                        isbn: key,
+                        first_seen: now,
+                        last_seen: now,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    pub fn get(&self, isbn: &str) -> Option<&LedgerEntry> {
+        self.entries.get(&isbn_key(isbn))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Пишет реестр через временный файл: прерванная запись не портит прошлый реестр.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let tmp = self.path.with_extension("csv.tmp");
+        {
+            let mut wtr = csv::Writer::from_path(&tmp)
+                .with_context(|| format!("can't create {}", tmp.display()))?;
+            for entry in self.entries.values() {
+                wtr.serialize(entry)?;
+            }
+            wtr.flush()?;
+        }
+        std::fs::rename(&tmp, &self.path)
+            .with_context(|| format!("can't replace {}", self.path.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// `--dedupe-across-runs`: всё пишет в `inner`, а книги с ISBN, которого нет в реестре,
+/// ещё и в `new_books` (JSON Lines, поля как в JSON-выгрузке). Реестр сохраняется в `finish`.
+pub struct LedgerSink<S, W: Write> {
+    inner: S,
+    ledger: IsbnLedger,
+    new_books: W,
+    now: u64,
+    new: usize,
+}
+
+impl<S: BookSink, W: Write> LedgerSink<S, W> {
+    pub fn new(inner: S, ledger: IsbnLedger, new_books: W, now: u64) -> Self {
+        Self {
+            inner,
+            ledger,
+            new_books,
+            now,
+            new: 0,
+        }
+    }
+
+    /// Сколько книг за прогон оказались новыми.
+    pub fn new_books(&self) -> usize {
+        self.new
+    }
+
+    pub fn ledger(&self) -> &IsbnLedger {
+        &self.ledger
+    }
+}
+
+impl<S: BookSink, W: Write> BookSink for LedgerSink<S, W> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        self.inner.write_book(book)?;
+        if self.ledger.observe(book.isbn.as_str(), self.now) {
+            serde_json::to_writer(
+                &mut self.new_books,
+                &Versioned::new(book, book.isbn13_group()),
+            )?;
+            self.new_books.write_all(b"\n")?;
+            self.new += 1;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.inner.finish()?;
+        self.new_books.flush()?;
+        self.ledger.save()?;
+        info!(
+            new = self.new,
+            known = self.ledger.len(),
+            ledger = %self.ledger.path().display(),
+            "isbn ledger updated"
+        );
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.new_books.flush()?;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::Isbn;
+    use crate::sink::CsvSink;
+    use crate::sink::tests::sample_book;
+
+    fn run(ledger_path: &Path, books: &[Book<String>], now: u64) -> Vec<serde_json::Value> {
+        let mut new_books = vec![];
+        let mut out = vec![];
+        let ledger = IsbnLedger::load(ledger_path).expect("ledger");
+        let mut sink = LedgerSink::new(
+            CsvSink::new(&mut out, false).expect("sink"),
+            ledger,
+            &mut new_books,
+            now,
+        );
+        for book in books {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+        drop(sink);
+        // В основную выгрузку попадает всё, не только новое.
+        assert_eq!(
+            String::from_utf8(out).expect("utf8").lines().count(),
+            books.len() + 1
+        );
+        String::from_utf8(new_books)
+            .expect("utf8")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("json line"))
+            .collect()
+    }
+
+    #[test]
+    fn second_run_emits_only_new_isbn() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_ledger_{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let first = sample_book(1);
+        let mut second = sample_book(2);
+        second.isbn = Isbn::try_from("978-5-04-156838-2".to_string()).expect("isbn");
+        let mut arrived = sample_book(3);
+        arrived.isbn = Isbn::try_from("978-5-04-122366-3".to_string()).expect("isbn");
+
+        let new = run(&path, &[first, second], 1_000);
+        assert_eq!(new.len(), 2);
+        assert_eq!(new[0]["_schema"], crate::csv_save::BOOK_SCHEMA_VERSION);
+
+        let first = sample_book(1);
+        let mut second = sample_book(2);
+        second.isbn = Isbn::try_from("9785041568382".to_string()).expect("isbn");
+        let new = run(&path, &[first, second, arrived], 2_000);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0]["isbn"], "978-5-04-122366-3");
+
+        let ledger = IsbnLedger::load(&path).expect("ledger");
+        assert_eq!(ledger.len(), 3);
+        assert_eq!(
+            ledger.get("978-5-9268-3015-3"),
+            Some(&LedgerEntry {
+                isbn: "9785926830153".to_string(),
+                first_seen: 1_000,
+                last_seen: 2_000,
+            })
+        );
+        assert_eq!(
+            ledger.get("9785041223663").map(|e| e.first_seen),
+            Some(2_000)
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,47 @@
+pub mod backfill;
+pub mod catalog_count;
+pub mod checkpoint;
+pub mod cli;
+pub mod collisions;
+pub mod config_parser;
+pub mod covers;
+pub mod csv_save;
+pub mod description;
+pub mod diagnose;
+pub mod eksmo;
+pub mod enrich;
+pub mod failure_html;
+pub mod failures;
+pub mod health;
+pub mod http;
+pub mod igraslov;
+pub mod isbn_only;
+pub mod json_ld;
+pub mod labirint;
+pub mod ledger;
+pub mod manifest;
+pub mod near_duplicates;
+pub mod offline;
+pub mod page_cache;
+#[cfg(feature = "parquet")]
+pub mod parquet_sink;
+pub mod parse_traits;
+pub mod pipeline;
+pub mod post_parse;
+pub mod precheck;
+pub mod progress;
+pub mod proxy_pool;
+pub mod publishers;
+pub mod ramp;
+pub mod registry;
+pub mod selectors;
+pub mod sink;
+pub mod sitemap;
+pub mod skip_existing;
+pub mod specs;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_sink;
+pub mod stdin_urls;
+pub mod telemetry;
+pub mod text_transform;
+pub mod url_filter;
@@ -1,33 +1,56 @@
+use anyhow::anyhow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::process::ExitCode;
 use std::sync::Arc;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use anyhow::{Context, anyhow};
-use futures::{StreamExt, stream};
-use quick_xml::de::from_str;
-use serde::Deserialize;
-
-use crate::eksmo::EksmoParser;
-use crate::igraslov::IgraSlov;
-use crate::labirint::*;
-use crate::parse_traits::{Book, BookParser};
-use tracing::{info, instrument, warn};
-mod csv_save;
-mod eksmo;
-mod igraslov;
-mod labirint;
-mod parse_traits;
-mod telemetry;
-use crate::csv_save::{BOOK_CSV_HEADERS, CsvSave};
-use crate::telemetry::init_tracing;
-#[derive(Debug, Deserialize)]
-struct BookUrl {
-    loc: String,
-}
-#[derive(Debug, Deserialize)]
-struct UrlSet {
-    #[serde(rename = "url")]
-    urls: Vec<BookUrl>,
-}
+use parser::backfill::BackfillSink;
+use parser::catalog_count::{count_by_site, format_counts};
+use parser::checkpoint::{Checkpoint, CheckpointSink};
+use parser::cli::{Config, OutputFormat, SortKey, print_help};
+use parser::collisions::{COLLISIONS_FILE, find_collisions, write_collisions};
+use parser::config_parser::{ConfigParser, SitesConfig};
+use parser::covers::download_covers;
+use parser::csv_save::write_csv_schema;
+use parser::diagnose::{
+    SelectorAudit, audit_urls, capture_fixture, diagnose_url, format_audit, parse_only,
+};
+use parser::enrich::OpenLibraryHook;
+use parser::failure_html;
+use parser::failures::{failure_channel, write_jsonl};
+use parser::health::{EXIT_OK, SiteOutcome, exit_code};
+use parser::http::{self, ControlPort, HttpConfig, IdentityRotator};
+use parser::isbn_only::{ISBNS_FILE, collect_isbns, write_isbns};
+use parser::ledger::{IsbnLedger, LEDGER_FILE, LedgerSink, NEW_BOOKS_FILE};
+use parser::manifest::{MANIFEST_FILE, Manifest, git_rev, unix_now};
+use parser::near_duplicates::{NEAR_DUPLICATES_FILE, find_near_duplicates, write_near_duplicates};
+use parser::offline::{self, OfflinePages};
+use parser::page_cache::{self, PageCache};
+use parser::parse_traits::{self, AuthorSplitter, Book, BookParser, NotABook, PriceFormat, Sites};
+use parser::pipeline::{self, Driver, ParseFn, Shutdown, default_parse_workers};
+use parser::post_parse::PostParseHooks;
+use parser::precheck::precheck_urls;
+use parser::progress::{PROGRESS_EVERY, Progress};
+use parser::proxy_pool::{self, DEAD_AFTER_FAILURES, ProxyPool, load_proxies};
+use parser::publishers::{PUBLISHERS_FILE, count_by_publisher, write_publishers};
+use parser::ramp::ramped_semaphore;
+use parser::registry::ParserRegistry;
+use parser::sink::{
+    BookSink, CsvSink, FilteredSink, FlushEvery, IdempotentSink, LanguageFilterSink,
+    PartitionedSink, QualityGateSink, sort_books, writer_sink,
+};
+use parser::sitemap::{
+    IGRASLOV_BOOK_INDICATORS, check_site_urls, fetch_igraslov_urls, fetch_urlset_entries,
+    fetch_urlsets, newest_first, sample_urls,
+};
+use parser::skip_existing::{SkipExistingSink, load_existing_isbns};
+use parser::stdin_urls::read_urls;
+use parser::telemetry::init_tracing;
+use parser::url_filter::UrlFilter;
+use tokio::sync::Notify;
+use tracing::{info, warn};
 const URL1: &str = "https://www.labirint.ru/smcatalog2.xml";
 const URL2: [&str; 7] = [
     "https://igraslov.store/product-sitemap.xml",
@@ -51,172 +74,672 @@ const fn get_sitemaps_eksmo() -> [&'static str; 8] {
         "https://eksmo.ru/sitemap/books8.xml",
     ]
 }
-// const URL3:[&str;_]
-static DEFAULT_PARSE_COUNT: usize = 3;
-static PARSE_FROM_ONE_SITE: usize = 1500;
-#[instrument(skip(sitemaps))]
-async fn parse_sitemaps_eksmo(sitemaps: [&str; 8]) -> anyhow::Result<Vec<String>> {
-    let mut books_url = vec![];
-    for sitemap in sitemaps {
-        let resp = reqwest::get(sitemap).await?.text().await?;
-        let mut urlset: UrlSet = from_str(&resp)?;
-        info!(target: "time", count = urlset.urls.len(), "fetched sitemap urls");
-        books_url.append(&mut urlset.urls);
-    }
-    Ok(books_url.into_iter().map(|x| x.loc).collect())
-}
-async fn parse_sitemap_igraslov(sitemap: &str) -> anyhow::Result<Vec<String>> {
-    let resp = reqwest::get(sitemap)
-        .await
-        .context("GET igraslov sitemap failed")?
-        .error_for_status()
-        .context("non-success status for igraslov sitemap")?
-        .text()
-        .await
-        .context("reading igraslov sitemap body failed")?;
-    let html = scraper::Html::parse_document(&resp);
-    let selector = scraper::Selector::parse("loc").expect("should");
-    let elems = html.select(&selector);
+/// С `--output -` stdout занят выгрузкой, и сообщения о ходе прогона идут в stderr.
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
 
-    let mut books_urls = vec![];
-    static BOOK_INDICATORS: [&str; 4] = ["tvyord", "klap", "myagk", "super"];
-
-    for (i, nodes) in elems.enumerate() {
-        if let Some(noderef) = nodes.first_child() {
-            let url_comment = noderef
-                .value()
-                .as_comment()
-                .ok_or_else(|| anyhow!("expected comment node for <loc>"))?;
-            let mut url: String = (*url_comment).parse()?;
-            url = url
-                .replace("[CDATA[", "")
-                .replace("]]", "")
-                .trim()
-                .to_string();
-            books_urls.push(url);
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+            eprintln!($($arg)*)
         } else {
-            warn!(index = i, "can't fetch url node");
+            println!($($arg)*)
         }
-    }
-    info!("fetched url's from igraslov sitemap");
-    Ok(books_urls
-        .into_iter()
-        .filter(|url| BOOK_INDICATORS.iter().any(|pat| url.contains(pat)))
-        .collect())
+    };
 }
-#[instrument(skip(sitemap))]
-async fn parse_sitemap_labirint(sitemap: &str) -> anyhow::Result<Vec<String>> {
-    let resp = reqwest::get(sitemap).await?.text().await?;
-    let urlset: UrlSet = from_str(&resp)?;
-    info!(target: "time", count = urlset.urls.len(), "fetched sitemap urls");
 
-    Ok(urlset
-        .urls
-        .into_iter()
-        .map(|u| u.loc)
-        .filter(|u| u.contains("/books/"))
-        .collect())
-}
 #[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
-    println!("HELP: parser <at_once> <how_much_from_one_store");
-    println!("OPTIONAL: <at_once> How much parse at moment, must be >=1");
-    println!("OPTIONAL: <how_much_from_one_store>, must be >=1");
-    println!("<at_once> default value={DEFAULT_PARSE_COUNT}");
-    println!("<how_much_from_one_store default value = {PARSE_FROM_ONE_SITE}");
-    let mut max_concurrent_parses = DEFAULT_PARSE_COUNT; // сколько книг парсится одновременно
-    let mut max_parses_per_source = PARSE_FROM_ONE_SITE; // сколько книг парсится с одного сайта
-
-    for (i, arg) in std::env::args().skip(1).enumerate() {
-        let (processing, name_var) = match i {
-            0 => (&mut max_concurrent_parses, "<at_once>"),
-            1 => (&mut max_parses_per_source, "<how_much_from_one_store>"),
-            _ => return Err(anyhow!("too much env args")),
-        };
-        let num: usize = arg.parse()?;
-        if num >= 1 {
-            *processing = num
-        } else {
-            return Err(anyhow!("given {name_var} is not a num or < 1"));
+async fn main() -> Result<ExitCode, anyhow::Error> {
+    let started_at = unix_now();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    // Переменные с не-UTF-8 значением флагами быть не могут, их пропускаем.
+    let vars = std::env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    let config = match Config::from_sources(args.clone(), vars) {
+        Ok(config) => config,
+        Err(e) => {
+            print_help();
+            return Err(e);
         }
-        println!("{name_var} value = {}", *processing);
+    };
+    STATUS_TO_STDERR.store(config.stdout_is_data(), Ordering::Relaxed);
+    if config.explain {
+        println!("{}", config.explain_json()?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if !config.stdout_is_data() {
+        print_help();
     }
-    let _guard = init_tracing().map_err(|e| anyhow!("{e}"))?;
+    status!("<at_once> value = {}", config.max_concurrent_parses);
+    status!(
+        "<how_much_from_one_store> value = {}",
+        config.max_parses_per_source
+    );
+    let max_concurrent_parses = config.max_concurrent_parses;
+    let _guard = init_tracing(config.stdout_is_data()).map_err(|e| anyhow!("{e}"))?;
     info!(target: "time", "starting parser");
 
-    let mut wtr = csv::Writer::from_path("books.csv")?;
-    wtr.write_record(BOOK_CSV_HEADERS)?;
+    let mut http_config = HttpConfig::for_concurrency(max_concurrent_parses)
+        .with_timeouts(config.timeout_profile, config.timeout_overrides);
+    http_config.max_retries = config.max_retries;
+    http_config.http_version = config.http_version;
+    http_config.max_body_bytes = config.max_body_bytes;
+    http_config.warm_up_sites = config.warm_up_sites.clone();
+    http_config.site_timeouts = config.site_timeouts.clone();
+    if let Some(path) = &config.user_agent_file {
+        http_config.user_agents = http::load_user_agents(path)?;
+        status!("rotating {} user agents", http_config.user_agents.len());
+    }
+    if let Some(pool) = config.pool_max_idle_per_host {
+        http_config.pool_max_idle_per_host = pool;
+    }
+    http_config.tor = config.tor.clone();
+    if let Some(tor) = &config.tor {
+        status!("fetching via tor at {}", tor.socks);
+        if let Some(every) = tor.new_identity_every {
+            let password = std::env::var(http::TOR_CONTROL_PASSWORD_ENV).ok();
+            http::init_tor_rotation(IdentityRotator::new(
+                ControlPort::new(&tor.control, password),
+                every,
+            ))?;
+        }
+    }
+    info!(?http_config, "http clients");
+    http::init_global(http_config)?;
+    parse_traits::init_text_limits(config.text_limits)?;
+    parse_traits::init_author_splitter(AuthorSplitter::new(&config.authors_split_regex)?)?;
+    if let Some(dir) = &config.save_failures_html {
+        failure_html::init_global(dir)?;
+    }
+    if let Some(dir) = &config.cache_dir {
+        page_cache::init_global(PageCache::open(dir)?)?;
+    }
+    if let Some(dir) = &config.offline {
+        offline::init_global(OfflinePages::open(dir)?)?;
+    }
+    if let Some(path) = &config.proxy_list {
+        let pool = ProxyPool::new(load_proxies(path)?, http::global(), DEAD_AFTER_FAILURES)?;
+        status!("rotating {} proxies", pool.health().len());
+        proxy_pool::init_global(pool)?;
+    }
 
-    let urls_labirint: Vec<String> = parse_sitemap_labirint(URL1)
-        .await?
-        .into_iter()
-        .take(max_parses_per_source)
-        .collect();
-    println!("urls_labirint have {} books", urls_labirint.len());
-    let urls_igraslov: Vec<String> = {
-        let mut books: Vec<String> = vec![];
-        for sitemap in URL2 {
-            books.append(&mut parse_sitemap_igraslov(sitemap).await?);
+    if let Some((url, path)) = &config.capture_fixture {
+        let bytes = capture_fixture(&http::global().build_client()?, url, path).await?;
+        status!("saved {bytes} bytes of {url} to {}", path.display());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut registry = ParserRegistry::default();
+    let mut custom_sitemaps: Vec<(Sites, Vec<String>)> = vec![];
+    if let Some(path) = &config.sites_config {
+        for site in SitesConfig::load(path)?.sites {
+            let parser = ConfigParser::new(site)?;
+            custom_sitemaps.push((parser.site(), parser.sitemap_urls().to_vec()));
+            registry.register(parser);
         }
-        books
     }
-    .into_iter()
-    .take(max_parses_per_source)
-    .collect();
-    println!("urls_igraslov have {} books", urls_igraslov.len());
-    let urls_eksmo: Vec<String> = parse_sitemaps_eksmo(URL3)
-        .await?
-        .into_iter()
-        .take(max_parses_per_source)
-        .collect();
-    println!("urls_eksmo have {} books", urls_eksmo.len());
+    if let Some(url) = &config.diagnose {
+        print!("{}", diagnose_url(&registry, url).await?);
+        return Ok(ExitCode::SUCCESS);
+    }
+    if let Some(url) = &config.parse_only {
+        return Ok(match parse_only(&registry, url).await {
+            Ok(book) => {
+                println!("{book}");
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                // stdout занят данными (`stdout_is_data`), `status!` пишет в stderr.
+                status!("error: {e:#}");
+                ExitCode::FAILURE
+            }
+        });
+    }
+    let shuffle_seed = config
+        .shuffle
+        .then(|| config.seed.unwrap_or_else(rand::random));
+    if let Some(seed) = shuffle_seed {
+        status!("shuffling urls with seed {seed}");
+    }
+    let url_filter = UrlFilter::new(&config.include_url_patterns, &config.exclude_url_patterns)?;
+    if config.head_only_count {
+        // Считается весь каталог, без обрезки до `<how_much_from_one_store>`.
+        let uncapped = Config {
+            max_parses_per_source: usize::MAX,
+            ..config.clone()
+        };
+        let mut urls = input_urls(&uncapped, None, &custom_sitemaps).await?;
+        urls.retain(|url| registry.for_url(url).is_none_or(|p| p.is_book_url(url)));
+        url_filter.retain(&mut urls);
+        print!("{}", format_counts(&count_by_site(&registry, &urls)));
+        return Ok(ExitCode::SUCCESS);
+    }
+    if config.warn_on_empty_selectors {
+        let mut urls = input_urls(&config, shuffle_seed, &custom_sitemaps).await?;
+        urls.retain(|url| registry.for_url(url).is_some_and(|p| p.is_book_url(url)));
+        url_filter.retain(&mut urls);
+        status!("auditing selectors on {} pages", urls.len());
+        let audit = audit_urls(&registry, &urls).await;
+        print!("{}", format_audit(&audit));
+        return Ok(if audit.iter().any(SelectorAudit::suspicious) {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        });
+    }
+    if config.isbn_only {
+        let mut urls = input_urls(&config, shuffle_seed, &custom_sitemaps).await?;
+        urls.retain(|url| registry.for_url(url).is_none_or(|p| p.is_book_url(url)));
+        url_filter.retain(&mut urls);
+        let total = urls.len() as u64;
+        let progress = Progress::new(total, PROGRESS_EVERY);
+        let results = collect_isbns(
+            Arc::new(registry),
+            urls,
+            max_concurrent_parses,
+            Shutdown::on_ctrl_c(config.shutdown_grace),
+            |url, result| {
+                if let Err(e) = result {
+                    warn!(url, "no isbn: {e:#}");
+                }
+                progress.record(result.is_ok());
+            },
+        )
+        .await;
+        let written = write_isbns(Path::new(ISBNS_FILE), results.iter().flatten())?;
+        status!("wrote {written}/{total} isbns to {ISBNS_FILE}");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let output = config.output;
+    let with_flags = config.with_flags;
+    let price_format = config.price_format;
+    let csv_bom = config.csv_bom;
+    // До открытия выгрузки: `--skip-existing` может указывать на тот же файл.
+    let existing_isbns = match &config.skip_existing {
+        Some(path) => {
+            let isbns = load_existing_isbns(path)?;
+            status!("{} isbns already in {}", isbns.len(), path.display());
+            Some(isbns)
+        }
+        None => None,
+    };
+    let mut backfill_urls = None;
+    let mut resumed_urls = None;
+    let mut sink: Box<dyn BookSink> = if let Some(path) = &config.only_missing_fields {
+        let backfill = BackfillSink::open(path, with_flags)?.with_price_format(price_format);
+        let urls = backfill.missing_urls();
+        status!("{} rows with missing fields to re-parse", urls.len());
+        backfill_urls = Some(urls);
+        Box::new(backfill)
+    } else if let Some(path) = &config.resume_from_checkpoint {
+        let (resumed, rows) = Checkpoint::resume(path, Path::new("books.csv"), with_flags)?;
+        status!("resuming: {} books already written", rows.len());
+        write_csv_schema(Path::new("books.schema.json"), with_flags)?;
+        let mut csv = CsvSink::create_atomic("books.csv", with_flags, csv_bom)?
+            .with_price_format(price_format);
+        for row in &rows {
+            csv.write_record(row)?;
+        }
+        resumed_urls = Some(resumed.done().clone());
+        Box::new(CheckpointSink::new(csv, resumed))
+    } else if config.output_stdout {
+        writer_sink(
+            output,
+            std::io::BufWriter::new(std::io::stdout()),
+            with_flags,
+            csv_bom,
+            price_format,
+        )?
+    } else if config.partition_by_site {
+        Box::new(PartitionedSink::new(move |site| {
+            open_sink(
+                output,
+                with_flags,
+                csv_bom,
+                price_format,
+                &format!("books_{site}"),
+            )
+        }))
+    } else {
+        open_sink(output, with_flags, csv_bom, price_format, "books")?
+    };
+    if let Some(every) = config.flush_every {
+        sink = Box::new(FlushEvery::new(sink, every));
+    }
+    if config.dedupe_across_runs {
+        let ledger = IsbnLedger::load(LEDGER_FILE)?;
+        status!("{} isbns known from previous runs", ledger.len());
+        let new_books = std::io::BufWriter::new(std::fs::File::create(NEW_BOOKS_FILE)?);
+        sink = Box::new(LedgerSink::new(sink, ledger, new_books, unix_now()));
+    }
+    if config.price_filter.is_active() {
+        sink = Box::new(FilteredSink::new(sink, config.price_filter));
+    }
+    if config.language_filter.is_active() {
+        sink = Box::new(LanguageFilterSink::new(
+            sink,
+            config.language_filter.clone(),
+        ));
+    }
+    if config.quality_gate.is_active() {
+        sink = Box::new(QualityGateSink::new(sink, config.quality_gate));
+    }
+    if let Some(isbns) = existing_isbns {
+        sink = Box::new(SkipExistingSink::new(sink, isbns));
+    }
+    if config.idempotent {
+        sink = Box::new(IdempotentSink::new(sink));
+    }
 
-    let mut urls: Vec<String> =
-        interleave(urls_igraslov.into_iter(), urls_labirint.into_iter()).collect();
-    urls = interleave(urls.into_iter(), urls_eksmo.into_iter()).collect();
+    let mut urls = match backfill_urls {
+        Some(urls) => urls,
+        None => input_urls(&config, shuffle_seed, &custom_sitemaps).await?,
+    };
+    if let Some(done) = &resumed_urls {
+        urls.retain(|url| !done.contains(url));
+    }
+    let before = urls.len();
+    urls.retain(|url| registry.for_url(url).is_none_or(|p| p.is_book_url(url)));
+    if urls.len() < before {
+        status!("skipped {} non-book urls", before - urls.len());
+    }
+    let filtered = url_filter.retain(&mut urls);
+    if filtered > 0 {
+        status!("skipped {filtered} urls by url patterns");
+    }
+    let (failure_sink, failure_collector) = failure_channel();
+    if config.precheck {
+        let client = http::global().build_client()?;
+        let checked = precheck_urls(&client, urls, max_concurrent_parses).await;
+        status!("precheck skipped {} dead urls", checked.dead.len());
+        for (url, status) in checked.dead {
+            failure_sink.record(url, anyhow!("precheck: {status}"));
+        }
+        urls = checked.alive;
+    }
     let total = urls.len() as u64;
+    let urls_total = urls.len();
+    let site_of = |registry: &ParserRegistry, url: &str| {
+        registry
+            .for_url(url)
+            .map_or_else(|| "unknown".to_string(), |p| p.site().to_string())
+    };
+    let mut site_outcomes: BTreeMap<String, SiteOutcome> = BTreeMap::new();
+    for url in &urls {
+        site_outcomes
+            .entry(site_of(&registry, url))
+            .or_default()
+            .attempted += 1;
+    }
 
-    let counter = Arc::new(AtomicU64::new(0));
-    let books: Vec<_> = stream::iter(urls)
-        .map(|url| {
-            let counter = Arc::clone(&counter);
-            async move {
-                let result;
-                if url.contains("labirint") {
-                    result = parse_book_page(&LabirintParser, url).await;
-                } else if url.contains("igraslov") {
-                    result = parse_book_page(&IgraSlov, url).await;
-                } else if url.contains("eksmo") {
-                    result = parse_book_page(&EksmoParser, url).await;
-                } else {
-                    panic!("unknown url : {url}");
-                }
-                let processed = counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
-                println!("processed: {processed}/{total}");
-                result
+    let progress = Progress::new(total, PROGRESS_EVERY);
+    let registry = Arc::new(registry);
+    let parse_registry = Arc::clone(&registry);
+    let parse: ParseFn<Book<String>> = Arc::new(move |url, body| {
+        parse_registry
+            .for_url(&url)
+            .ok_or_else(|| anyhow!("no parser registered for url: {url}"))?
+            .parse_page_blocking(url, &body)
+    });
+    let ramp = config
+        .concurrency_ramp
+        .map(|window| ramped_semaphore(max_concurrent_parses, window));
+    let fetch_registry = Arc::clone(&registry);
+    let fetch = move |url: String| {
+        let registry = Arc::clone(&fetch_registry);
+        let ramp = ramp.clone();
+        async move {
+            let _permit = match &ramp {
+                Some(ramp) => Some(ramp.acquire().await?),
+                None => None,
+            };
+            match registry.for_url(&url) {
+                Some(parser) => parser.fetch_body_dyn(url).await,
+                None => Err(anyhow!("no parser registered for url: {url}")),
             }
-        })
-        .buffer_unordered(max_concurrent_parses)
-        .collect()
-        .await;
+        }
+    };
+    // Сортировке, `--idempotent`, обогащению и обложкам нужен весь набор книг:
+    // с ними книги пишутся после обхода, иначе — по мере разбора.
+    let write_after_crawl =
+        config.output_sort_key().is_some() || config.enrich || config.download_covers.is_some();
+    if write_after_crawl {
+        info!("books will be written after the crawl");
+    }
+    let text_pipeline = config.text_transforms.pipeline();
+    if !text_pipeline.is_empty() {
+        info!(transforms = ?text_pipeline.names(), "applying text transforms");
+    }
+    let min_description_len = config.min_description_len;
+    // Сайты, у которых ошибок уже больше `--fail-threshold`: `--driver joinset`
+    // прерывает их оставшиеся URL.
+    let tripped_sites: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+    let write_failed = Arc::new(Notify::new());
+    let mut write_error = None;
     let mut success = 0;
-    for book in books.iter() {
-        match book {
+    let mut on_result = |url: &str, result: &mut anyhow::Result<Book<String>>| {
+        match result {
             Ok(book) => {
-                info!("succesfull parsed book with url {}", book.source);
                 success += 1;
-                book.write_csv_record(&mut wtr)?
+                text_pipeline.apply_to_book(book);
+                clear_short_description(book, min_description_len);
+                if !write_after_crawl && write_error.is_none() {
+                    info!("succesfull parsed book with url {}", book.source);
+                    if let Err(e) = sink.write_book(book) {
+                        warn!("can't write book, stopping: {e:#}");
+                        write_error = Some(e);
+                        write_failed.notify_one();
+                    }
+                }
             }
-            Err(e) => warn!("book unsuccesfull parse {e}"),
+            Err(e) => {
+                failure_sink.record(url.to_string(), &*e);
+                // Серия или подборка в sitemap — не сбой сайта, а пропуск.
+                if e.downcast_ref::<NotABook>().is_some() {
+                    info!(url, "skipped: not a book page");
+                } else {
+                    warn!("book unsuccesfull parse {e}");
+                    let site = site_of(&registry, url);
+                    let outcome = site_outcomes.entry(site.clone()).or_default();
+                    outcome.failed += 1;
+                    if config.driver == Driver::JoinSet
+                        && config
+                            .fail_threshold
+                            .is_some_and(|percent| outcome.exceeds(percent))
+                        && tripped_sites.borrow_mut().insert(site.clone())
+                    {
+                        warn!(
+                            site,
+                            "site is over --fail-threshold, aborting its remaining urls"
+                        );
+                    }
+                }
+            }
+        }
+        let processed = progress.record(result.is_ok());
+        status!("processed: {processed}/{total}");
+    };
+    let shutdown = Shutdown::on_ctrl_c(config.shutdown_grace).or_on({
+        let write_failed = Arc::clone(&write_failed);
+        async move { write_failed.notified().await }
+    });
+    let mut books = match config.driver {
+        Driver::Stream => {
+            pipeline::run(
+                urls,
+                fetch,
+                parse,
+                max_concurrent_parses,
+                default_parse_workers(),
+                shutdown,
+                &mut on_result,
+            )
+            .await
         }
+        Driver::JoinSet => {
+            pipeline::run_join_set(
+                urls,
+                fetch,
+                parse,
+                max_concurrent_parses,
+                default_parse_workers(),
+                shutdown,
+                |url| {
+                    let tripped = tripped_sites.borrow();
+                    !tripped.is_empty() && tripped.contains(&site_of(&registry, url))
+                },
+                &mut on_result,
+            )
+            .await
+        }
+    };
+    drop(failure_sink);
+    if let Some(e) = write_error {
+        return Err(e);
+    }
+    // Остальные URL не записаны в чекпоинт, и `--resume-from-checkpoint` их догонит.
+    let interrupted = books.len() < urls_total;
+    if interrupted {
+        status!("interrupted: {}/{total} urls processed", books.len());
+    }
+    let mut hooks = PostParseHooks::default();
+    if config.enrich && !interrupted {
+        hooks = hooks.with(OpenLibraryHook);
     }
-    println!("succesfull parsed {success}/{total}");
-    wtr.flush()?;
-    Ok(())
+    if !hooks.is_empty() {
+        info!(hooks = ?hooks.names(), "running post-parse hooks");
+        hooks.run(books.iter_mut().flatten()).await;
+    }
+    if let Some(dir) = config.download_covers.as_ref().filter(|_| !interrupted) {
+        let client = http::global().build_client()?;
+        let saved = download_covers(
+            &client,
+            dir,
+            books.iter_mut().flatten(),
+            max_concurrent_parses,
+        )
+        .await?;
+        status!("covers saved: {saved}, see {}", dir.display());
+    }
+    let mut parsed: Vec<_> = books.iter().flatten().collect();
+    let collisions = find_collisions(parsed.iter().copied());
+    if !collisions.is_empty() {
+        status!(
+            "{} isbns listed under several urls of one store, see {COLLISIONS_FILE}",
+            collisions.len()
+        );
+        write_collisions(Path::new(COLLISIONS_FILE), &collisions)?;
+    }
+    let near_duplicates = find_near_duplicates(parsed.iter().copied());
+    if !near_duplicates.is_empty() {
+        status!(
+            "{} works found under several isbns, see {NEAR_DUPLICATES_FILE}",
+            near_duplicates.len()
+        );
+        write_near_duplicates(Path::new(NEAR_DUPLICATES_FILE), &near_duplicates)?;
+    }
+    if config.count_by_publisher {
+        let counts = count_by_publisher(parsed.iter().copied());
+        write_publishers(Path::new(PUBLISHERS_FILE), &counts)?;
+        status!("{} publishers, see {PUBLISHERS_FILE}", counts.len());
+    }
+    if write_after_crawl {
+        // Повторы ISBN идут в порядке `source` при любом `--sort-output`: сортировка устойчивая.
+        if config.idempotent {
+            sort_books(&mut parsed, SortKey::Isbn);
+        }
+        if let Some(key) = config.output_sort_key() {
+            sort_books(&mut parsed, key);
+        }
+        for book in parsed {
+            info!("succesfull parsed book with url {}", book.source);
+            sink.write_book(book)?;
+        }
+    }
+    status!("succesfull parsed {success}/{total}");
+    let failures = failure_collector.finish();
+    status!("failed {}/{total}, see failures.jsonl", failures.len());
+    let retries = http::retry_stats();
+    info!(
+        recovered = retries.recovered(),
+        gave_up = retries.gave_up(),
+        "retry summary"
+    );
+    status!("{}", retries.summary());
+    if let Some(pool) = proxy_pool::global() {
+        for health in pool.health() {
+            info!(?health, "proxy health");
+        }
+        let dead = pool.health().iter().filter(|h| !h.alive).count();
+        if dead > 0 {
+            status!("{dead} proxies removed from rotation, see log");
+        }
+    }
+    write_jsonl(&failures, std::fs::File::create("failures.jsonl")?)?;
+    sink.finish()?;
+    if let Some(cache) = page_cache::global() {
+        let changed = cache.changed_urls();
+        status!("changed pages since last run: {}", changed.len());
+        if config.changed_txt {
+            cache.write_changed(std::path::Path::new("changed.txt"))?;
+        }
+    }
+    Manifest {
+        args,
+        version: env!("CARGO_PKG_VERSION"),
+        git_rev: git_rev(),
+        started_at,
+        finished_at: unix_now(),
+        shuffle_seed,
+        site_urls: site_outcomes
+            .iter()
+            .map(|(site, outcome)| (site.clone(), outcome.attempted as usize))
+            .collect(),
+        stats: progress.stats(),
+        config: &config,
+    }
+    .write(std::path::Path::new(MANIFEST_FILE))?;
+    let code = exit_code(&site_outcomes, config.fail_threshold);
+    if code != EXIT_OK {
+        warn!(
+            code,
+            ?site_outcomes,
+            "run is unhealthy, exiting with non-zero code"
+        );
+    }
+    Ok(ExitCode::from(code))
+}
+
+/// URL товаров из stdin (вход `-`) или из sitemap сайтов. Список из stdin берётся
+/// как есть: без `<how_much_from_one_store>` и перемешивания.
+async fn input_urls(
+    config: &Config,
+    shuffle_seed: Option<u64>,
+    custom_sitemaps: &[(Sites, Vec<String>)],
+) -> anyhow::Result<Vec<String>> {
+    if !config.urls_from_stdin {
+        return sitemap_urls(config, shuffle_seed, custom_sitemaps).await;
+    }
+    let urls = read_urls(std::io::stdin().lock())?;
+    if urls.is_empty() {
+        warn!("no urls on stdin");
+    }
+    status!("{} urls from stdin", urls.len());
+    Ok(urls)
+}
+
+async fn sitemap_urls(
+    config: &Config,
+    shuffle_seed: Option<u64>,
+    custom_sitemaps: &[(Sites, Vec<String>)],
+) -> anyhow::Result<Vec<String>> {
+    let select = |urls: Vec<String>| sample_urls(urls, config.max_parses_per_source, shuffle_seed);
+
+    let urls_labirint: Vec<String> = select(
+        fetch_site_urls(&[URL1], config.sort_by_lastmod)
+            .await
+            .into_iter()
+            .filter(|u| u.contains("/books/"))
+            .collect(),
+    );
+    status!("urls_labirint have {} books", urls_labirint.len());
+    let igraslov_filter = (!config.igraslov_all).then_some(&IGRASLOV_BOOK_INDICATORS[..]);
+    let urls_igraslov: Vec<String> = select({
+        let mut books: Vec<String> = vec![];
+        for sitemap in URL2 {
+            match fetch_igraslov_urls(sitemap, igraslov_filter).await {
+                Ok(mut found) => books.append(&mut found),
+                Err(e) => warn!("skipping sitemap: {e:#}"),
+            }
+        }
+        books
+    });
+    status!("urls_igraslov have {} books", urls_igraslov.len());
+    let urls_eksmo: Vec<String> = select(fetch_site_urls(&URL3, config.sort_by_lastmod).await);
+    status!("urls_eksmo have {} books", urls_eksmo.len());
+
+    let mut site_urls: Vec<(Sites, Vec<String>)> = vec![
+        (Sites::IgraSlov, urls_igraslov),
+        (Sites::Labirint, urls_labirint),
+        (Sites::Eksmo, urls_eksmo),
+    ];
+    for (site, sitemaps) in custom_sitemaps {
+        let urls: Vec<String> = select(fetch_site_urls(sitemaps, config.sort_by_lastmod).await);
+        status!("urls_{site} have {} books", urls.len());
+        site_urls.push((*site, urls));
+    }
+    check_site_urls(&site_urls)?;
+
+    let mut urls: Vec<String> = vec![];
+    for (_, site_urls) in site_urls {
+        urls = interleave(urls.into_iter(), site_urls.into_iter()).collect();
+    }
+    Ok(urls)
 }
-#[tracing::instrument(skip(parser), fields(url=%url))]
-async fn parse_book_page<T: BookParser>(parser: &T, url: T::Url) -> anyhow::Result<Book<T::Url>> {
-    parser.parse_book(url).await
+/// URL товаров из sitemap сайта; с `by_lastmod` — сначала обновлённые последними.
+async fn fetch_site_urls<S: AsRef<str>>(sitemaps: &[S], by_lastmod: bool) -> Vec<String> {
+    if by_lastmod {
+        newest_first(fetch_urlset_entries(sitemaps).await)
+    } else {
+        fetch_urlsets(sitemaps).await
+    }
+}
+/// Открывает `<stem>.csv` или `<stem>.json` в зависимости от формата.
+fn open_sink(
+    output: OutputFormat,
+    with_flags: bool,
+    csv_bom: bool,
+    price_format: PriceFormat,
+    stem: &str,
+) -> anyhow::Result<Box<dyn BookSink>> {
+    Ok(match output {
+        OutputFormat::Csv => Box::new({
+            write_csv_schema(
+                std::path::Path::new(&format!("{stem}.schema.json")),
+                with_flags,
+            )?;
+            CsvSink::create_atomic(format!("{stem}.csv"), with_flags, csv_bom)?
+                .with_price_format(price_format)
+        }),
+        OutputFormat::JsonArray(_) => writer_sink(
+            output,
+            std::io::BufWriter::new(std::fs::File::create(format!("{stem}.json"))?),
+            with_flags,
+            csv_bom,
+            price_format,
+        )?,
+        OutputFormat::Parquet => writer_sink(
+            output,
+            std::io::BufWriter::new(std::fs::File::create(format!("{stem}.parquet"))?),
+            with_flags,
+            csv_bom,
+            price_format,
+        )?,
+        #[cfg(feature = "sqlite")]
+        OutputFormat::Sqlite => Box::new(parser::sqlite_sink::SqliteSink::create(
+            format!("{stem}.sqlite"),
+            parser::sqlite_sink::BATCH_SIZE,
+        )?),
+        #[cfg(not(feature = "sqlite"))]
+        OutputFormat::Sqlite => {
+            anyhow::bail!("--output-sqlite requires a build with --features sqlite")
+        }
+    })
+}
+/// `--min-description-len`: слишком короткое описание пишется пустым.
+fn clear_short_description(book: &mut Book<String>, min_len: usize) {
+    if min_len == 0 {
+        return;
+    }
+    let len = book.description.char_len();
+    if book.description.clear_if_shorter(min_len) {
+        info!(
+            url = %book.source,
+            len,
+            min = min_len,
+            "description is too short, writing it empty"
+        );
+    }
 }
 fn interleave<I, J, T>(mut a: I, mut b: J) -> impl Iterator<Item = T>
 where
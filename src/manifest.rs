@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::cli::Config;
+use crate::progress::CrawlStats;
+
+/// Файл с описанием прогона, пишется рядом с выгрузкой.
+pub static MANIFEST_FILE: &str = "manifest.json";
+
+/// Всё, что нужно, чтобы повторить прогон: аргументы, версия, seed, итоговый `Config`
+/// и что получилось. Время — секунды Unix.
+#[derive(Debug, Serialize)]
+pub struct Manifest<'a> {
+    pub args: Vec<String>,
+    pub version: &'static str,
+    /// `git rev-parse HEAD` каталога запуска; `None` вне checkout.
+    pub git_rev: Option<String>,
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// Seed перемешивания, даже если он был выбран случайно.
+    pub shuffle_seed: Option<u64>,
+    /// URL, ушедших в парсинг, по сайтам.
+    pub site_urls: BTreeMap<String, usize>,
+    #[serde(flatten)]
+    pub stats: CrawlStats,
+    pub config: &'a Config,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn git_rev() -> Option<String> {
+    let out = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    let rev = String::from_utf8(out.stdout).ok()?.trim().to_string();
+    (out.status.success() && !rev.is_empty()).then_some(rev)
+}
+
+impl Manifest<'_> {
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("can't create {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_has_expected_keys() {
+        let config =
+            Config::from_args(["--shuffle", "--seed", "7"].map(String::from)).expect("config");
+        let manifest = Manifest {
+            args: vec!["--shuffle".into(), "--seed".into(), "7".into()],
+            version: env!("CARGO_PKG_VERSION"),
+            git_rev: None,
+            started_at: 1_700_000_000,
+            finished_at: 1_700_000_060,
+            shuffle_seed: Some(7),
+            site_urls: BTreeMap::from([("labirint".to_string(), 2), ("eksmo".to_string(), 1)]),
+            stats: CrawlStats {
+                total: 3,
+                processed: 3,
+                succeeded: 2,
+                failed: 1,
+            },
+            config: &config,
+        };
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_manifest_{}.json", std::process::id()));
+        manifest.write(&path).expect("write manifest");
+        let value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).expect("read")).expect("json");
+        std::fs::remove_file(&path).expect("cleanup");
+
+        let mut keys: Vec<&str> = value
+            .as_object()
+            .expect("object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort_unstable();
+        assert_eq!(
+            keys,
+            [
+                "args",
+                "config",
+                "failed",
+                "finished_at",
+                "git_rev",
+                "processed",
+                "shuffle_seed",
+                "site_urls",
+                "started_at",
+                "succeeded",
+                "total",
+                "version",
+            ]
+        );
+        assert_eq!(value["site_urls"]["labirint"], 2);
+        assert_eq!(value["config"]["seed"], 7);
+        assert_eq!(value["config"]["shuffle"], true);
+        assert_eq!(value["config"]["max_parses_per_source"], 1500);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::parse_traits::Book;
+use crate::sink::isbn_key;
+
+/// Отчёт о вероятно одних и тех же произведениях под разными ISBN.
+pub static NEAR_DUPLICATES_FILE: &str = "near_duplicates.csv";
+
+/// Книги с одним `Book::fingerprint`, но разными ISBN: переиздания, другие переплёты
+/// или одна книга в разных магазинах под своими ISBN.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearDuplicate {
+    pub fingerprint: u64,
+    /// Название и первый автор первой книги группы — для чтения отчёта глазами.
+    pub title: String,
+    pub author: String,
+    /// Разные ISBN группы в порядке появления.
+    pub isbns: Vec<String>,
+    /// URL карточек в порядке появления.
+    pub sources: Vec<String>,
+}
+
+/// Группы книг по отпечатку, в которых больше одного ISBN; повторы одного ISBN
+/// ловит дедупликация по ISBN и `collisions.csv`. Книги не удаляются, это только отчёт.
+pub fn find_near_duplicates<'a>(
+    books: impl IntoIterator<Item = &'a Book<String>>,
+) -> Vec<NearDuplicate> {
+    let mut by_fingerprint: BTreeMap<u64, NearDuplicate> = BTreeMap::new();
+    for book in books {
+        let fingerprint = book.fingerprint();
+        let group = by_fingerprint
+            .entry(fingerprint)
+            .or_insert_with(|| NearDuplicate {
+                fingerprint,
+                title: book.title.as_str().to_string(),
+                author: book
+                    .authors
+                    .first()
+                    .map(|author| author.as_str().to_string())
+                    .unwrap_or_default(),
+                isbns: vec![],
+                sources: vec![],
+            });
+        let isbn = book.isbn.as_str();
+        if !group
+            .isbns
+            .iter()
+            .any(|known| isbn_key(known) == isbn_key(isbn))
+        {
+            group.isbns.push(isbn.to_string());
+        }
+        if !group.sources.contains(&book.source) {
+            group.sources.push(book.source.clone());
+        }
+    }
+    by_fingerprint
+        .into_values()
+        .filter(|group| group.isbns.len() > 1)
+        .collect()
+}
+
+/// `fingerprint,title,author,isbns,sources`: отпечаток в hex, списки через `"; "`.
+pub fn write_near_duplicates(path: &Path, groups: &[NearDuplicate]) -> anyhow::Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["fingerprint", "title", "author", "isbns", "sources"])?;
+    for group in groups {
+        wtr.write_record([
+            format!("{:016x}", group.fingerprint),
+            group.title.clone(),
+            group.author.clone(),
+            group.isbns.join("; "),
+            group.sources.join("; "),
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::{Author, Isbn, Sites, Title};
+    use crate::sink::tests::sample_book;
+
+    #[test]
+    fn editions_grouped_by_fingerprint() {
+        let first = sample_book(1);
+        let mut reissue = sample_book(2);
+        reissue.isbn = Isbn::try_from("978-5-04-156838-2".to_string()).expect("isbn");
+        reissue.title = Title::new("Джейн Эйр - 2-е издание".to_string());
+        let mut eksmo = sample_book(3);
+        eksmo.site = Sites::Eksmo;
+        eksmo.source = "https://eksmo.ru/book/dzheyn-eyr/".to_string();
+        eksmo.isbn = Isbn::try_from("9785041568382".to_string()).expect("isbn");
+        eksmo.authors = vec![Author::new("Шарлотта Бронте".to_string())];
+        let mut other = sample_book(4);
+        other.title = Title::new("Грозовой перевал".to_string());
+        other.isbn = Isbn::try_from("978-5-04-122366-3".to_string()).expect("isbn");
+
+        let groups = find_near_duplicates([&first, &reissue, &eksmo, &other]);
+        assert_eq!(
+            groups,
+            [NearDuplicate {
+                fingerprint: first.fingerprint(),
+                title: "Джейн Эйр".to_string(),
+                author: "Бронте Шарлотта".to_string(),
+                isbns: vec![
+                    "978-5-9268-3015-3".to_string(),
+                    "978-5-04-156838-2".to_string(),
+                ],
+                sources: vec![
+                    first.source.clone(),
+                    reissue.source.clone(),
+                    eksmo.source.clone(),
+                ],
+            }]
+        );
+        // Один ISBN под несколькими URL — не почти-дубль, а коллизия.
+        assert!(find_near_duplicates([&first, &sample_book(5)]).is_empty());
+
+        let path = std::env::temp_dir().join(format!(
+            "bd_parser_near_duplicates_{}.csv",
+            std::process::id()
+        ));
+        write_near_duplicates(&path, &groups).expect("write");
+        let written = std::fs::read_to_string(&path).expect("read");
+        let mut lines = written.lines();
+        assert_eq!(lines.next(), Some("fingerprint,title,author,isbns,sources"));
+        assert!(lines.next().expect("row").starts_with(&format!(
+            "{:016x},Джейн Эйр,Бронте Шарлотта,978-5-9268-3015-3; 978-5-04-156838-2,",
+            first.fingerprint()
+        )));
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, anyhow};
+
+use crate::page_cache::content_hash;
+
+static GLOBAL: OnceLock<OfflinePages> = OnceLock::new();
+
+/// Страницы нет среди сохранённых: в офлайн-режиме это то же, что блокировка сайтом,
+/// — загрузить её неоткуда. Достаётся из цепочки `anyhow` через `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageNotSaved {
+    pub url: String,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for PageNotSaved {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "offline: page {} is not saved ({})",
+            self.url,
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for PageNotSaved {}
+
+/// `--offline`: сохранённые страницы вместо сети. Имена файлов те же, что у
+/// `--cache-dir` (`<sha256(url)>.html`), так что каталог кэша подходит как есть.
+#[derive(Debug)]
+pub struct OfflinePages {
+    dir: PathBuf,
+}
+
+impl OfflinePages {
+    pub fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(anyhow!("offline dir {} does not exist", dir.display()));
+        }
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path_for(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{}.html", content_hash(url)))
+    }
+
+    /// Тело сохранённой страницы; `PageNotSaved`, если файла нет.
+    pub fn page(&self, url: &str) -> anyhow::Result<String> {
+        let path = self.path_for(url);
+        match std::fs::read_to_string(&path) {
+            Ok(body) => Ok(body),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(PageNotSaved {
+                url: url.to_string(),
+                path,
+            }
+            .into()),
+            Err(e) => Err(e).with_context(|| format!("can't read {}", path.display())),
+        }
+    }
+}
+
+/// Переводит все загрузки страниц в офлайн; вызывается один раз до начала парсинга.
+pub fn init_global(pages: OfflinePages) -> anyhow::Result<()> {
+    GLOBAL
+        .set(pages)
+        .map_err(|_| anyhow!("offline pages are already initialized"))
+}
+
+pub fn global() -> Option<&'static OfflinePages> {
+    GLOBAL.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_page_is_read_and_missing_one_is_not_saved() {
+        let dir = std::env::temp_dir().join(format!("bd_parser_offline_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let pages = OfflinePages::open(&dir).expect("open");
+        let url = "https://eksmo.ru/book/1/";
+        std::fs::write(pages.path_for(url), "<p>saved</p>").expect("write");
+
+        assert_eq!(pages.page(url).expect("page"), "<p>saved</p>");
+        let err = pages.page("https://eksmo.ru/book/2/").expect_err("missing");
+        let not_saved = err.downcast_ref::<PageNotSaved>().expect("PageNotSaved");
+        assert_eq!(not_saved.url, "https://eksmo.ru/book/2/");
+        assert!(OfflinePages::open(dir.join("nope")).is_err());
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -0,0 +1,323 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{Context, anyhow};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::http::Validators;
+
+/// Target событий об изменившихся страницах.
+pub const CHANGES_TARGET: &str = "changes";
+
+static GLOBAL: OnceLock<PageCache> = OnceLock::new();
+
+/// Что произошло со страницей относительно прошлой сохранённой версии.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageChange {
+    New,
+    Unchanged,
+    Changed,
+}
+
+/// SHA-256 тела страницы в hex.
+pub fn content_hash(body: &str) -> String {
+    Sha256::digest(body.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Дисковый кэш страниц: `<sha256(url)>.html` с телом, `<sha256(url)>.sha256` с хэшем
+/// содержимого и `<sha256(url)>.validators.json` с `ETag`/`Last-Modified` для условных
+/// запросов. При повторной загрузке сравнивает хэши и запоминает изменившиеся URL.
+pub struct PageCache {
+    dir: PathBuf,
+    changed: Mutex<Vec<String>>,
+}
+
+impl PageCache {
+    pub fn open(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("can't create cache dir {}", dir.display()))?;
+        Ok(Self {
+            dir,
+            changed: Mutex::new(vec![]),
+        })
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = content_hash(url);
+        (
+            self.dir.join(format!("{key}.html")),
+            self.dir.join(format!("{key}.sha256")),
+        )
+    }
+
+    fn validators_path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.validators.json", content_hash(url)))
+    }
+
+    /// Тело страницы из кэша, если она уже загружалась.
+    pub fn cached(&self, url: &str) -> Option<String> {
+        std::fs::read_to_string(self.paths(url).0).ok()
+    }
+
+    /// Тело из кэша и валидаторы, с которыми его можно перезапросить условно;
+    /// `None`, если чего-то из этого нет.
+    pub fn conditional(&self, url: &str) -> Option<(String, Validators)> {
+        let text = std::fs::read_to_string(self.validators_path(url)).ok()?;
+        let validators: Validators = serde_json::from_str(&text).ok()?;
+        if validators.is_empty() {
+            return None;
+        }
+        Some((self.cached(url)?, validators))
+    }
+
+    /// Запоминает валидаторы свежего ответа; ответ без них стирает прежние,
+    /// чтобы не слать условный запрос к устаревшей версии.
+    pub fn store_validators(&self, url: &str, validators: &Validators) -> anyhow::Result<()> {
+        let path = self.validators_path(url);
+        if validators.is_empty() {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => return Ok(()),
+            }
+        }
+        std::fs::write(&path, serde_json::to_string(validators)?)?;
+        Ok(())
+    }
+
+    /// Сохраняет свежую версию страницы и сообщает, изменилась ли она.
+    pub fn store(&self, url: &str, body: &str) -> anyhow::Result<PageChange> {
+        let (body_path, hash_path) = self.paths(url);
+        let new_hash = content_hash(body);
+        let change = match std::fs::read_to_string(&hash_path) {
+            Ok(old_hash) if old_hash.trim() == new_hash => PageChange::Unchanged,
+            Ok(old_hash) => {
+                info!(
+                    target: CHANGES_TARGET,
+                    url,
+                    old_hash = old_hash.trim(),
+                    new_hash,
+                    "page content changed"
+                );
+                self.changed
+                    .lock()
+                    .expect("changed urls lock")
+                    .push(url.to_string());
+                PageChange::Changed
+            }
+            Err(_) => PageChange::New,
+        };
+        if change != PageChange::Unchanged {
+            std::fs::write(&body_path, body)?;
+            std::fs::write(&hash_path, &new_hash)?;
+        }
+        Ok(change)
+    }
+
+    /// URL, содержимое которых изменилось за этот прогон, в порядке обнаружения.
+    pub fn changed_urls(&self) -> Vec<String> {
+        self.changed.lock().expect("changed urls lock").clone()
+    }
+
+    pub fn write_changed(&self, path: &Path) -> anyhow::Result<()> {
+        let mut text = self.changed_urls().join("\n");
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Включает кэш для всех парсеров; вызывается один раз до начала парсинга.
+pub fn init_global(cache: PageCache) -> anyhow::Result<()> {
+    GLOBAL
+        .set(cache)
+        .map_err(|_| anyhow!("page cache is already initialized"))
+}
+
+pub fn global() -> Option<&'static PageCache> {
+    GLOBAL.get()
+}
+
+/// Сохраняет загруженную страницу в глобальный кэш, если он включён.
+/// Ошибки кэша не должны ронять парсинг, поэтому только логируются.
+pub fn observe(url: &str, body: &str) {
+    if let Some(cache) = global()
+        && let Err(e) = cache.store(url, body)
+    {
+        warn!(url, "can't store page in cache: {e:#}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bd_parser_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn refetch_reports_changes() {
+        let dir = temp_dir("page_cache");
+        let cache = PageCache::open(&dir).expect("cache");
+        let url = "https://eksmo.ru/book/1/";
+
+        assert_eq!(
+            cache.store(url, "<p>v1</p>").expect("store"),
+            PageChange::New
+        );
+        assert_eq!(
+            cache.store(url, "<p>v1</p>").expect("store"),
+            PageChange::Unchanged
+        );
+        assert!(cache.changed_urls().is_empty());
+
+        assert_eq!(
+            cache.store(url, "<p>v2</p>").expect("store"),
+            PageChange::Changed
+        );
+        assert_eq!(cache.changed_urls(), [url]);
+        assert_eq!(cache.cached(url).as_deref(), Some("<p>v2</p>"));
+
+        // Хэши переживают перезапуск: новый экземпляр видит прошлую версию.
+        let reopened = PageCache::open(&dir).expect("cache");
+        assert_eq!(
+            reopened.store(url, "<p>v2</p>").expect("store"),
+            PageChange::Unchanged
+        );
+
+        let changed = dir.join("changed.txt");
+        cache.write_changed(&changed).expect("write");
+        assert_eq!(
+            std::fs::read_to_string(&changed).expect("read"),
+            format!("{url}\n")
+        );
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[tokio::test]
+    async fn not_modified_page_served_from_cache() {
+        use crate::http::get_cached;
+        use wiremock::matchers::{header, header_exists, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("If-None-Match", "\"v1\""))
+            .and(header_exists("If-Modified-Since"))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .insert_header("Last-Modified", "Wed, 01 Oct 2025 10:00:00 GMT")
+                    .set_body_string("<p>v1</p>"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dir = temp_dir("conditional");
+        let cache = PageCache::open(&dir).expect("cache");
+        let client = reqwest::Client::new();
+        let url = format!("{}/book/1/", server.uri());
+
+        // Первый раз запрос безусловный: валидаторов ещё нет. Тело сохраняется сразу.
+        let body = get_cached(&client, &cache, &url, 0, None)
+            .await
+            .expect("200");
+        assert_eq!(
+            cache.conditional(&url).map(|(cached, _)| cached),
+            Some(body.clone())
+        );
+        assert_eq!(
+            cache.store(&url, &body).expect("store"),
+            PageChange::Unchanged
+        );
+
+        let body = get_cached(&client, &cache, &url, 0, None)
+            .await
+            .expect("304");
+        assert_eq!(body, "<p>v1</p>");
+        assert_eq!(
+            cache.store(&url, &body).expect("store"),
+            PageChange::Unchanged
+        );
+        assert!(cache.changed_urls().is_empty());
+
+        // Ответ без валидаторов стирает прежние.
+        cache
+            .store_validators(&url, &Validators::default())
+            .expect("clear");
+        assert_eq!(cache.conditional(&url), None);
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[tokio::test]
+    async fn not_modified_page_keeps_redirect_target() {
+        use crate::http::{get_cached, take_redirect};
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let moved = format!("{}/book/2/", server.uri());
+        Mock::given(method("GET"))
+            .and(path("/book/1/"))
+            .respond_with(ResponseTemplate::new(301).insert_header("Location", moved.as_str()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/book/2/"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/book/2/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string("<p>v1</p>"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let dir = temp_dir("conditional_redirect");
+        let cache = PageCache::open(&dir).expect("cache");
+        let client = reqwest::Client::new();
+        let url = format!("{}/book/1/", server.uri());
+
+        get_cached(&client, &cache, &url, 0, None)
+            .await
+            .expect("200");
+        assert_eq!(take_redirect(&url).as_deref(), Some(moved.as_str()));
+        let body = get_cached(&client, &cache, &url, 0, None)
+            .await
+            .expect("304");
+        assert_eq!(body, "<p>v1</p>");
+        assert_eq!(take_redirect(&url).as_deref(), Some(moved.as_str()));
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn content_hash_is_sha256_hex() {
+        assert_eq!(
+            content_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+}
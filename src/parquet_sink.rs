@@ -0,0 +1,284 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float32Builder, Int64Builder, ListBuilder, RecordBatch,
+    StringBuilder, UInt16Builder, UInt32Builder,
+};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+
+use crate::parse_traits::{Book, Price, Title};
+use crate::sink::BookSink;
+
+/// Строк в одной row group: на выгрузке в десятки тысяч книг это несколько групп,
+/// а буфер колонок остаётся в пределах пары десятков мегабайт.
+pub const ROW_GROUP_SIZE: usize = 10_000;
+
+/// Схема `books.parquet`: те же колонки, что в CSV, но с типами —
+/// цены в копейках `int64`, авторы списком строк.
+pub fn book_schema() -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("site", DataType::Utf8, false),
+        Field::new("source", DataType::Utf8, false),
+        Field::new("isbn", DataType::Utf8, false),
+        Field::new("title", DataType::Utf8, false),
+        Field::new_list(
+            "authors",
+            Field::new_list_field(DataType::Utf8, true),
+            false,
+        ),
+        Field::new("description", DataType::Utf8, false),
+        Field::new("price", DataType::Int64, false),
+        Field::new("about_author", DataType::Utf8, true),
+        Field::new("rating", DataType::Float32, true),
+        Field::new("review_count", DataType::UInt32, true),
+        Field::new("source_id", DataType::Utf8, true),
+        Field::new("price_max", DataType::Int64, true),
+        Field::new("price_is_from", DataType::Boolean, false),
+        Field::new("cover_image_url", DataType::Utf8, true),
+        Field::new("cover_path", DataType::Utf8, true),
+        Field::new("availability", DataType::Utf8, true),
+        Field::new("isbn13_group", DataType::Utf8, true),
+        Field::new("requested_url", DataType::Utf8, true),
+        Field::new("original_title", DataType::Utf8, true),
+        Field::new("publisher", DataType::Utf8, true),
+        Field::new("series", DataType::Utf8, true),
+        Field::new("series_index", DataType::UInt16, true),
+        Field::new("language", DataType::Utf8, true),
+    ]))
+}
+
+fn price_i64(price: Price) -> anyhow::Result<i64> {
+    let kopecks = u128::from(price);
+    i64::try_from(kopecks).map_err(|_| anyhow!("price {kopecks} doesn't fit into int64"))
+}
+
+/// Колонки текущей row group.
+#[derive(Default)]
+struct Columns {
+    len: usize,
+    site: StringBuilder,
+    source: StringBuilder,
+    isbn: StringBuilder,
+    title: StringBuilder,
+    authors: ListBuilder<StringBuilder>,
+    description: StringBuilder,
+    price: Int64Builder,
+    about_author: StringBuilder,
+    rating: Float32Builder,
+    review_count: UInt32Builder,
+    source_id: StringBuilder,
+    price_max: Int64Builder,
+    price_is_from: BooleanBuilder,
+    cover_image_url: StringBuilder,
+    cover_path: StringBuilder,
+    availability: StringBuilder,
+    isbn13_group: StringBuilder,
+    requested_url: StringBuilder,
+    original_title: StringBuilder,
+    publisher: StringBuilder,
+    series: StringBuilder,
+    series_index: UInt16Builder,
+    language: StringBuilder,
+}
+
+impl Columns {
+    fn push(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        // Цены проверяются до записи, чтобы колонки не разъехались по длине.
+        let price = price_i64(book.price)?;
+        let price_max = book.price_max.map(price_i64).transpose()?;
+        self.site.append_value(book.site.to_string());
+        self.source.append_value(&book.source);
+        self.isbn.append_value(book.isbn.as_str());
+        self.title.append_value(book.title.as_str());
+        for author in &book.authors {
+            self.authors.values().append_value(author.as_str());
+        }
+        self.authors.append(true);
+        self.description.append_value(book.description.as_str());
+        self.price.append_value(price);
+        self.about_author
+            .append_option(book.about_author.as_deref());
+        self.rating.append_option(book.rating);
+        self.review_count.append_option(book.review_count);
+        self.source_id.append_option(book.source_id.as_deref());
+        self.price_max.append_option(price_max);
+        self.price_is_from.append_value(book.price_is_from);
+        self.cover_image_url
+            .append_option(book.cover_image_url.as_deref());
+        self.cover_path.append_option(book.cover_path.as_deref());
+        self.availability
+            .append_option(book.availability.map(|a| a.to_string()));
+        self.isbn13_group.append_option(book.isbn13_group());
+        self.requested_url
+            .append_option(book.requested_url.as_deref());
+        self.original_title
+            .append_option(book.original_title.as_ref().map(Title::as_str));
+        self.publisher.append_option(book.publisher.as_deref());
+        self.series.append_option(book.series.as_deref());
+        self.series_index.append_option(book.series_index);
+        self.language.append_option(book.language.as_deref());
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Забирает накопленное в `RecordBatch` и очищает буферы.
+    fn take_batch(&mut self, schema: SchemaRef) -> anyhow::Result<RecordBatch> {
+        self.len = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.site.finish()),
+            Arc::new(self.source.finish()),
+            Arc::new(self.isbn.finish()),
+            Arc::new(self.title.finish()),
+            Arc::new(self.authors.finish()),
+            Arc::new(self.description.finish()),
+            Arc::new(self.price.finish()),
+            Arc::new(self.about_author.finish()),
+            Arc::new(self.rating.finish()),
+            Arc::new(self.review_count.finish()),
+            Arc::new(self.source_id.finish()),
+            Arc::new(self.price_max.finish()),
+            Arc::new(self.price_is_from.finish()),
+            Arc::new(self.cover_image_url.finish()),
+            Arc::new(self.cover_path.finish()),
+            Arc::new(self.availability.finish()),
+            Arc::new(self.isbn13_group.finish()),
+            Arc::new(self.requested_url.finish()),
+            Arc::new(self.original_title.finish()),
+            Arc::new(self.publisher.finish()),
+            Arc::new(self.series.finish()),
+            Arc::new(self.series_index.finish()),
+            Arc::new(self.language.finish()),
+        ];
+        Ok(RecordBatch::try_new(schema, columns)?)
+    }
+}
+
+/// `books.parquet` для аналитики (DuckDB, pandas): книги копятся по колонкам
+/// и уходят в файл целыми row group по `ROW_GROUP_SIZE` строк.
+pub struct ParquetSink<W: Write + Send> {
+    schema: SchemaRef,
+    columns: Columns,
+    writer: Option<ArrowWriter<W>>,
+}
+
+impl<W: Write + Send> ParquetSink<W> {
+    pub fn new(out: W) -> anyhow::Result<Self> {
+        let schema = book_schema();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(ROW_GROUP_SIZE)
+            .build();
+        let writer = ArrowWriter::try_new(out, Arc::clone(&schema), Some(props))?;
+        Ok(Self {
+            schema,
+            columns: Columns::default(),
+            writer: Some(writer),
+        })
+    }
+
+    fn flush_row_group(&mut self) -> anyhow::Result<()> {
+        if self.columns.len == 0 {
+            return Ok(());
+        }
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| anyhow!("parquet sink is already finished"))?;
+        let batch = self.columns.take_batch(Arc::clone(&self.schema))?;
+        writer.write(&batch)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> BookSink for ParquetSink<W> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        self.columns.push(book)?;
+        if self.columns.len >= ROW_GROUP_SIZE {
+            self.flush_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.flush_row_group()?;
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+    use arrow::array::{Array, AsArray};
+    use arrow::datatypes::{Int64Type, UInt32Type};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn books_read_back_with_types() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_books_{}.parquet", std::process::id()));
+        let mut sink =
+            ParquetSink::new(std::fs::File::create(&path).expect("create")).expect("sink");
+        let mut range = sample_book(2);
+        range.price_max = Some(Price::from(20002));
+        range.price_is_from = true;
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&range).expect("write");
+        sink.finish().expect("finish");
+
+        let reader =
+            ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&path).expect("open"))
+                .expect("reader");
+        assert_eq!(reader.schema().fields(), book_schema().fields());
+        let batches: Vec<RecordBatch> = reader
+            .build()
+            .expect("build")
+            .map(|batch| batch.expect("batch"))
+            .collect();
+        std::fs::remove_file(&path).expect("cleanup");
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let price = batch
+            .column_by_name("price")
+            .unwrap()
+            .as_primitive::<Int64Type>();
+        assert_eq!(price.value(1), 10002);
+        let price_max = batch
+            .column_by_name("price_max")
+            .unwrap()
+            .as_primitive::<Int64Type>();
+        assert!(price_max.is_null(0));
+        assert_eq!(price_max.value(1), 20002);
+        let authors = batch.column_by_name("authors").unwrap().as_list::<i32>();
+        let first = authors.value(0);
+        assert_eq!(
+            first.as_string::<i32>().iter().collect::<Vec<_>>(),
+            sample_book(1)
+                .authors
+                .iter()
+                .map(|a| Some(a.as_str()))
+                .collect::<Vec<_>>()
+        );
+        let reviews = batch
+            .column_by_name("review_count")
+            .unwrap()
+            .as_primitive::<UInt32Type>();
+        assert_eq!(reviews.len(), 2);
+        assert!(
+            batch
+                .column_by_name("price_is_from")
+                .unwrap()
+                .as_boolean()
+                .value(1)
+        );
+    }
+}
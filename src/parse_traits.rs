@@ -1,15 +1,52 @@
 use anyhow::{Context, Result, anyhow};
-use std::{fmt::Display, str::FromStr};
-use tracing::{info, instrument};
+use futures::future::LocalBoxFuture;
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    str::FromStr,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+use tracing::{info, instrument, warn};
 
 use reqwest::IntoUrl;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::telemetry::QUALITY_TARGET;
+
+/// Единый вид пробелов в текстовых полях: неразрывные (`\u{a0}`, `\u{202f}`), тонкие
+/// (`\u{2009}`) и прочие горизонтальные пробелы Юникода и табы становятся обычным пробелом,
+/// серии пробелов схлопываются в один, края текста и каждой строки обрезаются. Переводы
+/// строк остаются: по ним делятся абзацы описания и отбраковываются «имена» авторов из сайдбара.
+pub fn normalize_ws(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        let c = if c.is_whitespace() && c != '\n' && c != '\r' {
+            ' '
+        } else {
+            c
+        };
+        if c == ' ' && out.ends_with(' ') {
+            continue;
+        }
+        out.push(c);
+    }
+    out.split('\n')
+        .map(|line| line.trim_matches(' '))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Isbn(String);
 
 impl Isbn {
     fn new(s: String) -> Result<Self> {
-        let cleaned = s.trim().replace(['-', ' '], "");
+        let s = normalize_ws(&s);
+        let cleaned = s.replace(['-', ' '], "");
         if cleaned.len() >= 10 && cleaned.len() <= 13 && cleaned.chars().all(|c| c.is_ascii_digit())
         {
             Ok(Self(s))
@@ -43,6 +80,69 @@ impl Isbn {
     fn is_digit_13(isbn: &str) -> bool {
         isbn.chars().filter(|c| c.is_ascii_digit()).count() == 13
     }
+
+    /// Контрольная сумма ISBN-13 или ISBN-10. `Isbn::new` проверяет только длину, и под неё
+    /// подходят и артикулы магазина; сумма отличает настоящий ISBN.
+    pub fn checksum_ok(&self) -> bool {
+        let digits: Vec<u32> = self.0.chars().filter_map(|c| c.to_digit(10)).collect();
+        match digits.len() {
+            13 => isbn13_checksum_ok(&digits),
+            10 => {
+                digits
+                    .iter()
+                    .zip((1..=10).rev())
+                    .map(|(d, weight)| d * weight)
+                    .sum::<u32>()
+                    % 11
+                    == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Префикс EAN и группа регистрации (страна или язык): `978-5` у русскоязычных
+    /// издательств, `978-0` у англоязычных. ISBN-10 считается ISBN-13 с префиксом 978.
+    /// `None`, если группа не из известных диапазонов.
+    pub fn registration_group(&self) -> Option<String> {
+        let digits: String = self.0.chars().filter(char::is_ascii_digit).collect();
+        let (prefix, rest) = match digits.len() {
+            10 => ("978", digits.as_str()),
+            13 => digits.split_at(3),
+            _ => return None,
+        };
+        let len = registration_group_len(prefix, rest)?;
+        Some(format!("{prefix}-{}", &rest[..len]))
+    }
+}
+
+/// Длина группы регистрации по упрощённой таблице диапазонов International ISBN Agency:
+/// только границы групп, без разбивки издательств внутри группы.
+fn registration_group_len(prefix: &str, rest: &str) -> Option<usize> {
+    let lead = |len: usize| rest.get(..len)?.parse::<u32>().ok();
+    match prefix {
+        "978" => match lead(1)? {
+            0..=5 | 7 => Some(1),
+            6 => match lead(3)? {
+                600..=649 => Some(3),
+                650..=659 => Some(2),
+                _ => None,
+            },
+            8 => Some(2),
+            9 => match lead(2)? {
+                90..=94 => Some(2),
+                95..=98 => Some(3),
+                _ if lead(3)? < 999 => Some(4),
+                _ => Some(5),
+            },
+            _ => None,
+        },
+        "979" => match lead(1)? {
+            8 => Some(1),
+            1 if (10..=12).contains(&lead(2)?) => Some(2),
+            _ => None,
+        },
+        _ => None,
+    }
 }
 
 impl TryFrom<String> for Isbn {
@@ -60,12 +160,214 @@ impl Display for Isbn {
     }
 }
 
+/// ISBN-13 в произвольном тексте: префикс 978/979 и группы через дефис или пробел.
+static ISBN13_RE: OnceLock<Regex> = OnceLock::new();
+static SOURCE_ID_RE: OnceLock<[Regex; 3]> = OnceLock::new();
+
+/// Контрольная сумма ISBN-13: веса 1 и 3 по очереди, сумма кратна 10.
+fn isbn13_checksum_ok(digits: &[u32]) -> bool {
+    digits.len() == 13
+        && digits
+            .iter()
+            .enumerate()
+            .map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 })
+            .sum::<u32>()
+            % 10
+            == 0
+}
+
+/// Первый ISBN-13 с верной контрольной суммой в тексте страницы.
+pub fn scan_isbn(text: &str) -> Option<Isbn> {
+    let re = ISBN13_RE.get_or_init(|| Regex::new(r"97[89](?:[- ]?\d){10}").expect("isbn regex"));
+    re.find_iter(text).find_map(|m| {
+        let digits: Vec<u32> = m.as_str().chars().filter_map(|c| c.to_digit(10)).collect();
+        isbn13_checksum_ok(&digits).then(|| Isbn(m.as_str().to_string()))
+    })
+}
+
+/// Запасной путь для `parse_isbn`: если ячейка с ISBN не нашлась или не разобралась,
+/// берём ISBN из JSON-LD, затем ищем по всему тексту страницы. Иначе возвращаем исходную ошибку.
+pub fn isbn_fallback(ctx: &scraper::Html, err: anyhow::Error) -> Result<Isbn> {
+    let err = match crate::json_ld::isbn_from_json_ld(ctx, err) {
+        Ok(isbn) => return Ok(isbn),
+        Err(err) => err,
+    };
+    let text = ctx.root_element().text().collect::<Vec<_>>().join(" ");
+    match scan_isbn(&text) {
+        Some(isbn) => {
+            warn!(%isbn, "isbn selector failed ({err}), took isbn from page text");
+            Ok(isbn)
+        }
+        None => Err(err),
+    }
+}
+
+/// Оценка из текста вида `4,5` или `4.5`, ограниченная 0.0..=5.0.
+pub fn parse_rating_text(text: &str) -> Option<f32> {
+    let number: String = text
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == ',' || *c == '.')
+        .collect();
+    let rating: f32 = number.replace(',', ".").parse().ok()?;
+    rating.is_finite().then(|| rating.clamp(0.0, 5.0))
+}
+
+static SERIES_INDEX_RE: OnceLock<[Regex; 2]> = OnceLock::new();
+
+/// Римский номер в подписи серии больше этого — скорее слово («MIX», «DIM»), чем том.
+const MAX_ROMAN_SERIES_INDEX: u16 = 100;
+
+/// Римское число (`III`, `xiv`) в диапазоне `1..=3999`; неканоническая запись вроде `IIII`
+/// тоже читается, пустая строка и посторонние буквы — нет.
+pub fn parse_roman(text: &str) -> Option<u16> {
+    let digit = |c: char| match c.to_ascii_uppercase() {
+        'I' => Some(1),
+        'V' => Some(5),
+        'X' => Some(10),
+        'L' => Some(50),
+        'C' => Some(100),
+        'D' => Some(500),
+        'M' => Some(1000),
+        _ => None,
+    };
+    let digits: Vec<i32> = text.chars().map(digit).collect::<Option<_>>()?;
+    let total: i32 = digits
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| match digits.get(i + 1) {
+            Some(&next) if next > value => -value,
+            _ => value,
+        })
+        .sum();
+    (1..=3999).contains(&total).then_some(total as u16)
+}
+
+/// Номер книги в серии из подписи: «Книга 3», «Том III», «Часть 2», «№ 5», «#4»,
+/// «3 из 7», «Book 3 of 7». Одиночное число без такого слова номером не считается:
+/// в названиях серий бывают годы и «100 великих». Римские цифры — только заглавные
+/// и не больше `MAX_ROMAN_SERIES_INDEX`, иначе «Part Mix» читалось бы как 1009.
+pub fn series_index_from_text(text: &str) -> Option<u16> {
+    let [keyword, of] = SERIES_INDEX_RE.get_or_init(|| {
+        [
+            Regex::new(r"(?:\b(?i:книга|том|часть|выпуск|book|vol\.?|volume|part)|№|#)\s*(\d+|[IVXLCDM]+)\b")
+                .expect("series index regex"),
+            Regex::new(r"(?i)\b(\d+)\s+(?:из|of)\s+\d+\b").expect("series index of regex"),
+        ]
+    });
+    let number = keyword
+        .captures(text)
+        .or_else(|| of.captures(text))?
+        .get(1)?
+        .as_str();
+    number
+        .parse()
+        .ok()
+        .or_else(|| parse_roman(number).filter(|&n| n <= MAX_ROMAN_SERIES_INDEX))
+        .filter(|&n| n > 0)
+}
+
+/// Первое число в тексте вида `1 234 отзыва` или `(48 оценок)`; пробелы, неразрывные
+/// пробелы, запятые и точки внутри числа считаются разделителями тысяч.
+pub fn parse_count_text(text: &str) -> Option<u32> {
+    let digits: String = text
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit() || matches!(c, ' ' | '\u{a0}' | '\u{202f}' | ',' | '.'))
+        .filter(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+/// Пределы правдоподобия для заголовка и имени автора: иногда селектор цепляет
+/// целый сайдбар, и «названием» становится текст на пару тысяч символов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextLimits {
+    pub max_title_chars: usize,
+    pub max_author_chars: usize,
+    /// Больше слов в имени автора — скорее аннотация, чем имя.
+    pub max_author_words: usize,
+}
+
+impl Default for TextLimits {
+    fn default() -> Self {
+        Self {
+            max_title_chars: 300,
+            max_author_chars: 100,
+            max_author_words: 6,
+        }
+    }
+}
+
+static TEXT_LIMITS: OnceLock<TextLimits> = OnceLock::new();
+
+pub fn init_text_limits(limits: TextLimits) -> Result<()> {
+    TEXT_LIMITS
+        .set(limits)
+        .map_err(|_| anyhow!("text limits are already initialized"))
+}
+
+/// Пределы для `Title::new`/`Author::new`; `TextLimits::default()`, если `init_text_limits` не вызывали.
+pub fn text_limits() -> &'static TextLimits {
+    TEXT_LIMITS.get_or_init(TextLimits::default)
+}
+
+/// Значение поля не прошло проверку правдоподобия. Это не ошибка разбора: значение
+/// отбрасывается (поле остаётся пустым, срабатывают запасные пути), а в лог пишется предупреждение.
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationWarning {
+    pub field: &'static str,
+    pub reason: String,
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "implausible {}: {}", self.field, self.reason)
+    }
+}
+
+/// Пустое значение вместо отклонённого, с предупреждением в лог.
+fn rejected(value: String, warning: Option<ValidationWarning>) -> String {
+    match warning {
+        Some(warning) => {
+            let preview: String = value.chars().take(60).collect();
+            warn!(%warning, preview, "rejected field value");
+            String::new()
+        }
+        None => value,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Author(pub String);
 
 impl Author {
+    /// Имя длиннее `max_author_chars`, многострочное или длиннее `max_author_words`
+    /// слов отбрасывается с `ValidationWarning`, см. `text_limits`.
     pub fn new(s: String) -> Self {
-        Author(s.trim().to_string())
+        let name = normalize_ws(&s);
+        let warning = Self::check(&name, text_limits());
+        Author(rejected(name, warning))
+    }
+
+    pub fn check(name: &str, limits: &TextLimits) -> Option<ValidationWarning> {
+        let warning = |reason: String| {
+            Some(ValidationWarning {
+                field: "author",
+                reason,
+            })
+        };
+        let chars = name.chars().count();
+        let words = name.split_whitespace().count();
+        if chars > limits.max_author_chars {
+            warning(format!("{chars} chars, max {}", limits.max_author_chars))
+        } else if name.contains('\n') {
+            warning("contains a line break".to_string())
+        } else if words > limits.max_author_words {
+            warning(format!("{words} words, max {}", limits.max_author_words))
+        } else {
+            None
+        }
     }
 
     pub fn as_str(&self) -> &str {
@@ -73,6 +375,83 @@ impl Author {
     }
 }
 
+/// Разделители авторов в одной ячейке по умолчанию: запятая, точка с запятой и « и ».
+pub static DEFAULT_AUTHORS_SPLIT: &str = r"\s*[,;]\s*|\s+и\s+";
+
+static INITIALS_RE: OnceLock<Regex> = OnceLock::new();
+
+/// Делит ячейку авторов (`--authors-split-regex`) на отдельные имена. Часть из одних
+/// инициалов («Л. Н.», «А.») приклеивается к предыдущей через запятую: «Толстой, Л. Н.» —
+/// один автор, а не два. Запятую внутри имени можно и экранировать: `\,` не делит ячейку.
+#[derive(Debug, Clone)]
+pub struct AuthorSplitter {
+    regex: Regex,
+}
+
+impl Default for AuthorSplitter {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUTHORS_SPLIT).expect("default authors split regex")
+    }
+}
+
+impl AuthorSplitter {
+    pub fn new(pattern: &str) -> Result<Self> {
+        Ok(Self {
+            regex: Regex::new(pattern)
+                .with_context(|| format!("bad authors split regex {pattern:?}"))?,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    pub fn split(&self, cell: &str) -> Vec<String> {
+        let initials =
+            INITIALS_RE.get_or_init(|| Regex::new(r"^(?:\p{Lu}\.\s*)+$").expect("initials regex"));
+        // Экранированные запятые прячутся от регулярки и возвращаются после деления.
+        const ESCAPED_COMMA: char = '\u{E000}';
+        let cell = cell.trim().replace(r"\,", &ESCAPED_COMMA.to_string());
+        let mut names: Vec<String> = vec![];
+        for part in self.regex.split(&cell) {
+            let part = part.trim().replace(ESCAPED_COMMA, ",");
+            if part.is_empty() {
+                continue;
+            }
+            match names.last_mut() {
+                Some(name) if initials.is_match(&part) => {
+                    name.push_str(", ");
+                    name.push_str(&part);
+                }
+                _ => names.push(part),
+            }
+        }
+        names
+    }
+}
+
+static AUTHOR_SPLITTER: OnceLock<AuthorSplitter> = OnceLock::new();
+
+pub fn init_author_splitter(splitter: AuthorSplitter) -> Result<()> {
+    AUTHOR_SPLITTER
+        .set(splitter)
+        .map_err(|_| anyhow!("authors split regex is already initialized"))
+}
+
+/// `AuthorSplitter::default()`, если `init_author_splitter` не вызывали.
+pub fn author_splitter() -> &'static AuthorSplitter {
+    AUTHOR_SPLITTER.get_or_init(AuthorSplitter::default)
+}
+
+/// Авторы из текста одной ячейки для `parse_authors`: деление `author_splitter`, затем `Author::new`.
+pub fn split_authors(cell: &str) -> Vec<Author> {
+    author_splitter()
+        .split(cell)
+        .into_iter()
+        .map(Author::new)
+        .collect()
+}
+
 impl TryFrom<String> for Author {
     type Error = anyhow::Error;
 
@@ -87,12 +466,64 @@ impl Display for Author {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Хвосты названия, которые `Title::clean` срезает с конца: пометки о переплёте
+/// и формате в скобках и номер издания после тире или запятой. Подзаголовок после
+/// двоеточия под них не попадает: шаблоны требуют скобок или разделителя `-`/`,`.
+/// Пометка должна начинать слово: «(Информатика)» — не «формат».
+pub static TITLE_NOISE_PATTERNS: &[&str] = &[
+    r"(?i)\s*[(\[][^()\[\]]*\b(обложк|переплет|переплёт|издани|изд\.|формат|покет|pocket)[^()\[\]]*[)\]]\s*$",
+    r"(?i)(\s+[-–—]|,)\s*[^–—,:()]*?\b(издание|изд\.)[^:()]*$",
+];
+
+static TITLE_NOISE: OnceLock<Vec<Regex>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Title(pub String);
 
 impl Title {
+    /// Название чистится `normalize_ws` и `Title::clean`; длиннее `max_title_chars` — отбрасывается
+    /// с `ValidationWarning`.
     pub fn new(s: String) -> Self {
-        Title(s.trim().to_string())
+        let title = Self::clean(&normalize_ws(&s));
+        let warning = Self::check(&title, text_limits());
+        Title(rejected(title, warning))
+    }
+
+    /// `clean_with` по `TITLE_NOISE_PATTERNS`.
+    pub fn clean(title: &str) -> String {
+        let patterns = TITLE_NOISE.get_or_init(|| {
+            TITLE_NOISE_PATTERNS
+                .iter()
+                .map(|p| Regex::new(p).expect("title noise regex"))
+                .collect()
+        });
+        Self::clean_with(title, patterns)
+    }
+
+    /// Срезает ведущие `_` (так игра слов помечает часть товаров) и, пока хоть один
+    /// шаблон совпадает, шумные хвосты: «Книга (мягкая обложка) - 2-е издание» → «Книга».
+    pub fn clean_with(title: &str, patterns: &[Regex]) -> String {
+        let mut title = title
+            .trim()
+            .trim_start_matches('_')
+            .trim_start()
+            .to_string();
+        while let Some(found) = patterns.iter().find_map(|re| re.find(&title)) {
+            if found.start() == 0 {
+                break;
+            }
+            title.truncate(found.start());
+            title.truncate(title.trim_end().len());
+        }
+        title
+    }
+
+    pub fn check(title: &str, limits: &TextLimits) -> Option<ValidationWarning> {
+        let chars = title.chars().count();
+        (chars > limits.max_title_chars).then(|| ValidationWarning {
+            field: "title",
+            reason: format!("{chars} chars, max {}", limits.max_title_chars),
+        })
     }
 
     pub fn as_str(&self) -> &str {
@@ -113,11 +544,135 @@ impl Display for Title {
         write!(f, "{}", self.0)
     }
 }
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Sites {
     Labirint,
     IgraSlov,
     Eksmo,
+    /// Сайт, описанный в `--sites-config` (см. `config_parser`).
+    Custom {
+        name: &'static str,
+        host: &'static str,
+    },
+}
+impl Sites {
+    pub const BUILTIN: [Sites; 3] = [Self::Labirint, Self::IgraSlov, Self::Eksmo];
+
+    /// Определяет встроенный сайт по хосту URL (поддомены учитываются).
+    pub fn from_url(url: &str) -> Option<Self> {
+        let host = host_of(url)?;
+        Self::BUILTIN
+            .into_iter()
+            .find(|site| site.matches_host(&host))
+    }
+
+    pub fn domain(&self) -> &'static str {
+        match self {
+            Self::Labirint => "labirint.ru",
+            Self::IgraSlov => "igraslov.store",
+            Self::Eksmo => "eksmo.ru",
+            Self::Custom { host, .. } => host,
+        }
+    }
+
+    /// Короткий идентификатор книги в магазине из пути URL: номер у labirint,
+    /// slug у eksmo и игры слов. Query и завершающий `/` не учитываются.
+    pub fn source_id(&self, url: &str) -> Option<String> {
+        let [labirint, igraslov, eksmo] = SOURCE_ID_RE.get_or_init(|| {
+            [
+                Regex::new(r"^/books/(\d+)/?$").expect("labirint id regex"),
+                Regex::new(r"^/product/([^/]+)/?$").expect("igraslov id regex"),
+                Regex::new(r"^/book/([^/]+)/?$").expect("eksmo id regex"),
+            ]
+        });
+        let re = match self {
+            Self::Labirint => labirint,
+            Self::IgraSlov => igraslov,
+            Self::Eksmo => eksmo,
+            Self::Custom { .. } => return None,
+        };
+        let url = reqwest::Url::parse(url).ok()?;
+        Some(re.captures(url.path())?[1].to_string())
+    }
+
+    /// Валюта цен на витрине. Сейчас все сайты российские; новый регион задаёт свою здесь.
+    pub fn currency(&self) -> Currency {
+        match self {
+            Self::Labirint | Self::IgraSlov | Self::Eksmo | Self::Custom { .. } => Currency::Rub,
+        }
+    }
+
+    pub fn locale(&self) -> Locale {
+        match self {
+            Self::Labirint | Self::IgraSlov | Self::Eksmo | Self::Custom { .. } => Locale::RuRu,
+        }
+    }
+
+    pub fn matches_host(&self, host: &str) -> bool {
+        let domain = self.domain();
+        host == domain
+            || host
+                .strip_suffix(domain)
+                .is_some_and(|sub| sub.ends_with('.'))
+    }
+}
+/// Страница загрузилась, но это не карточка книги, а, например, лендинг серии с карточками
+/// книг: поля с неё дали бы мусорную запись. Достаётся из цепочки `anyhow` через `downcast_ref`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotABook {
+    pub url: String,
+}
+
+impl Display for NotABook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not a book page: {}", self.url)
+    }
+}
+
+impl std::error::Error for NotABook {}
+
+static H1_SEL: OnceLock<scraper::Selector> = OnceLock::new();
+
+/// Общая проверка `looks_like_book_page`: ровно один `<h1>` и ячейка ISBN или блок цены
+/// карточки товара. У серий и подборок заголовок тоже один, но нет ни ISBN, ни основной цены —
+/// только цены в карточках книг с другой разметкой.
+pub fn book_page_signals(
+    html: &scraper::Html,
+    price: &scraper::Selector,
+    isbn: &scraper::Selector,
+) -> bool {
+    let h1 = H1_SEL.get_or_init(|| scraper::Selector::parse("h1").expect("h1 selector"));
+    html.select(h1).count() == 1
+        && (html.select(isbn).next().is_some() || html.select(price).next().is_some())
+}
+
+/// Хост URL без схемы и пути; `None`, если URL не разбирается.
+pub fn host_of(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(str::to_string)
+}
+/// Абсолютный http(s)-адрес картинки: протокол-относительный `//host/...` дополняется
+/// `https:`, остальное (относительные пути, `data:`) отбрасывается.
+pub fn absolute_image_url(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let url = match raw.strip_prefix("//") {
+        Some(rest) => format!("https://{rest}"),
+        None => raw.to_string(),
+    };
+    let parsed = reqwest::Url::parse(&url).ok()?;
+    matches!(parsed.scheme(), "http" | "https").then_some(url)
+}
+/// Сегменты пути URL без пустых (`/books/1/` → `["books", "1"]`); `None`, если URL не разбирается.
+pub fn path_segments(url: &str) -> Option<Vec<String>> {
+    let url = reqwest::Url::parse(url).ok()?;
+    Some(
+        url.path_segments()?
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+    )
 }
 impl Display for Sites {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -125,22 +680,69 @@ impl Display for Sites {
             Self::Labirint => write!(f, "labirint"),
             Self::IgraSlov => write!(f, "igra_slov"),
             Self::Eksmo => write!(f, "eksmo"),
+            Self::Custom { name, .. } => write!(f, "{name}"),
         }
     }
 }
-#[derive(Debug)]
+impl Serialize for Sites {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[derive(Debug, Serialize)]
 pub struct Description(String);
 impl Description {
+    /// Пробелы приводятся `normalize_ws`, абзацы сохраняются.
     pub fn new(s: String) -> Self {
-        Self(s)
+        Self(normalize_ws(&s))
     }
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Символов без пробелов по краям: кириллица считается по буквам, а не по байтам.
+    pub fn char_len(&self) -> usize {
+        self.0.trim().chars().count()
+    }
+
+    /// `--min-description-len`: описание короче `min_chars` (заглушки вроде «—») становится
+    /// пустым. Возвращает `true`, если описание было непустым и его очистили.
+    pub fn clear_if_shorter(&mut self, min_chars: usize) -> bool {
+        let len = self.char_len();
+        if len == 0 || len >= min_chars {
+            return false;
+        }
+        self.0.clear();
+        true
+    }
 }
-#[derive(Debug)]
+/// Цена в минимальных единицах валюты сайта. Валюты `Price` не знает, поэтому сравнение
+/// и `+`/`-` осмысленны только для цен одного сайта (или сайтов с одной валютой);
+/// цены разных сайтов складываются и сравниваются через `Money`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize)]
 pub struct Price(u128);
 
+/// Сумма не переполняется и не паникует: упирается в `u128::MAX`.
+impl std::ops::Add for Price {
+    type Output = Price;
+
+    fn add(self, other: Price) -> Price {
+        Price(self.0.saturating_add(other.0))
+    }
+}
+
+/// Цена не бывает отрицательной: разность с большей ценой — ноль.
+impl std::ops::Sub for Price {
+    type Output = Price;
+
+    fn sub(self, other: Price) -> Price {
+        Price(self.0.saturating_sub(other.0))
+    }
+}
+
 impl From<u128> for Price {
     fn from(value: u128) -> Self {
         Self(value)
@@ -169,6 +771,159 @@ impl Price {
         let num = s.parse().unwrap();
         Self(num)
     }
+
+    /// Сумма для людей по правилам локали; `Price` хранит минимальные единицы валюты.
+    pub fn display_in(&self, locale: Locale, currency: Currency) -> String {
+        let unit = 10_u128.pow(currency.minor_units());
+        let major = (self.0 / unit).to_string();
+        let mut grouped = String::with_capacity(major.len() + major.len() / 3);
+        for (i, digit) in major.chars().enumerate() {
+            if i > 0 && (major.len() - i).is_multiple_of(3) {
+                grouped.push(locale.group_separator());
+            }
+            grouped.push(digit);
+        }
+        format!(
+            "{grouped}{}{:0width$} {}",
+            locale.decimal_separator(),
+            self.0 % unit,
+            currency.symbol(),
+            width = currency.minor_units() as usize
+        )
+    }
+
+    /// Рубли для людей: `89500` → `"895,00 ₽"`, разряды через пробел (`"12 345,60 ₽"`).
+    pub fn display_rub(&self) -> String {
+        self.display_in(Locale::RuRu, Currency::Rub)
+    }
+
+    pub fn format(&self, format: PriceFormat) -> String {
+        match format {
+            PriceFormat::Kopecks => self.to_string(),
+            PriceFormat::Rubles => self.display_rub(),
+        }
+    }
+
+    /// Как `format`, но человекочитаемая цена — в валюте и локали сайта.
+    pub fn format_for(&self, format: PriceFormat, site: Sites) -> String {
+        match format {
+            PriceFormat::Kopecks => self.to_string(),
+            PriceFormat::Rubles => self.display_in(site.locale(), site.currency()),
+        }
+    }
+}
+
+/// Сумма в валюте: `Price` вместе с `Currency` сайта, см. `Book::money`. Суммы в разных
+/// валютах не сравниваются (`partial_cmp` — `None`) и не складываются (ошибка).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Money {
+    pub amount: Price,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Price, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<()> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "currency mismatch: {} and {}",
+                self.currency.code(),
+                other.currency.code()
+            ))
+        }
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money> {
+        self.same_currency(&other)?;
+        Ok(Money::new(self.amount + other.amount, self.currency))
+    }
+
+    /// Как `Price - Price`: разность не уходит ниже нуля.
+    pub fn checked_sub(self, other: Money) -> Result<Money> {
+        self.same_currency(&other)?;
+        Ok(Money::new(self.amount - other.amount, self.currency))
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<std::cmp::Ordering> {
+        (self.currency == other.currency).then(|| self.amount.cmp(&other.amount))
+    }
+}
+
+/// Валюта цен сайта.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Currency {
+    Rub,
+    /// Ни один сайт сейчас не торгует в долларах; валюта для цен из JSON-LD
+    /// иностранных витрин и проверки `Money` на смешение валют.
+    Usd,
+}
+
+impl Currency {
+    /// Код ISO 4217.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Rub => "RUB",
+            Self::Usd => "USD",
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Rub => "₽",
+            Self::Usd => "$",
+        }
+    }
+
+    /// Знаков после запятой: сколько минимальных единиц (копеек) в `Price` на одну основную.
+    pub fn minor_units(&self) -> u32 {
+        match self {
+            Self::Rub | Self::Usd => 2,
+        }
+    }
+}
+
+/// Локаль, по которой сайт пишет числа и по которой цены форматируются для людей.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Locale {
+    RuRu,
+}
+
+impl Locale {
+    /// Тег BCP 47.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::RuRu => "ru-RU",
+        }
+    }
+
+    pub fn group_separator(&self) -> char {
+        match self {
+            Self::RuRu => ' ',
+        }
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Self::RuRu => ',',
+        }
+    }
+}
+
+/// Как писать цену в выходные файлы (`--human-prices`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum PriceFormat {
+    /// Целое число копеек — удобно для машинной обработки.
+    #[default]
+    Kopecks,
+    /// `Price::format_for`: в валюте и локали сайта.
+    Rubles,
 }
 impl FromStr for Price {
     type Err = anyhow::Error;
@@ -188,63 +943,1241 @@ impl Display for Price {
     }
 }
 
-#[derive(Debug)]
+/// Цена, как её показывает витрина: одна, «от X» или диапазон «X–Y».
+#[derive(Debug, Clone, Copy)]
+pub struct PriceQuote {
+    /// Единственная цена, X у «от X» или нижняя граница диапазона.
+    pub min: Price,
+    /// Верхняя граница диапазона.
+    pub max: Option<Price>,
+    /// Витрина показала «от X»: настоящая цена может быть выше.
+    pub is_from: bool,
+}
+
+impl PriceQuote {
+    pub fn single(price: Price) -> Self {
+        Self {
+            min: price,
+            max: None,
+            is_from: false,
+        }
+    }
+}
+
+/// Разбирает «от 895 ₽», «895–1 200 ₽» и обычную цену. Слово «от» и тире снимаются
+/// здесь, а числа переводит в копейки `parse` сайта: форматы сумм у сайтов разные.
+pub fn price_quote_from_text(
+    text: &str,
+    parse: impl Fn(&str) -> Result<Price>,
+) -> Result<PriceQuote> {
+    let text = text.trim();
+    let (is_from, rest) = match text.get(.."от".len()) {
+        Some(prefix) if prefix.to_lowercase() == "от" => (true, &text["от".len()..]),
+        _ => (false, text),
+    };
+    match rest.split_once(['–', '—', '-']) {
+        Some((min, max)) => {
+            let min = parse(min.trim())?;
+            let max = parse(max.trim())?;
+            if max.0 < min.0 {
+                return Err(anyhow!("price range {text:?} is reversed"));
+            }
+            Ok(PriceQuote {
+                min,
+                max: Some(max),
+                is_from: false,
+            })
+        }
+        None => Ok(PriceQuote {
+            is_from,
+            ..PriceQuote::single(parse(rest.trim())?)
+        }),
+    }
+}
+
+/// Наличие товара на витрине.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    InStock,
+    OutOfStock,
+}
+
+impl Availability {
+    /// Значение schema.org `availability`: `https://schema.org/InStock`, `OutOfStock` и т.п.
+    /// Предзаказ и «под заказ» считаются наличием — у них есть цена.
+    pub fn from_schema_org(value: &str) -> Option<Self> {
+        let name = value.trim().rsplit('/').next().unwrap_or_default();
+        match name {
+            "InStock"
+            | "LimitedAvailability"
+            | "OnlineOnly"
+            | "InStoreOnly"
+            | "PreOrder"
+            | "PreSale"
+            | "BackOrder"
+            | "MadeToOrder" => Some(Self::InStock),
+            "OutOfStock" | "SoldOut" | "Discontinued" => Some(Self::OutOfStock),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Availability {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::InStock => "in_stock",
+            Self::OutOfStock => "out_of_stock",
+        })
+    }
+}
+
+/// Разобранная страница: строится из тела ответа и сериализуется обратно
+/// для снимков `--save-failures-html`.
+pub trait PageHtml {
+    fn from_body(body: &str) -> Self
+    where
+        Self: Sized;
+    fn page_html(&self) -> String;
+}
+
+impl PageHtml for scraper::Html {
+    fn from_body(body: &str) -> Self {
+        scraper::Html::parse_document(body)
+    }
+
+    fn page_html(&self) -> String {
+        self.html()
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct Book<T: IntoUrl + Into<String> + Display + Clone> {
     pub authors: Vec<Author>,
     pub isbn: Isbn,
     pub source: T,
+    /// Исходный URL, если сайт отредиректил его на `source`.
+    pub requested_url: Option<String>,
+    /// Идентификатор книги в магазине, см. `Sites::source_id`.
+    pub source_id: Option<String>,
     pub title: Title,
+    /// Название оригинала переводной книги.
+    pub original_title: Option<Title>,
+    /// Издательство из таблицы характеристик.
+    pub publisher: Option<String>,
+    /// Серия, в которой вышла книга.
+    pub series: Option<String>,
+    /// Номер книги в серии, см. `series_index_from_text`.
+    pub series_index: Option<u16>,
+    /// Коды языков издания через запятую (`ru`, `ru,en`), см. `specs::language_codes`.
+    pub language: Option<String>,
     pub site: Sites,
     pub description: Description,
     pub price: Price,
+    /// Верхняя граница, если витрина показала диапазон цен.
+    pub price_max: Option<Price>,
+    /// Витрина показала «от X», `price` — это X.
+    pub price_is_from: bool,
+    /// Блок «Об авторе», если сайт отделяет его от аннотации.
+    pub about_author: Option<String>,
+    /// Средняя оценка покупателей, 0.0..=5.0.
+    pub rating: Option<f32>,
+    pub review_count: Option<u32>,
+    /// Адрес обложки на сайте магазина.
+    pub cover_image_url: Option<String>,
+    /// Куда `--download-covers` сохранил обложку.
+    pub cover_path: Option<String>,
+    /// `None`, если сайт не размечает наличие.
+    pub availability: Option<Availability>,
 }
+
+impl<T: IntoUrl + Into<String> + Display + Clone> Book<T> {
+    /// Цена в валюте сайта.
+    pub fn money(&self) -> Money {
+        Money::new(self.price, self.site.currency())
+    }
+
+    /// Группа регистрации ISBN для разбивки каталога по странам и языкам, см. `Isbn::registration_group`.
+    pub fn isbn13_group(&self) -> Option<String> {
+        self.isbn.registration_group()
+    }
+
+    /// Проверки, которые затрагивают несколько полей сразу. В отличие от `Title::check`
+    /// ничего не отбрасывается: непонятно, какое из полей неверно.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let has_price = u128::from(self.price) > 0;
+        match (self.availability, has_price) {
+            (Some(Availability::OutOfStock), true) => vec![ValidationWarning {
+                field: "price",
+                reason: format!("price {} on an out-of-stock page", self.price),
+            }],
+            (Some(Availability::InStock), false) => vec![ValidationWarning {
+                field: "price",
+                reason: "no price on an in-stock page".to_string(),
+            }],
+            _ => vec![],
+        }
+    }
+
+    /// Отпечаток произведения для поиска почти-дублей между магазинами и изданиями:
+    /// хэш FNV-1a нормализованного названия и первого автора. ISBN в него не входит, так
+    /// что переиздания и карточки разных магазинов с разными ISBN совпадают. Хэш стабилен
+    /// между прогонами и версиями компилятора — отпечатки можно сравнивать по выгрузкам.
+    pub fn fingerprint(&self) -> u64 {
+        let title = fingerprint_words(self.title.as_str()).join(" ");
+        // Магазины пишут автора то «Имя Фамилия», то «Фамилия Имя»: порядок слов не важен.
+        let mut author = self
+            .authors
+            .first()
+            .map(|author| fingerprint_words(author.as_str()))
+            .unwrap_or_default();
+        author.sort_unstable();
+        fnv1a([title.as_bytes(), b"\n", author.join(" ").as_bytes()])
+    }
+}
+
+/// Нормализация для `Book::fingerprint`: нижний регистр, «ё» → «е», пунктуация
+/// и кавычки — разделители слов, пробелы схлопываются.
+fn fingerprint_words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn fnv1a<'a>(parts: impl IntoIterator<Item = &'a [u8]>) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    parts.into_iter().flatten().fold(OFFSET, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Поля книги, извлечённые со страницы (без `source` и `site`).
+#[derive(Debug)]
+pub struct BookFields {
+    pub authors: Vec<Author>,
+    pub isbn: Isbn,
+    pub title: Title,
+    pub description: Description,
+    pub price: Price,
+    pub price_max: Option<Price>,
+    pub price_is_from: bool,
+    pub about_author: Option<String>,
+    pub rating: Option<f32>,
+    pub review_count: Option<u32>,
+    pub cover_image_url: Option<String>,
+    pub availability: Option<Availability>,
+    pub original_title: Option<Title>,
+    pub publisher: Option<String>,
+    pub series: Option<String>,
+    pub series_index: Option<u16>,
+    pub language: Option<String>,
+}
+
+/// Результат future и время его выполнения.
+async fn timed<T>(fut: impl Future<Output = T>) -> (T, Duration) {
+    let started = Instant::now();
+    let out = fut.await;
+    (out, started.elapsed())
+}
+
+// Future не могут быть `Send` из-за `scraper::Html`, так что явные границы ничего не дают.
+#[allow(async_fn_in_trait)]
 pub trait BookParser {
-    const SITE: Sites;
+    fn site(&self) -> Sites;
+
+    type Url: IntoUrl + Into<String> + From<String> + Display + Clone + Send + Sync + 'static;
+
+    type Context: Send + PageHtml;
+
+    /// Селекторы полей `(поле, CSS)` для `--diagnose`; пусто, если парсер не на селекторах.
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
 
-    type Url: IntoUrl + Into<String> + Display + Clone + Send + Sync + 'static;
+    /// Ведёт ли URL на страницу книги, а не на категорию или лендинг. Проверяется
+    /// до загрузки, чтобы чужие страницы не доходили до парсинга полей.
+    fn is_book_url(_url: &str) -> bool {
+        true
+    }
 
-    type Context: Send;
+    /// Похожа ли загруженная страница на карточку книги (см. `book_page_signals`).
+    /// Проверяется сразу после загрузки: иначе `parse_page` отдаёт `NotABook`.
+    fn looks_like_book_page(_ctx: &Self::Context) -> bool {
+        true
+    }
 
-    async fn fetch(&self, url: &Self::Url) -> Result<Self::Context>;
+    /// Загрузка тела страницы — IO-стадия конвейера (`pipeline`).
+    async fn fetch_body(&self, url: &Self::Url) -> Result<String>;
+    async fn fetch(&self, url: &Self::Url) -> Result<Self::Context> {
+        let body = self.fetch_body(url).await?;
+        Ok(Self::Context::from_body(&body))
+    }
     async fn parse_authors(&self, ctx: &Self::Context, log_url: &Self::Url) -> Result<Vec<Author>>;
     async fn parse_isbn(&self, ctx: &Self::Context, log_url: &Self::Url) -> Result<Isbn>;
     async fn parse_title(&self, ctx: &Self::Context, log_url: &Self::Url) -> Result<Title>;
     async fn parse_description(&self, ctx: &Self::Context) -> Result<Description>;
     async fn parse_price(&self, ctx: &Self::Context) -> Result<Price>;
+    /// Цена с «от» и диапазонами; по умолчанию — одна цена из `parse_price`.
+    async fn parse_price_quote(&self, ctx: &Self::Context) -> Result<PriceQuote> {
+        self.parse_price(ctx).await.map(PriceQuote::single)
+    }
+    /// Биография автора отдельно от аннотации; у большинства сайтов её нет.
+    async fn parse_about_author(&self, _ctx: &Self::Context) -> Result<Option<String>> {
+        Ok(None)
+    }
+    /// Оценка покупателей; `None`, если сайт её не показывает или оценок нет.
+    async fn parse_rating(&self, _ctx: &Self::Context) -> Result<Option<f32>> {
+        Ok(None)
+    }
+    async fn parse_review_count(&self, _ctx: &Self::Context) -> Result<Option<u32>> {
+        Ok(None)
+    }
+    /// Абсолютный адрес обложки.
+    async fn parse_cover_url(&self, _ctx: &Self::Context) -> Result<Option<String>> {
+        Ok(None)
+    }
+    async fn parse_availability(&self, _ctx: &Self::Context) -> Result<Option<Availability>> {
+        Ok(None)
+    }
+    /// Название оригинала у переводных книг, см. `Specs::original_title`.
+    async fn parse_original_title(&self, _ctx: &Self::Context) -> Result<Option<Title>> {
+        Ok(None)
+    }
+    /// Издательство, см. `Specs::publisher`.
+    async fn parse_publisher(&self, _ctx: &Self::Context) -> Result<Option<String>> {
+        Ok(None)
+    }
+    async fn parse_series(&self, _ctx: &Self::Context) -> Result<Option<String>> {
+        Ok(None)
+    }
+    /// Номер в серии для сайтов с отдельной строкой «Номер в серии». Без неё номер
+    /// берётся из уже разобранной подписи серии (`series_index_from_text`).
+    async fn parse_series_index(&self, _ctx: &Self::Context) -> Result<Option<u16>> {
+        Ok(None)
+    }
+    async fn parse_language(&self, _ctx: &Self::Context) -> Result<Option<String>> {
+        Ok(None)
+    }
 
-    #[instrument(skip(self),fields(url=%url))]
-    async fn parse_book(&self, url: Self::Url) -> Result<Book<Self::Url>> {
-        info!(target: "time","start processing");
-        let ctx = self.fetch(&url).await?;
-        let authors = self
-            .parse_authors(&ctx, &url)
-            .await
-            .with_context(|| format!("parse_authors failed: {}", url))?;
-        let title = self
-            .parse_title(&ctx, &url)
-            .await
-            .with_context(|| format!("parse_title failed: {}", url))?;
-        let isbn = self
-            .parse_isbn(&ctx, &url)
-            .await
-            .with_context(|| format!("parse_isbn failed: {}", url))?;
-        let description = self
-            .parse_description(&ctx)
-            .await
-            .with_context(|| format!("parse_description failed: {}", url))?;
-        let price = self
-            .parse_price(&ctx)
-            .await
-            .with_context(|| format!("parce_price failed: {}", url))?;
-        info!(target: "time","end processing");
-        Ok(Book {
+    /// Извлекает все поля страницы; единая точка входа для `parse_book`.
+    /// По умолчанию вызывает парсеры полей по очереди: однопроходный обход DOM
+    /// через `Selector::matches` оказался медленнее (см. `benches/parsing.rs`).
+    async fn parse_fields(&self, ctx: &Self::Context, url: &Self::Url) -> Result<BookFields> {
+        // Все поля разбираются даже после ошибки, чтобы сводка `quality` была полной;
+        // наружу уходит первая ошибка в прежнем порядке.
+        let started = Instant::now();
+        let (authors, authors_t) = timed(self.parse_authors(ctx, url)).await;
+        let (title, title_t) = timed(self.parse_title(ctx, url)).await;
+        let (isbn, isbn_t) = timed(self.parse_isbn(ctx, url)).await;
+        let (description, description_t) = timed(self.parse_description(ctx)).await;
+        let (price, price_t) = timed(self.parse_price_quote(ctx)).await;
+        let (about_author, about_author_t) = timed(self.parse_about_author(ctx)).await;
+        let (rating, rating_t) = timed(self.parse_rating(ctx)).await;
+        let (review_count, review_count_t) = timed(self.parse_review_count(ctx)).await;
+        let (cover_image_url, cover_t) = timed(self.parse_cover_url(ctx)).await;
+        let (availability, availability_t) = timed(self.parse_availability(ctx)).await;
+        let (original_title, original_title_t) = timed(self.parse_original_title(ctx)).await;
+        let (publisher, publisher_t) = timed(self.parse_publisher(ctx)).await;
+        let (series, series_t) = timed(self.parse_series(ctx)).await;
+        let (series_index, series_index_t) = timed(self.parse_series_index(ctx)).await;
+        let series_index = series_index.map(|index| {
+            index.or_else(|| {
+                let series = series.as_ref().ok()?.as_deref()?;
+                series_index_from_text(series)
+            })
+        });
+        let (language, language_t) = timed(self.parse_language(ctx)).await;
+        info!(
+            target: QUALITY_TARGET,
+            site = %self.site(),
+            url = %url,
+            authors_found = authors.as_ref().is_ok_and(|a| !a.is_empty()),
+            authors_count = authors.as_ref().map_or(0, Vec::len),
+            title_len = title.as_ref().map_or(0, |t| t.as_str().chars().count()),
+            isbn_found = isbn.is_ok(),
+            description_len = description.as_ref().map_or(0, |d| d.as_str().chars().count()),
+            price_found = price.is_ok(),
+            about_author_found = matches!(about_author, Ok(Some(_))),
+            rating_found = matches!(rating, Ok(Some(_))),
+            review_count_found = matches!(review_count, Ok(Some(_))),
+            cover_found = matches!(cover_image_url, Ok(Some(_))),
+            availability_found = matches!(availability, Ok(Some(_))),
+            original_title_found = matches!(original_title, Ok(Some(_))),
+            publisher_found = matches!(publisher, Ok(Some(_))),
+            series_found = matches!(series, Ok(Some(_))),
+            series_index_found = matches!(series_index, Ok(Some(_))),
+            language_found = matches!(language, Ok(Some(_))),
+            authors_us = authors_t.as_micros() as u64,
+            title_us = title_t.as_micros() as u64,
+            isbn_us = isbn_t.as_micros() as u64,
+            description_us = description_t.as_micros() as u64,
+            price_us = price_t.as_micros() as u64,
+            about_author_us = about_author_t.as_micros() as u64,
+            rating_us = rating_t.as_micros() as u64,
+            review_count_us = review_count_t.as_micros() as u64,
+            cover_us = cover_t.as_micros() as u64,
+            availability_us = availability_t.as_micros() as u64,
+            original_title_us = original_title_t.as_micros() as u64,
+            publisher_us = publisher_t.as_micros() as u64,
+            series_us = series_t.as_micros() as u64,
+            series_index_us = series_index_t.as_micros() as u64,
+            language_us = language_t.as_micros() as u64,
+            total_us = started.elapsed().as_micros() as u64,
+            "book fields quality"
+        );
+        let authors = authors.with_context(|| format!("parse_authors failed: {}", url))?;
+        let title = title.with_context(|| format!("parse_title failed: {}", url))?;
+        let isbn = isbn.with_context(|| format!("parse_isbn failed: {}", url))?;
+        let description =
+            description.with_context(|| format!("parse_description failed: {}", url))?;
+        let price = price.with_context(|| format!("parce_price failed: {}", url))?;
+        let about_author =
+            about_author.with_context(|| format!("parse_about_author failed: {}", url))?;
+        let rating = rating.with_context(|| format!("parse_rating failed: {}", url))?;
+        let review_count =
+            review_count.with_context(|| format!("parse_review_count failed: {}", url))?;
+        let cover_image_url =
+            cover_image_url.with_context(|| format!("parse_cover_url failed: {}", url))?;
+        let availability =
+            availability.with_context(|| format!("parse_availability failed: {}", url))?;
+        let original_title =
+            original_title.with_context(|| format!("parse_original_title failed: {}", url))?;
+        let publisher = publisher.with_context(|| format!("parse_publisher failed: {}", url))?;
+        let series = series.with_context(|| format!("parse_series failed: {}", url))?;
+        let series_index =
+            series_index.with_context(|| format!("parse_series_index failed: {}", url))?;
+        let language = language.with_context(|| format!("parse_language failed: {}", url))?;
+        Ok(BookFields {
             authors,
             isbn,
-            source: url,
             title,
-            site: Self::SITE,
             description,
-            price,
+            price: price.min,
+            price_max: price.max,
+            price_is_from: price.is_from,
+            about_author,
+            rating,
+            review_count,
+            cover_image_url,
+            availability,
+            original_title,
+            publisher,
+            series,
+            series_index,
+            language,
+        })
+    }
+
+    /// Все поля страницы строками — для `--diagnose` и обобщённых инструментов, которым
+    /// не нужен конкретный набор полей. Собирается из парсеров отдельных полей;
+    /// поле, которое не разобралось или пусто, в карту не попадает.
+    async fn extract_fields(
+        &self,
+        ctx: &Self::Context,
+        url: &Self::Url,
+    ) -> Result<BTreeMap<&'static str, String>> {
+        let mut fields = BTreeMap::new();
+        if let Ok(authors) = self.parse_authors(ctx, url).await {
+            let names: Vec<&str> = authors.iter().map(Author::as_str).collect();
+            fields.insert("authors", names.join("; "));
+        }
+        if let Ok(isbn) = self.parse_isbn(ctx, url).await {
+            fields.insert("isbn", isbn.as_str().to_string());
+        }
+        if let Ok(title) = self.parse_title(ctx, url).await {
+            fields.insert("title", title.as_str().to_string());
+        }
+        if let Ok(description) = self.parse_description(ctx).await {
+            fields.insert("description", description.as_str().to_string());
+        }
+        if let Ok(quote) = self.parse_price_quote(ctx).await {
+            fields.insert("price", u128::from(quote.min).to_string());
+            if let Some(max) = quote.max {
+                fields.insert("price_max", u128::from(max).to_string());
+            }
+            fields.insert("price_is_from", quote.is_from.to_string());
+        }
+        if let Ok(Some(about_author)) = self.parse_about_author(ctx).await {
+            fields.insert("about_author", about_author);
+        }
+        if let Ok(Some(rating)) = self.parse_rating(ctx).await {
+            fields.insert("rating", rating.to_string());
+        }
+        if let Ok(Some(review_count)) = self.parse_review_count(ctx).await {
+            fields.insert("review_count", review_count.to_string());
+        }
+        if let Ok(Some(cover_image_url)) = self.parse_cover_url(ctx).await {
+            fields.insert("cover_image_url", cover_image_url);
+        }
+        if let Ok(Some(availability)) = self.parse_availability(ctx).await {
+            fields.insert("availability", availability.to_string());
+        }
+        if let Ok(Some(original_title)) = self.parse_original_title(ctx).await {
+            fields.insert("original_title", original_title.as_str().to_string());
+        }
+        if let Ok(Some(publisher)) = self.parse_publisher(ctx).await {
+            fields.insert("publisher", publisher);
+        }
+        let series = self.parse_series(ctx).await.ok().flatten();
+        let series_index = self.parse_series_index(ctx).await.ok().flatten();
+        if let Some(series_index) =
+            series_index.or_else(|| series_index_from_text(series.as_deref()?))
+        {
+            fields.insert("series_index", series_index.to_string());
+        }
+        if let Some(series) = series {
+            fields.insert("series", series);
+        }
+        if let Ok(Some(language)) = self.parse_language(ctx).await {
+            fields.insert("language", language);
+        }
+        fields.retain(|_, value| !value.trim().is_empty());
+        Ok(fields)
+    }
+
+    #[instrument(skip(self),fields(url=%url))]
+    async fn parse_book(&self, url: Self::Url) -> Result<Book<Self::Url>> {
+        let body = self.fetch_body(&url).await?;
+        self.parse_page(url, &body).await
+    }
+
+    /// `--isbn-only`: со страницы берётся только ISBN, остальные поля и `Book` не строятся.
+    async fn parse_isbn_page(&self, url: &Self::Url, body: &str) -> Result<Isbn> {
+        let ctx = Self::Context::from_body(body);
+        self.parse_isbn(&ctx, url).await
+    }
+
+    /// Разбор уже загруженной страницы — CPU-стадия конвейера: ни одно поле не ходит в сеть,
+    /// так что future можно выполнить `block_on` в пуле `spawn_blocking`.
+    #[instrument(skip(self, body), fields(url=%url))]
+    async fn parse_page(&self, url: Self::Url, body: &str) -> Result<Book<Self::Url>> {
+        info!(target: "time","start processing");
+        let ctx = Self::Context::from_body(body);
+        if !Self::looks_like_book_page(&ctx) {
+            return Err(NotABook {
+                url: url.to_string(),
+            }
+            .into());
+        }
+        let fields = match self.parse_fields(&ctx, &url).await {
+            Ok(fields) => fields,
+            Err(e) => {
+                crate::failure_html::observe(&url.to_string(), &ctx);
+                return Err(e);
+            }
+        };
+        info!(target: "time","end processing");
+        let site = self.site();
+        // Старые адреса товаров редиректят на новые: в `source` идёт канонический URL.
+        let (source, requested_url) = match crate::http::take_redirect(&url.to_string()) {
+            Some(final_url) if Self::is_book_url(&final_url) => {
+                (Self::Url::from(final_url), Some(url.to_string()))
+            }
+            Some(final_url) => {
+                warn!(
+                    final_url,
+                    "redirected to a non-book page, keeping the requested url"
+                );
+                (url, None)
+            }
+            None => (url, None),
+        };
+        let book = Book {
+            authors: fields.authors,
+            isbn: fields.isbn,
+            source_id: site.source_id(&source.to_string()),
+            source,
+            requested_url,
+            title: fields.title,
+            original_title: fields.original_title,
+            publisher: fields.publisher,
+            series: fields.series,
+            series_index: fields.series_index,
+            language: fields.language,
+            site,
+            description: fields.description,
+            price: fields.price,
+            price_max: fields.price_max,
+            price_is_from: fields.price_is_from,
+            about_author: fields.about_author,
+            rating: fields.rating,
+            review_count: fields.review_count,
+            cover_image_url: fields.cover_image_url,
+            cover_path: None,
+            availability: fields.availability,
+        };
+        for warning in book.validate() {
+            warn!(%warning, "inconsistent book fields");
+        }
+        Ok(book)
+    }
+}
+
+/// Object-safe обёртка над `BookParser`, чтобы хранить парсеры разных сайтов
+/// в одной коллекции и выбирать их во время выполнения.
+///
+/// Future не `Send`: `scraper::Html` не `Sync` и живёт между `.await`.
+pub trait DynBookParser: Send + Sync {
+    fn site(&self) -> Sites;
+    fn field_selectors(&self) -> Vec<(&'static str, String)>;
+    fn is_book_url(&self, url: &str) -> bool;
+    fn parse_book_dyn(&self, url: String) -> LocalBoxFuture<'_, Result<Book<String>>>;
+    fn fetch_body_dyn(&self, url: String) -> LocalBoxFuture<'_, Result<String>>;
+    /// Синхронный `parse_page` для потоков `spawn_blocking`; блокирует вызывающий поток.
+    fn parse_page_blocking(&self, url: String, body: &str) -> Result<Book<String>>;
+    fn parse_isbn_blocking(&self, url: String, body: &str) -> Result<Isbn>;
+    fn extract_fields_dyn(
+        &self,
+        url: String,
+        body: String,
+    ) -> LocalBoxFuture<'_, Result<BTreeMap<&'static str, String>>>;
+}
+
+impl<T> DynBookParser for T
+where
+    T: BookParser<Url = String> + Send + Sync,
+{
+    fn site(&self) -> Sites {
+        BookParser::site(self)
+    }
+    fn field_selectors(&self) -> Vec<(&'static str, String)> {
+        BookParser::field_selectors(self)
+    }
+    fn is_book_url(&self, url: &str) -> bool {
+        <T as BookParser>::is_book_url(url)
+    }
+    fn parse_book_dyn(&self, url: String) -> LocalBoxFuture<'_, Result<Book<String>>> {
+        Box::pin(self.parse_book(url))
+    }
+    fn fetch_body_dyn(&self, url: String) -> LocalBoxFuture<'_, Result<String>> {
+        Box::pin(async move { self.fetch_body(&url).await })
+    }
+    fn parse_page_blocking(&self, url: String, body: &str) -> Result<Book<String>> {
+        futures::executor::block_on(self.parse_page(url, body))
+    }
+    fn parse_isbn_blocking(&self, url: String, body: &str) -> Result<Isbn> {
+        futures::executor::block_on(self.parse_isbn_page(&url, body))
+    }
+    fn extract_fields_dyn(
+        &self,
+        url: String,
+        body: String,
+    ) -> LocalBoxFuture<'_, Result<BTreeMap<&'static str, String>>> {
+        Box::pin(async move {
+            let ctx = T::Context::from_body(&body);
+            self.extract_fields(&ctx, &url).await
         })
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::eksmo::EksmoParser;
+    use crate::specs::SpecsPage;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    type EksmoPage = SpecsPage<EksmoParser>;
+
+    #[test]
+    fn noisy_title_suffixes_are_cleaned() {
+        for (noisy, clean) in [
+            ("Джейн Эйр (мягкая обложка)", "Джейн Эйр"),
+            ("Мастер и Маргарита - 2-е издание", "Мастер и Маргарита"),
+            (
+                "Мастер и Маргарита (Твёрдый переплёт) — 3-е изд., испр.",
+                "Мастер и Маргарита",
+            ),
+            (
+                "Python: к вершинам мастерства, 2-е издание",
+                "Python: к вершинам мастерства",
+            ),
+            ("Дюна [покет]", "Дюна"),
+            (
+                "_Каркассон. Охотники и собиратели",
+                "Каркассон. Охотники и собиратели",
+            ),
+            ("Джейн Эйр: Шарлотта Бронте", "Джейн Эйр: Шарлотта Бронте"),
+            (
+                "Алиса в Стране чудес (с иллюстрациями)",
+                "Алиса в Стране чудес (с иллюстрациями)",
+            ),
+            (
+                "Преступление и наказание - роман",
+                "Преступление и наказание - роман",
+            ),
+            ("(мягкая обложка)", "(мягкая обложка)"),
+            (
+                "Основы программирования (информатика)",
+                "Основы программирования (информатика)",
+            ),
+            ("Дюна (Формат А5)", "Дюна"),
+        ] {
+            assert_eq!(Title::clean(noisy), clean, "{noisy}");
+        }
+        assert_eq!(
+            Title::new(" _Дюна (мягкая обложка) ".to_string()).as_str(),
+            "Дюна"
+        );
+        let custom = [Regex::new(r"\s*\(комплект\)$").expect("regex")];
+        assert_eq!(Title::clean_with("Дюна (комплект)", &custom), "Дюна");
+        assert_eq!(
+            Title::clean_with("Дюна (мягкая обложка)", &custom),
+            "Дюна (мягкая обложка)"
+        );
+    }
+
+    #[test]
+    fn odd_whitespace_normalized_in_every_field() {
+        assert_eq!(
+            normalize_ws("\u{a0}a\u{2009}\u{2009}b\t\tc\u{202f} "),
+            "a b c"
+        );
+        assert_eq!(normalize_ws("абзац\u{a0}\n\nвторой\t"), "абзац\n\nвторой");
+        assert_eq!(normalize_ws("строка \n\t отступ"), "строка\nотступ");
+
+        assert_eq!(
+            Title::new("Джейн\u{a0}Эйр\t(мягкая\u{2009}обложка)".to_string()).as_str(),
+            "Джейн Эйр"
+        );
+        assert_eq!(
+            Author::new("\tБронте\u{a0}\u{a0}Шарлотта\u{2009}".to_string()).as_str(),
+            "Бронте Шарлотта"
+        );
+        assert_eq!(
+            Description::new("Роман\u{a0}о\u{2009}гувернантке.\t\n\nВторой абзац".to_string())
+                .as_str(),
+            "Роман о гувернантке.\n\nВторой абзац"
+        );
+        assert_eq!(
+            Isbn::try_from("978\u{a0}5\u{2009}9268\t3015-3".to_string())
+                .expect("isbn")
+                .as_str(),
+            "978 5 9268 3015-3"
+        );
+    }
+
+    #[test]
+    fn series_index_arabic_roman_and_absent() {
+        assert_eq!(series_index_from_text("Гарри Поттер. Книга 3"), Some(3));
+        assert_eq!(series_index_from_text("Book 3 of 7"), Some(3));
+        assert_eq!(series_index_from_text("Хроники Нарнии, 2 из 7"), Some(2));
+        assert_eq!(series_index_from_text("Тёмная башня. Том IV"), Some(4));
+        assert_eq!(series_index_from_text("Часть XII"), Some(12));
+        assert_eq!(series_index_from_text("часть xii"), None);
+        assert_eq!(series_index_from_text("Part Mix"), None);
+        assert_eq!(series_index_from_text("Vol. Dim"), None);
+        assert_eq!(series_index_from_text("Vol. MIX"), None);
+        assert_eq!(parse_roman("MCMXCIV"), Some(1994));
+        assert_eq!(parse_roman("Речь"), None);
+
+        assert_eq!(series_index_from_text("Образ Речи"), None);
+        assert_eq!(series_index_from_text("100 великих"), None);
+        assert_eq!(series_index_from_text("Книга 0"), None);
+    }
+
+    #[test]
+    fn implausible_titles_and_authors_are_rejected() {
+        let limits = TextLimits::default();
+        assert_eq!(Title::check("Джейн Эйр", &limits), None);
+        assert_eq!(Title::new("  Джейн Эйр ".to_string()).as_str(), "Джейн Эйр");
+
+        let sidebar = "Новинки Бестселлеры Скидки ".repeat(100);
+        let warning = Title::check(&sidebar, &limits).expect("too long");
+        assert_eq!(warning.field, "title");
+        assert_eq!(Title::new(sidebar.clone()).as_str(), "");
+        let relaxed = TextLimits {
+            max_title_chars: 10_000,
+            ..limits
+        };
+        assert_eq!(Title::check(&sidebar, &relaxed), None);
+
+        assert_eq!(Author::check("Бронте Шарлотта", &limits), None);
+        assert_eq!(
+            Author::new("Бронте Шарлотта".to_string()).as_str(),
+            "Бронте Шарлотта"
+        );
+        let blurb = "Бронте Шарлотта\nАнглийская писательница, старшая из сестёр";
+        let warning = Author::check(blurb, &limits).expect("multi-line");
+        assert_eq!(
+            warning.to_string(),
+            "implausible author: contains a line break"
+        );
+        assert_eq!(Author::new(blurb.to_string()).as_str(), "");
+        assert!(
+            Author::check(
+                "одна из самых известных английских писательниц века",
+                &limits
+            )
+            .is_some()
+        );
+    }
+
+    #[test]
+    fn short_descriptions_are_cleared() {
+        let cleared = |text: &str, min: usize| {
+            let mut descr = Description::new(text.to_string());
+            let changed = descr.clear_if_shorter(min);
+            (changed, descr.as_str().to_string())
+        };
+        // 5 букв кириллицей — 10 байт: порог считается по символам.
+        assert_eq!(cleared("Роман", 5), (false, "Роман".to_string()));
+        assert_eq!(cleared("Роман", 6), (true, String::new()));
+        assert_eq!(cleared("  Роман  ", 6), (true, String::new()));
+        assert_eq!(cleared(" — ", 2), (true, String::new()));
+        assert_eq!(cleared("Роман", 0), (false, "Роман".to_string()));
+        assert_eq!(cleared("", 10), (false, String::new()));
+    }
+
+    #[test]
+    fn price_quote_text() {
+        let kopecks = |s: &str| -> Result<Price> {
+            Ok(Price::from(s.replace(' ', "").parse::<u128>()? * 100))
+        };
+        let plain = price_quote_from_text("895", kopecks).expect("plain");
+        assert_eq!(
+            (
+                u128::from(plain.min),
+                plain.max.map(u128::from),
+                plain.is_from
+            ),
+            (89_500, None, false)
+        );
+        let from = price_quote_from_text(" От 895 ", kopecks).expect("from");
+        assert_eq!(
+            (u128::from(from.min), from.max.map(u128::from), from.is_from),
+            (89_500, None, true)
+        );
+        let range = price_quote_from_text("895 – 1 200", kopecks).expect("range");
+        assert_eq!(
+            (
+                u128::from(range.min),
+                range.max.map(u128::from),
+                range.is_from
+            ),
+            (89_500, Some(120_000), false)
+        );
+        assert!(price_quote_from_text("1 200 — 895", kopecks).is_err());
+        assert!(price_quote_from_text("от", kopecks).is_err());
+    }
+
+    #[test]
+    fn prices_compare_and_add_within_currency() {
+        let (cheap, dear) = (Price::from(89_500), Price::from(120_000));
+        assert!(cheap < dear);
+        assert_eq!(cheap.max(dear), dear);
+        assert_eq!([dear, cheap].into_iter().min(), Some(cheap));
+        assert_eq!(u128::from(cheap + dear), 209_500);
+        assert_eq!(u128::from(dear - cheap), 30_500);
+        assert_eq!(cheap - dear, Price::default());
+        let max = Price::from(u128::MAX);
+        assert_eq!(max + cheap, max);
+
+        let book = crate::sink::tests::sample_book(1);
+        let rub = book.money();
+        assert_eq!(rub.currency, Currency::Rub);
+        let more = Money::new(Price::from(100), Currency::Rub);
+        assert_eq!(
+            rub.checked_add(more).expect("same currency").amount,
+            book.price + Price::from(100)
+        );
+        assert!(more < rub);
+
+        let usd = Money::new(Price::from(100), Currency::Usd);
+        assert_eq!(rub.partial_cmp(&usd), None);
+        assert_eq!((usd < rub, usd > rub), (false, false));
+        let err = rub.checked_add(usd).expect_err("mismatched currencies");
+        assert_eq!(err.to_string(), "currency mismatch: RUB and USD");
+        assert!(usd.checked_sub(rub).is_err());
+    }
+
+    #[test]
+    fn sites_default_currency_and_locale() {
+        let custom = Sites::Custom {
+            name: "my_store",
+            host: "my-store.ru",
+        };
+        for site in Sites::BUILTIN.into_iter().chain([custom]) {
+            assert_eq!(site.currency(), Currency::Rub, "{site}");
+            assert_eq!(site.currency().code(), "RUB");
+            assert_eq!(site.locale().tag(), "ru-RU", "{site}");
+        }
+        assert_eq!(
+            Price::from(108_450).format_for(PriceFormat::Rubles, Sites::Eksmo),
+            "1 084,50 ₽"
+        );
+        assert_eq!(
+            Price::from(108_450).format_for(PriceFormat::Kopecks, Sites::Eksmo),
+            "108450"
+        );
+    }
+
+    #[test]
+    fn display_rub_groups_thousands() {
+        let rub = |kopecks: u128| Price::from(kopecks).display_rub();
+        assert_eq!(rub(0), "0,00 ₽");
+        assert_eq!(rub(5), "0,05 ₽");
+        assert_eq!(rub(89_500), "895,00 ₽");
+        assert_eq!(rub(108_450), "1 084,50 ₽");
+        assert_eq!(rub(8_950_000), "89 500,00 ₽");
+        assert_eq!(rub(12_345_678_901), "123 456 789,01 ₽");
+        assert_eq!(Price::from(89_500).format(PriceFormat::Kopecks), "89500");
+    }
+
+    #[test]
+    fn source_id_per_site() {
+        let cases = [
+            (
+                Sites::Labirint,
+                "https://www.labirint.ru/books/801841/",
+                Some("801841"),
+            ),
+            (
+                Sites::Labirint,
+                "https://www.labirint.ru/books/801841?utm_source=x",
+                Some("801841"),
+            ),
+            (
+                Sites::Labirint,
+                "https://www.labirint.ru/genres/2308/",
+                None,
+            ),
+            (
+                Sites::IgraSlov,
+                "https://igraslov.store/product/dzhejn-ejr-tvyordyj/",
+                Some("dzhejn-ejr-tvyordyj"),
+            ),
+            (
+                Sites::IgraSlov,
+                "https://igraslov.store/product-category/knigi/",
+                None,
+            ),
+            (
+                Sites::Eksmo,
+                "https://eksmo.ru/book/dzheyn-eyr-ITD1131209/?from=search#reviews",
+                Some("dzheyn-eyr-ITD1131209"),
+            ),
+            (Sites::Eksmo, "https://eksmo.ru/book/", None),
+            (Sites::Eksmo, "not a url", None),
+        ];
+        for (site, url, expected) in cases {
+            assert_eq!(site.source_id(url).as_deref(), expected, "{url}");
+        }
+        let custom = Sites::Custom {
+            name: "shop",
+            host: "shop.example",
+        };
+        assert_eq!(custom.source_id("https://shop.example/books/1/"), None);
+    }
+
+    #[test]
+    fn scan_isbn_picks_first_valid_checksum() {
+        // Первый кандидат с неверной контрольной суммой пропускается.
+        let text = "Артикул 978-5-04-156838-3, ISBN: 978-5-04-156838-2, 9785926830153";
+        assert_eq!(
+            scan_isbn(text).map(|i| i.as_str().to_string()),
+            Some("978-5-04-156838-2".to_string())
+        );
+        assert_eq!(
+            scan_isbn("isbn 978 5 9268 3015 3").map(|i| i.as_str().to_string()),
+            Some("978 5 9268 3015 3".to_string())
+        );
+    }
+
+    #[test]
+    fn isbn_checksum() {
+        let isbn = |s: &str| Isbn::try_from(s.to_string()).expect("isbn");
+        assert!(isbn("978-5-04-156838-2").checksum_ok());
+        assert!(!isbn("978-5-04-156838-3").checksum_ok());
+        assert!(isbn("5-04-156838-3").checksum_ok());
+        assert!(!isbn("5-04-156838-0").checksum_ok());
+        // Артикул проходит проверку длины, но не суммы.
+        assert!(!isbn("100312345678").checksum_ok());
+    }
+
+    /// Слой, который запоминает поля событий `target: "quality"`.
+    #[derive(Clone, Default)]
+    pub(crate) struct QualityCapture(pub(crate) Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for QualityCapture {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if event.metadata().target() == QUALITY_TARGET {
+                let mut fields = HashMap::new();
+                event.record(&mut FieldVisitor(&mut fields));
+                self.0.lock().expect("lock").push(fields);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn parse_fields_emits_quality_event() {
+        let capture = QualityCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        let ctx = EksmoPage::from_body(include_str!("../page_examples/eksmo.html"));
+        let url = "https://eksmo.ru/book/example".to_string();
+        EksmoParser.parse_fields(&ctx, &url).await.expect("fields");
+        let empty = EksmoPage::from_body("<html><body></body></html>");
+        assert!(EksmoParser.parse_fields(&empty, &url).await.is_err());
+
+        // Кроме сводки по книге, в `quality` пишут и цепочки селекторов (`field`).
+        let events = capture.0.lock().expect("lock");
+        let events: Vec<_> = events.iter().filter(|e| e.contains_key("site")).collect();
+        assert_eq!(events.len(), 2);
+        let ok = &events[0];
+        assert_eq!(ok["site"], "eksmo");
+        assert_eq!(ok["url"], url);
+        assert_eq!(ok["authors_found"], "true");
+        assert_eq!(ok["authors_count"], "1");
+        assert_eq!(ok["isbn_found"], "true");
+        assert_eq!(ok["price_found"], "true");
+        assert_eq!(ok["about_author_found"], "false");
+        assert_eq!(ok["title_len"], "74");
+        assert!(ok["description_len"].parse::<usize>().expect("len") > 100);
+        assert!(ok.contains_key("total_us") && ok.contains_key("isbn_us"));
+
+        let failed = &events[1];
+        assert_eq!(failed["authors_found"], "false");
+        assert_eq!(failed["isbn_found"], "false");
+        assert_eq!(failed["title_len"], "0");
+    }
+
+    #[tokio::test]
+    async fn extract_fields_covers_core_fields() {
+        let ctx = EksmoPage::from_body(include_str!("../page_examples/eksmo.html"));
+        let url = "https://eksmo.ru/book/example".to_string();
+        let fields = EksmoParser
+            .extract_fields(&ctx, &url)
+            .await
+            .expect("fields");
+        for key in ["isbn", "title", "authors", "description", "price"] {
+            assert!(
+                fields.get(key).is_some_and(|v| !v.is_empty()),
+                "{key}: {fields:?}"
+            );
+        }
+        assert_eq!(fields["authors"], "Андрей Самарин");
+        assert!(!fields.contains_key("about_author"));
+
+        let empty = EksmoPage::from_body("<html><body></body></html>");
+        let fields = EksmoParser
+            .extract_fields(&empty, &url)
+            .await
+            .expect("fields");
+        assert!(!fields.contains_key("isbn") && !fields.contains_key("title"));
+    }
+
+    #[test]
+    fn isbn13_group_russian_and_foreign() {
+        let isbn = |s: &str| Isbn::try_from(s.to_string()).expect("isbn");
+        let book = crate::sink::tests::sample_book(1);
+        assert_eq!(book.isbn13_group().as_deref(), Some("978-5"));
+        assert_eq!(
+            isbn("978-0-306-40615-7").registration_group().as_deref(),
+            Some("978-0")
+        );
+        assert_eq!(
+            isbn("9788535902778").registration_group().as_deref(),
+            Some("978-85")
+        );
+        assert_eq!(
+            isbn("978-99921-58-10-7").registration_group().as_deref(),
+            Some("978-99921")
+        );
+        assert_eq!(
+            isbn("979-10-90636-07-1").registration_group().as_deref(),
+            Some("979-10")
+        );
+        // ISBN-10 — та же группа под префиксом 978.
+        assert_eq!(
+            isbn("5-17-012345-6").registration_group().as_deref(),
+            Some("978-5")
+        );
+        assert_eq!(isbn("9790000000001").registration_group(), None);
+    }
+
+    #[test]
+    fn availability_from_schema_org() {
+        assert_eq!(
+            Availability::from_schema_org("https://schema.org/InStock"),
+            Some(Availability::InStock)
+        );
+        assert_eq!(
+            Availability::from_schema_org("http://schema.org/PreOrder"),
+            Some(Availability::InStock)
+        );
+        assert_eq!(
+            Availability::from_schema_org("OutOfStock"),
+            Some(Availability::OutOfStock)
+        );
+        assert_eq!(Availability::from_schema_org("в наличии"), None);
+    }
+
+    #[test]
+    fn price_and_availability_agree() {
+        let mut book = crate::sink::tests::sample_book(1);
+        assert!(book.validate().is_empty());
+        book.availability = Some(Availability::InStock);
+        assert!(book.validate().is_empty());
+        book.availability = Some(Availability::OutOfStock);
+        book.price = Price::from(0);
+        assert!(book.validate().is_empty());
+    }
+
+    #[test]
+    fn price_and_availability_disagree() {
+        let mut book = crate::sink::tests::sample_book(1);
+        book.availability = Some(Availability::OutOfStock);
+        let warnings = book.validate();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].field, "price");
+        assert_eq!(
+            warnings[0].to_string(),
+            "implausible price: price 10001 on an out-of-stock page"
+        );
+
+        book.availability = Some(Availability::InStock);
+        book.price = Price::from(0);
+        assert_eq!(book.validate()[0].reason, "no price on an in-stock page");
+    }
+
+    #[test]
+    fn image_urls_are_made_absolute() {
+        assert_eq!(
+            absolute_image_url(" //imo10.labirint.ru/books/696481/cover.jpg/484-0 ").as_deref(),
+            Some("https://imo10.labirint.ru/books/696481/cover.jpg/484-0")
+        );
+        assert_eq!(
+            absolute_image_url("https://eksmo.ru/upload/cover.jpg").as_deref(),
+            Some("https://eksmo.ru/upload/cover.jpg")
+        );
+        assert_eq!(absolute_image_url("/upload/cover.jpg"), None);
+        assert_eq!(absolute_image_url("data:image/png;base64,AAAA"), None);
+    }
+
+    #[test]
+    fn rating_text_formats() {
+        assert_eq!(parse_rating_text("4,5"), Some(4.5));
+        assert_eq!(parse_rating_text(" 4.25 "), Some(4.25));
+        assert_eq!(parse_rating_text("7"), Some(5.0));
+        assert_eq!(parse_rating_text(""), None);
+        assert_eq!(parse_rating_text("нет оценок"), None);
+    }
+
+    #[test]
+    fn count_text_formats() {
+        assert_eq!(parse_count_text("(48 оценок)"), Some(48));
+        assert_eq!(parse_count_text("1 234 отзыва"), Some(1234));
+        assert_eq!(parse_count_text("1\u{a0}234"), Some(1234));
+        assert_eq!(parse_count_text("12,345"), Some(12345));
+        assert_eq!(parse_count_text("отзывов нет"), None);
+    }
+
+    #[test]
+    fn scan_isbn_none_without_valid_isbn() {
+        assert!(scan_isbn("Телефон 8 800 555 35 35, артикул 9781234567890").is_none());
+        assert!(scan_isbn("").is_none());
+    }
+
+    #[test]
+    fn editions_share_fingerprint() {
+        use crate::sink::tests::sample_book;
+
+        let labirint = sample_book(1);
+        let mut eksmo = sample_book(2);
+        eksmo.site = Sites::Eksmo;
+        eksmo.isbn = Isbn::try_from("978-5-04-156838-2".to_string()).expect("isbn");
+        eksmo.title = Title::new("«ДЖЕЙН  ЭЙР». (мягкая обложка)".to_string());
+        eksmo.authors = vec![
+            Author::new("Шарлотта Бронте".to_string()),
+            Author::new("Переводчик".to_string()),
+        ];
+        assert_eq!(labirint.fingerprint(), eksmo.fingerprint());
+
+        let mut yo = sample_book(3);
+        yo.title = Title::new("Ёлка".to_string());
+        let mut ye = sample_book(4);
+        ye.title = Title::new("елка!".to_string());
+        assert_eq!(yo.fingerprint(), ye.fingerprint());
+
+        let mut other_work = sample_book(5);
+        other_work.title = Title::new("Джейн Эйр 2".to_string());
+        assert_ne!(labirint.fingerprint(), other_work.fingerprint());
+        let mut other_author = sample_book(6);
+        other_author.authors = vec![Author::new("Бронте Эмили".to_string())];
+        assert_ne!(labirint.fingerprint(), other_author.fingerprint());
+        // Известные значения FNV-1a: хэш не должен меняться между версиями.
+        assert_eq!(fnv1a([b"".as_slice()]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a([b"a".as_slice()]), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn author_cells_split_by_delimiters() {
+        let splitter = AuthorSplitter::default();
+        for (cell, authors) in [
+            ("Бронте Шарлотта", vec!["Бронте Шарлотта"]),
+            (
+                "Ильф Илья, Петров Евгений",
+                vec!["Ильф Илья", "Петров Евгений"],
+            ),
+            ("Ильф И.; Петров Е. ;", vec!["Ильф И.", "Петров Е."]),
+            (
+                "Стругацкий А. Н. и Стругацкий Б. Н.",
+                vec!["Стругацкий А. Н.", "Стругацкий Б. Н."],
+            ),
+            ("Толстой, Л. Н.", vec!["Толстой, Л. Н."]),
+            (
+                "Толстой, Л.Н., Чехов, А. П.",
+                vec!["Толстой, Л.Н.", "Чехов, А. П."],
+            ),
+            (
+                r"Dumas\, Alexandre, Hugo Victor",
+                vec!["Dumas, Alexandre", "Hugo Victor"],
+            ),
+            ("Иван Ивановский", vec!["Иван Ивановский"]),
+            ("  ", vec![]),
+        ] {
+            assert_eq!(splitter.split(cell), authors, "{cell}");
+        }
+    }
+
+    #[test]
+    fn author_cells_split_by_custom_regex() {
+        let slash = AuthorSplitter::new(r"\s*/\s*").expect("regex");
+        assert_eq!(
+            slash.split("Ильф И. / Петров Е., мл."),
+            ["Ильф И.", "Петров Е., мл."]
+        );
+        assert!(AuthorSplitter::new("(").is_err());
+        assert_eq!(
+            split_authors("Ильф Илья и Петров Евгений")
+                .iter()
+                .map(Author::as_str)
+                .collect::<Vec<_>>(),
+            ["Ильф Илья", "Петров Евгений"]
+        );
+    }
+}
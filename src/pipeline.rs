@@ -0,0 +1,718 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use futures::future::{self, BoxFuture};
+use futures::{FutureExt, StreamExt, stream};
+use serde::Serialize;
+use tokio::sync::{Semaphore, mpsc};
+use tokio::task::{AbortHandle, JoinSet, LocalSet};
+use tracing::warn;
+
+/// Сколько загруженных страниц может ждать разбора, на один поток разбора.
+/// Когда очередь полна, загрузка приостанавливается.
+pub const QUEUE_PER_WORKER: usize = 2;
+
+/// Потоков разбора по умолчанию: по числу ядер.
+pub fn default_parse_workers() -> usize {
+    std::thread::available_parallelism().map_or(4, |n| n.get())
+}
+
+/// Сколько ждать начатые загрузки и разборы после Ctrl-C, если не задан `--shutdown-grace-secs`.
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// Остановка конвейера: после `signal` новые URL не берутся, а начатые загрузки и разборы
+/// ждут не дольше `grace` и затем прерываются. Без этого зависшая загрузка держит выход
+/// до таймаута клиента.
+pub struct Shutdown {
+    signal: BoxFuture<'static, ()>,
+    grace: Duration,
+}
+
+impl Shutdown {
+    pub fn new(signal: impl Future<Output = ()> + Send + 'static, grace: Duration) -> Self {
+        Self {
+            signal: signal.boxed(),
+            grace,
+        }
+    }
+
+    /// Остановка по Ctrl-C (SIGINT).
+    pub fn on_ctrl_c(grace: Duration) -> Self {
+        Self::new(
+            async {
+                // Без обработчика сигнала конвейер просто работает до конца.
+                if tokio::signal::ctrl_c().await.is_err() {
+                    future::pending::<()>().await;
+                }
+            },
+            grace,
+        )
+    }
+
+    /// Остановка по первому из двух сигналов, например по Ctrl-C или по ошибке записи.
+    pub fn or_on(self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        Self {
+            signal: future::select(self.signal, signal.boxed())
+                .map(|_| ())
+                .boxed(),
+            grace: self.grace,
+        }
+    }
+
+    /// Конвейер без остановки: все URL обрабатываются до конца.
+    pub fn never() -> Self {
+        Self::new(future::pending(), Duration::ZERO)
+    }
+}
+
+/// Чем обходить URL (`--driver`): оба дают одни и те же результаты и одинаково
+/// останавливаются по `Shutdown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum Driver {
+    /// `run`: поток URL с `buffer_unordered` и отдельной стадией разбора.
+    #[default]
+    Stream,
+    /// `run_join_set`: задача на каждый URL, отдельные задачи можно прервать.
+    JoinSet,
+}
+
+impl FromStr for Driver {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "stream" => Ok(Self::Stream),
+            "joinset" => Ok(Self::JoinSet),
+            other => Err(anyhow!("unknown driver {other}, expected stream|joinset")),
+        }
+    }
+}
+
+/// Разбор тела страницы в потоке `spawn_blocking`.
+pub type ParseFn<T> = Arc<dyn Fn(String, String) -> anyhow::Result<T> + Send + Sync>;
+
+/// Двухстадийный конвейер: `fetch` (IO) до `fetch_concurrency` запросов одновременно
+/// кладёт тела в ограниченный канал, `parse` (CPU) разбирает их на `parse_workers`
+/// блокирующих потоках, не занимая потоки Tokio. `on_result` вызывается для каждого
+/// URL по мере готовности, в том числе для неудачных загрузок, и может поправить
+/// результат до того, как он попадёт в возвращаемый список.
+///
+/// После `shutdown` возвращаются только результаты, готовые к концу окна ожидания:
+/// необработанные URL в них не попадают. Прерванный разбор в блокирующем потоке
+/// доработает в фоне, но его результат отбрасывается.
+pub async fn run<T, F, Fut>(
+    urls: Vec<String>,
+    fetch: F,
+    parse: ParseFn<T>,
+    fetch_concurrency: usize,
+    parse_workers: usize,
+    shutdown: Shutdown,
+    mut on_result: impl FnMut(&str, &mut anyhow::Result<T>),
+) -> Vec<anyhow::Result<T>>
+where
+    T: Send + 'static,
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = anyhow::Result<String>>,
+{
+    let parse_workers = parse_workers.max(1);
+    let (tx, mut rx) = mpsc::channel(parse_workers * QUEUE_PER_WORKER);
+    let fetch = &fetch;
+    let stopping = &Cell::new(false);
+    let producer = async move {
+        stream::iter(urls)
+            .take_while(|_| future::ready(!stopping.get()))
+            .map(|url| async move {
+                let body = fetch(url.clone()).await;
+                (url, body)
+            })
+            .buffer_unordered(fetch_concurrency.max(1))
+            .for_each(|fetched| {
+                let tx = tx.clone();
+                async move {
+                    // Получатель живёт до конца `run`, ошибка отправки невозможна.
+                    let _ = tx.send(fetched).await;
+                }
+            })
+            .await;
+    };
+
+    let results = &RefCell::new(vec![]);
+    let consumer = async {
+        let mut finish = |url: &str, mut result: anyhow::Result<T>| {
+            on_result(url, &mut result);
+            results.borrow_mut().push(result);
+        };
+        let mut parsing: JoinSet<anyhow::Result<T>> = JoinSet::new();
+        let mut parsed_urls: HashMap<tokio::task::Id, String> = HashMap::new();
+        loop {
+            tokio::select! {
+                Some(joined) = parsing.join_next_with_id(), if !parsing.is_empty() => {
+                    let (url, result) = joined_parse(&mut parsed_urls, joined);
+                    finish(&url, result);
+                }
+                // Новая страница берётся из канала, только когда есть свободный поток разбора.
+                fetched = rx.recv(), if parsing.len() < parse_workers => match fetched {
+                    Some((url, Ok(body))) => {
+                        let parse = Arc::clone(&parse);
+                        let handle = parsing.spawn_blocking({
+                            let url = url.clone();
+                            move || parse(url, body)
+                        });
+                        parsed_urls.insert(handle.id(), url);
+                    }
+                    Some((url, Err(e))) => finish(&url, Err(e)),
+                    None => break,
+                },
+            }
+        }
+        while let Some(joined) = parsing.join_next_with_id().await {
+            let (url, result) = joined_parse(&mut parsed_urls, joined);
+            finish(&url, result);
+        }
+    };
+
+    let mut work = Box::pin(async {
+        tokio::join!(producer, consumer);
+    });
+    let Shutdown { signal, grace } = shutdown;
+    tokio::select! {
+        () = &mut work => {}
+        () = signal => {
+            stopping.set(true);
+            warn!(
+                grace_secs = grace.as_secs_f64(),
+                "shutdown requested, waiting for in-flight pages"
+            );
+            if tokio::time::timeout(grace, &mut work).await.is_err() {
+                warn!("shutdown grace period is over, aborting in-flight pages");
+            }
+        }
+    }
+    // Незавершённые загрузки отменяются вместе с `work`, задачи разбора — вместе с `JoinSet`.
+    drop(work);
+    results.take()
+}
+
+/// URL и результат завершённого разбора `run`. Паника разбора становится ошибкой своего
+/// URL, как в `run_join_set`, а не роняет весь прогон.
+fn joined_parse<T>(
+    parsed_urls: &mut HashMap<tokio::task::Id, String>,
+    joined: Result<(tokio::task::Id, anyhow::Result<T>), tokio::task::JoinError>,
+) -> (String, anyhow::Result<T>) {
+    match joined {
+        Ok((id, result)) => (parsed_urls.remove(&id).unwrap_or_default(), result),
+        Err(e) => {
+            let url = parsed_urls.remove(&e.id()).unwrap_or_default();
+            let result = Err(anyhow!("parse of {url} failed: {e}"));
+            (url, result)
+        }
+    }
+}
+
+/// Драйвер на `JoinSet`: каждый URL — отдельная задача, которая берёт разрешение
+/// `Semaphore` на загрузку (не больше `fetch_concurrency` одновременно), затем на разбор
+/// (не больше `parse_workers`, разбор в `spawn_blocking`). Задачи создаются по мере
+/// завершения прежних, не больше `fetch_concurrency + parse_workers` сразу, так что
+/// длинный список URL не превращается в столько же ждущих задач.
+///
+/// В отличие от `run` отдельные задачи можно прервать: после каждого неудачного URL
+/// незавершённые задачи, для URL которых `should_abort` вернул `true` (например, сайт,
+/// на котором сработал предохранитель), отменяются, и для них приходит ошибка
+/// `aborted`; такие URL из ещё не начатых получают её без задачи. Паника в задаче, как
+/// и в `run`, становится ошибкой своего URL, а не всего прогона.
+/// `fetch` и его future не обязаны быть `Send`: задачи крутятся в `LocalSet`.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_join_set<T, F, Fut>(
+    urls: Vec<String>,
+    fetch: F,
+    parse: ParseFn<T>,
+    fetch_concurrency: usize,
+    parse_workers: usize,
+    shutdown: Shutdown,
+    should_abort: impl Fn(&str) -> bool,
+    mut on_result: impl FnMut(&str, &mut anyhow::Result<T>),
+) -> Vec<anyhow::Result<T>>
+where
+    T: Send + 'static,
+    F: Fn(String) -> Fut + 'static,
+    Fut: Future<Output = anyhow::Result<String>> + 'static,
+{
+    let local = LocalSet::new();
+    local
+        .run_until(async move {
+            let fetch = Rc::new(fetch);
+            let fetch_permits = Arc::new(Semaphore::new(fetch_concurrency.max(1)));
+            let parse_permits = Arc::new(Semaphore::new(parse_workers.max(1)));
+            let stopping = Rc::new(Cell::new(false));
+            let max_tasks = fetch_concurrency.max(1) + parse_workers.max(1);
+            // `None` от задачи — URL не начат до остановки и в результаты не попадает.
+            let mut tasks: JoinSet<Option<anyhow::Result<T>>> = JoinSet::new();
+            let mut pending: HashMap<tokio::task::Id, (String, AbortHandle)> = HashMap::new();
+            let task = |url: String| {
+                let fetch = Rc::clone(&fetch);
+                let fetch_permits = Arc::clone(&fetch_permits);
+                let parse_permits = Arc::clone(&parse_permits);
+                let stopping = Rc::clone(&stopping);
+                let parse = Arc::clone(&parse);
+                async move {
+                    let body = {
+                        let _permit = fetch_permits.acquire_owned().await.ok()?;
+                        if stopping.get() {
+                            return None;
+                        }
+                        fetch(url.clone()).await
+                    };
+                    Some(match body {
+                        Ok(body) => {
+                            let _permit = parse_permits.acquire_owned().await.ok()?;
+                            let parsed = tokio::task::spawn_blocking({
+                                let url = url.clone();
+                                move || parse(url, body)
+                            })
+                            .await;
+                            parsed.unwrap_or_else(|e| Err(anyhow!("parse of {url} failed: {e}")))
+                        }
+                        Err(e) => Err(e),
+                    })
+                }
+            };
+
+            let mut urls = urls.into_iter();
+            let Shutdown { mut signal, grace } = shutdown;
+            let mut deadline: Option<std::pin::Pin<Box<tokio::time::Sleep>>> = None;
+            let mut results = vec![];
+            loop {
+                while !stopping.get() && tasks.len() < max_tasks {
+                    let Some(url) = urls.next() else { break };
+                    if should_abort(&url) {
+                        let mut result = Err(anyhow!("aborted"));
+                        on_result(&url, &mut result);
+                        results.push(result);
+                        continue;
+                    }
+                    let handle = tasks.spawn_local(task(url.clone()));
+                    pending.insert(handle.id(), (url, handle));
+                }
+                tokio::select! {
+                    joined = tasks.join_next_with_id() => {
+                        let Some(joined) = joined else { break };
+                        let (id, mut result) = match joined {
+                            Ok((id, Some(result))) => (id, result),
+                            Ok((id, None)) => {
+                                pending.remove(&id);
+                                continue;
+                            }
+                            Err(e) if e.is_cancelled() => (e.id(), Err(anyhow!("aborted"))),
+                            Err(e) => (e.id(), Err(anyhow!("task panicked: {e}"))),
+                        };
+                        let url = pending.remove(&id).map(|(url, _)| url).unwrap_or_default();
+                        on_result(&url, &mut result);
+                        if result.is_err() {
+                            for (url, handle) in pending.values() {
+                                if should_abort(url) {
+                                    handle.abort();
+                                }
+                            }
+                        }
+                        results.push(result);
+                    }
+                    () = &mut signal, if deadline.is_none() => {
+                        stopping.set(true);
+                        warn!(
+                            grace_secs = grace.as_secs_f64(),
+                            "shutdown requested, waiting for in-flight pages"
+                        );
+                        deadline = Some(Box::pin(tokio::time::sleep(grace)));
+                    }
+                    () = async { deadline.as_mut().expect("guarded").await }, if deadline.is_some() => {
+                        warn!("shutdown grace period is over, aborting in-flight pages");
+                        break;
+                    }
+                }
+            }
+            // Незавершённые задачи отменяются вместе с `JoinSet`.
+            tasks.abort_all();
+            results
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+    use crate::sink::{BookSink, CsvSink};
+    use anyhow::anyhow;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn batch_is_parsed_off_async_thread() {
+        let async_thread = std::thread::current().id();
+        let parse_threads = Arc::new(Mutex::new(vec![]));
+        let threads = Arc::clone(&parse_threads);
+        let parse: ParseFn<usize> = Arc::new(move |url, body| {
+            threads
+                .lock()
+                .expect("threads lock")
+                .push(std::thread::current().id());
+            let html = scraper::Html::parse_document(&body);
+            let h1 = scraper::Selector::parse("h1").expect("selector");
+            let title = html
+                .select(&h1)
+                .next()
+                .ok_or_else(|| anyhow!("no title on {url}"))?;
+            Ok(title.text().collect::<String>().len())
+        });
+        let urls: Vec<String> = (0..20)
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let mut seen = vec![];
+        let results = run(
+            urls,
+            |url: String| async move {
+                match url.rsplit('/').next() {
+                    Some("13") => Err(anyhow!("connection refused")),
+                    Some("7") => Ok("<html><body></body></html>".to_string()),
+                    Some(n) => Ok(format!(
+                        "<html><body><h1>{}</h1></body></html>",
+                        "x".repeat(n.parse().unwrap())
+                    )),
+                    None => unreachable!(),
+                }
+            },
+            parse,
+            4,
+            2,
+            Shutdown::never(),
+            |url, result| seen.push((url.to_string(), result.is_ok())),
+        )
+        .await;
+
+        assert_eq!(results.len(), 20);
+        assert_eq!(results.iter().filter(|r| r.is_err()).count(), 2);
+        let mut lens: Vec<usize> = results.into_iter().flatten().collect();
+        lens.sort_unstable();
+        let expected: Vec<usize> = (0..20).filter(|n| *n != 7 && *n != 13).collect();
+        assert_eq!(lens, expected);
+        assert_eq!(seen.len(), 20);
+        assert!(seen.contains(&("https://example.com/13".to_string(), false)));
+
+        let parse_threads = parse_threads.lock().expect("threads lock");
+        // Неудачная загрузка до разбора не доходит.
+        assert_eq!(parse_threads.len(), 19);
+        assert!(parse_threads.iter().all(|id| *id != async_thread));
+    }
+
+    fn parse_book_number() -> ParseFn<crate::parse_traits::Book<String>> {
+        Arc::new(|_url, body| Ok(sample_book(body.parse()?)))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_aborts_pages_beyond_grace_and_sink_finishes() {
+        let started = tokio::time::Instant::now();
+        let urls: Vec<String> = ["1", "2", "hang", "3"]
+            .iter()
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let results = run(
+            urls,
+            |url: String| async move {
+                let n = url.rsplit('/').next().expect("segment").to_string();
+                if n == "hang" {
+                    // Зависшая загрузка, дольше таймаута клиента.
+                    tokio::time::sleep(Duration::from_secs(600)).await;
+                }
+                Ok(n)
+            },
+            parse_book_number(),
+            4,
+            2,
+            Shutdown::new(
+                tokio::time::sleep(Duration::from_secs(1)),
+                Duration::from_secs(2),
+            ),
+            |_, _| {},
+        )
+        .await;
+        assert!(started.elapsed() < Duration::from_secs(4));
+        let mut books: Vec<_> = results.into_iter().flatten().collect();
+        assert_eq!(books.len(), 3);
+        books.sort_by(|a, b| a.source.cmp(&b.source));
+
+        let mut buf = vec![];
+        let mut sink = CsvSink::new(&mut buf, false).expect("sink");
+        for book in &books {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+        drop(sink);
+        assert_eq!(String::from_utf8(buf).expect("utf8").lines().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn flushed_rows_reach_disk_while_crawl_is_running() {
+        use crate::sink::{FlushEvery, pending_path};
+
+        let dir = std::env::temp_dir().join(format!("bd_parser_flush_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let csv = dir.join("books.csv");
+        let mut sink =
+            FlushEvery::new(CsvSink::create_atomic(&csv, false, false).expect("sink"), 2);
+        let lines_on_disk = || {
+            std::fs::read_to_string(pending_path(&csv))
+                .expect("pending csv")
+                .lines()
+                .count()
+        };
+        let mut on_disk = vec![];
+        let urls: Vec<String> = (1..=6)
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        run(
+            urls,
+            |url: String| async move { Ok(url.rsplit('/').next().expect("segment").to_string()) },
+            parse_book_number(),
+            1,
+            1,
+            Shutdown::never(),
+            |_, result| {
+                sink.write_book(result.as_ref().expect("book"))
+                    .expect("write");
+                on_disk.push(lines_on_disk());
+            },
+        )
+        .await;
+        // Заголовок и каждые две книги видны в файле до `finish`.
+        assert_eq!([on_disk[1], on_disk[3], on_disk[5]], [3, 5, 7]);
+        sink.finish().expect("finish");
+        assert_eq!(
+            std::fs::read_to_string(&csv).expect("csv").lines().count(),
+            7
+        );
+        std::fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn shutdown_stops_new_urls_and_waits_for_in_flight() {
+        let fetched = Cell::new(0);
+        let urls: Vec<String> = (1..=10)
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let results = run(
+            urls,
+            |url: String| {
+                fetched.set(fetched.get() + 1);
+                async move {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(url.rsplit('/').next().expect("segment").to_string())
+                }
+            },
+            parse_book_number(),
+            1,
+            1,
+            // Третья загрузка идёт в момент сигнала и укладывается в окно.
+            Shutdown::new(
+                tokio::time::sleep(Duration::from_millis(2500)),
+                Duration::from_secs(10),
+            ),
+            |_, _| {},
+        )
+        .await;
+        assert_eq!(results.iter().flatten().count(), 3);
+        assert_eq!(fetched.get(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn join_set_respects_concurrency_and_isolates_failures() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (fetch_in_flight, fetch_peak) = (Arc::clone(&in_flight), Arc::clone(&peak));
+        let urls: Vec<String> = (1..=12)
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let parse: ParseFn<crate::parse_traits::Book<String>> = Arc::new(|_url, body| {
+            assert_ne!(body, "5", "parser bug on one page");
+            Ok(sample_book(body.parse()?))
+        });
+        let mut seen = vec![];
+        let results = run_join_set(
+            urls,
+            move |url: String| {
+                let in_flight = Arc::clone(&fetch_in_flight);
+                let peak = Arc::clone(&fetch_peak);
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    let n = url.rsplit('/').next().expect("segment").to_string();
+                    if n == "9" {
+                        return Err(anyhow!("connection refused"));
+                    }
+                    Ok(n)
+                }
+            },
+            parse,
+            3,
+            2,
+            Shutdown::never(),
+            |_| false,
+            |url, result| seen.push((url.to_string(), result.is_ok())),
+        )
+        .await;
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(results.len(), 12);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 10);
+        assert!(seen.contains(&("https://example.com/5".to_string(), false)));
+        assert!(seen.contains(&("https://example.com/9".to_string(), false)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn join_set_aborts_tasks_of_failed_site() {
+        let urls: Vec<String> = (1..=4)
+            .flat_map(|n| {
+                [
+                    format!("https://a.example/{n}"),
+                    format!("https://b.example/{n}"),
+                ]
+            })
+            .collect();
+        let open_circuits: RefCell<Vec<String>> = RefCell::new(vec![]);
+        let host = |url: &str| crate::parse_traits::host_of(url).expect("host");
+        let results = run_join_set(
+            urls,
+            |url: String| async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if url == "https://a.example/1" {
+                    return Err(anyhow!("503 from a.example"));
+                }
+                Ok(url.rsplit('/').next().expect("segment").to_string())
+            },
+            parse_book_number(),
+            1,
+            1,
+            Shutdown::never(),
+            |url| open_circuits.borrow().contains(&host(url)),
+            |url, result| {
+                if result.is_err() && !open_circuits.borrow().contains(&host(url)) {
+                    open_circuits.borrow_mut().push(host(url));
+                }
+            },
+        )
+        .await;
+        assert_eq!(results.len(), 8);
+        let errors: Vec<String> = results
+            .iter()
+            .filter_map(|r| r.as_ref().err().map(|e| e.to_string()))
+            .collect();
+        assert_eq!(errors.iter().filter(|e| *e == "aborted").count(), 3);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn join_set_shutdown_drops_unstarted_urls() {
+        let started = tokio::time::Instant::now();
+        let urls: Vec<String> = ["1", "hang", "2", "3", "4"]
+            .iter()
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let results = run_join_set(
+            urls,
+            |url: String| async move {
+                let n = url.rsplit('/').next().expect("segment").to_string();
+                let wait = if n == "hang" { 600 } else { 1 };
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+                Ok(n)
+            },
+            parse_book_number(),
+            2,
+            1,
+            Shutdown::new(
+                tokio::time::sleep(Duration::from_millis(1500)),
+                Duration::from_secs(2),
+            ),
+            |_| false,
+            |_, _| {},
+        )
+        .await;
+        assert!(started.elapsed() < Duration::from_secs(4));
+        // «1» и «2» успели; «hang» прерван по окну, «3» и «4» не начинались.
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn both_drivers_isolate_a_panicking_parse() {
+        let urls: Vec<String> = (1..=4)
+            .map(|n| format!("https://example.com/{n}"))
+            .collect();
+        let parse: ParseFn<crate::parse_traits::Book<String>> = Arc::new(|_url, body| {
+            assert_ne!(body, "3", "parser bug on one page");
+            Ok(sample_book(body.parse()?))
+        });
+        let fetch =
+            |url: String| async move { Ok(url.rsplit('/').next().expect("segment").to_string()) };
+        let mut failed = vec![];
+        let streamed = run(
+            urls.clone(),
+            fetch,
+            Arc::clone(&parse),
+            2,
+            2,
+            Shutdown::never(),
+            |url, result| {
+                if result.is_err() {
+                    failed.push(url.to_string());
+                }
+            },
+        )
+        .await;
+        let joined = run_join_set(
+            urls,
+            fetch,
+            parse,
+            2,
+            2,
+            Shutdown::never(),
+            |_| false,
+            |url, result| {
+                if result.is_err() {
+                    failed.push(url.to_string());
+                }
+            },
+        )
+        .await;
+        for results in [&streamed, &joined] {
+            assert_eq!(results.len(), 4);
+            assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+            let error = results
+                .iter()
+                .find_map(|r| r.as_ref().err())
+                .expect("error");
+            assert!(
+                error
+                    .to_string()
+                    .starts_with("parse of https://example.com/3 failed:"),
+                "{error}"
+            );
+        }
+        assert_eq!(failed, ["https://example.com/3", "https://example.com/3"]);
+    }
+
+    #[test]
+    fn driver_from_str() {
+        assert_eq!(
+            "joinset".parse::<Driver>().expect("driver"),
+            Driver::JoinSet
+        );
+        assert_eq!("stream".parse::<Driver>().expect("driver"), Driver::Stream);
+        assert!("threads".parse::<Driver>().is_err());
+    }
+}
@@ -0,0 +1,156 @@
+use futures::future::LocalBoxFuture;
+use tracing::warn;
+
+use crate::parse_traits::Book;
+
+/// Обработка готовой книги после разбора и до записи: пометить, перевести, дополнить
+/// из внешнего источника. Хуки выполняются по порядку (`PostParseHooks`), каждый видит
+/// изменения предыдущих. Ошибка хука не роняет книгу: она пишется как есть, с предупреждением.
+#[allow(async_fn_in_trait)]
+pub trait PostParse {
+    /// Имя для логов.
+    fn name(&self) -> &'static str;
+    async fn process(&self, book: &mut Book<String>) -> anyhow::Result<()>;
+}
+
+/// Хук по умолчанию: ничего не делает.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoOp;
+
+impl PostParse for NoOp {
+    fn name(&self) -> &'static str {
+        "no-op"
+    }
+
+    async fn process(&self, _book: &mut Book<String>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// `PostParse` для списка хуков: `async fn` в трейте не позволяет `dyn PostParse`.
+pub trait DynPostParse {
+    fn name(&self) -> &'static str;
+    fn process_dyn<'a>(
+        &'a self,
+        book: &'a mut Book<String>,
+    ) -> LocalBoxFuture<'a, anyhow::Result<()>>;
+}
+
+impl<T: PostParse> DynPostParse for T {
+    fn name(&self) -> &'static str {
+        PostParse::name(self)
+    }
+
+    fn process_dyn<'a>(
+        &'a self,
+        book: &'a mut Book<String>,
+    ) -> LocalBoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(self.process(book))
+    }
+}
+
+/// Хуки по порядку; пустой список книги не трогает.
+#[derive(Default)]
+pub struct PostParseHooks {
+    hooks: Vec<Box<dyn DynPostParse>>,
+}
+
+impl PostParseHooks {
+    pub fn with(mut self, hook: impl PostParse + 'static) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.hooks.iter().map(|hook| hook.name()).collect()
+    }
+
+    /// Прогоняет каждую книгу через все хуки; возвращает число неудачных вызовов.
+    pub async fn run<'a>(&self, books: impl IntoIterator<Item = &'a mut Book<String>>) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        let mut failed = 0;
+        for book in books {
+            for hook in &self.hooks {
+                if let Err(e) = hook.process_dyn(book).await {
+                    warn!(
+                        hook = hook.name(),
+                        url = %book.source,
+                        "post-parse hook failed: {e:#}"
+                    );
+                    failed += 1;
+                }
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::Title;
+    use crate::sink::tests::sample_book;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// Ставит метку в `about_author` и считает вызовы.
+    #[derive(Clone, Default)]
+    struct Tag {
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl PostParse for Tag {
+        fn name(&self) -> &'static str {
+            "tag"
+        }
+
+        async fn process(&self, book: &mut Book<String>) -> anyhow::Result<()> {
+            self.calls.set(self.calls.get() + 1);
+            book.about_author = Some(format!("tagged {}", book.title.as_str()));
+            Ok(())
+        }
+    }
+
+    struct Uppercase;
+
+    impl PostParse for Uppercase {
+        fn name(&self) -> &'static str {
+            "uppercase"
+        }
+
+        async fn process(&self, book: &mut Book<String>) -> anyhow::Result<()> {
+            if book.source.ends_with("/2/") {
+                return Err(anyhow::anyhow!("translation service is down"));
+            }
+            book.title = Title(book.title.as_str().to_uppercase());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn hooks_run_in_order_on_each_book() {
+        let tag = Tag::default();
+        let hooks = PostParseHooks::default()
+            .with(NoOp)
+            .with(Uppercase)
+            .with(tag.clone());
+        assert_eq!(hooks.names(), ["no-op", "uppercase", "tag"]);
+        let mut books: Vec<_> = (1..=3).map(sample_book).collect();
+        assert_eq!(hooks.run(books.iter_mut()).await, 1);
+        assert_eq!(tag.calls.get(), 3);
+        // Хук видит результат предыдущего, а ошибка одного хука не останавливает следующие.
+        assert_eq!(books[0].about_author.as_deref(), Some("tagged ДЖЕЙН ЭЙР"));
+        assert_eq!(books[1].about_author.as_deref(), Some("tagged Джейн Эйр"));
+        assert_eq!(books[2].title.as_str(), "ДЖЕЙН ЭЙР");
+
+        let mut book = sample_book(4);
+        assert_eq!(PostParseHooks::default().run([&mut book]).await, 0);
+        assert_eq!(book.about_author, None);
+    }
+}
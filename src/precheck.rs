@@ -0,0 +1,101 @@
+use futures::{StreamExt, stream};
+use reqwest::StatusCode;
+use tracing::{debug, info, warn};
+
+/// Итог `--precheck`: живые URL в исходном порядке и отброшенные со статусом.
+#[derive(Debug, Default)]
+pub struct Precheck {
+    pub alive: Vec<String>,
+    pub dead: Vec<(String, StatusCode)>,
+}
+
+/// Статус страницы по `HEAD`; если сервер не поддерживает `HEAD`, повторяет `GET`.
+async fn status_of(client: &reqwest::Client, url: &str) -> reqwest::Result<StatusCode> {
+    let status = client.head(url).send().await?.status();
+    if status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_IMPLEMENTED {
+        debug!(url, %status, "HEAD rejected, falling back to GET");
+        return Ok(client.get(url).send().await?.status());
+    }
+    Ok(status)
+}
+
+/// Дешёвая проверка URL до полного `GET`+парсинга: отбрасываются только ответы 4xx.
+/// Сетевые ошибки и 5xx не считаются приговором — такие URL разберёт основной проход.
+pub async fn precheck_urls(
+    client: &reqwest::Client,
+    urls: Vec<String>,
+    concurrency: usize,
+) -> Precheck {
+    let statuses: Vec<_> = stream::iter(urls)
+        .map(|url| async move {
+            let status = status_of(client, &url).await;
+            (url, status)
+        })
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut result = Precheck::default();
+    for (url, status) in statuses {
+        match status {
+            Ok(status) if status.is_client_error() => result.dead.push((url, status)),
+            Ok(_) => result.alive.push(url),
+            Err(e) => {
+                warn!(url, "precheck failed, keeping url: {e}");
+                result.alive.push(url);
+            }
+        }
+    }
+    info!(
+        alive = result.alive.len(),
+        dead = result.dead.len(),
+        "precheck finished"
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mount(server: &MockServer, verb: &str, route: &str, status: u16) {
+        Mock::given(method(verb))
+            .and(path(route))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn dead_urls_are_skipped() {
+        let server = MockServer::start().await;
+        mount(&server, "HEAD", "/alive", 200).await;
+        mount(&server, "HEAD", "/dead", 404).await;
+        mount(&server, "HEAD", "/no-head", 405).await;
+        mount(&server, "GET", "/no-head", 200).await;
+        mount(&server, "HEAD", "/no-head-gone", 405).await;
+        mount(&server, "GET", "/no-head-gone", 410).await;
+        mount(&server, "HEAD", "/broken", 503).await;
+
+        let urls: Vec<String> = ["/alive", "/dead", "/no-head", "/no-head-gone", "/broken"]
+            .iter()
+            .map(|p| format!("{}{p}", server.uri()))
+            .collect();
+        let client = reqwest::Client::new();
+        let result = precheck_urls(&client, urls.clone(), 4).await;
+
+        assert_eq!(
+            result.alive,
+            [urls[0].clone(), urls[2].clone(), urls[4].clone()]
+        );
+        assert_eq!(
+            result.dead,
+            [
+                (urls[1].clone(), StatusCode::NOT_FOUND),
+                (urls[3].clone(), StatusCode::GONE)
+            ]
+        );
+    }
+}
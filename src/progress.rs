@@ -0,0 +1,157 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::info;
+
+/// Как часто писать сводку прогресса в лог `time`.
+pub const PROGRESS_EVERY: u64 = 25;
+
+/// Скорость и оставшееся время.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Книг в секунду.
+    pub rate: f64,
+    /// `None`, пока скорость неизвестна (ничего не обработано или прошло 0 секунд).
+    pub eta: Option<Duration>,
+}
+
+/// ETA по средней скорости с начала прогона.
+pub fn estimate(processed: u64, total: u64, elapsed: Duration) -> Estimate {
+    let secs = elapsed.as_secs_f64();
+    if processed == 0 || secs == 0.0 {
+        return Estimate {
+            rate: 0.0,
+            eta: None,
+        };
+    }
+    let rate = processed as f64 / secs;
+    let left = total.saturating_sub(processed) as f64;
+    Estimate {
+        rate,
+        eta: Some(Duration::from_secs_f64(left / rate)),
+    }
+}
+
+/// Итог прогона для `manifest.json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct CrawlStats {
+    pub total: u64,
+    pub processed: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+/// Счётчики прогона без блокировок: задачи парсинга обновляют их конкурентно.
+#[derive(Debug)]
+pub struct Progress {
+    total: u64,
+    every: u64,
+    started: Instant,
+    processed: AtomicU64,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Progress {
+    pub fn new(total: u64, every: u64) -> Self {
+        Self {
+            total,
+            every: every.max(1),
+            started: Instant::now(),
+            processed: AtomicU64::new(0),
+            succeeded: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Учитывает одну обработанную книгу и возвращает номер её по порядку.
+    /// Каждые `every` книг и на последней пишет сводку со скоростью и ETA.
+    pub fn record(&self, success: bool) -> u64 {
+        let counter = if success {
+            &self.succeeded
+        } else {
+            &self.failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        let processed = self.processed.fetch_add(1, Ordering::Relaxed) + 1;
+        if processed.is_multiple_of(self.every) || processed == self.total {
+            let estimate = estimate(processed, self.total, self.started.elapsed());
+            info!(
+                target: "time",
+                processed,
+                total = self.total,
+                succeeded = self.succeeded.load(Ordering::Relaxed),
+                failed = self.failed.load(Ordering::Relaxed),
+                rate = format!("{:.2}/s", estimate.rate),
+                eta_secs = estimate.eta.map(|eta| eta.as_secs()),
+                "progress"
+            );
+        }
+        processed
+    }
+
+    pub fn processed(&self) -> u64 {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    pub fn succeeded(&self) -> u64 {
+        self.succeeded.load(Ordering::Relaxed)
+    }
+
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    pub fn stats(&self) -> CrawlStats {
+        CrawlStats {
+            total: self.total,
+            processed: self.processed(),
+            succeeded: self.succeeded(),
+            failed: self.failed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eta_from_rate() {
+        let e = estimate(50, 200, Duration::from_secs(25));
+        assert_eq!(e.rate, 2.0);
+        assert_eq!(e.eta, Some(Duration::from_secs(75)));
+
+        let done = estimate(200, 200, Duration::from_secs(100));
+        assert_eq!(done.eta, Some(Duration::ZERO));
+
+        assert_eq!(estimate(0, 200, Duration::from_secs(10)).eta, None);
+        assert_eq!(estimate(10, 200, Duration::ZERO).eta, None);
+        // Лишние книги (total занижен) не дают отрицательного ETA.
+        assert_eq!(
+            estimate(300, 200, Duration::from_secs(10)).eta,
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn counters_split_by_outcome() {
+        let progress = Progress::new(3, 2);
+        assert_eq!(progress.record(true), 1);
+        assert_eq!(progress.record(false), 2);
+        assert_eq!(progress.record(true), 3);
+        assert_eq!(progress.processed(), 3);
+        assert_eq!(progress.succeeded(), 2);
+        assert_eq!(progress.failed(), 1);
+        assert_eq!(
+            progress.stats(),
+            CrawlStats {
+                total: 3,
+                processed: 3,
+                succeeded: 2,
+                failed: 1,
+            }
+        );
+    }
+}
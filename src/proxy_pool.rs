@@ -0,0 +1,251 @@
+use std::path::Path;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, anyhow};
+use serde::Serialize;
+use tracing::warn;
+
+use crate::http::{FetchError, HttpConfig, get_with_timeout};
+
+/// Столько сетевых ошибок подряд, и прокси выбывает из ротации до конца прогона.
+pub const DEAD_AFTER_FAILURES: u32 = 3;
+
+static GLOBAL: OnceLock<ProxyPool> = OnceLock::new();
+
+/// Прокси пула: свой клиент (а значит, свои соединения и cookie) и счётчики здоровья.
+#[derive(Debug)]
+struct ProxyEntry {
+    url: String,
+    client: reqwest::Client,
+    requests: AtomicU64,
+    failures: AtomicU64,
+    failures_in_row: AtomicU32,
+    alive: AtomicBool,
+}
+
+/// Здоровье прокси за прогон: для лога в конце и тестов.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProxyHealth {
+    pub url: String,
+    pub requests: u64,
+    pub failures: u64,
+    pub alive: bool,
+}
+
+/// `--proxy-list`: страницы товаров грузятся по кругу через прокси пула. Прокси,
+/// на котором `dead_after` загрузок подряд закончились сетевой ошибкой, выбывает;
+/// ответы сайта с кодом ошибки (404 и т.п.) и слишком длинные тела прокси не в вину.
+#[derive(Debug)]
+pub struct ProxyPool {
+    entries: Vec<ProxyEntry>,
+    next: AtomicUsize,
+    dead_after: u32,
+}
+
+impl ProxyPool {
+    pub fn new(proxies: Vec<String>, config: &HttpConfig, dead_after: u32) -> anyhow::Result<Self> {
+        if proxies.is_empty() {
+            return Err(anyhow!("proxy pool is empty"));
+        }
+        let entries = proxies
+            .into_iter()
+            .map(|url| {
+                let proxy =
+                    reqwest::Proxy::all(&url).with_context(|| format!("bad proxy url {url}"))?;
+                Ok(ProxyEntry {
+                    client: config.client_builder()?.proxy(proxy).build()?,
+                    url,
+                    requests: AtomicU64::new(0),
+                    failures: AtomicU64::new(0),
+                    failures_in_row: AtomicU32::new(0),
+                    alive: AtomicBool::new(true),
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            entries,
+            next: AtomicUsize::new(0),
+            dead_after: dead_after.max(1),
+        })
+    }
+
+    /// Следующий живой прокси по кругу; `None`, если выбыли все.
+    fn next_alive(&self) -> Option<&ProxyEntry> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        (0..self.entries.len())
+            .map(|offset| &self.entries[(start + offset) % self.entries.len()])
+            .find(|entry| entry.alive.load(Ordering::Relaxed))
+    }
+
+    fn record(&self, entry: &ProxyEntry, result: &anyhow::Result<String>) {
+        entry.requests.fetch_add(1, Ordering::Relaxed);
+        let network_error = result
+            .as_ref()
+            .err()
+            .and_then(|e| e.downcast_ref::<FetchError>())
+            .is_some_and(|e| !matches!(e, FetchError::Status(_) | FetchError::TooLarge { .. }));
+        if !network_error {
+            entry.failures_in_row.store(0, Ordering::Relaxed);
+            return;
+        }
+        entry.failures.fetch_add(1, Ordering::Relaxed);
+        let in_row = entry.failures_in_row.fetch_add(1, Ordering::Relaxed) + 1;
+        if in_row >= self.dead_after && entry.alive.swap(false, Ordering::Relaxed) {
+            warn!(
+                proxy = entry.url,
+                failures_in_row = in_row,
+                "proxy removed from rotation"
+            );
+        }
+    }
+
+    /// `GET` страницы с повторами через следующий живой прокси; `timeout` — как в `get_with_timeout`.
+    pub async fn fetch(
+        &self,
+        url: &str,
+        max_retries: u8,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<String> {
+        let entry = self
+            .next_alive()
+            .ok_or_else(|| anyhow!("all {} proxies are dead", self.entries.len()))?;
+        let result = get_with_timeout(&entry.client, url, max_retries, timeout).await;
+        self.record(entry, &result);
+        result.with_context(|| format!("via proxy {}", entry.url))
+    }
+
+    pub fn health(&self) -> Vec<ProxyHealth> {
+        self.entries
+            .iter()
+            .map(|entry| ProxyHealth {
+                url: entry.url.clone(),
+                requests: entry.requests.load(Ordering::Relaxed),
+                failures: entry.failures.load(Ordering::Relaxed),
+                alive: entry.alive.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// Читает `--proxy-list`: по URL прокси на строку (`http://` или `https://`),
+/// пустые строки и строки с `#` пропускаются. Файл без единого прокси — ошибка.
+pub fn load_proxies(path: &Path) -> anyhow::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("can't read proxy list {}", path.display()))?;
+    let proxies: Vec<String> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if proxies.is_empty() {
+        return Err(anyhow!("proxy list {} is empty", path.display()));
+    }
+    Ok(proxies)
+}
+
+/// Включает ротацию прокси для всех загрузок страниц; вызывается один раз до парсинга.
+pub fn init_global(pool: ProxyPool) -> anyhow::Result<()> {
+    GLOBAL
+        .set(pool)
+        .map_err(|_| anyhow!("proxy pool is already initialized"))
+}
+
+pub fn global() -> Option<&'static ProxyPool> {
+    GLOBAL.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// HTTP-прокси, который на любой запрос отвечает своим именем.
+    async fn named_proxy(name: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(name))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Адрес, на котором никто не слушает.
+    fn dead_proxy() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        drop(listener);
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn requests_round_robin_across_proxies() {
+        let (a, b) = (named_proxy("a").await, named_proxy("b").await);
+        let pool = ProxyPool::new(vec![a.uri(), b.uri()], &HttpConfig::default(), 3).expect("pool");
+        let mut bodies = vec![];
+        for n in 0..4 {
+            let url = format!("http://books.example/product/{n}/");
+            bodies.push(pool.fetch(&url, 0, None).await.expect("page"));
+        }
+        assert_eq!(bodies, ["a", "b", "a", "b"]);
+        assert!(
+            pool.health()
+                .iter()
+                .all(|h| h.requests == 2 && h.failures == 0 && h.alive)
+        );
+    }
+
+    #[tokio::test]
+    async fn dead_proxy_is_dropped() {
+        let alive = named_proxy("alive").await;
+        let dead = dead_proxy();
+        let pool = ProxyPool::new(vec![alive.uri(), dead.clone()], &HttpConfig::default(), 2)
+            .expect("pool");
+        let mut failed = 0;
+        for n in 0..8 {
+            let url = format!("http://books.example/product/{n}/");
+            match pool.fetch(&url, 0, None).await {
+                Ok(body) => assert_eq!(body, "alive"),
+                Err(e) => {
+                    assert!(format!("{e:#}").contains(&dead));
+                    failed += 1;
+                }
+            }
+        }
+        assert_eq!(failed, 2);
+        let health = pool.health();
+        assert!(health[0].alive);
+        assert_eq!(health[0].requests, 6);
+        assert_eq!(
+            health[1],
+            ProxyHealth {
+                url: dead,
+                requests: 2,
+                failures: 2,
+                alive: false,
+            }
+        );
+    }
+
+    #[test]
+    fn proxy_list_file() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_proxies_{}.txt", std::process::id()));
+        std::fs::write(
+            &path,
+            "# pool\nhttp://10.0.0.1:3128\n\n  https://10.0.0.2:8443  \n",
+        )
+        .expect("write");
+        assert_eq!(
+            load_proxies(&path).expect("proxies"),
+            ["http://10.0.0.1:3128", "https://10.0.0.2:8443"]
+        );
+        std::fs::write(&path, "# nothing\n").expect("write");
+        assert!(load_proxies(&path).is_err());
+        std::fs::remove_file(&path).expect("cleanup");
+        assert!(ProxyPool::new(vec![], &HttpConfig::default(), 3).is_err());
+    }
+}
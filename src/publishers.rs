@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::parse_traits::Book;
+
+/// Отчёт `--count-by-publisher`.
+pub static PUBLISHERS_FILE: &str = "publishers.csv";
+
+/// Число книг по издательствам, от самых крупных; при равенстве — по имени, чтобы
+/// отчёт не менялся между прогонами. Книги без издательства не считаются: сайт
+/// его не показал, и строка «без издательства» только исказила бы картину каталога.
+/// Регистр не различается («БОМБОРА» у eksmo и «Бомбора» у других сайтов — одно
+/// издательство), в отчёт идёт самое частое написание.
+pub fn count_by_publisher<'a>(
+    books: impl IntoIterator<Item = &'a Book<String>>,
+) -> Vec<(String, usize)> {
+    let mut spellings: HashMap<String, HashMap<&str, usize>> = HashMap::new();
+    for book in books {
+        if let Some(publisher) = book.publisher.as_deref() {
+            *spellings
+                .entry(publisher.to_lowercase())
+                .or_default()
+                .entry(publisher)
+                .or_default() += 1;
+        }
+    }
+    let mut counts: Vec<(String, usize)> = spellings
+        .into_values()
+        .map(|spellings| {
+            let books = spellings.values().sum();
+            let (name, _) = spellings
+                .into_iter()
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+                .expect("publisher has at least one spelling");
+            (name.to_string(), books)
+        })
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+/// `publisher,books` в порядке `count_by_publisher`.
+pub fn write_publishers(path: &Path, counts: &[(String, usize)]) -> anyhow::Result<()> {
+    let mut wtr = csv::Writer::from_path(path)?;
+    wtr.write_record(["publisher", "books"])?;
+    for (publisher, books) in counts {
+        wtr.write_record([publisher.as_str(), &books.to_string()])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+
+    #[test]
+    fn books_counted_per_publisher() {
+        let books: Vec<_> = [
+            Some("Эксмо"),
+            Some("АСТ"),
+            None,
+            Some("Эксмо"),
+            Some("Речь"),
+            Some("АСТ"),
+            Some("Эксмо"),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(n, publisher)| {
+            let mut book = sample_book(n as u128);
+            book.publisher = publisher.map(str::to_string);
+            book
+        })
+        .collect();
+        let counts = count_by_publisher(&books);
+        assert_eq!(
+            counts,
+            [
+                ("Эксмо".to_string(), 3),
+                ("АСТ".to_string(), 2),
+                ("Речь".to_string(), 1),
+            ]
+        );
+        assert!(count_by_publisher(&books[2..3]).is_empty());
+
+        let mixed_case: Vec<_> = ["БОМБОРА", "Бомбора", "БОМБОРА", "Речь"]
+            .into_iter()
+            .enumerate()
+            .map(|(n, publisher)| {
+                let mut book = sample_book(n as u128);
+                book.publisher = Some(publisher.to_string());
+                book
+            })
+            .collect();
+        assert_eq!(
+            count_by_publisher(&mixed_case),
+            [("БОМБОРА".to_string(), 3), ("Речь".to_string(), 1)]
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_publishers_{}.csv", std::process::id()));
+        write_publishers(&path, &counts).expect("write");
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "publisher,books\nЭксмо,3\nАСТ,2\nРечь,1\n"
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
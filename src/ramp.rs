@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// Окно разгона по умолчанию для `--concurrency-ramp` без `--ramp-secs`.
+pub const DEFAULT_RAMP: Duration = Duration::from_secs(30);
+
+/// `--concurrency-ramp`: семафор загрузок, который стартует с одного разрешения
+/// и равномерно добавляет остальные, пока через `window` их не станет `max`.
+/// Первая волна запросов не упирается в rate limit сайта сразу всей конкурентностью.
+///
+/// Разрешения добавляет фоновая задача; она держит только `Weak`
+/// и завершается, если семафор уже никому не нужен.
+pub fn ramped_semaphore(max: usize, window: Duration) -> Arc<Semaphore> {
+    let max = max.max(1);
+    let semaphore = Arc::new(Semaphore::new(1));
+    if max == 1 {
+        return semaphore;
+    }
+    let weak = Arc::downgrade(&semaphore);
+    let started = Instant::now();
+    let steps = max - 1;
+    tokio::spawn(async move {
+        for step in 1..=steps {
+            let at = started + window.mul_f64(step as f64 / steps as f64);
+            tokio::time::sleep_until(at).await;
+            let Some(semaphore) = weak.upgrade() else {
+                return;
+            };
+            semaphore.add_permits(1);
+            debug!(permits = step + 1, max, "concurrency ramp");
+        }
+    });
+    semaphore
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn permits_at(semaphore: &Semaphore, elapsed: Duration, started: Instant) -> usize {
+        tokio::time::sleep_until(started + elapsed).await;
+        // Дать фоновой задаче отработать пробуждение на том же шаге времени.
+        for _ in 0..4 {
+            tokio::task::yield_now().await;
+        }
+        semaphore.available_permits()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permits_grow_linearly_to_max() {
+        let started = Instant::now();
+        let semaphore = ramped_semaphore(5, Duration::from_secs(8));
+        assert_eq!(semaphore.available_permits(), 1);
+        assert_eq!(
+            permits_at(&semaphore, Duration::from_secs(1), started).await,
+            1
+        );
+        assert_eq!(
+            permits_at(&semaphore, Duration::from_secs(2), started).await,
+            2
+        );
+        assert_eq!(
+            permits_at(&semaphore, Duration::from_secs(5), started).await,
+            3
+        );
+        assert_eq!(
+            permits_at(&semaphore, Duration::from_secs(8), started).await,
+            5
+        );
+        assert_eq!(
+            permits_at(&semaphore, Duration::from_secs(60), started).await,
+            5
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn single_permit_and_zero_window() {
+        let single = ramped_semaphore(1, Duration::from_secs(8));
+        assert_eq!(single.available_permits(), 1);
+        let started = Instant::now();
+        let instant = ramped_semaphore(3, Duration::ZERO);
+        assert_eq!(permits_at(&instant, Duration::ZERO, started).await, 3);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::eksmo::EksmoParser;
+use crate::igraslov::IgraSlov;
+use crate::labirint::LabirintParser;
+use crate::parse_traits::{DynBookParser, Sites, host_of};
+
+/// Владеет по одному экземпляру парсера на сайт и выбирает нужный по URL.
+pub struct ParserRegistry {
+    parsers: Vec<Box<dyn DynBookParser>>,
+}
+
+impl ParserRegistry {
+    pub fn empty() -> Self {
+        Self { parsers: vec![] }
+    }
+
+    /// Регистрирует парсер; повторная регистрация сайта заменяет прежний парсер.
+    pub fn register<P: DynBookParser + 'static>(&mut self, parser: P) -> &mut Self {
+        let site = parser.site();
+        self.parsers.retain(|p| p.site() != site);
+        self.parsers.push(Box::new(parser));
+        self
+    }
+
+    pub fn for_site(&self, site: Sites) -> Option<&dyn DynBookParser> {
+        self.parsers
+            .iter()
+            .find(|p| p.site() == site)
+            .map(|p| p.as_ref())
+    }
+
+    /// Встроенные сайты определяются через `Sites::from_url`, сайты из конфига — по хосту.
+    pub fn for_url(&self, url: &str) -> Option<&dyn DynBookParser> {
+        if let Some(site) = Sites::from_url(url) {
+            return self.for_site(site);
+        }
+        let host = host_of(url)?;
+        self.parsers
+            .iter()
+            .find(|p| p.site().matches_host(&host))
+            .map(|p| p.as_ref())
+    }
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register(LabirintParser)
+            .register(IgraSlov)
+            .register(EksmoParser);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn for_url_known_hosts() {
+        let registry = ParserRegistry::default();
+        let cases = [
+            ("https://www.labirint.ru/books/801841/", Sites::Labirint),
+            ("https://igraslov.store/product/kniga/", Sites::IgraSlov),
+            ("https://eksmo.ru/book/example/", Sites::Eksmo),
+        ];
+        for (url, site) in cases {
+            let parser = registry.for_url(url).expect("parser registered");
+            assert_eq!(parser.site(), site, "wrong parser for {url}");
+        }
+    }
+
+    #[test]
+    fn for_url_unknown_host() {
+        let registry = ParserRegistry::default();
+        assert!(registry.for_url("https://example.com/books/1/").is_none());
+        assert!(
+            registry
+                .for_url("https://notlabirint.ru/books/1/")
+                .is_none()
+        );
+        assert!(registry.for_url("not a url").is_none());
+    }
+
+    #[test]
+    fn empty_registry_has_no_parsers() {
+        let registry = ParserRegistry::empty();
+        assert!(registry.for_url("https://eksmo.ru/book/example/").is_none());
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, OnceLock};
+
+use anyhow::anyhow;
+use dashmap::DashMap;
+use scraper::{ElementRef, Selector};
+use tracing::{debug, info, trace};
+
+use crate::telemetry::QUALITY_TARGET;
+
+/// `<loc>` в sitemap.
+pub static LOC_SEL_STR: &str = "loc";
+/// OpenGraph и подобные `<meta property="..." content="...">`.
+pub static META_PROPERTY_SEL_STR: &str = "meta[property]";
+pub static JSON_LD_SEL_STR: &str = r#"script[type="application/ld+json"]"#;
+/// Наличие в microdata schema.org: `<meta itemprop="availability" content="...InStock">`.
+pub static AVAILABILITY_SEL_STR: &str = "[itemprop=availability]";
+/// Блоки, где обычно лежит аннотация, если селектор сайта перестал её находить.
+pub static DESCRIPTION_CANDIDATE_SEL_STR: &str = "[itemprop=description], .description, article p";
+/// Оформление страницы: текст внутри него не бывает аннотацией.
+pub static CHROME_SEL_STR: &str =
+    "nav, header, footer, aside, menu, [role=navigation], .breadcrumbs, .menu, .footer, .header";
+
+static LOC_SEL: OnceLock<Selector> = OnceLock::new();
+static META_PROPERTY_SEL: OnceLock<Selector> = OnceLock::new();
+static JSON_LD_SEL: OnceLock<Selector> = OnceLock::new();
+static AVAILABILITY_SEL: OnceLock<Selector> = OnceLock::new();
+static DESCRIPTION_CANDIDATE_SEL: OnceLock<Selector> = OnceLock::new();
+static CHROME_SEL: OnceLock<Selector> = OnceLock::new();
+
+/// Общие селекторы — константы, их компиляция проверяется тестом `shared_selectors_compile`,
+/// поэтому паника здесь означает ошибку в этом модуле, а не во входных данных.
+fn compiled(cell: &'static OnceLock<Selector>, selector: &str) -> &'static Selector {
+    cell.get_or_init(|| {
+        Selector::parse(selector)
+            .unwrap_or_else(|e| panic!("shared selector {selector:?} must compile: {e}"))
+    })
+}
+
+pub fn loc() -> &'static Selector {
+    compiled(&LOC_SEL, LOC_SEL_STR)
+}
+
+pub fn meta_property() -> &'static Selector {
+    compiled(&META_PROPERTY_SEL, META_PROPERTY_SEL_STR)
+}
+
+pub fn json_ld_script() -> &'static Selector {
+    compiled(&JSON_LD_SEL, JSON_LD_SEL_STR)
+}
+
+pub fn availability() -> &'static Selector {
+    compiled(&AVAILABILITY_SEL, AVAILABILITY_SEL_STR)
+}
+
+pub fn description_candidate() -> &'static Selector {
+    compiled(&DESCRIPTION_CANDIDATE_SEL, DESCRIPTION_CANDIDATE_SEL_STR)
+}
+
+pub fn chrome() -> &'static Selector {
+    compiled(&CHROME_SEL, CHROME_SEL_STR)
+}
+
+/// Селекторы поля сайта по порядку: текущая разметка первой, за ней прежняя. Пока сайт
+/// выкатывает редизайн, часть страниц отдаётся в старой вёрстке, часть в новой, и цепочка
+/// разбирает обе. Когда старая разметка пропадёт совсем, её селектор просто удаляется из списка.
+pub struct SelectorChain {
+    field: &'static str,
+    selectors: &'static [&'static str],
+    compiled: OnceLock<Vec<Selector>>,
+    any: OnceLock<Selector>,
+}
+
+impl SelectorChain {
+    pub const fn new(field: &'static str, selectors: &'static [&'static str]) -> Self {
+        Self {
+            field,
+            selectors,
+            compiled: OnceLock::new(),
+            any: OnceLock::new(),
+        }
+    }
+
+    pub fn selectors(&self) -> &'static [&'static str] {
+        self.selectors
+    }
+
+    /// Строки цепочки — константы сайта, поэтому паника, как и у `compiled`, — ошибка в коде.
+    fn compiled(&self) -> &[Selector] {
+        self.compiled.get_or_init(|| {
+            self.selectors
+                .iter()
+                .map(|s| {
+                    Selector::parse(s).unwrap_or_else(|e| {
+                        panic!("{} selector {s:?} must compile: {e}", self.field)
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Узлы первого селектора, у которого хоть один узел с непустым текстом; пусто, если
+    /// не сработал ни один. Узлы без текста (пустой контейнер новой вёрстки) не считаются.
+    ///
+    /// Какой селектор сработал, пишется событием в `target: "quality"` (`index` — номер
+    /// в цепочке, 0 — основной). Запасной селектор и промах всей цепочки пишутся на уровне
+    /// `info` и попадают в `logs/quality.log` без `RUST_LOG`: видно, что сайт молча живёт
+    /// на запасных селекторах и основной пора чинить. Срабатывание основного — `trace`.
+    pub fn select<'a>(&self, html: &'a scraper::Html) -> Vec<ElementRef<'a>> {
+        for (i, selector) in self.compiled().iter().enumerate() {
+            let nodes: Vec<ElementRef> = html.select(selector).collect();
+            if nodes
+                .iter()
+                .any(|node| node.text().any(|t| !t.trim().is_empty()))
+            {
+                if i == 0 {
+                    trace!(
+                        target: QUALITY_TARGET,
+                        field = self.field,
+                        index = i,
+                        selector = self.selectors[i],
+                        fallback = false,
+                        "field selector matched"
+                    );
+                } else {
+                    info!(
+                        target: QUALITY_TARGET,
+                        field = self.field,
+                        index = i,
+                        selector = self.selectors[i],
+                        fallback = true,
+                        "field selector matched"
+                    );
+                    debug!(
+                        field = self.field,
+                        selector = self.selectors[i],
+                        "primary selector found nothing, used fallback"
+                    );
+                }
+                return nodes;
+            }
+        }
+        info!(
+            target: QUALITY_TARGET,
+            field = self.field,
+            chain_len = self.selectors.len(),
+            "no field selector matched"
+        );
+        vec![]
+    }
+
+    /// Список `a, b, ...` всей цепочки: совпадает, если совпал любой из селекторов.
+    pub fn any(&self) -> &Selector {
+        self.any.get_or_init(|| {
+            let list = self.selectors.join(", ");
+            Selector::parse(&list)
+                .unwrap_or_else(|e| panic!("{} selector {list:?} must compile: {e}", self.field))
+        })
+    }
+}
+
+/// Селекторы из конфигов и запасных путей: строка компилируется один раз на процесс.
+static DYNAMIC: LazyLock<DashMap<String, Arc<Selector>>> = LazyLock::new(DashMap::new);
+
+/// Скомпилированный селектор по строке из общего кэша. Ошибка разбора не кэшируется.
+pub fn selector(s: &str) -> anyhow::Result<Arc<Selector>> {
+    if let Some(cached) = DYNAMIC.get(s) {
+        return Ok(Arc::clone(&cached));
+    }
+    let compiled = Selector::parse(s).map_err(|e| anyhow!("bad selector {s:?}: {e}"))?;
+    // Два потока могут скомпилировать одну строку одновременно; в кэше останется первый.
+    Ok(Arc::clone(
+        &DYNAMIC.entry(s.to_string()).or_insert(Arc::new(compiled)),
+    ))
+}
+
+/// `content` первого `<meta property>` с нужным именем (`og:title`, `book:isbn`, ...).
+pub fn meta_content(html: &scraper::Html, property: &str) -> Option<String> {
+    html.select(meta_property())
+        .find(|meta| meta.value().attr("property") == Some(property))
+        .and_then(|meta| meta.value().attr("content"))
+        .map(|content| content.trim().to_string())
+        .filter(|content| !content.is_empty())
+}
+
+/// Вся таблица характеристик: подпись строки (без двоеточия в конце) → текст строки
+/// без подписи, пробелы схлопнуты. Строки без подписи или без значения пропускаются;
+/// из повторяющихся подписей остаётся первая.
+pub fn spec_table(
+    html: &scraper::Html,
+    row: &Selector,
+    label: &Selector,
+) -> HashMap<String, String> {
+    let mut table = HashMap::new();
+    for row in html.select(row) {
+        let Some(label) = row.select(label).next() else {
+            continue;
+        };
+        let name: String = label.text().collect();
+        let key = name.trim().trim_end_matches(':').trim();
+        let text: String = row.text().collect();
+        let value = text
+            .replacen(&name, "", 1)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !key.is_empty() && !value.is_empty() {
+            table.entry(key.to_string()).or_insert(value);
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_selectors_compile() {
+        for (selector, compile) in [
+            (LOC_SEL_STR, loc as fn() -> &'static Selector),
+            (META_PROPERTY_SEL_STR, meta_property),
+            (JSON_LD_SEL_STR, json_ld_script),
+            (AVAILABILITY_SEL_STR, availability),
+            (DESCRIPTION_CANDIDATE_SEL_STR, description_candidate),
+            (CHROME_SEL_STR, chrome),
+        ] {
+            assert!(Selector::parse(selector).is_ok(), "{selector}");
+            compile();
+        }
+    }
+
+    #[test]
+    fn meta_content_by_property() {
+        let html = scraper::Html::parse_document(
+            r#"<html><head>
+            <meta property="og:type" content="book">
+            <meta property="og:title" content=" Джейн Эйр ">
+            <meta name="description" content="не property">
+            </head></html>"#,
+        );
+        assert_eq!(
+            meta_content(&html, "og:title").as_deref(),
+            Some("Джейн Эйр")
+        );
+        assert_eq!(meta_content(&html, "description"), None);
+        assert_eq!(html.select(meta_property()).count(), 2);
+    }
+
+    #[test]
+    fn spec_table_by_label() {
+        let html = scraper::Html::parse_document(
+            r#"<table>
+            <tr class="spec"><th class="name">Язык</th><td><p>Русский</p></td></tr>
+            <tr class="spec"><th class="name">Оригинальное название:</th><td><p> Jane
+                Eyre </p></td></tr>
+            <tr class="spec"><th class="name">Размеры</th><td></td></tr>
+            <tr class="spec"><td>без подписи</td></tr>
+            </table>"#,
+        );
+        let row = selector("tr.spec").expect("row");
+        let label = selector(".name").expect("label");
+        assert_eq!(
+            spec_table(&html, &row, &label),
+            HashMap::from([
+                ("Язык".to_string(), "Русский".to_string()),
+                ("Оригинальное название".to_string(), "Jane Eyre".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn chain_falls_back_to_next_selector() {
+        static TITLE: SelectorChain =
+            SelectorChain::new("title", &["h1._h1_new", "#product-title h1", "h1"]);
+        let old = scraper::Html::parse_document(
+            r#"<div class="_h1_new"></div><h1 class="_h1_new"> </h1>
+            <div id="product-title"><h1>Джейн Эйр</h1></div>"#,
+        );
+        let nodes = TITLE.select(&old);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].text().collect::<String>(), "Джейн Эйр");
+
+        let new = scraper::Html::parse_document(r#"<h1 class="_h1_new">Идиот</h1>"#);
+        assert_eq!(TITLE.select(&new)[0].text().collect::<String>(), "Идиот");
+        assert_eq!(new.select(TITLE.any()).count(), 1);
+
+        let none = scraper::Html::parse_document("<p>нет заголовка</p>");
+        assert!(TITLE.select(&none).is_empty());
+        assert_eq!(TITLE.selectors().len(), 3);
+    }
+
+    #[test]
+    fn chain_records_matched_selector_index() {
+        use crate::parse_traits::tests::QualityCapture;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        static AUTHOR: SelectorChain =
+            SelectorChain::new("authors", &[".authors-new a", ".authors a"]);
+        let capture = QualityCapture::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(capture.clone()));
+        AUTHOR.select(&scraper::Html::parse_document(
+            r#"<div class="authors-new"><a>Бронте</a></div>"#,
+        ));
+        AUTHOR.select(&scraper::Html::parse_document(
+            r#"<div class="authors"><a>Бронте</a></div>"#,
+        ));
+        AUTHOR.select(&scraper::Html::parse_document("<p>без автора</p>"));
+
+        let events = capture.0.lock().expect("lock");
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0]["field"], "authors");
+        assert_eq!(events[0]["index"], "0");
+        assert_eq!(events[0]["fallback"], "false");
+        assert_eq!(events[1]["index"], "1");
+        assert_eq!(events[1]["selector"], ".authors a");
+        assert_eq!(events[1]["fallback"], "true");
+        assert!(!events[2].contains_key("index"));
+        assert_eq!(events[2]["chain_len"], "2");
+    }
+
+    #[test]
+    fn fallback_and_miss_pass_default_filter() {
+        use crate::parse_traits::tests::QualityCapture;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        static AUTHOR: SelectorChain =
+            SelectorChain::new("authors", &[".authors-new a", ".authors a"]);
+        let capture = QualityCapture::default();
+        let _guard = tracing::subscriber::set_default(
+            tracing_subscriber::registry()
+                .with(crate::telemetry::default_filter())
+                .with(capture.clone()),
+        );
+        AUTHOR.select(&scraper::Html::parse_document(
+            r#"<div class="authors-new"><a>Бронте</a></div>"#,
+        ));
+        AUTHOR.select(&scraper::Html::parse_document(
+            r#"<div class="authors"><a>Бронте</a></div>"#,
+        ));
+        AUTHOR.select(&scraper::Html::parse_document("<p>без автора</p>"));
+
+        // Основной селектор — штатный случай и в лог по умолчанию не идёт.
+        let events = capture.0.lock().expect("lock");
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["fallback"], "true");
+        assert_eq!(events[1]["chain_len"], "2");
+    }
+
+    #[test]
+    fn dynamic_selectors_are_cached() {
+        let first = selector("div.product-title > h1").expect("valid selector");
+        let second = selector("div.product-title > h1").expect("valid selector");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(selector("div[").is_err());
+        assert!(selector("div[").is_err());
+    }
+}
@@ -0,0 +1,1236 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use serde::Serialize;
+
+use crate::cli::{JsonArrayMode, OutputFormat, SortKey};
+use crate::csv_save::{BOOK_SCHEMA_VERSION, CsvSave, book_csv_headers};
+use crate::parse_traits::{Book, Price, PriceFormat, Sites};
+use tracing::{info, warn};
+
+/// Устойчивая сортировка книг по ключу: книги с равным ключом сохраняют исходный порядок.
+/// ISBN сравниваются без дефисов (`isbn_key`), а книги с одним ISBN — по `source`: порядок
+/// не зависит от того, какая страница разобралась раньше, и `--idempotent` оставляет
+/// из повторов всегда одну и ту же.
+pub fn sort_books(books: &mut [&Book<String>], key: SortKey) {
+    match key {
+        SortKey::Isbn => {
+            books.sort_by_cached_key(|b| (isbn_key(b.isbn.as_str()), b.source.clone()))
+        }
+        SortKey::Title => books.sort_by(|a, b| a.title.as_str().cmp(b.title.as_str())),
+        SortKey::Price => books.sort_by_key(|b| b.price),
+        SortKey::Source => books.sort_by(|a, b| a.source.cmp(&b.source)),
+    }
+}
+
+/// Приёмник распарсенных книг: CSV, JSON и т.д.
+pub trait BookSink {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()>;
+    /// Дописывает всё, что осталось в буферах. Вызывается один раз в конце прогона.
+    fn finish(&mut self) -> anyhow::Result<()>;
+    /// Сбрасывает уже записанное на диск, не завершая выгрузку (`--flush-every`).
+    /// Приёмники, которые копят данные до `finish` (JSON-массив в памяти, Parquet
+    /// row group, SQLite-транзакции), оставляют реализацию по умолчанию.
+    fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: BookSink + ?Sized> BookSink for Box<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        (**self).write_book(book)
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        (**self).flush()
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        (**self).finish()
+    }
+}
+
+pub struct CsvSink<W: Write> {
+    wtr: csv::Writer<W>,
+    with_flags: bool,
+    price_format: PriceFormat,
+    /// Временный файл и итоговое имя для `create_atomic`.
+    rename: Option<(PathBuf, PathBuf)>,
+}
+
+/// Метка порядка байтов UTF-8 для `--csv-bom`.
+pub const UTF8_BOM: &str = "\u{feff}";
+
+/// Куда `CsvSink::create_atomic` пишет до `finish`.
+pub fn pending_path(path: &Path) -> PathBuf {
+    path.with_extension("csv.tmp")
+}
+
+impl<W: Write> CsvSink<W> {
+    /// `with_flags` добавляет колонки `has_*` (см. `BOOK_CSV_FLAG_HEADERS`).
+    pub fn new(out: W, with_flags: bool) -> anyhow::Result<Self> {
+        let mut wtr = csv::Writer::from_writer(out);
+        wtr.write_record(book_csv_headers(with_flags))?;
+        Ok(Self {
+            wtr,
+            with_flags,
+            price_format: PriceFormat::default(),
+            rename: None,
+        })
+    }
+
+    pub fn with_price_format(mut self, price_format: PriceFormat) -> Self {
+        self.price_format = price_format;
+        self
+    }
+
+    /// Как `new`, но файл начинается с `UTF8_BOM`: без него Excel читает кириллицу
+    /// в неверной кодировке. `csv::Reader` BOM пропускает, колонка `site` читается как есть.
+    pub fn new_with_bom(mut out: W, with_flags: bool) -> anyhow::Result<Self> {
+        out.write_all(UTF8_BOM.as_bytes())?;
+        Self::new(out, with_flags)
+    }
+
+    /// Готовая строка в текущем наборе колонок, например уже выгруженная в прошлом прогоне.
+    pub fn write_record(&mut self, record: &csv::StringRecord) -> anyhow::Result<()> {
+        self.wtr.write_record(record)?;
+        Ok(())
+    }
+}
+
+impl CsvSink<BufWriter<File>> {
+    /// Пишет в `pending_path(path)` и переименовывает в `path` в `finish`: после падения
+    /// на месте `path` не остаётся файла с оборванной последней строкой.
+    /// С `bom` файл начинается с `UTF8_BOM`, см. `new_with_bom`.
+    pub fn create_atomic(
+        path: impl Into<PathBuf>,
+        with_flags: bool,
+        bom: bool,
+    ) -> anyhow::Result<Self> {
+        let path = path.into();
+        let tmp = pending_path(&path);
+        let file = File::create(&tmp).with_context(|| format!("can't create {}", tmp.display()))?;
+        let out = BufWriter::new(file);
+        let mut sink = if bom {
+            Self::new_with_bom(out, with_flags)?
+        } else {
+            Self::new(out, with_flags)?
+        };
+        sink.rename = Some((tmp, path));
+        Ok(sink)
+    }
+}
+
+impl<W: Write> BookSink for CsvSink<W> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        book.write_csv_record(&mut self.wtr, self.with_flags, self.price_format)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.wtr.flush()?;
+        if let Some((tmp, path)) = self.rename.take() {
+            std::fs::rename(&tmp, &path)
+                .with_context(|| format!("can't rename {} to {}", tmp.display(), path.display()))?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.wtr.flush()?;
+        Ok(())
+    }
+}
+
+/// Элемент JSON-выгрузки: `_schema` первым полем, за ним поля книги.
+#[derive(Serialize)]
+pub(crate) struct Versioned<'a, T: Serialize> {
+    _schema: u32,
+    #[serde(flatten)]
+    book: &'a T,
+    /// Вычисляется из ISBN, в `Book` не хранится.
+    isbn13_group: Option<String>,
+}
+
+impl<'a, T: Serialize> Versioned<'a, T> {
+    pub(crate) fn new(book: &'a T, isbn13_group: Option<String>) -> Self {
+        Self {
+            _schema: BOOK_SCHEMA_VERSION,
+            book,
+            isbn13_group,
+        }
+    }
+}
+
+/// Один pretty-printed JSON-массив. В режиме `Buffered` книги копятся в памяти
+/// и пишутся разом в `finish`; в `Streaming` элементы пишутся сразу, скобки и запятые — вручную.
+pub struct JsonArraySink<W: Write> {
+    out: W,
+    mode: JsonArrayMode,
+    price_format: PriceFormat,
+    buffered: Vec<String>,
+    written: usize,
+}
+
+impl<W: Write> JsonArraySink<W> {
+    pub fn new(out: W, mode: JsonArrayMode) -> Self {
+        Self {
+            out,
+            mode,
+            price_format: PriceFormat::default(),
+            buffered: vec![],
+            written: 0,
+        }
+    }
+
+    /// С `PriceFormat::Rubles` поле `price` пишется строкой в валюте сайта (`Price::format_for`).
+    pub fn with_price_format(mut self, price_format: PriceFormat) -> Self {
+        self.price_format = price_format;
+        self
+    }
+
+    fn write_element(&mut self, element: &str) -> anyhow::Result<()> {
+        let sep = if self.written == 0 { "[\n" } else { ",\n" };
+        self.out.write_all(sep.as_bytes())?;
+        // Отступ элемента как у `to_writer_pretty` для массива.
+        let indented = element
+            .lines()
+            .map(|line| format!("  {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.out.write_all(indented.as_bytes())?;
+        self.written += 1;
+        Ok(())
+    }
+}
+
+impl<W: Write> BookSink for JsonArraySink<W> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        let element = match self.price_format {
+            PriceFormat::Kopecks => {
+                serde_json::to_string_pretty(&Versioned::new(book, book.isbn13_group()))?
+            }
+            PriceFormat::Rubles => {
+                let mut value = serde_json::to_value(book)?;
+                let human = |price: Price| price.format_for(PriceFormat::Rubles, book.site);
+                value["price"] = human(book.price).into();
+                if let Some(max) = book.price_max {
+                    value["price_max"] = human(max).into();
+                }
+                serde_json::to_string_pretty(&Versioned::new(&value, book.isbn13_group()))?
+            }
+        };
+        match self.mode {
+            JsonArrayMode::Buffered => self.buffered.push(element),
+            JsonArrayMode::Streaming => self.write_element(&element)?,
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        for element in std::mem::take(&mut self.buffered) {
+            self.write_element(&element)?;
+        }
+        if self.written == 0 {
+            self.out.write_all(b"[]\n")?;
+        } else {
+            self.out.write_all(b"\n]\n")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Приёмник формата `output` поверх произвольного писателя — для `--output -` это stdout.
+/// Файловые выгрузки открываются в `main`: CSV там пишется атомарно и со sidecar-схемой.
+pub fn writer_sink<'a, W: Write + Send + 'a>(
+    output: OutputFormat,
+    out: W,
+    with_flags: bool,
+    csv_bom: bool,
+    price_format: PriceFormat,
+) -> anyhow::Result<Box<dyn BookSink + 'a>> {
+    Ok(match output {
+        OutputFormat::Csv if csv_bom => {
+            Box::new(CsvSink::new_with_bom(out, with_flags)?.with_price_format(price_format))
+        }
+        OutputFormat::Csv => {
+            Box::new(CsvSink::new(out, with_flags)?.with_price_format(price_format))
+        }
+        OutputFormat::JsonArray(mode) => {
+            Box::new(JsonArraySink::new(out, mode).with_price_format(price_format))
+        }
+        #[cfg(feature = "parquet")]
+        OutputFormat::Parquet => Box::new(crate::parquet_sink::ParquetSink::new(out)?),
+        #[cfg(not(feature = "parquet"))]
+        OutputFormat::Parquet => {
+            anyhow::bail!("--output-parquet requires a build with --features parquet")
+        }
+        OutputFormat::Sqlite => anyhow::bail!("sqlite output needs a file, not a stream"),
+    })
+}
+
+/// Отдельный приёмник на каждый сайт (`books_labirint.csv`, `books_eksmo.csv`, ...).
+/// Приёмники создаются `open` при первой книге сайта, поэтому заголовки пишутся
+/// ровно один раз на файл.
+pub struct PartitionedSink<S, F> {
+    open: F,
+    sinks: HashMap<Sites, S>,
+}
+
+impl<S, F> PartitionedSink<S, F>
+where
+    S: BookSink,
+    F: FnMut(Sites) -> anyhow::Result<S>,
+{
+    pub fn new(open: F) -> Self {
+        Self {
+            open,
+            sinks: HashMap::new(),
+        }
+    }
+}
+
+impl<S, F> BookSink for PartitionedSink<S, F>
+where
+    S: BookSink,
+    F: FnMut(Sites) -> anyhow::Result<S>,
+{
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        let sink = match self.sinks.entry(book.site) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert((self.open)(book.site)?),
+        };
+        sink.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        for sink in self.sinks.values_mut() {
+            sink.finish()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        for sink in self.sinks.values_mut() {
+            sink.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Диапазон цен (`--filter-min-price`/`--filter-max-price`) в копейках, как в CSV.
+/// Границы включительные. Цена 0 означает, что сайт цену не показал: такие книги
+/// с диапазоном не сравниваются и отбрасываются только с `drop_priceless`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct PriceFilter {
+    pub min: Option<u128>,
+    pub max: Option<u128>,
+    pub drop_priceless: bool,
+}
+
+impl PriceFilter {
+    pub fn is_active(&self) -> bool {
+        self.min.is_some() || self.max.is_some() || self.drop_priceless
+    }
+
+    pub fn accepts(&self, book: &Book<String>) -> bool {
+        let price = book.price;
+        if price == Price::default() {
+            return !self.drop_priceless;
+        }
+        self.min.is_none_or(|min| price >= Price::from(min))
+            && self.max.is_none_or(|max| price <= Price::from(max))
+    }
+}
+
+/// `--language-filter`: коды языков, книги на которых записываются. Пустой набор — без фильтра.
+/// Книги, у которых сайт язык не указал, не отбрасываются: у Лабиринта его нет вовсе.
+/// Двуязычное издание (`ru,en`) проходит, если в наборе есть хотя бы один из его языков.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct LanguageFilter {
+    pub languages: BTreeSet<String>,
+}
+
+impl LanguageFilter {
+    pub fn is_active(&self) -> bool {
+        !self.languages.is_empty()
+    }
+
+    pub fn accepts(&self, book: &Book<String>) -> bool {
+        match book.language.as_deref() {
+            Some(codes) if self.is_active() => {
+                codes.split(',').any(|code| self.languages.contains(code))
+            }
+            _ => true,
+        }
+    }
+}
+
+/// ISBN для сравнения: без дефисов и пробелов.
+pub fn isbn_key(isbn: &str) -> String {
+    isbn.replace(['-', ' '], "")
+}
+
+/// `--idempotent`: не больше одной записи на ISBN (без дефисов и пробелов) в любом формате.
+/// Остаётся первая книга, повторы с тем же ISBN отбрасываются.
+pub struct IdempotentSink<S> {
+    inner: S,
+    seen: HashSet<String>,
+    duplicates: usize,
+}
+
+impl<S: BookSink> IdempotentSink<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            seen: HashSet::new(),
+            duplicates: 0,
+        }
+    }
+
+    /// Сколько повторов отброшено.
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+}
+
+impl<S: BookSink> BookSink for IdempotentSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        if !self.seen.insert(isbn_key(book.isbn.as_str())) {
+            self.duplicates += 1;
+            return Ok(());
+        }
+        self.inner.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        if self.duplicates > 0 {
+            info!(
+                duplicates = self.duplicates,
+                "books with already written isbn skipped"
+            );
+        }
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Пропускает в `inner` только книги, подходящие под `PriceFilter`.
+pub struct FilteredSink<S> {
+    inner: S,
+    filter: PriceFilter,
+    dropped: usize,
+}
+
+impl<S: BookSink> FilteredSink<S> {
+    pub fn new(inner: S, filter: PriceFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            dropped: 0,
+        }
+    }
+
+    /// Сколько книг отброшено фильтром.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl<S: BookSink> BookSink for FilteredSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        if !self.filter.accepts(book) {
+            self.dropped += 1;
+            return Ok(());
+        }
+        self.inner.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        if self.dropped > 0 {
+            info!(dropped = self.dropped, "books dropped by price filter");
+        }
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Пропускает в `inner` только книги на языках из `LanguageFilter`.
+pub struct LanguageFilterSink<S> {
+    inner: S,
+    filter: LanguageFilter,
+    dropped: usize,
+}
+
+impl<S: BookSink> LanguageFilterSink<S> {
+    pub fn new(inner: S, filter: LanguageFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            dropped: 0,
+        }
+    }
+
+    /// Сколько книг отброшено фильтром.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl<S: BookSink> BookSink for LanguageFilterSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        if !self.filter.accepts(book) {
+            self.dropped += 1;
+            return Ok(());
+        }
+        self.inner.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        if self.dropped > 0 {
+            info!(dropped = self.dropped, "books dropped by language filter");
+        }
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Требования к полноте записи: `--require-title`, `--require-price`, `--min-authors`.
+/// Разбор при этом не падает — книга, которая им не отвечает, просто не попадает в выгрузку.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct QualityGate {
+    pub require_title: bool,
+    pub require_price: bool,
+    pub min_authors: usize,
+}
+
+impl QualityGate {
+    pub fn is_active(&self) -> bool {
+        self.require_title || self.require_price || self.min_authors > 0
+    }
+
+    /// Первое невыполненное требование; `None`, если книга проходит все.
+    pub fn rejects(&self, book: &Book<String>) -> Option<&'static str> {
+        if self.require_title && book.title.as_str().trim().is_empty() {
+            return Some("no title");
+        }
+        if self.require_price && u128::from(book.price) == 0 {
+            return Some("no price");
+        }
+        if book.authors.len() < self.min_authors {
+            return Some("too few authors");
+        }
+        None
+    }
+}
+
+/// Пропускает в `inner` только книги, прошедшие `QualityGate`; отброшенные пишутся в лог
+/// и считаются по причинам.
+pub struct QualityGateSink<S> {
+    inner: S,
+    gate: QualityGate,
+    dropped: BTreeMap<&'static str, usize>,
+}
+
+impl<S: BookSink> QualityGateSink<S> {
+    pub fn new(inner: S, gate: QualityGate) -> Self {
+        Self {
+            inner,
+            gate,
+            dropped: BTreeMap::new(),
+        }
+    }
+
+    /// Сколько книг отброшено по каждой причине.
+    pub fn dropped(&self) -> &BTreeMap<&'static str, usize> {
+        &self.dropped
+    }
+}
+
+impl<S: BookSink> BookSink for QualityGateSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        if let Some(reason) = self.gate.rejects(book) {
+            warn!(url = %book.source, reason, "book dropped by quality gate");
+            *self.dropped.entry(reason).or_default() += 1;
+            return Ok(());
+        }
+        self.inner.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        for (reason, dropped) in &self.dropped {
+            info!(reason, dropped, "books dropped by quality gate");
+        }
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// `--flush-every`: сбрасывает `inner` на диск после каждых `every` книг. Книги пишутся
+/// по мере разбора, так что после падения посреди обхода на диске остаётся всё до
+/// последнего сброса. Сброс на каждой книге упирается в запись на диск, а без сброса
+/// падение теряет весь буфер.
+pub struct FlushEvery<S> {
+    inner: S,
+    every: usize,
+    since_flush: usize,
+}
+
+impl<S: BookSink> FlushEvery<S> {
+    pub fn new(inner: S, every: usize) -> Self {
+        Self {
+            inner,
+            every: every.max(1),
+            since_flush: 0,
+        }
+    }
+}
+
+impl<S: BookSink> BookSink for FlushEvery<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        self.inner.write_book(book)?;
+        self.since_flush += 1;
+        if self.since_flush >= self.every {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.since_flush = 0;
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::csv_save::BOOK_CSV_HEADERS;
+    use crate::parse_traits::{Author, Description, Isbn, Sites, Title};
+
+    pub(crate) fn sample_book(n: u128) -> Book<String> {
+        Book {
+            authors: vec![Author::new("Бронте Шарлотта".to_string())],
+            isbn: Isbn::try_from("978-5-9268-3015-3".to_string()).expect("isbn"),
+            source: format!("https://www.labirint.ru/books/{n}/"),
+            requested_url: None,
+            source_id: Some(n.to_string()),
+            title: Title::new("Джейн Эйр".to_string()),
+            original_title: None,
+            publisher: None,
+            series: None,
+            series_index: None,
+            language: None,
+            site: Sites::Labirint,
+            description: Description::new("Роман".to_string()),
+            price: Price::from(10_000 + n),
+            price_max: None,
+            price_is_from: false,
+            about_author: None,
+            rating: Some(4.5),
+            review_count: Some(32),
+            cover_image_url: None,
+            cover_path: None,
+            availability: None,
+        }
+    }
+
+    /// Записывает, после какой по счёту книги пришёл каждый `flush`.
+    #[derive(Default)]
+    struct FlushLog {
+        written: usize,
+        flushed_at: Vec<usize>,
+        finished: bool,
+    }
+
+    impl BookSink for FlushLog {
+        fn write_book(&mut self, _book: &Book<String>) -> anyhow::Result<()> {
+            self.written += 1;
+            Ok(())
+        }
+
+        fn finish(&mut self) -> anyhow::Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+
+        fn flush(&mut self) -> anyhow::Result<()> {
+            self.flushed_at.push(self.written);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_every_n_books() {
+        let mut sink = FlushEvery::new(FlushLog::default(), 3);
+        for n in 1..=10 {
+            sink.write_book(&sample_book(n)).expect("write");
+        }
+        assert_eq!(sink.inner.flushed_at, [3, 6, 9]);
+        sink.finish().expect("finish");
+        assert!(sink.inner.finished);
+
+        let mut every_book = FlushEvery::new(FlushLog::default(), 1);
+        for n in 1..=3 {
+            every_book.write_book(&sample_book(n)).expect("write");
+        }
+        assert_eq!(every_book.inner.flushed_at, [1, 2, 3]);
+    }
+
+    #[test]
+    fn csv_flush_reaches_the_writer() {
+        let path = std::env::temp_dir().join(format!("bd_parser_flush_{}.csv", std::process::id()));
+        let file = File::create(&path).expect("create");
+        let mut sink = FlushEvery::new(CsvSink::new(BufWriter::new(file), false).expect("sink"), 2);
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&sample_book(2)).expect("write");
+        let on_disk = std::fs::read_to_string(&path).expect("read");
+        assert_eq!(on_disk.lines().count(), 3, "header and two books flushed");
+        sink.write_book(&sample_book(3)).expect("write");
+        assert_eq!(
+            std::fs::read_to_string(&path)
+                .expect("read")
+                .lines()
+                .count(),
+            3
+        );
+        sink.finish().expect("finish");
+        assert_eq!(
+            std::fs::read_to_string(&path)
+                .expect("read")
+                .lines()
+                .count(),
+            4
+        );
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn stdout_sink_writes_csv_to_given_writer() {
+        let mut stdout = vec![];
+        let mut sink = writer_sink(
+            OutputFormat::Csv,
+            &mut stdout,
+            false,
+            false,
+            PriceFormat::default(),
+        )
+        .expect("sink");
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&sample_book(2)).expect("write");
+        sink.finish().expect("finish");
+        drop(sink);
+
+        let mut rdr = csv::Reader::from_reader(stdout.as_slice());
+        assert_eq!(
+            rdr.headers().expect("headers").iter().collect::<Vec<_>>(),
+            BOOK_CSV_HEADERS
+        );
+        let sources: Vec<String> = rdr
+            .records()
+            .map(|row| row.expect("row")[1].to_string())
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "https://www.labirint.ru/books/1/",
+                "https://www.labirint.ru/books/2/"
+            ]
+        );
+    }
+
+    fn write_json(mode: JsonArrayMode, count: u128) -> String {
+        let mut buf = vec![];
+        let mut sink = JsonArraySink::new(&mut buf, mode);
+        for n in 0..count {
+            sink.write_book(&sample_book(n)).expect("write");
+        }
+        sink.finish().expect("finish");
+        String::from_utf8(buf).expect("utf8")
+    }
+
+    #[test]
+    fn json_array_parses_with_expected_length() {
+        for mode in [JsonArrayMode::Buffered, JsonArrayMode::Streaming] {
+            let text = write_json(mode, 3);
+            let value: serde_json::Value = serde_json::from_str(&text).expect("valid json");
+            let books = value.as_array().expect("array");
+            assert_eq!(books.len(), 3, "{mode:?}");
+            assert_eq!(books[1]["source"], "https://www.labirint.ru/books/1/");
+            assert_eq!(books[1]["site"], "labirint");
+            assert_eq!(books[1]["price"], 10001);
+            assert_eq!(books[1]["_schema"], BOOK_SCHEMA_VERSION);
+            assert!(
+                text.contains(&format!(
+                    "\"_schema\": {BOOK_SCHEMA_VERSION},\n    \"authors\""
+                )),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn json_human_prices() {
+        let mut buf = vec![];
+        let mut sink = JsonArraySink::new(&mut buf, JsonArrayMode::Buffered)
+            .with_price_format(PriceFormat::Rubles);
+        sink.write_book(&sample_book(1_000_000)).expect("write");
+        sink.finish().expect("finish");
+        let value: serde_json::Value = serde_json::from_slice(&buf).expect("valid json");
+        assert_eq!(value[0]["price"], "10 100,00 ₽");
+    }
+
+    #[test]
+    fn json_array_streaming_matches_buffered() {
+        assert_eq!(
+            write_json(JsonArrayMode::Streaming, 2),
+            write_json(JsonArrayMode::Buffered, 2)
+        );
+        assert_eq!(
+            write_json(JsonArrayMode::Streaming, 0),
+            write_json(JsonArrayMode::Buffered, 0)
+        );
+    }
+
+    /// Буфер, который остаётся доступен тесту после передачи в приёмник.
+    #[derive(Clone, Default)]
+    struct SharedBuf(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn partitioned_sink_routes_books_by_site() {
+        let buffers: std::rc::Rc<std::cell::RefCell<HashMap<Sites, SharedBuf>>> =
+            Default::default();
+        let opened = std::rc::Rc::clone(&buffers);
+        let mut sink = PartitionedSink::new(move |site| {
+            let buf = SharedBuf::default();
+            opened.borrow_mut().insert(site, buf.clone());
+            CsvSink::new(buf, false)
+        });
+        let mut eksmo = sample_book(2);
+        eksmo.site = Sites::Eksmo;
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&eksmo).expect("write");
+        sink.write_book(&sample_book(3)).expect("write");
+        sink.finish().expect("finish");
+
+        let buffers = buffers.borrow();
+        assert_eq!(buffers.len(), 2);
+        let text = |site| String::from_utf8(buffers[&site].0.borrow().clone()).expect("utf8");
+        let labirint = text(Sites::Labirint);
+        let header = BOOK_CSV_HEADERS.join(",");
+        assert_eq!(labirint.matches(header.as_str()).count(), 1);
+        assert_eq!(labirint.lines().count(), 3);
+        assert!(labirint.lines().skip(1).all(|l| l.starts_with("labirint,")));
+        let eksmo = text(Sites::Eksmo);
+        assert_eq!(eksmo.lines().count(), 2);
+        assert!(eksmo.lines().nth(1).expect("row").starts_with("eksmo,"));
+    }
+
+    fn sorted_sources(books: &[Book<String>], key: SortKey) -> Vec<&str> {
+        let mut refs: Vec<&Book<String>> = books.iter().collect();
+        sort_books(&mut refs, key);
+        refs.iter().map(|b| b.source.as_str()).collect()
+    }
+
+    fn unsorted_books() -> Vec<Book<String>> {
+        let mut books: Vec<_> = [3, 1, 2].into_iter().map(sample_book).collect();
+        books[0].isbn = Isbn::try_from("978-5-04-156838-2".to_string()).expect("isbn");
+        books[0].title = Title::new("Анна Каренина".to_string());
+        books[2].title = Title::new("Бесы".to_string());
+        books
+    }
+
+    #[test]
+    fn sort_by_isbn() {
+        // У книг 1 и 2 одинаковый ISBN: они упорядочены по `source`.
+        assert_eq!(
+            sorted_sources(&unsorted_books(), SortKey::Isbn),
+            [
+                "https://www.labirint.ru/books/3/",
+                "https://www.labirint.ru/books/1/",
+                "https://www.labirint.ru/books/2/"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_title() {
+        assert_eq!(
+            sorted_sources(&unsorted_books(), SortKey::Title),
+            [
+                "https://www.labirint.ru/books/3/",
+                "https://www.labirint.ru/books/2/",
+                "https://www.labirint.ru/books/1/"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_price() {
+        assert_eq!(
+            sorted_sources(&unsorted_books(), SortKey::Price),
+            [
+                "https://www.labirint.ru/books/1/",
+                "https://www.labirint.ru/books/2/",
+                "https://www.labirint.ru/books/3/"
+            ]
+        );
+    }
+
+    #[test]
+    fn sort_by_source() {
+        let mut books = unsorted_books();
+        books[1].source = "https://eksmo.ru/book/9/".to_string();
+        assert_eq!(
+            sorted_sources(&books, SortKey::Source),
+            [
+                "https://eksmo.ru/book/9/",
+                "https://www.labirint.ru/books/2/",
+                "https://www.labirint.ru/books/3/"
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_sink_writes_header_and_rows() {
+        let mut buf = vec![];
+        let mut sink = CsvSink::new(&mut buf, false).expect("sink");
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.finish().expect("finish");
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some(BOOK_CSV_HEADERS.join(",").as_str()));
+        assert!(lines.next().expect("row").starts_with("labirint,"));
+    }
+
+    #[test]
+    fn atomic_csv_sink_renames_on_finish() {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_atomic_{}.csv", std::process::id()));
+        let mut sink = CsvSink::create_atomic(&path, false, false).expect("sink");
+        sink.write_book(&sample_book(1)).expect("write");
+        assert!(!path.exists());
+        assert!(pending_path(&path).exists());
+        sink.finish().expect("finish");
+        assert!(!pending_path(&path).exists());
+        let text = std::fs::read_to_string(&path).expect("read");
+        assert_eq!(text.lines().count(), 2);
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn csv_bom_is_written_once_and_skipped_by_readers() {
+        for bom in [false, true] {
+            let mut buf = vec![];
+            let mut sink = if bom {
+                CsvSink::new_with_bom(&mut buf, false)
+            } else {
+                CsvSink::new(&mut buf, false)
+            }
+            .expect("sink");
+            sink.write_book(&sample_book(1)).expect("write");
+            sink.write_book(&sample_book(2)).expect("write");
+            sink.finish().expect("finish");
+            drop(sink);
+
+            assert_eq!(buf.starts_with(UTF8_BOM.as_bytes()), bom);
+            let text = String::from_utf8(buf).expect("utf8");
+            assert_eq!(text.matches(UTF8_BOM).count(), usize::from(bom));
+            let mut rdr = csv::Reader::from_reader(text.as_bytes());
+            assert_eq!(&rdr.headers().expect("headers")[0], "site");
+            let rows: Vec<csv::StringRecord> = rdr.records().map(|r| r.expect("row")).collect();
+            assert_eq!(rows.len(), 2);
+            assert_eq!(&rows[0][0], "labirint");
+        }
+    }
+
+    #[test]
+    fn csv_sink_with_flags_extends_header() {
+        let mut buf = vec![];
+        let mut sink = CsvSink::new(&mut buf, true).expect("sink");
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.finish().expect("finish");
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        let header = text.lines().next().expect("header");
+        assert!(header.ends_with(",has_authors,has_isbn,has_title,has_description,has_price"));
+        assert!(
+            text.lines()
+                .nth(1)
+                .expect("row")
+                .ends_with(",true,true,true,true,true")
+        );
+    }
+
+    #[test]
+    fn price_filter_boundaries_are_inclusive() {
+        let filter = PriceFilter {
+            min: Some(10_005),
+            max: Some(10_010),
+            drop_priceless: false,
+        };
+        let accepted: Vec<u128> = (0..15)
+            .filter(|&n| filter.accepts(&sample_book(n)))
+            .collect();
+        assert_eq!(accepted, (5..=10).collect::<Vec<_>>());
+
+        let only_min = PriceFilter {
+            min: Some(10_005),
+            ..PriceFilter::default()
+        };
+        assert!(!only_min.accepts(&sample_book(4)));
+        assert!(only_min.accepts(&sample_book(1_000_000)));
+        assert!(!PriceFilter::default().is_active());
+        assert!(only_min.is_active());
+    }
+
+    #[test]
+    fn priceless_books_follow_drop_flag() {
+        let mut priceless = sample_book(0);
+        priceless.price = Price::from(0);
+        let keep = PriceFilter {
+            min: Some(100),
+            max: Some(200),
+            drop_priceless: false,
+        };
+        assert!(keep.accepts(&priceless));
+        let drop = PriceFilter {
+            drop_priceless: true,
+            ..keep
+        };
+        assert!(!drop.accepts(&priceless));
+        assert!(
+            !PriceFilter {
+                drop_priceless: true,
+                ..PriceFilter::default()
+            }
+            .accepts(&priceless)
+        );
+    }
+
+    #[test]
+    fn filtered_sink_drops_books_outside_range() {
+        let mut buf = vec![];
+        let filter = PriceFilter {
+            max: Some(10_001),
+            ..PriceFilter::default()
+        };
+        let mut sink = FilteredSink::new(CsvSink::new(&mut buf, false).expect("sink"), filter);
+        for n in 0..4 {
+            sink.write_book(&sample_book(n)).expect("write");
+        }
+        sink.finish().expect("finish");
+        assert_eq!(sink.dropped(), 2);
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        assert_eq!(text.lines().count(), 3);
+    }
+
+    #[test]
+    fn language_filter_keeps_only_chosen_languages() {
+        let with_language = |n, language: Option<&str>| Book {
+            language: language.map(str::to_string),
+            ..sample_book(n)
+        };
+        let batch = [
+            with_language(1, Some("ru")),
+            with_language(2, Some("en")),
+            with_language(3, Some("ru,en")),
+            with_language(4, Some("de")),
+            with_language(5, None),
+        ];
+        let filter = LanguageFilter {
+            languages: BTreeSet::from(["ru".to_string()]),
+        };
+        let mut buf = vec![];
+        let mut sink =
+            LanguageFilterSink::new(CsvSink::new(&mut buf, false).expect("sink"), filter.clone());
+        for book in &batch {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+        assert_eq!(sink.dropped(), 2);
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        assert_eq!(text.lines().count(), 4);
+
+        let kept: Vec<u128> = batch
+            .iter()
+            .filter(|book| filter.accepts(book))
+            .map(|book| u128::from(book.price))
+            .collect();
+        assert_eq!(
+            kept,
+            [1, 3, 5].map(|n| u128::from(sample_book(n).price)).to_vec()
+        );
+        assert!(!LanguageFilter::default().is_active());
+        assert!(LanguageFilter::default().accepts(&batch[3]));
+    }
+
+    #[test]
+    fn quality_gate_require_title() {
+        let gate = QualityGate {
+            require_title: true,
+            ..QualityGate::default()
+        };
+        let mut untitled = sample_book(1);
+        untitled.title = Title::new("  ".to_string());
+        assert_eq!(gate.rejects(&sample_book(1)), None);
+        assert_eq!(gate.rejects(&untitled), Some("no title"));
+        assert_eq!(QualityGate::default().rejects(&untitled), None);
+    }
+
+    #[test]
+    fn quality_gate_require_price() {
+        let gate = QualityGate {
+            require_price: true,
+            ..QualityGate::default()
+        };
+        let mut priceless = sample_book(1);
+        priceless.price = Price::from(0);
+        assert_eq!(gate.rejects(&sample_book(1)), None);
+        assert_eq!(gate.rejects(&priceless), Some("no price"));
+    }
+
+    #[test]
+    fn quality_gate_min_authors() {
+        let gate = QualityGate {
+            min_authors: 2,
+            ..QualityGate::default()
+        };
+        let mut coauthored = sample_book(1);
+        coauthored.authors.push(coauthored.authors[0].clone());
+        assert_eq!(gate.rejects(&sample_book(1)), Some("too few authors"));
+        assert_eq!(gate.rejects(&coauthored), None);
+    }
+
+    #[test]
+    fn quality_gate_sink_counts_dropped_by_reason() {
+        let mut buf = vec![];
+        let gate = QualityGate {
+            require_title: true,
+            require_price: true,
+            min_authors: 1,
+        };
+        let mut sink = QualityGateSink::new(CsvSink::new(&mut buf, false).expect("sink"), gate);
+        let mut untitled = sample_book(2);
+        untitled.title = Title::new(String::new());
+        let mut priceless = sample_book(3);
+        priceless.price = Price::from(0);
+        let mut anonymous = sample_book(4);
+        anonymous.authors.clear();
+        for book in [&sample_book(1), &untitled, &priceless, &anonymous] {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+        assert_eq!(
+            sink.dropped(),
+            &BTreeMap::from([("no price", 1), ("no title", 1), ("too few authors", 1)])
+        );
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        assert_eq!(text.lines().count(), 2);
+    }
+
+    #[test]
+    fn idempotent_csv_keeps_one_row_per_isbn() {
+        let mut buf = vec![];
+        let mut sink = IdempotentSink::new(CsvSink::new(&mut buf, false).expect("sink"));
+        let mut other_isbn = sample_book(2);
+        other_isbn.isbn = Isbn::try_from("9785041234567".to_string()).expect("isbn");
+        for book in [&sample_book(1), &sample_book(1), &other_isbn] {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+        assert_eq!(sink.duplicates(), 1);
+        drop(sink);
+        let text = String::from_utf8(buf).expect("utf8");
+        assert_eq!(text.lines().count(), 3);
+    }
+
+    #[test]
+    fn idempotent_winner_does_not_depend_on_completion_order() {
+        let mut plain = sample_book(1);
+        plain.isbn = Isbn::try_from("9785926830153".to_string()).expect("isbn");
+        let mut other_isbn = sample_book(3);
+        other_isbn.isbn = Isbn::try_from("978-5-04-156838-2".to_string()).expect("isbn");
+        let books = [sample_book(2), plain, other_isbn];
+        let write = |order: [usize; 3]| {
+            let mut refs: Vec<&Book<String>> = order.iter().map(|&i| &books[i]).collect();
+            sort_books(&mut refs, SortKey::Isbn);
+            let mut buf = vec![];
+            let mut sink = IdempotentSink::new(CsvSink::new(&mut buf, false).expect("sink"));
+            for book in refs {
+                sink.write_book(book).expect("write");
+            }
+            sink.finish().expect("finish");
+            drop(sink);
+            String::from_utf8(buf).expect("utf8")
+        };
+        let first = write([0, 1, 2]);
+        for order in [[0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]] {
+            assert_eq!(write(order), first, "{order:?}");
+        }
+        // Из двух записей 978-5-9268-3015-3 остаётся та, у которой меньше `source`.
+        assert!(first.contains("/books/1/"));
+        assert!(!first.contains("/books/2/"));
+        assert_eq!(first.lines().count(), 3);
+    }
+
+    #[test]
+    fn idempotent_json_keeps_one_element_per_isbn() {
+        for mode in [JsonArrayMode::Buffered, JsonArrayMode::Streaming] {
+            let mut buf = vec![];
+            let mut sink = IdempotentSink::new(JsonArraySink::new(&mut buf, mode));
+            // Тот же ISBN в другой записи — та же книга, например с другого сайта.
+            let mut same_isbn = sample_book(2);
+            same_isbn.isbn = Isbn::try_from("9785926830153".to_string()).expect("isbn");
+            for book in [&sample_book(1), &sample_book(1), &same_isbn] {
+                sink.write_book(book).expect("write");
+            }
+            sink.finish().expect("finish");
+            drop(sink);
+            let value: serde_json::Value = serde_json::from_slice(&buf).expect("json");
+            let books = value.as_array().expect("array");
+            assert_eq!(books.len(), 1, "{mode:?}");
+            assert_eq!(books[0]["source"], "https://www.labirint.ru/books/1/");
+        }
+    }
+}
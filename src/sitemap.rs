@@ -0,0 +1,342 @@
+use std::fmt::Display;
+
+use anyhow::{Context, anyhow};
+use quick_xml::de::from_str;
+use rand::SeedableRng;
+use rand::seq::SliceRandom;
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use tracing::{info, instrument, warn};
+
+use crate::selectors;
+
+/// `<url>` из sitemap: адрес и дата последнего изменения, если сайт её отдаёт.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SitemapEntry {
+    pub loc: String,
+    #[serde(default)]
+    pub lastmod: Option<String>,
+}
+#[derive(Debug, Deserialize)]
+struct UrlSet {
+    #[serde(rename = "url")]
+    urls: Vec<SitemapEntry>,
+}
+
+/// Проверяет, что sitemap действительно вернул XML, а не HTML-страницу ошибки
+/// (soft 404 со статусом 200).
+pub fn ensure_xml(sitemap: &str, content_type: Option<&str>, body: &str) -> anyhow::Result<()> {
+    let start = body.trim_start_matches('\u{feff}').trim_start();
+    let html_type = content_type.is_some_and(|ct| ct.contains("html"));
+    if html_type || !(start.starts_with("<?xml") || start.starts_with("<urlset")) {
+        return Err(anyhow!("sitemap {sitemap} did not return XML"));
+    }
+    Ok(())
+}
+
+/// URL из стандартного sitemap (`<urlset><url><loc>`).
+pub fn parse_urlset(
+    sitemap: &str,
+    content_type: Option<&str>,
+    body: &str,
+) -> anyhow::Result<Vec<String>> {
+    Ok(parse_urlset_entries(sitemap, content_type, body)?
+        .into_iter()
+        .map(|u| u.loc)
+        .collect())
+}
+
+/// Записи стандартного sitemap вместе с `<lastmod>`.
+pub fn parse_urlset_entries(
+    sitemap: &str,
+    content_type: Option<&str>,
+    body: &str,
+) -> anyhow::Result<Vec<SitemapEntry>> {
+    ensure_xml(sitemap, content_type, body)?;
+    let urlset: UrlSet =
+        from_str(body).with_context(|| format!("can't parse sitemap {sitemap}"))?;
+    Ok(urlset.urls)
+}
+
+/// Скачивает sitemap и возвращает тело, если это XML.
+pub async fn fetch_xml(sitemap: &str) -> anyhow::Result<String> {
+    let resp = reqwest::get(sitemap)
+        .await
+        .with_context(|| format!("GET sitemap {sitemap} failed"))?
+        .error_for_status()
+        .with_context(|| format!("non-success status for sitemap {sitemap}"))?;
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let body = resp
+        .text()
+        .await
+        .with_context(|| format!("reading sitemap {sitemap} body failed"))?;
+    ensure_xml(sitemap, content_type.as_deref(), &body)?;
+    Ok(body)
+}
+
+#[instrument]
+pub async fn fetch_urlset(sitemap: &str) -> anyhow::Result<Vec<SitemapEntry>> {
+    let body = fetch_xml(sitemap).await?;
+    let urls = parse_urlset_entries(sitemap, None, &body)?;
+    info!(target: "time", count = urls.len(), "fetched sitemap urls");
+    Ok(urls)
+}
+
+/// Собирает URL из нескольких sitemap; сломанные пропускаются с предупреждением,
+/// чтобы один протухший адрес не ронял весь прогон.
+pub async fn fetch_urlsets<S: AsRef<str>>(sitemaps: &[S]) -> Vec<String> {
+    fetch_urlset_entries(sitemaps)
+        .await
+        .into_iter()
+        .map(|u| u.loc)
+        .collect()
+}
+
+/// Как `fetch_urlsets`, но с `<lastmod>` каждой записи.
+pub async fn fetch_urlset_entries<S: AsRef<str>>(sitemaps: &[S]) -> Vec<SitemapEntry> {
+    let mut urls = vec![];
+    for sitemap in sitemaps {
+        match fetch_urlset(sitemap.as_ref()).await {
+            Ok(mut found) => urls.append(&mut found),
+            Err(e) => warn!("skipping sitemap: {e:#}"),
+        }
+    }
+    urls
+}
+
+/// `--sort-sitemaps-by-lastmod`: сначала самые свежие записи, чтобы обрезка до
+/// `<how_much_from_one_store>` брала их. `<lastmod>` в формате W3C Datetime сравниваются
+/// как строки: у sitemap одного сайта формат и часовой пояс одни и те же. Записи без
+/// `<lastmod>` идут в конце; сортировка устойчивая, так что среди них и среди записей
+/// с одной датой остаётся порядок sitemap.
+pub fn newest_first(mut entries: Vec<SitemapEntry>) -> Vec<String> {
+    entries.sort_by(|a, b| {
+        let key = |e: &SitemapEntry| e.lastmod.as_deref().map(str::trim).map(str::to_owned);
+        key(b).cmp(&key(a))
+    });
+    entries.into_iter().map(|u| u.loc).collect()
+}
+
+/// Подстроки в URL игры слов, по которым отличаются книги (переплёты) от прочих товаров.
+pub static IGRASLOV_BOOK_INDICATORS: [&str; 4] = ["tvyord", "klap", "myagk", "super"];
+
+/// URL из sitemap игры слов: `<loc>` там завёрнуты в CDATA, которые HTML-парсер видит
+/// как комментарии. С `indicators` сохраняются только URL, содержащие одну из подстрок,
+/// без — все товары.
+pub fn extract_igraslov_urls(
+    body: &str,
+    indicators: Option<&[&str]>,
+) -> anyhow::Result<Vec<String>> {
+    let html = scraper::Html::parse_document(body);
+
+    let mut books_urls = vec![];
+    for (i, nodes) in html.select(selectors::loc()).enumerate() {
+        let Some(noderef) = nodes.first_child() else {
+            warn!(index = i, "can't fetch url node");
+            continue;
+        };
+        let url_comment = noderef
+            .value()
+            .as_comment()
+            .ok_or_else(|| anyhow!("expected comment node for <loc>"))?;
+        let url = url_comment.replace("[CDATA[", "").replace("]]", "");
+        let url = url.trim();
+        if indicators.is_none_or(|pats| pats.iter().any(|pat| url.contains(pat))) {
+            books_urls.push(url.to_string());
+        }
+    }
+    Ok(books_urls)
+}
+
+pub async fn fetch_igraslov_urls(
+    sitemap: &str,
+    indicators: Option<&[&str]>,
+) -> anyhow::Result<Vec<String>> {
+    let body = fetch_xml(sitemap).await?;
+    let urls = extract_igraslov_urls(&body, indicators)?;
+    info!(count = urls.len(), "fetched url's from igraslov sitemap");
+    Ok(urls)
+}
+
+/// Оставляет не больше `limit` URL сайта. С `seed` список сначала перемешивается:
+/// sitemap сгруппированы по категориям, и без этого обрезка берёт только первые из них.
+/// `ChaCha8Rng` даёт одинаковый порядок для одного `seed` на любой платформе.
+pub fn sample_urls(mut urls: Vec<String>, limit: usize, seed: Option<u64>) -> Vec<String> {
+    if let Some(seed) = seed {
+        urls.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+    }
+    urls.truncate(limit);
+    urls
+}
+
+/// Проверяет, что у сайтов нашлись URL. Пустой сайт (лежит или поменял формат sitemap)
+/// только громко логируется, чтобы остальные сайты отработали; ошибка — если пусты все.
+pub fn check_site_urls<S: Display>(sites: &[(S, Vec<String>)]) -> anyhow::Result<()> {
+    let mut empty = vec![];
+    for (site, urls) in sites {
+        if urls.is_empty() {
+            warn!(%site, "NO URLS FOUND FOR SITE, skipping it: sitemap is empty, site is down or its format changed");
+            empty.push(site.to_string());
+        }
+    }
+    if empty.len() == sites.len() {
+        return Err(anyhow!(
+            "no urls found for any site ({}), nothing to parse",
+            empty.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://eksmo.ru/book/1/</loc></url>
+  <url><loc>https://eksmo.ru/book/2/</loc></url>
+</urlset>"#;
+
+    const SOFT_404: &str = "<!DOCTYPE html><html><body><h1>Страница не найдена</h1></body></html>";
+
+    #[test]
+    fn html_body_is_rejected() {
+        let err = parse_urlset("https://eksmo.ru/sitemap/books9.xml", None, SOFT_404)
+            .expect_err("html is not a sitemap");
+        assert_eq!(
+            err.to_string(),
+            "sitemap https://eksmo.ru/sitemap/books9.xml did not return XML"
+        );
+        assert!(ensure_xml("s", Some("text/html; charset=utf-8"), URLSET).is_err());
+    }
+
+    #[test]
+    fn urlset_is_parsed() {
+        let urls = parse_urlset("s", Some("application/xml"), URLSET).expect("urls");
+        assert_eq!(
+            urls,
+            ["https://eksmo.ru/book/1/", "https://eksmo.ru/book/2/"]
+        );
+    }
+
+    const IGRASLOV_SITEMAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc><![CDATA[https://igraslov.store/product/dzhejn-ejr-tvyordyj/]]></loc></url>
+  <url><loc><![CDATA[https://igraslov.store/product/shopper-bag/]]></loc></url>
+  <url><loc><![CDATA[ https://igraslov.store/product/idiot-myagkaya/ ]]></loc></url>
+</urlset>"#;
+
+    #[test]
+    fn igraslov_urls_filtered_by_indicators() {
+        let urls =
+            extract_igraslov_urls(IGRASLOV_SITEMAP, Some(&IGRASLOV_BOOK_INDICATORS)).expect("urls");
+        assert_eq!(
+            urls,
+            [
+                "https://igraslov.store/product/dzhejn-ejr-tvyordyj/",
+                "https://igraslov.store/product/idiot-myagkaya/"
+            ]
+        );
+    }
+
+    #[test]
+    fn igraslov_urls_unfiltered() {
+        let urls = extract_igraslov_urls(IGRASLOV_SITEMAP, None).expect("urls");
+        assert_eq!(urls.len(), 3);
+        assert_eq!(urls[1], "https://igraslov.store/product/shopper-bag/");
+    }
+
+    #[test]
+    fn lastmod_sorts_newest_first() {
+        let body = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url><loc>https://eksmo.ru/book/old/</loc><lastmod>2023-01-15</lastmod></url>
+  <url><loc>https://eksmo.ru/book/undated-1/</loc></url>
+  <url><loc>https://eksmo.ru/book/new/</loc><lastmod>2024-06-02T10:00:00+03:00</lastmod></url>
+  <url><loc>https://eksmo.ru/book/undated-2/</loc></url>
+  <url><loc>https://eksmo.ru/book/mid/</loc><lastmod>2024-06-01</lastmod></url>
+</urlset>"#;
+        let entries = parse_urlset_entries("s", None, body).expect("entries");
+        assert_eq!(entries[0].lastmod.as_deref(), Some("2023-01-15"));
+        assert_eq!(entries[1].lastmod, None);
+        assert_eq!(
+            newest_first(entries),
+            [
+                "https://eksmo.ru/book/new/",
+                "https://eksmo.ru/book/mid/",
+                "https://eksmo.ru/book/old/",
+                "https://eksmo.ru/book/undated-1/",
+                "https://eksmo.ru/book/undated-2/",
+            ]
+        );
+    }
+
+    #[test]
+    fn shuffle_with_seed_is_deterministic() {
+        let urls: Vec<String> = (0..50)
+            .map(|n| format!("https://eksmo.ru/book/{n}/"))
+            .collect();
+        let first = sample_urls(urls.clone(), usize::MAX, Some(42));
+        assert_eq!(first, sample_urls(urls.clone(), usize::MAX, Some(42)));
+        assert_ne!(first, urls);
+
+        let mut sorted = first.clone();
+        sorted.sort();
+        let mut expected = urls.clone();
+        expected.sort();
+        assert_eq!(sorted, expected);
+
+        assert_eq!(sample_urls(urls.clone(), 10, None), urls[..10]);
+        assert_eq!(sample_urls(urls, 10, Some(42)), first[..10]);
+    }
+
+    #[tokio::test]
+    async fn broken_sitemap_is_skipped() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/books1.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SOFT_404, "text/html"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/books2.xml"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(URLSET, "application/xml"))
+            .mount(&server)
+            .await;
+        let sitemaps = [
+            format!("{}/books1.xml", server.uri()),
+            format!("{}/books2.xml", server.uri()),
+        ];
+        assert_eq!(fetch_urlsets(&sitemaps).await.len(), 2);
+    }
+
+    #[test]
+    fn one_empty_site_is_skipped() {
+        let sites = [
+            (
+                "labirint",
+                vec!["https://www.labirint.ru/books/1/".to_string()],
+            ),
+            ("eksmo", vec![]),
+        ];
+        assert!(check_site_urls(&sites).is_ok());
+    }
+
+    #[test]
+    fn all_empty_sites_fail_the_run() {
+        let sites: [(&str, Vec<String>); 2] = [("labirint", vec![]), ("eksmo", vec![])];
+        let err = check_site_urls(&sites).expect_err("nothing to parse");
+        assert_eq!(
+            err.to_string(),
+            "no urls found for any site (labirint, eksmo), nothing to parse"
+        );
+        assert!(check_site_urls::<&str>(&[]).is_err());
+    }
+}
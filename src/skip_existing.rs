@@ -0,0 +1,166 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{Context, anyhow};
+use tracing::info;
+
+use crate::parse_traits::Book;
+use crate::sink::{BookSink, isbn_key};
+
+/// ISBN из прошлой выгрузки для `--skip-existing`: CSV с колонкой `isbn`, JSONL
+/// (объект на строку) или JSON-массив `--output-pretty-json`. Формат — по расширению.
+pub fn load_existing_isbns(path: &Path) -> anyhow::Result<HashSet<String>> {
+    let isbns: Vec<String> = match path.extension().and_then(|e| e.to_str()) {
+        Some("csv") => {
+            let mut rdr = csv::Reader::from_path(path)
+                .with_context(|| format!("can't open {}", path.display()))?;
+            let idx = rdr
+                .headers()?
+                .iter()
+                .position(|h| h == "isbn")
+                .ok_or_else(|| anyhow!("{} has no isbn column", path.display()))?;
+            let mut isbns = vec![];
+            for row in rdr.records() {
+                let row = row.with_context(|| format!("bad csv {}", path.display()))?;
+                isbns.extend(row.get(idx).map(str::to_string));
+            }
+            isbns
+        }
+        Some("jsonl") => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("can't read {}", path.display()))?;
+            let mut isbns = vec![];
+            for (n, line) in text.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let value: serde_json::Value = serde_json::from_str(line)
+                    .with_context(|| format!("bad json on line {} of {}", n + 1, path.display()))?;
+                isbns.extend(value["isbn"].as_str().map(str::to_string));
+            }
+            isbns
+        }
+        Some("json") => {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("can't read {}", path.display()))?;
+            let books: Vec<serde_json::Value> = serde_json::from_str(&text)
+                .with_context(|| format!("{} is not a json array", path.display()))?;
+            books
+                .iter()
+                .filter_map(|book| book["isbn"].as_str().map(str::to_string))
+                .collect()
+        }
+        _ => {
+            return Err(anyhow!(
+                "--skip-existing expects a .csv, .jsonl or .json file, got {}",
+                path.display()
+            ));
+        }
+    };
+    Ok(isbns
+        .iter()
+        .map(|isbn| isbn_key(isbn))
+        .filter(|key| !key.is_empty())
+        .collect())
+}
+
+/// Не пропускает в `inner` книги, чей ISBN уже есть в прошлой выгрузке. Дополняет
+/// `--resume-from-checkpoint`: там сравниваются URL одного прогона, здесь — ISBN между прогонами.
+pub struct SkipExistingSink<S> {
+    inner: S,
+    existing: HashSet<String>,
+    skipped: usize,
+}
+
+impl<S: BookSink> SkipExistingSink<S> {
+    pub fn new(inner: S, existing: HashSet<String>) -> Self {
+        Self {
+            inner,
+            existing,
+            skipped: 0,
+        }
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+impl<S: BookSink> BookSink for SkipExistingSink<S> {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        if self.existing.contains(&isbn_key(book.isbn.as_str())) {
+            self.skipped += 1;
+            return Ok(());
+        }
+        self.inner.write_book(book)
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        info!(
+            skipped = self.skipped,
+            "books already present in the previous output skipped"
+        );
+        self.inner.finish()
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::Isbn;
+    use crate::sink::CsvSink;
+    use crate::sink::tests::sample_book;
+
+    fn temp_file(name: &str, text: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("bd_parser_{}_{name}", std::process::id()));
+        std::fs::write(&path, text).expect("write");
+        path
+    }
+
+    #[test]
+    fn existing_isbns_load_from_csv_and_jsonl() {
+        let csv = temp_file(
+            "existing.csv",
+            "site,source,isbn\nlabirint,https://www.labirint.ru/books/1/,978-5-9268-3015-3\n",
+        );
+        let jsonl = temp_file(
+            "existing.jsonl",
+            "{\"isbn\":\"9785041234567\"}\n\n{\"title\":\"без isbn\"}\n",
+        );
+        assert_eq!(
+            load_existing_isbns(&csv).expect("csv"),
+            HashSet::from(["9785926830153".to_string()])
+        );
+        assert_eq!(
+            load_existing_isbns(&jsonl).expect("jsonl"),
+            HashSet::from(["9785041234567".to_string()])
+        );
+        assert!(load_existing_isbns(Path::new("books.txt")).is_err());
+        std::fs::remove_file(&csv).expect("cleanup");
+        std::fs::remove_file(&jsonl).expect("cleanup");
+    }
+
+    #[test]
+    fn new_batch_is_filtered_against_existing() {
+        let existing = HashSet::from(["9785926830153".to_string()]);
+        let mut buf = vec![];
+        let mut sink =
+            SkipExistingSink::new(CsvSink::new(&mut buf, false).expect("sink"), existing);
+        let mut fresh = sample_book(2);
+        fresh.isbn = Isbn::try_from("978-5-04-123456-7".to_string()).expect("isbn");
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.write_book(&fresh).expect("write");
+        sink.finish().expect("finish");
+        assert_eq!(sink.skipped(), 1);
+        drop(sink);
+
+        let text = String::from_utf8(buf).expect("utf8");
+        let rows: Vec<&str> = text.lines().skip(1).collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].contains("https://www.labirint.ru/books/2/"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use crate::parse_traits::{
+    Isbn, PageHtml, Title, normalize_ws, parse_roman, series_index_from_text,
+};
+
+/// Подписи строк таблицы характеристик, по которым берутся поля. Сравниваются без учёта
+/// регистра и двоеточия, так что порядок строк и мелкие различия вёрстки не важны.
+pub static ISBN_LABELS: &[&str] = &["ISBN", "ISBN/ISSN"];
+pub static PUBLISHER_LABELS: &[&str] = &["Издательство"];
+pub static LANGUAGE_LABELS: &[&str] = &["Язык", "Язык издания"];
+pub static SERIES_LABELS: &[&str] = &["Серия", "Цикл"];
+pub static SERIES_INDEX_LABELS: &[&str] =
+    &["Номер в серии", "Номер книги в серии", "Книга в серии"];
+pub static ORIGINAL_TITLE_LABELS: &[&str] = &[
+    "Оригинальное название",
+    "Название оригинала",
+    "Название на языке оригинала",
+    "Original title",
+];
+
+/// Таблица характеристик страницы (`parse_specs` сайта): подпись строки → значение.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Specs(HashMap<String, String>);
+
+impl From<HashMap<String, String>> for Specs {
+    fn from(map: HashMap<String, String>) -> Self {
+        Self(map)
+    }
+}
+
+impl Specs {
+    /// Значение первой из `labels`, которая есть в таблице.
+    pub fn get(&self, labels: &[&str]) -> Option<&str> {
+        labels.iter().find_map(|label| {
+            let label = label.to_lowercase();
+            self.0
+                .iter()
+                .find(|(name, _)| name.to_lowercase() == label)
+                .map(|(_, value)| value.as_str())
+        })
+    }
+
+    /// ISBN из строки «ISBN»; у сборников там бывает несколько через запятую — берётся первый валидный.
+    pub fn isbn(&self) -> Option<Isbn> {
+        self.get(ISBN_LABELS)?
+            .split([',', ';'])
+            .find_map(|raw| Isbn::try_from(raw.trim().to_string()).ok())
+    }
+
+    pub fn publisher(&self) -> Option<&str> {
+        self.get(PUBLISHER_LABELS)
+            .filter(|publisher| !publisher.trim().is_empty())
+    }
+
+    pub fn series(&self) -> Option<String> {
+        Some(normalize_ws(self.get(SERIES_LABELS)?)).filter(|series| !series.is_empty())
+    }
+
+    /// Номер в серии: строка «Номер в серии» (`3`, `III`, `3 из 7`), иначе подпись серии
+    /// вида «Гарри Поттер. Книга 3».
+    pub fn series_index(&self) -> Option<u16> {
+        let from_label = self.get(SERIES_INDEX_LABELS).and_then(|value| {
+            let value = value.trim();
+            leading_number(value)
+                .or_else(|| parse_roman(value))
+                .or_else(|| series_index_from_text(value))
+                .filter(|&n| n > 0)
+        });
+        from_label.or_else(|| series_index_from_text(&self.series()?))
+    }
+
+    /// Языки издания кодами ISO 639-1, см. `language_codes`.
+    pub fn language(&self) -> Option<String> {
+        Some(language_codes(self.get(LANGUAGE_LABELS)?)).filter(|codes| !codes.is_empty())
+    }
+
+    /// Название оригинала; у отечественных книг такой строки нет.
+    pub fn original_title(&self) -> Option<Title> {
+        Some(Title::new(self.get(ORIGINAL_TITLE_LABELS)?.to_string()))
+            .filter(|title| !title.as_str().is_empty())
+    }
+}
+
+/// Сайт с таблицей характеристик: как прочитать её со страницы.
+pub trait SpecsTable {
+    fn parse_specs(html: &scraper::Html) -> HashMap<String, String>;
+}
+
+/// `Context` парсеров с таблицей характеристик: DOM страницы и `Specs`, разобранные один
+/// раз при загрузке, а не в каждом `parse_*`. К DOM — через `Deref`.
+pub struct SpecsPage<S> {
+    html: scraper::Html,
+    specs: Specs,
+    site: PhantomData<fn() -> S>,
+}
+
+impl<S> SpecsPage<S> {
+    pub fn specs(&self) -> &Specs {
+        &self.specs
+    }
+}
+
+impl<S: SpecsTable> PageHtml for SpecsPage<S> {
+    fn from_body(body: &str) -> Self {
+        let html = scraper::Html::parse_document(body);
+        let specs = Specs::from(S::parse_specs(&html));
+        Self {
+            html,
+            specs,
+            site: PhantomData,
+        }
+    }
+
+    fn page_html(&self) -> String {
+        self.html.html()
+    }
+}
+
+impl<S> Deref for SpecsPage<S> {
+    type Target = scraper::Html;
+
+    fn deref(&self) -> &scraper::Html {
+        &self.html
+    }
+}
+
+/// Коды ISO 639-1 и названия языков, которые встречаются в характеристиках: русские,
+/// английские и трёхбуквенные коды ISO 639-2 (`eng`, `rus`).
+static LANGUAGE_NAMES: &[(&str, &[&str])] = &[
+    ("ru", &["русский", "russian", "rus"]),
+    ("en", &["английский", "english", "eng"]),
+    ("de", &["немецкий", "german", "deutsch", "ger", "deu"]),
+    ("fr", &["французский", "french", "fre", "fra"]),
+    ("es", &["испанский", "spanish", "spa"]),
+    ("it", &["итальянский", "italian", "ita"]),
+    ("zh", &["китайский", "chinese", "chi", "zho"]),
+    ("ja", &["японский", "japanese", "jpn"]),
+    ("ko", &["корейский", "korean", "kor"]),
+    ("uk", &["украинский", "ukrainian", "ukr"]),
+    ("be", &["белорусский", "belarusian", "bel"]),
+    ("kk", &["казахский", "kazakh", "kaz"]),
+    ("tt", &["татарский", "tatar", "tat"]),
+];
+
+/// Список языков («Русский, Английский», «русский / english») → коды через запятую (`ru,en`).
+/// Известное название или код переводится в код ISO 639-1, незнакомое остаётся как есть
+/// в нижнем регистре. Повторы убираются.
+pub fn language_codes(value: &str) -> String {
+    let mut codes: Vec<String> = vec![];
+    for name in value.split([',', ';', '/']) {
+        let name = normalize_ws(name).to_lowercase();
+        if name.is_empty() {
+            continue;
+        }
+        let code = LANGUAGE_NAMES
+            .iter()
+            .find(|(_, names)| names.contains(&name.as_str()))
+            .map_or(name, |(code, _)| code.to_string());
+        if !codes.contains(&code) {
+            codes.push(code);
+        }
+    }
+    codes.join(",")
+}
+
+fn leading_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    let digits: String = value
+        .trim_start()
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn specs(rows: &[(&str, &str)]) -> Specs {
+        Specs::from(
+            rows.iter()
+                .map(|(label, value)| (label.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn fields_by_known_labels() {
+        let specs = specs(&[
+            ("Кол-во страниц", "448 стр."),
+            ("ISBN/ISSN", "978-5-04-122366-3"),
+            ("Издательство", "Inspiria"),
+            ("год издания", "2021"),
+            ("Тип обложки", "Переплет"),
+        ]);
+        assert_eq!(
+            specs.isbn().map(|isbn| isbn.as_str().to_string()),
+            Some("978-5-04-122366-3".to_string())
+        );
+        assert_eq!(specs.publisher(), Some("Inspiria"));
+        assert_eq!(specs.original_title(), None);
+        assert_eq!((specs.series(), specs.series_index()), (None, None));
+        assert_eq!(specs.language(), None);
+    }
+
+    #[test]
+    fn language_names_become_codes() {
+        assert_eq!(
+            specs(&[("Язык", "Русский")]).language().as_deref(),
+            Some("ru")
+        );
+        assert_eq!(language_codes("Русский, Английский / русский"), "ru,en");
+        assert_eq!(language_codes("русский / english"), "ru,en");
+        assert_eq!(language_codes("ENG; Deutsch; fr"), "en,de,fr");
+        assert_eq!(language_codes("EN; Эсперанто"), "en,эсперанто");
+        assert_eq!(specs(&[("Язык", " ")]).language(), None);
+    }
+
+    #[test]
+    fn series_index_from_own_row_or_series_label() {
+        let own_row = specs(&[("Серия", "Тёмная башня"), ("Номер в серии", "IV")]);
+        assert_eq!(own_row.series().as_deref(), Some("Тёмная башня"));
+        assert_eq!(own_row.series_index(), Some(4));
+        assert_eq!(
+            specs(&[("Номер в серии", "3 из 7")]).series_index(),
+            Some(3)
+        );
+        assert_eq!(
+            specs(&[("Серия", "Гарри Поттер. Книга 2")]).series_index(),
+            Some(2)
+        );
+        assert_eq!(specs(&[("Серия", "Образ Речи")]).series_index(), None);
+    }
+
+    #[test]
+    fn isbn_takes_first_valid_of_several() {
+        let specs = specs(&[("ISBN", "нет, 978-5-04-156838-2, 978-5-04-122366-3")]);
+        assert_eq!(
+            specs.isbn().map(|isbn| isbn.as_str().to_string()),
+            Some("978-5-04-156838-2".to_string())
+        );
+        assert_eq!(Specs::default().isbn(), None);
+    }
+}
@@ -0,0 +1,462 @@
+use std::path::Path;
+use std::thread::JoinHandle;
+
+use anyhow::{Context, anyhow};
+use rusqlite::{Connection, params};
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::parse_traits::{Book, Price};
+use crate::sink::{BookSink, isbn_key};
+
+/// Книг в одной транзакции: фиксация на каждую книгу упирается в fsync,
+/// а одна транзакция на весь прогон теряет всё при падении.
+pub const BATCH_SIZE: usize = 500;
+
+/// Таблица первой версии; колонки, добавленные позже, приносит `MIGRATIONS`.
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS books (
+    site TEXT NOT NULL,
+    source TEXT PRIMARY KEY,
+    isbn TEXT NOT NULL,
+    title TEXT NOT NULL,
+    authors TEXT NOT NULL,
+    description TEXT NOT NULL,
+    price INTEGER NOT NULL,
+    about_author TEXT,
+    rating REAL,
+    review_count INTEGER,
+    source_id TEXT,
+    price_max INTEGER,
+    price_is_from INTEGER NOT NULL,
+    cover_image_url TEXT,
+    cover_path TEXT,
+    availability TEXT,
+    isbn13_group TEXT
+)";
+
+/// Книга — это ISBN: та же книга с другого сайта или по новому адресу после редиректа
+/// обновляет строку, а не добавляет вторую.
+const UPSERT_BOOK: &str = "INSERT INTO books (
+    site, source, isbn, title, authors, description, price, about_author, rating,
+    review_count, source_id, price_max, price_is_from, cover_image_url, cover_path,
+    availability, isbn13_group, requested_url, original_title, publisher, series, series_index,
+    language, isbn_key
+) VALUES (
+    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+    ?21, ?22, ?23, ?24
+) ON CONFLICT (isbn_key) DO UPDATE SET
+    site = excluded.site, source = excluded.source, isbn = excluded.isbn,
+    title = excluded.title, authors = excluded.authors, description = excluded.description,
+    price = excluded.price, about_author = excluded.about_author, rating = excluded.rating,
+    review_count = excluded.review_count, source_id = excluded.source_id,
+    price_max = excluded.price_max, price_is_from = excluded.price_is_from,
+    cover_image_url = excluded.cover_image_url, cover_path = excluded.cover_path,
+    availability = excluded.availability, isbn13_group = excluded.isbn13_group,
+    requested_url = excluded.requested_url, original_title = excluded.original_title,
+    publisher = excluded.publisher, series = excluded.series,
+    series_index = excluded.series_index, language = excluded.language";
+
+/// Страница `source` теперь с другим ISBN: старая строка по этому адресу устарела,
+/// иначе обновление по `isbn_key` упрётся в первичный ключ `source`.
+const DELETE_STALE_SOURCE: &str = "DELETE FROM books WHERE source = ?1 AND isbn_key <> ?2";
+
+/// Шаги схемы по порядку: колонка и SQL, который её добавляет. `PRAGMA user_version`
+/// хранит число применённых шагов. Базы прогонов до появления `user_version` уже могут
+/// иметь колонку шага — тогда шаг только отмечается применённым.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "requested_url",
+        "ALTER TABLE books ADD COLUMN requested_url TEXT",
+    ),
+    (
+        "original_title",
+        "ALTER TABLE books ADD COLUMN original_title TEXT",
+    ),
+    ("publisher", "ALTER TABLE books ADD COLUMN publisher TEXT"),
+    ("series", "ALTER TABLE books ADD COLUMN series TEXT"),
+    (
+        "series_index",
+        "ALTER TABLE books ADD COLUMN series_index INTEGER",
+    ),
+    ("language", "ALTER TABLE books ADD COLUMN language TEXT"),
+    // ISBN без дефисов и пробелов (`sink::isbn_key`). Из повторов остаётся последняя запись.
+    (
+        "isbn_key",
+        "ALTER TABLE books ADD COLUMN isbn_key TEXT;
+        UPDATE books SET isbn_key = replace(replace(isbn, '-', ''), ' ', '');
+        DELETE FROM books WHERE rowid NOT IN (SELECT MAX(rowid) FROM books GROUP BY isbn_key);
+        CREATE UNIQUE INDEX books_isbn_key ON books (isbn_key);",
+    ),
+];
+
+/// Доводит таблицу `books` до текущей схемы.
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    conn.execute(CREATE_TABLE, [])?;
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version >= MIGRATIONS.len() {
+        return Ok(());
+    }
+    let tx = conn.transaction()?;
+    let columns: Vec<String> = tx
+        .prepare("SELECT name FROM pragma_table_info('books')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    for (column, sql) in &MIGRATIONS[version..] {
+        if !columns.iter().any(|existing| existing == column) {
+            info!(column, "adding sqlite column");
+            tx.execute_batch(sql)
+                .with_context(|| format!("can't add column {column}"))?;
+        }
+    }
+    tx.pragma_update(None, "user_version", MIGRATIONS.len())?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn price_i64(price: Price) -> anyhow::Result<i64> {
+    let kopecks = u128::from(price);
+    i64::try_from(kopecks).map_err(|_| anyhow!("price {kopecks} doesn't fit into int64"))
+}
+
+/// Строка таблицы `books`. Собирается на стороне производителя, так что ошибки
+/// преобразования (цена вне `int64`) возвращаются из `write`, а не из потока записи.
+#[derive(Debug)]
+struct Row {
+    site: String,
+    source: String,
+    isbn: String,
+    title: String,
+    authors: String,
+    description: String,
+    price: i64,
+    about_author: Option<String>,
+    rating: Option<f32>,
+    review_count: Option<u32>,
+    source_id: Option<String>,
+    price_max: Option<i64>,
+    price_is_from: bool,
+    cover_image_url: Option<String>,
+    cover_path: Option<String>,
+    availability: Option<String>,
+    isbn13_group: Option<String>,
+    requested_url: Option<String>,
+    original_title: Option<String>,
+    publisher: Option<String>,
+    series: Option<String>,
+    series_index: Option<u16>,
+    language: Option<String>,
+    isbn_key: String,
+}
+
+impl Row {
+    fn from_book(book: &Book<String>) -> anyhow::Result<Self> {
+        let authors: Vec<&str> = book.authors.iter().map(|a| a.as_str()).collect();
+        Ok(Self {
+            site: book.site.to_string(),
+            source: book.source.clone(),
+            isbn: book.isbn.as_str().to_string(),
+            title: book.title.as_str().to_string(),
+            authors: serde_json::to_string(&authors)?,
+            description: book.description.as_str().to_string(),
+            price: price_i64(book.price)?,
+            about_author: book.about_author.clone(),
+            rating: book.rating,
+            review_count: book.review_count,
+            source_id: book.source_id.clone(),
+            price_max: book.price_max.map(price_i64).transpose()?,
+            price_is_from: book.price_is_from,
+            cover_image_url: book.cover_image_url.clone(),
+            cover_path: book.cover_path.clone(),
+            availability: book.availability.map(|a| a.to_string()),
+            isbn13_group: book.isbn13_group(),
+            requested_url: book.requested_url.clone(),
+            original_title: book.original_title.as_ref().map(|t| t.as_str().to_string()),
+            publisher: book.publisher.clone(),
+            series: book.series.clone(),
+            series_index: book.series_index,
+            language: book.language.clone(),
+            isbn_key: isbn_key(book.isbn.as_str()),
+        })
+    }
+}
+
+fn insert_batch(conn: &mut Connection, batch: &[Row]) -> anyhow::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut delete_stale = tx.prepare_cached(DELETE_STALE_SOURCE)?;
+        let mut upsert = tx.prepare_cached(UPSERT_BOOK)?;
+        for row in batch {
+            delete_stale.execute(params![row.source, row.isbn_key])?;
+            upsert
+                .execute(params![
+                    row.site,
+                    row.source,
+                    row.isbn,
+                    row.title,
+                    row.authors,
+                    row.description,
+                    row.price,
+                    row.about_author,
+                    row.rating,
+                    row.review_count,
+                    row.source_id,
+                    row.price_max,
+                    row.price_is_from,
+                    row.cover_image_url,
+                    row.cover_path,
+                    row.availability,
+                    row.isbn13_group,
+                    row.requested_url,
+                    row.original_title,
+                    row.publisher,
+                    row.series,
+                    row.series_index,
+                    row.language,
+                    row.isbn_key,
+                ])
+                .with_context(|| format!("can't insert {}", row.source))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Цикл пишущего потока: ждёт первую книгу, добирает из канала всё, что уже пришло
+/// (не больше `batch_size`), и фиксирует пачку одной транзакцией.
+fn run_writer(
+    mut conn: Connection,
+    mut rx: mpsc::UnboundedReceiver<Row>,
+    batch_size: usize,
+) -> anyhow::Result<usize> {
+    let mut written = 0;
+    let mut batch = Vec::with_capacity(batch_size);
+    while let Some(book) = rx.blocking_recv() {
+        batch.push(book);
+        while batch.len() < batch_size
+            && let Ok(book) = rx.try_recv()
+        {
+            batch.push(book);
+        }
+        insert_batch(&mut conn, &batch)?;
+        written += batch.len();
+        batch.clear();
+    }
+    Ok(written)
+}
+
+/// `books.sqlite`: записи идут через канал в отдельный поток с единственным соединением
+/// и фиксируются пачками по `batch_size`, так что fsync базы не тормозит обход.
+pub struct SqliteSink {
+    tx: Option<mpsc::UnboundedSender<Row>>,
+    thread: Option<JoinHandle<anyhow::Result<usize>>>,
+}
+
+impl SqliteSink {
+    pub fn create(path: impl AsRef<Path>, batch_size: usize) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut conn =
+            Connection::open(path).with_context(|| format!("can't open {}", path.display()))?;
+        // WAL: читатели базы не мешают писателю, пока прогон ещё идёт.
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn).with_context(|| format!("can't migrate {}", path.display()))?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let batch_size = batch_size.max(1);
+        let thread = std::thread::Builder::new()
+            .name("sqlite-writer".to_string())
+            .spawn(move || run_writer(conn, rx, batch_size))?;
+        Ok(Self {
+            tx: Some(tx),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl BookSink for SqliteSink {
+    fn write_book(&mut self, book: &Book<String>) -> anyhow::Result<()> {
+        let tx = self
+            .tx
+            .as_ref()
+            .ok_or_else(|| anyhow!("sqlite sink is already finished"))?;
+        tx.send(Row::from_book(book)?)
+            .map_err(|_| anyhow!("sqlite writer has stopped, see the error on finish"))
+    }
+
+    fn finish(&mut self) -> anyhow::Result<()> {
+        self.tx.take();
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        let written = thread
+            .join()
+            .map_err(|_| anyhow!("sqlite writer thread panicked"))??;
+        info!(written, "books written to sqlite");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::{Isbn, Sites};
+    use crate::sink::tests::sample_book;
+
+    fn temp_db(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("bd_parser_{name}_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Валидный ISBN-13 `978-5-04-NNNNNN-C` с контрольной цифрой.
+    fn numbered_isbn(n: u32) -> Isbn {
+        let digits = format!("978504{n:06}");
+        let sum: u32 = digits
+            .chars()
+            .enumerate()
+            .map(|(i, c)| c.to_digit(10).expect("digit") * if i % 2 == 0 { 1 } else { 3 })
+            .sum();
+        Isbn::try_from(format!("{digits}{}", (10 - sum % 10) % 10)).expect("isbn")
+    }
+
+    fn count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM books", [], |row| row.get(0))
+            .expect("count")
+    }
+
+    #[test]
+    fn batches_of_books_all_land_in_db() {
+        let path = temp_db("books");
+        let mut sink = SqliteSink::create(&path, 16).expect("sink");
+        for n in 0..400 {
+            let mut book = sample_book(1);
+            book.source = format!("https://www.labirint.ru/books/batch-{n}/");
+            book.isbn = numbered_isbn(n);
+            sink.write_book(&book).expect("write");
+        }
+        sink.write_book(&sample_book(1)).expect("write");
+        sink.finish().expect("finish");
+
+        let conn = Connection::open(&path).expect("open");
+        assert_eq!(count(&conn), 400 + 1);
+        let (authors, price): (String, i64) = conn
+            .query_row(
+                "SELECT authors, price FROM books WHERE source = ?1",
+                [sample_book(1).source],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .expect("row");
+        assert!(authors.starts_with("[\""));
+        assert_eq!(price, price_i64(sample_book(1).price).expect("price"));
+        drop(conn);
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn same_isbn_updates_one_row() {
+        let path = temp_db("upsert");
+        let mut sink = SqliteSink::create(&path, 1).expect("sink");
+        let labirint = sample_book(1);
+        // Та же книга на другом сайте, ISBN записан без дефисов.
+        let mut eksmo = sample_book(2);
+        eksmo.site = Sites::Eksmo;
+        eksmo.source = "https://eksmo.ru/book/dzheyn-eyr/".to_string();
+        eksmo.isbn = Isbn::try_from("9785926830153".to_string()).expect("isbn");
+        // По адресу первой книги теперь другая книга.
+        let mut replaced = sample_book(3);
+        replaced.source = labirint.source.clone();
+        replaced.isbn = numbered_isbn(7);
+        for book in [&labirint, &replaced, &eksmo] {
+            sink.write_book(book).expect("write");
+        }
+        sink.finish().expect("finish");
+
+        let conn = Connection::open(&path).expect("open");
+        let rows: Vec<(String, String, i64)> = conn
+            .prepare("SELECT site, source, price FROM books ORDER BY isbn_key")
+            .expect("select")
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .expect("rows")
+            .collect::<Result<_, _>>()
+            .expect("row");
+        assert_eq!(
+            rows,
+            [
+                (
+                    "labirint".to_string(),
+                    labirint.source.clone(),
+                    price_i64(replaced.price).expect("price")
+                ),
+                (
+                    "eksmo".to_string(),
+                    eksmo.source.clone(),
+                    price_i64(eksmo.price).expect("price")
+                ),
+            ]
+        );
+        drop(conn);
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    fn columns(conn: &Connection) -> Vec<String> {
+        conn.prepare("SELECT name FROM pragma_table_info('books')")
+            .expect("table info")
+            .query_map([], |row| row.get(0))
+            .expect("columns")
+            .collect::<Result<_, _>>()
+            .expect("column")
+    }
+
+    #[test]
+    fn old_schema_database_is_migrated() {
+        let path = temp_db("old_books");
+        let old = Connection::open(&path).expect("open");
+        // `CREATE_TABLE` без шагов `MIGRATIONS` — схема первого выпуска с SQLite.
+        old.execute(CREATE_TABLE, []).expect("old table");
+        old.execute(
+            "INSERT INTO books (site, source, isbn, title, authors, description, price,
+                price_is_from) VALUES ('eksmo', 'https://eksmo.ru/book/1/', '978-5-04-122366-3',
+                'Старая', '[]', '', 100, 0),
+                ('igra_slov', 'https://igraslov.store/product/1/', '9785041223663',
+                'Старая', '[]', '', 200, 0)",
+            [],
+        )
+        .expect("old rows");
+        drop(old);
+
+        let mut sink = SqliteSink::create(&path, 16).expect("sink");
+        let mut book = sample_book(1);
+        book.requested_url = Some("https://www.labirint.ru/books/old/".to_string());
+        sink.write_book(&book).expect("write");
+        sink.finish().expect("finish");
+
+        let conn = Connection::open(&path).expect("open");
+        let columns = columns(&conn);
+        for (column, _) in MIGRATIONS {
+            assert!(columns.iter().any(|c| c == column), "{column}");
+        }
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .expect("version");
+        assert_eq!(version, MIGRATIONS.len());
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM books", [], |row| row.get(0))
+            .expect("count");
+        assert_eq!(count, 2);
+        let requested: String = conn
+            .query_row(
+                "SELECT requested_url FROM books WHERE source = ?1",
+                [&book.source],
+                |row| row.get(0),
+            )
+            .expect("row");
+        assert_eq!(requested, "https://www.labirint.ru/books/old/");
+        drop(conn);
+
+        // Повторное открытие уже мигрированной базы ничего не меняет.
+        SqliteSink::create(&path, 16)
+            .expect("reopen")
+            .finish()
+            .expect("finish");
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+use std::io::BufRead;
+
+use anyhow::Context;
+use tracing::warn;
+
+/// URL товаров для входа `-`: по URL на строку, как из `cat urls.txt`. Пустые строки
+/// и `#`-комментарии пропускаются, строки не-http(s) — с предупреждением, повторы
+/// отбрасываются с сохранением порядка. Пустой вход — не ошибка, просто нет URL.
+pub fn read_urls(reader: impl BufRead) -> anyhow::Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut urls = vec![];
+    for (n, line) in reader.lines().enumerate() {
+        let line = line.with_context(|| format!("can't read url on line {}", n + 1))?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let is_http =
+            reqwest::Url::parse(line).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !is_http {
+            warn!(line = n + 1, "skipping non-url input: {line}");
+            continue;
+        }
+        if seen.insert(line.to_string()) {
+            urls.push(line.to_string());
+        }
+    }
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_traits::Sites;
+    use crate::registry::ParserRegistry;
+
+    #[test]
+    fn urls_from_stdin_are_dispatched_by_site() {
+        let stdin: &[u8] = b"https://www.labirint.ru/books/801841/\n\
+            \n\
+            # eksmo\n\
+            https://eksmo.ru/book/dzheyn-eyr/\r\n\
+            not a url\n\
+            https://igraslov.store/product/kniga/\n\
+            https://www.labirint.ru/books/801841/\n\
+            https://example.com/book/1";
+        let urls = read_urls(stdin).expect("urls");
+        assert_eq!(urls.len(), 4);
+
+        let registry = ParserRegistry::default();
+        let sites: Vec<Option<Sites>> = urls
+            .iter()
+            .map(|url| registry.for_url(url).map(|parser| parser.site()))
+            .collect();
+        assert_eq!(
+            sites,
+            [
+                Some(Sites::Labirint),
+                Some(Sites::Eksmo),
+                Some(Sites::IgraSlov),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_stdin_gives_no_urls() {
+        assert!(read_urls(&b""[..]).expect("urls").is_empty());
+        assert!(read_urls(&b"\n  \n"[..]).expect("urls").is_empty());
+    }
+}
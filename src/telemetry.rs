@@ -1,27 +1,54 @@
 use std::error::Error;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::{Layer, layer::SubscriberExt, util::SubscriberInitExt};
-pub fn init_tracing() -> Result<tracing_appender::non_blocking::WorkerGuard, Box<dyn Error>> {
+/// Target событий со сводкой по полям книги; они пишутся только в `logs/quality.log`.
+pub const QUALITY_TARGET: &str = "quality";
+
+/// Держит фоновые писатели логов; логи дописываются, пока guard жив.
+pub struct TracingGuards {
+    _file: tracing_appender::non_blocking::WorkerGuard,
+    _quality: tracing_appender::non_blocking::WorkerGuard,
+}
+
+/// Терминальный вывод логов: stdout, а с `to_stderr` (при `--output -`) — stderr,
+/// чтобы логи не смешивались с выгрузкой.
+fn terminal(to_stderr: bool) -> BoxMakeWriter {
+    if to_stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    }
+}
+
+/// Фильтр без `RUST_LOG`: `info` для всего, в том числе для `target: "quality"`.
+pub(crate) fn default_filter() -> tracing_subscriber::EnvFilter {
+    tracing_subscriber::EnvFilter::new("info")
+}
+
+pub fn init_tracing(terminal_to_stderr: bool) -> Result<TracingGuards, Box<dyn Error>> {
     // Ensure logs directory exists
     std::fs::create_dir_all("logs")?;
 
     let file_appender = tracing_appender::rolling::daily("logs", "parser.log");
     let (file_nb, guard) = tracing_appender::non_blocking(file_appender);
-    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+    let quality_appender = tracing_appender::rolling::daily("logs", "quality.log");
+    let (quality_nb, quality_guard) = tracing_appender::non_blocking(quality_appender);
+    let filter =
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter());
 
     // Terminal: no timestamp by default
     let stdout_no_ts = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stdout)
+        .with_writer(terminal(terminal_to_stderr))
         .with_ansi(true)
         .with_target(false)
         .without_time()
         .with_filter(tracing_subscriber::filter::filter_fn(|meta| {
-            meta.target() != "time"
+            meta.target() != "time" && meta.target() != QUALITY_TARGET
         }));
 
     // Terminal: timestamp only for target "time"
     let stdout_ts = tracing_subscriber::fmt::layer()
-        .with_writer(std::io::stdout)
+        .with_writer(terminal(terminal_to_stderr))
         .with_ansi(true)
         .with_target(false)
         .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
@@ -36,7 +63,7 @@ pub fn init_tracing() -> Result<tracing_appender::non_blocking::WorkerGuard, Box
         .with_target(false)
         .without_time()
         .with_filter(tracing_subscriber::filter::filter_fn(|meta| {
-            meta.target() != "time"
+            meta.target() != "time" && meta.target() != QUALITY_TARGET
         }));
 
     // File: timestamp only for target "time"
@@ -49,12 +76,26 @@ pub fn init_tracing() -> Result<tracing_appender::non_blocking::WorkerGuard, Box
             meta.target() == "time"
         }));
 
+    // Отдельный файл: сводка по полям каждой книги для мониторинга
+    let file_quality = tracing_subscriber::fmt::layer()
+        .with_writer(quality_nb)
+        .with_ansi(false)
+        .with_target(false)
+        .with_timer(tracing_subscriber::fmt::time::UtcTime::rfc_3339())
+        .with_filter(tracing_subscriber::filter::filter_fn(|meta| {
+            meta.target() == QUALITY_TARGET
+        }));
+
     tracing_subscriber::registry()
         .with(filter)
         .with(stdout_no_ts)
         .with(stdout_ts)
         .with(file_no_ts)
         .with(file_ts)
+        .with(file_quality)
         .try_init()?;
-    Ok(guard)
+    Ok(TracingGuards {
+        _file: guard,
+        _quality: quality_guard,
+    })
 }
@@ -0,0 +1,208 @@
+use serde::Serialize;
+
+use crate::parse_traits::{Author, Book, Description, Title};
+
+/// Чистка текста уже извлечённой книги под нужды потребителя выгрузки. Разбор страниц
+/// про неё ничего не знает: парсеры сайтов отдают текст как есть, а преобразования
+/// применяются к готовым `Book` одним `TextPipeline`.
+pub trait TextTransform: Send + Sync {
+    /// Имя для логов и отладки.
+    fn name(&self) -> &'static str;
+    fn apply(&self, text: &str) -> String;
+}
+
+/// `--normalize-quotes`: типографские кавычки («ёлочки», „лапки“, ‘одинарные’) → ASCII `"` и `'`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NormalizeQuotes;
+
+impl TextTransform for NormalizeQuotes {
+    fn name(&self) -> &'static str {
+        "normalize-quotes"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        text.chars()
+            .map(|c| match c {
+                '«' | '»' | '„' | '“' | '”' | '‟' | '″' => '"',
+                '‘' | '’' | '‚' | '‛' | '′' => '\'',
+                c => c,
+            })
+            .collect()
+    }
+}
+
+/// `--strip-emoji`: убирает эмодзи, включая флаги, модификаторы и склейки (ZWJ).
+/// Пробелы вокруг удалённого остаются — их схлопывает `CollapseWhitespace`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripEmoji;
+
+impl StripEmoji {
+    fn is_emoji(c: char) -> bool {
+        matches!(
+            c,
+            '\u{1F000}'..='\u{1FAFF}'
+                | '\u{2600}'..='\u{27BF}'
+                | '\u{2B00}'..='\u{2BFF}'
+                | '\u{FE00}'..='\u{FE0F}'
+                | '\u{200D}'
+                | '\u{20E3}'
+                | '\u{E0020}'..='\u{E007F}'
+        )
+    }
+}
+
+impl TextTransform for StripEmoji {
+    fn name(&self) -> &'static str {
+        "strip-emoji"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        text.chars().filter(|&c| !Self::is_emoji(c)).collect()
+    }
+}
+
+/// `--collapse-whitespace`: серии пробелов и табов → один пробел, строки без пробелов по краям,
+/// подряд идущие пустые строки → одна. Абзацы описания при этом сохраняются.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CollapseWhitespace;
+
+impl TextTransform for CollapseWhitespace {
+    fn name(&self) -> &'static str {
+        "collapse-whitespace"
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut lines: Vec<String> = vec![];
+        for line in text.lines() {
+            let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            if line.is_empty() && lines.last().is_none_or(String::is_empty) {
+                continue;
+            }
+            lines.push(line);
+        }
+        if lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines.join("\n")
+    }
+}
+
+/// Какие преобразования включены флагами. Порядок применения фиксирован (см. `pipeline`),
+/// а не зависит от порядка флагов: эмодзи убираются до схлопывания пробелов.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TextTransforms {
+    pub strip_emoji: bool,
+    pub normalize_quotes: bool,
+    pub collapse_whitespace: bool,
+}
+
+impl TextTransforms {
+    pub fn pipeline(&self) -> TextPipeline {
+        let mut pipeline = TextPipeline::default();
+        if self.strip_emoji {
+            pipeline = pipeline.with(StripEmoji);
+        }
+        if self.normalize_quotes {
+            pipeline = pipeline.with(NormalizeQuotes);
+        }
+        if self.collapse_whitespace {
+            pipeline = pipeline.with(CollapseWhitespace);
+        }
+        pipeline
+    }
+}
+
+/// Преобразования по порядку; пустой конвейер текст не трогает.
+#[derive(Default)]
+pub struct TextPipeline {
+    transforms: Vec<Box<dyn TextTransform>>,
+}
+
+impl TextPipeline {
+    pub fn with(mut self, transform: impl TextTransform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.transforms.iter().map(|t| t.name()).collect()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.transforms
+            .iter()
+            .fold(text.to_string(), |text, transform| transform.apply(&text))
+    }
+
+    /// Название, название оригинала, авторы и описание книги. Результат снова проходит
+    /// `Title::new`, `Author::new` и `Description::new`: убранный эмодзи не оставит пробел
+    /// на краю, а проверки длины увидят уже преобразованный текст.
+    pub fn apply_to_book(&self, book: &mut Book<String>) {
+        if self.is_empty() {
+            return;
+        }
+        book.title = Title::new(self.apply(book.title.as_str()));
+        if let Some(original) = book.original_title.as_mut() {
+            *original = Title::new(self.apply(original.as_str()));
+        }
+        for author in &mut book.authors {
+            *author = Author::new(self.apply(author.as_str()));
+        }
+        book.description = Description::new(self.apply(book.description.as_str()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sink::tests::sample_book;
+
+    #[test]
+    fn transforms_apply_in_sequence() {
+        let pipeline = TextTransforms {
+            strip_emoji: true,
+            normalize_quotes: true,
+            collapse_whitespace: true,
+        }
+        .pipeline();
+        assert_eq!(
+            pipeline.names(),
+            ["strip-emoji", "normalize-quotes", "collapse-whitespace"]
+        );
+        assert_eq!(
+            pipeline.apply("  Роман «Идиот» 📚 — ‘лучшее’  издание 🇷🇺  "),
+            "Роман \"Идиот\" — 'лучшее' издание"
+        );
+        assert_eq!(
+            pipeline.apply("Первый абзац 👍🏻\n\n\n  Второй\tабзац\n"),
+            "Первый абзац\n\nВторой абзац"
+        );
+    }
+
+    #[test]
+    fn empty_pipeline_keeps_book() {
+        let mut book = sample_book(1);
+        book.title = Title("«Идиот» 📚".to_string());
+        book.authors = vec![Author("📚 Достоевский".to_string())];
+        let description = book.description.as_str().to_string();
+        TextTransforms::default()
+            .pipeline()
+            .apply_to_book(&mut book);
+        assert_eq!(book.title.as_str(), "«Идиот» 📚");
+
+        TextTransforms {
+            strip_emoji: true,
+            normalize_quotes: true,
+            collapse_whitespace: false,
+        }
+        .pipeline()
+        .apply_to_book(&mut book);
+        assert_eq!(book.title.as_str(), "\"Идиот\"");
+        assert_eq!(book.authors[0].as_str(), "Достоевский");
+        assert_eq!(book.description.as_str(), description);
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::Context;
+use regex::RegexSet;
+
+/// `--include-url-patterns`/`--exclude-url-patterns`: отбор URL регулярными выражениями
+/// после разворачивания sitemap. URL проходит, если совпал хотя бы с одним include
+/// (или include не заданы) и ни с одним exclude — exclude всегда сильнее.
+#[derive(Debug)]
+pub struct UrlFilter {
+    include: Option<RegexSet>,
+    exclude: Option<RegexSet>,
+}
+
+fn compile(flag: &str, patterns: &[String]) -> anyhow::Result<Option<RegexSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    RegexSet::new(patterns)
+        .map(Some)
+        .with_context(|| format!("--{flag}: invalid regex in {patterns:?}"))
+}
+
+impl UrlFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: compile("include-url-patterns", include)?,
+            exclude: compile("exclude-url-patterns", exclude)?,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.include.is_some() || self.exclude.is_some()
+    }
+
+    pub fn matches(&self, url: &str) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(url))
+            && !self.exclude.as_ref().is_some_and(|set| set.is_match(url))
+    }
+
+    /// Оставляет подходящие URL в исходном порядке, возвращает число отброшенных.
+    pub fn retain(&self, urls: &mut Vec<String>) -> usize {
+        let before = urls.len();
+        if self.is_active() {
+            urls.retain(|url| self.matches(url));
+        }
+        before - urls.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(p: &[&str]) -> Vec<String> {
+        p.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn kept(filter: &UrlFilter) -> Vec<String> {
+        let mut urls = patterns(&[
+            "https://www.labirint.ru/books/1/",
+            "https://igraslov.store/product/detskaya-kniga-1/",
+            "https://igraslov.store/product/detskaya-igra-2/",
+            "https://eksmo.ru/book/roman-3/",
+        ]);
+        filter.retain(&mut urls);
+        urls
+    }
+
+    #[test]
+    fn include_only() {
+        let filter =
+            UrlFilter::new(&patterns(&["detskaya", r"/books/\d+/$"]), &[]).expect("filter");
+        assert_eq!(
+            kept(&filter),
+            [
+                "https://www.labirint.ru/books/1/",
+                "https://igraslov.store/product/detskaya-kniga-1/",
+                "https://igraslov.store/product/detskaya-igra-2/",
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_only() {
+        let filter = UrlFilter::new(&[], &patterns(&["igraslov"])).expect("filter");
+        assert_eq!(
+            kept(&filter),
+            [
+                "https://www.labirint.ru/books/1/",
+                "https://eksmo.ru/book/roman-3/",
+            ]
+        );
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let filter =
+            UrlFilter::new(&patterns(&["detskaya"]), &patterns(&["-igra-"])).expect("filter");
+        assert_eq!(
+            kept(&filter),
+            ["https://igraslov.store/product/detskaya-kniga-1/"]
+        );
+        let nothing = UrlFilter::new(&[], &[]).expect("filter");
+        assert!(!nothing.is_active());
+        assert_eq!(kept(&nothing).len(), 4);
+        assert!(UrlFilter::new(&patterns(&["books/("]), &[]).is_err());
+    }
+}
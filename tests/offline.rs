@@ -0,0 +1,39 @@
+//! `--offline` включает глобальный режим на весь процесс, поэтому проверяется отдельным
+//! бинарником: в юнит-тестах он перехватил бы загрузки из тестов с wiremock.
+
+use parser::labirint::LabirintParser;
+use parser::offline::{self, OfflinePages, PageNotSaved};
+use parser::parse_traits::BookParser;
+
+const SAVED_URL: &str = "https://www.labirint.ru/books/123456/";
+
+#[tokio::test]
+async fn parse_book_reads_saved_page() {
+    let dir = std::env::temp_dir().join(format!("bd_parser_offline_e2e_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("dir");
+    let pages = OfflinePages::open(&dir).expect("open");
+    std::fs::write(
+        pages.path_for(SAVED_URL),
+        include_str!("../page_examples/labirint.html"),
+    )
+    .expect("write fixture");
+    offline::init_global(pages).expect("init offline");
+
+    let book = LabirintParser
+        .parse_book(SAVED_URL.to_string())
+        .await
+        .expect("book from saved page");
+    assert_eq!(book.source, SAVED_URL);
+    assert_eq!(book.isbn.as_str(), "978-5-9268-3015-3");
+    assert_eq!(book.title.as_str(), "Джейн Эйр: Шарлотта Бронте");
+    assert_eq!(book.authors.len(), 1);
+    assert_eq!(book.authors[0].as_str(), "Бронте Шарлотта");
+
+    let err = LabirintParser
+        .parse_book("https://www.labirint.ru/books/654321/".to_string())
+        .await
+        .expect_err("page is not saved");
+    let not_saved = err.downcast_ref::<PageNotSaved>().expect("PageNotSaved");
+    assert_eq!(not_saved.url, "https://www.labirint.ru/books/654321/");
+    std::fs::remove_dir_all(&dir).expect("cleanup");
+}